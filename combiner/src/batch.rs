@@ -0,0 +1,168 @@
+use crate::{CombineError, Result};
+use clap::ValueEnum;
+use image::ImageFormat;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// How the images of a directory are put into groups to combine.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pairing {
+    /// The first and second images by name, then the third and fourth and
+    /// so on.
+    #[default]
+    Sequence,
+    /// Images named the same up to a last `_` or `-`, like `cat_a.png` and
+    /// `cat_b.png`.
+    Name,
+}
+
+/// Images of a directory combined into one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    /// Name of the combined image, without an extension.
+    pub name: String,
+    pub inputs: Vec<PathBuf>,
+}
+
+/// Name of `path` without its extension.
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The images in `dir`, told by their extensions, grouped as `pairing`
+/// says. Images left without a partner make groups of one.
+pub fn group_images(dir: impl AsRef<Path>, pairing: Pairing) -> Result<Vec<Group>> {
+    let dir = dir.as_ref();
+    let entries = fs::read_dir(dir).map_err(|e| CombineError::Io(dir.to_path_buf(), e))?;
+    let mut images = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| CombineError::Io(dir.to_path_buf(), e))?
+            .path();
+        if path.is_file() && ImageFormat::from_path(&path).is_ok() {
+            images.push(path);
+        }
+    }
+    images.sort();
+
+    let groups = match pairing {
+        Pairing::Sequence => images
+            .chunks(2)
+            .map(|inputs| Group {
+                name: inputs
+                    .iter()
+                    .map(|path| stem(path))
+                    .collect::<Vec<_>>()
+                    .join("+"),
+                inputs: inputs.to_vec(),
+            })
+            .collect(),
+        Pairing::Name => {
+            let mut groups = BTreeMap::<String, Vec<PathBuf>>::new();
+            for path in images {
+                let stem = stem(&path);
+                let name = match stem.rfind(['_', '-']) {
+                    Some(end) if end > 0 => stem[..end].to_string(),
+                    _ => stem,
+                };
+                groups.entry(name).or_default().push(path);
+            }
+            groups
+                .into_iter()
+                .map(|(name, inputs)| Group { name, inputs })
+                .collect()
+        }
+    };
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh directory holding empty files of `names`, which is enough to
+    /// group them by name.
+    fn directory(test: &str, names: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("combiner-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in names {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        dir
+    }
+
+    fn names(groups: &[Group]) -> Vec<(&str, Vec<String>)> {
+        groups
+            .iter()
+            .map(|group| {
+                let inputs = group
+                    .inputs
+                    .iter()
+                    .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                    .collect();
+                (group.name.as_str(), inputs)
+            })
+            .collect()
+    }
+
+    const FILES: [&str; 6] = [
+        "b_2.png",
+        "a_1.png",
+        "a_2.jpg",
+        "b_1.png",
+        "notes.txt",
+        "c-d_x.png",
+    ];
+
+    #[test]
+    fn pairs_images_in_order_of_their_names() {
+        let dir = directory("sequence", &FILES);
+        let groups = group_images(&dir, Pairing::Sequence).unwrap();
+        assert_eq!(
+            names(&groups),
+            [
+                (
+                    "a_1+a_2",
+                    vec!["a_1.png".to_string(), "a_2.jpg".to_string()]
+                ),
+                (
+                    "b_1+b_2",
+                    vec!["b_1.png".to_string(), "b_2.png".to_string()]
+                ),
+                ("c-d_x", vec!["c-d_x.png".to_string()]),
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn groups_images_named_the_same_up_to_a_separator() {
+        let dir = directory("name", &FILES);
+        let groups = group_images(&dir, Pairing::Name).unwrap();
+        assert_eq!(
+            names(&groups),
+            [
+                ("a", vec!["a_1.png".to_string(), "a_2.jpg".to_string()]),
+                ("b", vec!["b_1.png".to_string(), "b_2.png".to_string()]),
+                ("c-d", vec!["c-d_x.png".to_string()]),
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_directories_fail_with_their_path() {
+        let dir = std::env::temp_dir().join("combiner-no-such-directory");
+        assert!(matches!(
+            group_images(&dir, Pairing::Name),
+            Err(CombineError::Io(path, _)) if path == dir
+        ));
+    }
+}
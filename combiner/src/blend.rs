@@ -0,0 +1,195 @@
+use image::{GrayImage, Rgba, RgbaImage};
+
+/// Compositing of one image over another, per pixel. The color of the top
+/// image is mixed with the one under it by the mode and laid over it by its
+/// alpha, like the blend modes of image editors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The top color as is.
+    Alpha,
+    /// Product of the colors, darkening.
+    Multiply,
+    /// Inverse of the product of the inverted colors, lightening.
+    Screen,
+    /// Multiply where the bottom is dark and screen where it is light.
+    Overlay,
+    /// Absolute difference of the colors.
+    Difference,
+}
+
+impl BlendMode {
+    /// Mix of the channels `bottom` and `top`, both between 0 and 1.
+    fn mix(self, bottom: f32, top: f32) -> f32 {
+        match self {
+            BlendMode::Alpha => top,
+            BlendMode::Multiply => bottom * top,
+            BlendMode::Screen => 1. - (1. - bottom) * (1. - top),
+            BlendMode::Overlay => {
+                if bottom < 0.5 {
+                    2. * bottom * top
+                } else {
+                    1. - 2. * (1. - bottom) * (1. - top)
+                }
+            }
+            BlendMode::Difference => (bottom - top).abs(),
+        }
+    }
+
+    /// `top` composited over `bottom`. Where the bottom is transparent the
+    /// top color shows unmixed.
+    pub fn blend(self, bottom: Rgba<u8>, top: Rgba<u8>) -> Rgba<u8> {
+        let channel = |value: u8| value as f32 / 255.;
+        let bottom_alpha = channel(bottom[3]);
+        let top_alpha = channel(top[3]);
+        let alpha = top_alpha + bottom_alpha * (1. - top_alpha);
+        if alpha == 0. {
+            return Rgba([0, 0, 0, 0]);
+        }
+
+        let mut blended = [0; 4];
+        for i in 0..3 {
+            let (b, t) = (channel(bottom[i]), channel(top[i]));
+            let mixed = (1. - bottom_alpha) * t + bottom_alpha * self.mix(b, t);
+            let color = (top_alpha * mixed + bottom_alpha * b * (1. - top_alpha)) / alpha;
+            blended[i] = (color * 255.).round() as u8;
+        }
+        blended[3] = (alpha * 255.).round() as u8;
+        Rgba(blended)
+    }
+
+    /// The images composited in order, each over those before it.
+    pub(crate) fn blend_images(self, images: &[RgbaImage]) -> RgbaImage {
+        let mut blended = images[0].clone();
+        for image in &images[1..] {
+            for (bottom, &top) in blended.pixels_mut().zip(image.pixels()) {
+                *bottom = self.blend(*bottom, top);
+            }
+        }
+        blended
+    }
+}
+
+/// `ratio` of `a` and the rest of `b`, mixed with premultiplied alpha so the
+/// colors of transparent pixels don't bleed into the mix.
+pub fn mix(a: Rgba<u8>, b: Rgba<u8>, ratio: f32) -> Rgba<u8> {
+    let (wa, wb) = (ratio * a[3] as f32, (1. - ratio) * b[3] as f32);
+    let alpha = wa + wb;
+    if alpha == 0. {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    let mut mixed = [0; 4];
+    for i in 0..3 {
+        mixed[i] = ((wa * a[i] as f32 + wb * b[i] as f32) / alpha).round() as u8;
+    }
+    mixed[3] = alpha.round() as u8;
+    Rgba(mixed)
+}
+
+/// The images mixed in order, the mix of those before each weighted
+/// `ratio` and it the rest.
+pub(crate) fn mix_images(images: &[RgbaImage], ratio: f32) -> RgbaImage {
+    let mut mixed = images[0].clone();
+    for image in &images[1..] {
+        for (a, &b) in mixed.pixels_mut().zip(image.pixels()) {
+            *a = mix(*a, b, ratio);
+        }
+    }
+    mixed
+}
+
+/// `a` where `mask` is white and `b` where it is black, mixed by the shade
+/// of gray between.
+pub(crate) fn mask_images(a: &RgbaImage, b: &RgbaImage, mask: &GrayImage) -> RgbaImage {
+    let (width, height) = a.dimensions();
+    RgbaImage::from_fn(width, height, |x, y| {
+        let ratio = mask.get_pixel(x, y)[0] as f32 / 255.;
+        mix(*a.get_pixel(x, y), *b.get_pixel(x, y), ratio)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    fn opaque(r: u8, g: u8, b: u8) -> Rgba<u8> {
+        Rgba([r, g, b, 255])
+    }
+
+    #[test]
+    fn modes_mix_opaque_colors() {
+        let (bottom, top) = (opaque(200, 100, 0), opaque(100, 255, 0));
+        assert_eq!(BlendMode::Alpha.blend(bottom, top), top);
+        assert_eq!(BlendMode::Multiply.blend(bottom, top), opaque(78, 100, 0));
+        assert_eq!(BlendMode::Screen.blend(bottom, top), opaque(222, 255, 0));
+        assert_eq!(
+            BlendMode::Difference.blend(bottom, top),
+            opaque(100, 155, 0)
+        );
+        // Light bottom channels screen, dark ones multiply.
+        assert_eq!(BlendMode::Overlay.blend(bottom, top), opaque(188, 200, 0));
+    }
+
+    #[test]
+    fn transparency_shows_the_other_layer() {
+        let color = opaque(10, 20, 30);
+        for mode in [BlendMode::Alpha, BlendMode::Multiply, BlendMode::Difference] {
+            assert_eq!(mode.blend(color, CLEAR), color);
+            assert_eq!(mode.blend(CLEAR, color), color);
+            assert_eq!(mode.blend(CLEAR, CLEAR), CLEAR);
+        }
+    }
+
+    #[test]
+    fn half_transparent_tops_cover_half() {
+        let blended = BlendMode::Alpha.blend(opaque(0, 0, 0), Rgba([255, 255, 255, 128]));
+        assert_eq!(blended, opaque(128, 128, 128));
+    }
+
+    #[test]
+    fn mixes_by_the_ratio() {
+        let (a, b) = (opaque(255, 0, 100), opaque(0, 255, 100));
+        assert_eq!(mix(a, b, 1.), a);
+        assert_eq!(mix(a, b, 0.), b);
+        assert_eq!(mix(a, b, 0.25), opaque(64, 191, 100));
+    }
+
+    #[test]
+    fn transparent_colors_dont_bleed_into_mixes() {
+        let red = opaque(255, 0, 0);
+        assert_eq!(mix(red, Rgba([0, 255, 0, 0]), 0.5), Rgba([255, 0, 0, 128]));
+        assert_eq!(mix(CLEAR, CLEAR, 0.5), CLEAR);
+    }
+
+    #[test]
+    fn masks_pick_by_brightness() {
+        let a = RgbaImage::from_pixel(3, 1, opaque(255, 255, 255));
+        let b = RgbaImage::from_pixel(3, 1, opaque(0, 0, 0));
+        let mask = GrayImage::from_raw(3, 1, vec![255, 0, 64]).unwrap();
+        let masked = mask_images(&a, &b, &mask);
+        assert_eq!(*masked.get_pixel(0, 0), opaque(255, 255, 255));
+        assert_eq!(*masked.get_pixel(1, 0), opaque(0, 0, 0));
+        assert_eq!(*masked.get_pixel(2, 0), opaque(64, 64, 64));
+    }
+
+    #[test]
+    fn images_blend_and_mix_in_order() {
+        let images = [
+            RgbaImage::from_pixel(1, 1, opaque(0, 0, 0)),
+            RgbaImage::from_pixel(1, 1, opaque(255, 0, 0)),
+            RgbaImage::from_pixel(1, 1, Rgba([0, 0, 255, 0])),
+        ];
+        assert_eq!(
+            *BlendMode::Alpha.blend_images(&images).get_pixel(0, 0),
+            opaque(255, 0, 0)
+        );
+        // The first two mix half and half, then that with the transparent
+        // third keeps its color at half the alpha.
+        assert_eq!(
+            *mix_images(&images, 0.5).get_pixel(0, 0),
+            Rgba([128, 0, 0, 128])
+        );
+    }
+}
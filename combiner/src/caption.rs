@@ -0,0 +1,125 @@
+use crate::{BlendMode, CombineError, Position, Result};
+use image::{Rgba, RgbaImage};
+use rusttype::{point, Font, Scale};
+use std::{fs, path::Path};
+
+/// Font captions are written in without another one, Fira Sans Bold.
+const DEFAULT_FONT: &[u8] = include_bytes!("../assets/fonts/FiraSans-Bold.ttf");
+
+/// Text written on an image.
+#[derive(Clone, Debug)]
+pub struct Caption {
+    pub text: String,
+    pub position: Position,
+    /// Height of the text, in pixels.
+    pub size: f32,
+    pub color: Rgba<u8>,
+    /// Color of a line around the letters, keeping the text readable on any
+    /// image.
+    pub outline: Option<Rgba<u8>>,
+    /// Width of the outline, in pixels.
+    pub outline_width: u32,
+}
+
+/// The font bundled for captions.
+pub fn default_font() -> Font<'static> {
+    Font::try_from_bytes(DEFAULT_FONT).expect("the bundled font should be valid")
+}
+
+/// Reads the TrueType or OpenType font at `path`.
+pub fn load_font(path: impl AsRef<Path>) -> Result<Font<'static>> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|e| CombineError::Io(path.to_path_buf(), e))?;
+    Font::try_from_vec(data).ok_or_else(|| CombineError::UnknownFont(path.to_path_buf()))
+}
+
+/// Coverage of the text by the letters, from 0 to 1 for every pixel of a
+/// box fitting it with `border` pixels around.
+fn rasterize(text: &str, font: &Font, size: f32, border: u32) -> (u32, u32, Vec<f32>) {
+    let scale = Scale::uniform(size);
+    let ascent = font.v_metrics(scale).ascent;
+    let glyphs: Vec<_> = font
+        .layout(text, scale, point(border as f32, border as f32 + ascent))
+        .collect();
+
+    let text_width = glyphs
+        .iter()
+        .rev()
+        .find_map(|glyph| glyph.pixel_bounding_box().map(|bounds| bounds.max.x))
+        .unwrap_or(0)
+        .max(0) as u32;
+    let (width, height) = (text_width + border, size.ceil() as u32 + 2 * border);
+    let mut coverage = vec![0.; width as usize * height as usize];
+    for glyph in &glyphs {
+        if let Some(bounds) = glyph.pixel_bounding_box() {
+            glyph.draw(|x, y, value| {
+                let (x, y) = (bounds.min.x + x as i32, bounds.min.y + y as i32);
+                if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+                    let cell = &mut coverage[y as usize * width as usize + x as usize];
+                    *cell = f32::max(*cell, value);
+                }
+            });
+        }
+    }
+    (width, height, coverage)
+}
+
+/// `coverage` grown by `radius` pixels in every direction, each pixel the
+/// most covered one within the radius.
+fn dilate(width: u32, height: u32, coverage: &[f32], radius: u32) -> Vec<f32> {
+    let (width, height, radius) = (width as i32, height as i32, radius as i32);
+    let mut grown = vec![0.; coverage.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let mut most: f32 = 0.;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if dx * dx + dy * dy <= radius * radius
+                        && (0..width).contains(&nx)
+                        && (0..height).contains(&ny)
+                    {
+                        most = most.max(coverage[(ny * width + nx) as usize]);
+                    }
+                }
+            }
+            grown[(y * width + x) as usize] = most;
+        }
+    }
+    grown
+}
+
+/// Writes `caption` on `image` in `font`, half the text size away from the
+/// edges it is placed at.
+pub fn draw_caption(image: &mut RgbaImage, caption: &Caption, font: &Font) {
+    let border = match caption.outline {
+        Some(_) => caption.outline_width,
+        None => 0,
+    };
+    let (width, height, text) = rasterize(&caption.text, font, caption.size, border);
+    let layers = match caption.outline {
+        Some(color) if border > 0 => vec![
+            (color, dilate(width, height, &text, border)),
+            (caption.color, text),
+        ],
+        _ => vec![(caption.color, text)],
+    };
+
+    let (x, y) = caption.position.place(
+        image.dimensions(),
+        (width, height),
+        (caption.size / 2.) as u32,
+    );
+    for (color, coverage) in layers {
+        for (i, &covered) in coverage.iter().enumerate() {
+            let (px, py) = (x + i as u32 % width, y + i as u32 / width);
+            if covered == 0. || px >= image.width() || py >= image.height() {
+                continue;
+            }
+            let Rgba([r, g, b, a]) = color;
+            let top = Rgba([r, g, b, (a as f32 * covered).round() as u8]);
+            let bottom = image.get_pixel_mut(px, py);
+            *bottom = BlendMode::Alpha.blend(*bottom, top);
+        }
+    }
+}
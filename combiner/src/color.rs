@@ -0,0 +1,43 @@
+use image::Rgba;
+
+/// Reads a color written as hex digits, `rrggbb` or `rrggbbaa` with an
+/// optional `#`.
+pub fn parse_color(text: &str) -> Result<Rgba<u8>, String> {
+    let hex = text.trim_start_matches('#');
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    let channels = match hex.len() {
+        6 | 8 if hex.is_ascii() => (0..hex.len())
+            .step_by(2)
+            .map(channel)
+            .collect::<Result<Vec<_>, _>>()
+            .ok(),
+        _ => None,
+    };
+    match channels.as_deref() {
+        Some(&[r, g, b]) => Ok(Rgba([r, g, b, 255])),
+        Some(&[r, g, b, a]) => Ok(Rgba([r, g, b, a])),
+        _ => Err(format!(
+            "expected a color like ff8800 or ff880080, got {}",
+            text
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_colors_with_and_without_alpha() {
+        assert_eq!(parse_color("ff8800"), Ok(Rgba([255, 136, 0, 255])));
+        assert_eq!(parse_color("#ff880080"), Ok(Rgba([255, 136, 0, 128])));
+        assert_eq!(parse_color("00000000"), Ok(Rgba([0, 0, 0, 0])));
+    }
+
+    #[test]
+    fn rejects_other_lengths_and_digits() {
+        for text in ["ff88", "ff88001", "zzzzzz", "", "#", "ééé"] {
+            assert!(parse_color(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{metrics, CombineError, Result};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+#[derive(Clone, Copy, Debug)]
+pub struct DiffOptions {
+    /// Most any channel of a pixel may differ by for it to count as the same.
+    pub tolerance: u8,
+    /// Color the differing pixels are painted in.
+    pub highlight: Rgba<u8>,
+    /// Brightness the same pixels are kept at, from 0 for black to 1 for
+    /// unchanged.
+    pub dim: f32,
+}
+
+impl Default for DiffOptions {
+    fn default() -> DiffOptions {
+        DiffOptions {
+            tolerance: 0,
+            highlight: Rgba([255, 0, 255, 255]),
+            dim: 0.3,
+        }
+    }
+}
+
+/// Where two images differ.
+#[derive(Clone, Debug)]
+pub struct Diff {
+    /// The first image dimmed on black, with the pixels that differ
+    /// highlighted.
+    pub image: RgbaImage,
+    /// Pixels that differ.
+    pub changed: u64,
+    /// Pixels of either image.
+    pub total: u64,
+    /// Peak signal-to-noise ratio of the second image to the first, in
+    /// decibels, infinite for the same colors.
+    pub psnr: f64,
+    /// Structural similarity of the images, 1 for the same.
+    pub ssim: f64,
+}
+
+impl Diff {
+    /// Part of the pixels that differ, in percent.
+    pub fn percentage(&self) -> f64 {
+        self.changed as f64 * 100. / self.total.max(1) as f64
+    }
+}
+
+/// Compares `a` and `b` pixel by pixel and measures how alike they look.
+/// They have to be the same size.
+pub fn diff(a: &DynamicImage, b: &DynamicImage, options: DiffOptions) -> Result<Diff> {
+    if a.dimensions() != b.dimensions() {
+        return Err(CombineError::SizeMismatch(a.dimensions(), b.dimensions()));
+    }
+
+    let (a, b) = (a.to_rgba8(), b.to_rgba8());
+    let dim = options.dim.clamp(0., 1.);
+    let mut changed = 0;
+    let image = RgbaImage::from_fn(a.width(), a.height(), |x, y| {
+        let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+        let differs =
+            pa.0.iter()
+                .zip(pb.0.iter())
+                .any(|(&ca, &cb)| ca.abs_diff(cb) > options.tolerance);
+        if differs {
+            changed += 1;
+            options.highlight
+        } else {
+            let Rgba([r, g, b, alpha]) = *pa;
+            let dimmed = |value: u8| (value as f32 * dim * alpha as f32 / 255.).round() as u8;
+            Rgba([dimmed(r), dimmed(g), dimmed(b), 255])
+        }
+    });
+    let total = a.width() as u64 * a.height() as u64;
+    Ok(Diff {
+        image,
+        changed,
+        total,
+        psnr: metrics::psnr(&a, &b),
+        ssim: metrics::ssim(&a, &b),
+    })
+}
@@ -0,0 +1,121 @@
+use crate::blend::mix;
+use clap::ValueEnum;
+use image::RgbaImage;
+
+/// Direction one image fades into another in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Gradient {
+    /// Left to right.
+    #[default]
+    Horizontal,
+    /// Top to bottom.
+    Vertical,
+    /// From the center out to the corners.
+    Radial,
+}
+
+impl Gradient {
+    /// How far along the gradient `(x, y)` is on a canvas of `(width,
+    /// height)`, from 0 to 1.
+    fn position(self, x: u32, y: u32, (width, height): (u32, u32)) -> f32 {
+        let along = |at: u32, length: u32| at as f32 / (length.max(2) - 1) as f32;
+        match self {
+            Gradient::Horizontal => along(x, width),
+            Gradient::Vertical => along(y, height),
+            Gradient::Radial => {
+                let (dx, dy) = (along(x, width) - 0.5, along(y, height) - 0.5);
+                (dx * dx + dy * dy).sqrt() / 0.5f32.sqrt()
+            }
+        }
+    }
+}
+
+/// `a` fading into `b` along `gradient`, all `a` before `start` and all `b`
+/// after `end`, both fractions of the way along it. The fade runs backwards
+/// with `start` after `end` and is a hard edge with them equal.
+pub(crate) fn crossfade(
+    a: &RgbaImage,
+    b: &RgbaImage,
+    gradient: Gradient,
+    start: f32,
+    end: f32,
+) -> RgbaImage {
+    let size = a.dimensions();
+    RgbaImage::from_fn(size.0, size.1, |x, y| {
+        let position = gradient.position(x, y, size);
+        let towards_b = if start == end {
+            if position < start {
+                0.
+            } else {
+                1.
+            }
+        } else {
+            ((position - start) / (end - start)).clamp(0., 1.)
+        };
+        mix(*a.get_pixel(x, y), *b.get_pixel(x, y), 1. - towards_b)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn fade(width: u32, height: u32, gradient: Gradient, start: f32, end: f32) -> RgbaImage {
+        let a = RgbaImage::from_pixel(width, height, BLACK);
+        let b = RgbaImage::from_pixel(width, height, WHITE);
+        crossfade(&a, &b, gradient, start, end)
+    }
+
+    /// Red channel of the pixel, how far it went from black to white.
+    fn shade(image: &RgbaImage, x: u32, y: u32) -> u8 {
+        image.get_pixel(x, y)[0]
+    }
+
+    #[test]
+    fn positions_run_from_0_to_1() {
+        let size = (5, 3);
+        assert_eq!(Gradient::Horizontal.position(0, 2, size), 0.);
+        assert_eq!(Gradient::Horizontal.position(4, 0, size), 1.);
+        assert_eq!(Gradient::Vertical.position(4, 1, size), 0.5);
+        assert_eq!(Gradient::Radial.position(2, 1, size), 0.);
+        assert!((Gradient::Radial.position(4, 2, size) - 1.).abs() < 1e-6);
+        assert_eq!(Gradient::Horizontal.position(0, 0, (1, 1)), 0.);
+    }
+
+    #[test]
+    fn fades_linearly_between_start_and_end() {
+        let faded = fade(5, 1, Gradient::Horizontal, 0., 1.);
+        let shades: Vec<_> = (0..5).map(|x| shade(&faded, x, 0)).collect();
+        assert_eq!(shades, [0, 64, 128, 191, 255]);
+
+        let faded = fade(5, 1, Gradient::Horizontal, 0.25, 0.75);
+        let shades: Vec<_> = (0..5).map(|x| shade(&faded, x, 0)).collect();
+        assert_eq!(shades, [0, 0, 128, 255, 255]);
+    }
+
+    #[test]
+    fn equal_bounds_make_a_hard_edge() {
+        let faded = fade(1, 4, Gradient::Vertical, 0.5, 0.5);
+        let shades: Vec<_> = (0..4).map(|y| shade(&faded, 0, y)).collect();
+        assert_eq!(shades, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn reversed_bounds_fade_backwards() {
+        let faded = fade(3, 1, Gradient::Horizontal, 1., 0.);
+        let shades: Vec<_> = (0..3).map(|x| shade(&faded, x, 0)).collect();
+        assert_eq!(shades, [255, 128, 0]);
+    }
+
+    #[test]
+    fn radial_fades_from_the_center() {
+        let faded = fade(5, 5, Gradient::Radial, 0., 1.);
+        assert_eq!(shade(&faded, 2, 2), 0);
+        assert_eq!(shade(&faded, 0, 0), 255);
+        assert!(shade(&faded, 2, 0) < shade(&faded, 0, 0));
+    }
+}
@@ -0,0 +1,224 @@
+use crate::resize::{contained_size, scale};
+use clap::ValueEnum;
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, GenericImageView, Rgba, RgbaImage,
+};
+
+/// Where images narrower than the stack line up.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// The images side by side, left to right and `gap` pixels apart, on a
+/// canvas of `background`. They are scaled to the height of the lowest of
+/// them with `filter`, keeping their aspect ratios.
+pub(crate) fn stitch_horizontal(
+    images: &[DynamicImage],
+    gap: u32,
+    background: Rgba<u8>,
+    filter: FilterType,
+) -> RgbaImage {
+    let height = images.iter().map(|image| image.height()).min().unwrap();
+    let images: Vec<_> = images
+        .iter()
+        .map(|image| {
+            let (w, h) = image.dimensions();
+            image
+                .resize_exact(scale(w, height, h), height, filter)
+                .to_rgba8()
+        })
+        .collect();
+
+    let width =
+        images.iter().map(|image| image.width()).sum::<u32>() + gap * (images.len() as u32 - 1);
+    let mut canvas = RgbaImage::from_pixel(width, height, background);
+    let mut x = 0;
+    for image in &images {
+        imageops::overlay(&mut canvas, image, x, 0);
+        x += image.width() + gap;
+    }
+    canvas
+}
+
+/// The images stacked top to bottom, `gap` pixels apart, on a canvas of
+/// `background`. Without `align` they are scaled to the width of the
+/// narrowest of them, keeping their aspect ratios, with it they keep their
+/// sizes and line up on that side of the widest.
+pub(crate) fn stitch_vertical(
+    images: &[DynamicImage],
+    gap: u32,
+    background: Rgba<u8>,
+    align: Option<Align>,
+    filter: FilterType,
+) -> RgbaImage {
+    let images: Vec<_> = match align {
+        Some(_) => images.iter().map(|image| image.to_rgba8()).collect(),
+        None => {
+            let width = images.iter().map(|image| image.width()).min().unwrap();
+            images
+                .iter()
+                .map(|image| {
+                    let (w, h) = image.dimensions();
+                    image
+                        .resize_exact(width, scale(h, width, w), filter)
+                        .to_rgba8()
+                })
+                .collect()
+        }
+    };
+
+    let width = images.iter().map(|image| image.width()).max().unwrap();
+    let height =
+        images.iter().map(|image| image.height()).sum::<u32>() + gap * (images.len() as u32 - 1);
+    let mut canvas = RgbaImage::from_pixel(width, height, background);
+    let mut y = 0;
+    for image in &images {
+        let x = match align {
+            Some(Align::Center) => (width - image.width()) / 2,
+            Some(Align::Right) => width - image.width(),
+            Some(Align::Left) | None => 0,
+        };
+        imageops::overlay(&mut canvas, image, x, y);
+        y += image.height() + gap;
+    }
+    canvas
+}
+
+/// Reads a size written as `WIDTHxHEIGHT`, like `320x240`.
+pub fn parse_size(text: &str) -> Result<(u32, u32), String> {
+    let size = text
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+    match size {
+        Some((w, h)) if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(format!("expected a size like 320x240, got {}", text)),
+    }
+}
+
+/// Lays the images out in rows of `columns` cells, left to right and top to
+/// bottom, on a canvas of `background`. Every cell is `cell_size`, or the
+/// size of the smallest image without one, and has `padding` pixels around
+/// it. Images are scaled to fit their cells, keeping their aspect ratios,
+/// and centered in them.
+pub(crate) fn grid(
+    images: &[DynamicImage],
+    columns: u32,
+    cell_size: Option<(u32, u32)>,
+    padding: u32,
+    background: Rgba<u8>,
+    filter: FilterType,
+) -> RgbaImage {
+    let columns = columns.clamp(1, images.len() as u32);
+    let rows = (images.len() as u32).div_ceil(columns);
+    let (cell_width, cell_height) = cell_size.unwrap_or_else(|| {
+        images
+            .iter()
+            .map(|image| image.dimensions())
+            .min_by_key(|&(w, h)| w as u64 * h as u64)
+            .unwrap()
+    });
+
+    let mut canvas = RgbaImage::from_pixel(
+        columns * (cell_width + padding) + padding,
+        rows * (cell_height + padding) + padding,
+        background,
+    );
+    for (i, image) in images.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let (width, height) = contained_size(image.dimensions(), (cell_width, cell_height));
+        let image = image.resize_exact(width, height, filter).to_rgba8();
+        let x = padding + column * (cell_width + padding) + (cell_width - width) / 2;
+        let y = padding + row * (cell_height + padding) + (cell_height - height) / 2;
+        imageops::overlay(&mut canvas, &image, x, y);
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    fn filled(width: u32, height: u32, color: Rgba<u8>) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, color))
+    }
+
+    #[test]
+    fn reads_sizes() {
+        assert_eq!(parse_size("320x240"), Ok((320, 240)));
+        for text in ["320", "0x240", "320x0", "ax2", "3x2x1", ""] {
+            assert!(parse_size(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn stitching_side_by_side_scales_to_the_lowest_image() {
+        let images = [filled(10, 20, RED), filled(30, 10, BLUE)];
+        let stitched = stitch_horizontal(&images, 2, CLEAR, FilterType::Nearest);
+        assert_eq!(stitched.dimensions(), (5 + 2 + 30, 10));
+        assert_eq!(*stitched.get_pixel(4, 9), RED);
+        assert_eq!(*stitched.get_pixel(5, 0), CLEAR);
+        assert_eq!(*stitched.get_pixel(7, 0), BLUE);
+    }
+
+    #[test]
+    fn stacking_scales_to_the_narrowest_image_without_align() {
+        let images = [filled(20, 10, RED), filled(10, 10, BLUE)];
+        let stacked = stitch_vertical(&images, 0, CLEAR, None, FilterType::Nearest);
+        assert_eq!(stacked.dimensions(), (10, 5 + 10));
+    }
+
+    #[test]
+    fn aligned_stacks_keep_the_widths() {
+        let images = [filled(4, 2, RED), filled(2, 2, BLUE)];
+        let right = stitch_vertical(&images, 1, CLEAR, Some(Align::Right), FilterType::Nearest);
+        assert_eq!(right.dimensions(), (4, 5));
+        assert_eq!(*right.get_pixel(0, 2), CLEAR);
+        assert_eq!(*right.get_pixel(1, 4), CLEAR);
+        assert_eq!(*right.get_pixel(2, 4), BLUE);
+
+        let center = stitch_vertical(&images, 0, CLEAR, Some(Align::Center), FilterType::Nearest);
+        assert_eq!(*center.get_pixel(0, 3), CLEAR);
+        assert_eq!(*center.get_pixel(1, 3), BLUE);
+        assert_eq!(*center.get_pixel(3, 3), CLEAR);
+    }
+
+    #[test]
+    fn grids_fit_images_in_equal_cells() {
+        let images = [
+            filled(10, 10, RED),
+            filled(20, 10, BLUE),
+            filled(10, 10, RED),
+        ];
+        let grid = grid(&images, 2, None, 1, CLEAR, FilterType::Nearest);
+        // Two rows of two cells of 10x10 with a pixel around each.
+        assert_eq!(grid.dimensions(), (23, 23));
+        assert_eq!(*grid.get_pixel(0, 0), CLEAR);
+        assert_eq!(*grid.get_pixel(1, 1), RED);
+        // The wide image is scaled to 10x5 and centered in its cell.
+        assert_eq!(*grid.get_pixel(12, 2), CLEAR);
+        assert_eq!(*grid.get_pixel(12, 3), BLUE);
+        assert_eq!(*grid.get_pixel(21, 7), BLUE);
+        assert_eq!(*grid.get_pixel(12, 8), CLEAR);
+        // The second row has a single image on the left.
+        assert_eq!(*grid.get_pixel(12, 12), CLEAR);
+    }
+
+    #[test]
+    fn grid_cells_can_be_sized() {
+        let images = [filled(10, 10, RED), filled(10, 10, BLUE)];
+        let grid = grid(&images, 5, Some((20, 10)), 0, CLEAR, FilterType::Nearest);
+        // Columns are limited to the images there are.
+        assert_eq!(grid.dimensions(), (40, 10));
+        assert_eq!(*grid.get_pixel(4, 5), CLEAR);
+        assert_eq!(*grid.get_pixel(5, 5), RED);
+        assert_eq!(*grid.get_pixel(25, 5), BLUE);
+    }
+}
@@ -0,0 +1,527 @@
+//! Combines images into one. `combine` takes decoded images and returns the
+//! combined one, `load_image` and `save_image` read and write image files of
+//! any format.
+
+mod batch;
+mod blend;
+mod caption;
+mod color;
+mod diff;
+mod gradient;
+mod layout;
+mod metrics;
+mod resize;
+mod transform;
+mod watermark;
+
+pub use batch::{group_images, Group, Pairing};
+pub use blend::{mix, BlendMode};
+pub use caption::{default_font, draw_caption, load_font, Caption};
+pub use color::parse_color;
+pub use diff::{diff, Diff, DiffOptions};
+pub use gradient::Gradient;
+pub use layout::{parse_size, Align};
+pub use resize::{Filter, Fit};
+pub use transform::{parse_crop, parse_flip, parse_rotate, Axis, Crop, Flip, Preprocess, Rotate};
+pub use watermark::{watermark, Position, WatermarkOptions};
+
+use clap::ValueEnum;
+use image::{io::Reader, DynamicImage, GenericImageView, ImageError, ImageFormat, Rgba, RgbaImage};
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// How the pixels of the images are mixed.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Takes blocks of pixels from each image in turn, in a checkerboard.
+    #[default]
+    Checkerboard,
+    /// Averages the images with equal weights.
+    Average,
+    /// Mixes the first image by a ratio with the second, and that mix with
+    /// any next image by the same ratio.
+    Blend,
+    /// Fades from the first image into the second across the image.
+    Gradient,
+    /// Lays each image over those before it by its alpha.
+    Alpha,
+    /// Multiplies the colors, darkening.
+    Multiply,
+    /// Screens the colors, lightening.
+    Screen,
+    /// Multiplies dark and screens light parts of the images below.
+    Overlay,
+    /// Takes the difference of the colors.
+    Difference,
+    /// Places the images side by side at the same height.
+    #[value(name = "stitch-h")]
+    StitchH,
+    /// Stacks the images top to bottom at the same width.
+    #[value(name = "stitch-v")]
+    StitchV,
+    /// Lays the images out in a grid of equal cells.
+    Grid,
+}
+
+impl Mode {
+    /// How the images are composited in blend modes.
+    pub fn blend_mode(self) -> Option<BlendMode> {
+        match self {
+            Mode::Checkerboard
+            | Mode::Average
+            | Mode::Blend
+            | Mode::Gradient
+            | Mode::StitchH
+            | Mode::StitchV
+            | Mode::Grid => None,
+            Mode::Alpha => Some(BlendMode::Alpha),
+            Mode::Multiply => Some(BlendMode::Multiply),
+            Mode::Screen => Some(BlendMode::Screen),
+            Mode::Overlay => Some(BlendMode::Overlay),
+            Mode::Difference => Some(BlendMode::Difference),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CombineOptions {
+    pub mode: Mode,
+    /// Width and height of the squares of the checkerboard, in pixels.
+    pub block_size: u32,
+    /// Space between stitched images, in pixels.
+    pub gap: u32,
+    /// Color of the canvas around stitched images.
+    pub background: Rgba<u8>,
+    /// Side stacked images of different widths line up on, which keeps
+    /// them from being scaled to the same width.
+    pub align: Option<Align>,
+    /// Cells in a row of the grid, enough for a square grid without.
+    pub columns: Option<u32>,
+    /// Size of the cells of the grid, that of the smallest image without.
+    pub cell_size: Option<(u32, u32)>,
+    /// Space around the cells of the grid, in pixels.
+    pub padding: u32,
+    /// Filter of every resize.
+    pub filter: Filter,
+    /// How images mixed pixel by pixel are resized to the smallest one.
+    pub fit: Fit,
+    /// Color around images fitted with `Fit::Pad`.
+    pub pad_color: Rgba<u8>,
+    /// Weight of the first image in the blend, that of the second being the
+    /// rest to 1.
+    pub ratio: f32,
+    /// Direction the first image fades into the second in.
+    pub gradient: Gradient,
+    /// Fractions of the way along the gradient where the fade starts and
+    /// ends.
+    pub gradient_start: f32,
+    pub gradient_end: f32,
+}
+
+impl Default for CombineOptions {
+    fn default() -> CombineOptions {
+        CombineOptions {
+            mode: Mode::default(),
+            block_size: 1,
+            gap: 0,
+            background: Rgba([0, 0, 0, 0]),
+            align: None,
+            columns: None,
+            cell_size: None,
+            padding: 0,
+            filter: Filter::default(),
+            fit: Fit::default(),
+            pad_color: Rgba([0, 0, 0, 0]),
+            ratio: 0.5,
+            gradient: Gradient::default(),
+            gradient_start: 0.,
+            gradient_end: 1.,
+        }
+    }
+}
+
+/// Why images couldn't be read, combined or written. File errors carry the
+/// path of the file.
+#[derive(Debug)]
+pub enum CombineError {
+    /// The file couldn't be opened or read.
+    Io(PathBuf, io::Error),
+    /// The format of the file couldn't be guessed from its contents.
+    UnknownFormat(PathBuf),
+    /// The image couldn't be decoded.
+    Decode(PathBuf, ImageError),
+    /// The output format couldn't be told from the file extension.
+    UnknownOutputFormat(PathBuf),
+    /// The image couldn't be encoded or written.
+    Encode(PathBuf, ImageError),
+    /// The file isn't a font that can be read.
+    UnknownFont(PathBuf),
+    /// Combining needs at least two images, this many were given.
+    TooFewImages(usize),
+    /// Gradients and masks mix exactly two images, this many were given.
+    NotTwoImages(usize),
+    /// An input was picked by an index past the count of inputs.
+    NoSuchInput(usize, usize),
+    /// Images compared have to be the same size, these two aren't.
+    SizeMismatch((u32, u32), (u32, u32)),
+    /// The crop reaches past the input at the index, of the size.
+    CropOutside(Crop, usize, (u32, u32)),
+}
+
+impl fmt::Display for CombineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CombineError::Io(path, e) => write!(f, "couldn't read {}: {}", path.display(), e),
+            CombineError::UnknownFormat(path) => {
+                write!(f, "{} isn't an image of a known format", path.display())
+            }
+            CombineError::Decode(path, e) => {
+                write!(f, "couldn't decode {}: {}", path.display(), e)
+            }
+            CombineError::UnknownOutputFormat(path) => write!(
+                f,
+                "no image format has the extension of {}, pick one with --format",
+                path.display()
+            ),
+            CombineError::Encode(path, e) => {
+                write!(f, "couldn't write {}: {}", path.display(), e)
+            }
+            CombineError::UnknownFont(path) => {
+                write!(f, "{} isn't a TrueType or OpenType font", path.display())
+            }
+            CombineError::TooFewImages(count) => {
+                write!(f, "at least 2 images are combined, got {}", count)
+            }
+            CombineError::NotTwoImages(count) => write!(
+                f,
+                "a gradient or mask mixes exactly 2 images, got {}",
+                count
+            ),
+            CombineError::NoSuchInput(index, count) => write!(
+                f,
+                "there is no input {}, the {} inputs count from 0",
+                index, count
+            ),
+            CombineError::SizeMismatch((aw, ah), (bw, bh)) => write!(
+                f,
+                "the images are different sizes, {}x{} and {}x{}",
+                aw, ah, bw, bh
+            ),
+            CombineError::CropOutside(crop, index, (w, h)) => write!(
+                f,
+                "crop {} reaches past input {}, which is {}x{}",
+                crop, index, w, h
+            ),
+        }
+    }
+}
+
+impl Error for CombineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CombineError::Io(_, e) => Some(e),
+            CombineError::Decode(_, e) | CombineError::Encode(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, CombineError>;
+
+/// Reads the image at `path`, in whatever format its contents are.
+pub fn load_image(path: impl AsRef<Path>) -> Result<DynamicImage> {
+    let path = path.as_ref();
+    let reader = Reader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| CombineError::Io(path.to_path_buf(), e))?;
+    if reader.format().is_none() {
+        return Err(CombineError::UnknownFormat(path.to_path_buf()));
+    }
+    reader
+        .decode()
+        .map_err(|e| CombineError::Decode(path.to_path_buf(), e))
+}
+
+/// Writes `image` to `path` in `format`, or the format of its extension
+/// without one. Formats without an alpha channel get the colors only.
+pub fn save_image(
+    image: &DynamicImage,
+    path: impl AsRef<Path>,
+    format: Option<ImageFormat>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let format = output_format(path, format)?;
+    let result = match format {
+        ImageFormat::Jpeg => {
+            DynamicImage::ImageRgb8(image.to_rgb8()).save_with_format(path, format)
+        }
+        _ => image.save_with_format(path, format),
+    };
+    result.map_err(|e| CombineError::Encode(path.to_path_buf(), e))
+}
+
+/// `format`, or the format of the extension of `path` without one, to know
+/// whether an image can be written there before making it.
+pub fn output_format(path: impl AsRef<Path>, format: Option<ImageFormat>) -> Result<ImageFormat> {
+    let path = path.as_ref();
+    match format {
+        Some(format) => Ok(format),
+        None => path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| parse_format(extension).ok())
+            .ok_or_else(|| CombineError::UnknownOutputFormat(path.to_path_buf())),
+    }
+}
+
+/// Reads an image format named like its file extension, as `png` or `jpg`.
+pub fn parse_format(text: &str) -> std::result::Result<ImageFormat, String> {
+    ImageFormat::from_extension(text).ok_or_else(|| format!("unknown image format {}", text))
+}
+
+/// Reads a number between 0 and 1, like `0.25`.
+pub fn parse_fraction(text: &str) -> std::result::Result<f32, String> {
+    match text.parse() {
+        Ok(fraction) if (0. ..=1.).contains(&fraction) => Ok(fraction),
+        _ => Err(format!("expected a number from 0 to 1, got {}", text)),
+    }
+}
+
+/// Combines `images` into one. Modes mixing pixels make it the size of the
+/// smallest image, the others resized to it as `options.fit` says.
+pub fn combine(images: &[DynamicImage], options: CombineOptions) -> Result<DynamicImage> {
+    if images.len() < 2 {
+        return Err(CombineError::TooFewImages(images.len()));
+    }
+
+    let filter = options.filter.filter_type();
+    let combined = match options.mode.blend_mode() {
+        Some(blend_mode) => blend_mode.blend_images(&standardize_size(images, options)),
+        None => match options.mode {
+            Mode::Average => average_pixels(&standardize_size(images, options)),
+            Mode::Blend => blend::mix_images(&standardize_size(images, options), options.ratio),
+            Mode::Gradient => {
+                if images.len() != 2 {
+                    return Err(CombineError::NotTwoImages(images.len()));
+                }
+                let images = standardize_size(images, options);
+                gradient::crossfade(
+                    &images[0],
+                    &images[1],
+                    options.gradient,
+                    options.gradient_start,
+                    options.gradient_end,
+                )
+            }
+            Mode::StitchH => {
+                layout::stitch_horizontal(images, options.gap, options.background, filter)
+            }
+            Mode::StitchV => layout::stitch_vertical(
+                images,
+                options.gap,
+                options.background,
+                options.align,
+                filter,
+            ),
+            Mode::Grid => {
+                // The fewest columns that make no more rows than columns.
+                let square = (1..).find(|n| n * n >= images.len() as u32).unwrap();
+                layout::grid(
+                    images,
+                    options.columns.unwrap_or(square),
+                    options.cell_size,
+                    options.padding,
+                    options.background,
+                    filter,
+                )
+            }
+            _ => checkerboard(&standardize_size(images, options), options.block_size),
+        },
+    };
+
+    Ok(DynamicImage::ImageRgba8(combined))
+}
+
+/// The first image where `mask` is white and the second where it is black,
+/// mixed by the shade of gray between. The images are resized to the
+/// smallest of them and the mask to that too, as `options.fit` says.
+pub fn combine_masked(
+    images: &[DynamicImage],
+    mask: &DynamicImage,
+    options: CombineOptions,
+) -> Result<DynamicImage> {
+    if images.len() != 2 {
+        return Err(CombineError::NotTwoImages(images.len()));
+    }
+
+    let images = standardize_size(images, options);
+    let mask = resize::resize(
+        mask,
+        images[0].dimensions(),
+        options.fit,
+        options.filter.filter_type(),
+        Rgba([0, 0, 0, 0]),
+    );
+    let mask = DynamicImage::ImageRgba8(mask).to_luma8();
+    Ok(DynamicImage::ImageRgba8(blend::mask_images(
+        &images[0], &images[1], &mask,
+    )))
+}
+
+/// Dimensions of the image with the fewest pixels.
+fn get_smallest_dimension(images: &[DynamicImage]) -> (u32, u32) {
+    images
+        .iter()
+        .map(|image| image.dimensions())
+        .min_by_key(|&(w, h)| w as u64 * h as u64)
+        .unwrap()
+}
+
+/// The images as RGBA, resized to the smallest of them as the options say.
+fn standardize_size(images: &[DynamicImage], options: CombineOptions) -> Vec<RgbaImage> {
+    let size = get_smallest_dimension(images);
+    let filter = options.filter.filter_type();
+
+    images
+        .iter()
+        .map(|image| resize::resize(image, size, options.fit, filter, options.pad_color))
+        .collect()
+}
+
+/// Squares of `block_size` pixels taken from the `n` images in turn, the
+/// square in column `x` and row `y` from image `(x + y) % n`.
+fn checkerboard(images: &[RgbaImage], block_size: u32) -> RgbaImage {
+    let block_size = block_size.max(1);
+    let (width, height) = images[0].dimensions();
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let block = (x / block_size + y / block_size) as usize;
+        *images[block % images.len()].get_pixel(x, y)
+    })
+}
+
+/// Mean of every channel over the images.
+fn average_pixels(images: &[RgbaImage]) -> RgbaImage {
+    let mut sums = vec![0u32; images[0].as_raw().len()];
+    for image in images {
+        for (sum, &value) in sums.iter_mut().zip(image.as_raw()) {
+            *sum += value as u32;
+        }
+    }
+
+    let count = images.len() as u32;
+    let data = sums
+        .into_iter()
+        .map(|sum| ((sum + count / 2) / count) as u8)
+        .collect();
+    let (width, height) = images[0].dimensions();
+    RgbaImage::from_raw(width, height, data).expect("averaged pixels should fill the image")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(width: u32, height: u32, value: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            width,
+            height,
+            Rgba([value, value, value, 255]),
+        ))
+    }
+
+    #[test]
+    fn reads_formats_and_fractions() {
+        assert_eq!(parse_format("png"), Ok(ImageFormat::Png));
+        assert_eq!(parse_format("JPG"), Ok(ImageFormat::Jpeg));
+        assert!(parse_format("doc").is_err());
+
+        assert_eq!(output_format("out.PNG", None).unwrap(), ImageFormat::Png);
+        assert_eq!(
+            output_format("out.doc", Some(ImageFormat::Jpeg)).unwrap(),
+            ImageFormat::Jpeg
+        );
+        for path in ["out.doc", "out"] {
+            assert!(matches!(
+                output_format(path, None),
+                Err(CombineError::UnknownOutputFormat(_))
+            ));
+        }
+
+        assert_eq!(parse_fraction("0.25"), Ok(0.25));
+        assert_eq!(parse_fraction("1"), Ok(1.));
+        for text in ["1.5", "-0.1", "NaN", "half"] {
+            assert!(parse_fraction(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn combining_needs_two_images() {
+        let result = combine(&[filled(2, 2, 0)], CombineOptions::default());
+        assert!(matches!(result, Err(CombineError::TooFewImages(1))));
+    }
+
+    #[test]
+    fn mixing_modes_make_the_size_of_the_smallest_image() {
+        let images = [filled(8, 6, 0), filled(4, 2, 255)];
+        let options = CombineOptions {
+            mode: Mode::Average,
+            ..CombineOptions::default()
+        };
+        let averaged = combine(&images, options).unwrap().to_rgba8();
+        assert_eq!(averaged.dimensions(), (4, 2));
+        assert_eq!(*averaged.get_pixel(3, 1), Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn checkerboards_take_blocks_in_turn() {
+        let images = [filled(4, 4, 0), filled(4, 4, 100), filled(4, 4, 200)];
+        let options = CombineOptions {
+            block_size: 2,
+            ..CombineOptions::default()
+        };
+        let board = combine(&images, options).unwrap().to_rgba8();
+        let row = |y| (0..4).map(|x| board.get_pixel(x, y)[0]).collect::<Vec<_>>();
+        assert_eq!(row(0), [0, 0, 100, 100]);
+        assert_eq!(row(3), [100, 100, 200, 200]);
+    }
+
+    #[test]
+    fn gradients_and_masks_need_exactly_two_images() {
+        let images = [filled(2, 2, 0), filled(2, 2, 100), filled(2, 2, 200)];
+        let options = CombineOptions {
+            mode: Mode::Gradient,
+            ..CombineOptions::default()
+        };
+        assert!(matches!(
+            combine(&images, options),
+            Err(CombineError::NotTwoImages(3))
+        ));
+        assert!(combine(&images[..2], options).is_ok());
+
+        let mask = filled(2, 2, 255);
+        assert!(matches!(
+            combine_masked(&images, &mask, CombineOptions::default()),
+            Err(CombineError::NotTwoImages(3))
+        ));
+        assert!(matches!(
+            combine_masked(&images[..1], &mask, CombineOptions::default()),
+            Err(CombineError::NotTwoImages(1))
+        ));
+    }
+
+    #[test]
+    fn masks_take_the_first_image_where_white() {
+        let images = [filled(2, 1, 200), filled(2, 1, 0)];
+        let mask =
+            DynamicImage::ImageLuma8(image::GrayImage::from_raw(2, 1, vec![255, 0]).unwrap());
+        let masked = combine_masked(&images, &mask, CombineOptions::default())
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(masked.get_pixel(0, 0)[0], 200);
+        assert_eq!(masked.get_pixel(1, 0)[0], 0);
+    }
+}
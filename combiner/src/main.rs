@@ -1,143 +1,525 @@
-use image::ImageError;
-use image::{
-    imageops::FilterType::Triangle, io::Reader, DynamicImage, GenericImageView, ImageFormat,
+use clap::{error::ErrorKind, Args, CommandFactory, Parser, Subcommand};
+use combiner::{
+    combine, combine_masked, default_font, diff, draw_caption, group_images, load_font, load_image,
+    output_format, parse_color, parse_crop, parse_flip, parse_format, parse_fraction, parse_rotate,
+    parse_size, save_image, watermark, Align, Caption, CombineError, CombineOptions, Crop,
+    DiffOptions, Filter, Fit, Flip, Gradient, Mode, Pairing, Position, Preprocess, Rotate,
+    WatermarkOptions,
 };
-use std::convert::TryInto;
-use std::env::{args, Args};
-use std::fs::File;
-use std::io::BufReader;
-
-#[derive(Debug)]
-enum ImageDataErrors {
-    DifferentImageFormat,
-    BufferTooSmall,
-    ImageBufferSaveFailed(ImageError),
+use image::{DynamicImage, ImageError, ImageFormat, Rgba};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// Combines images into one by mixing their pixels. The larger images are
+/// resized to the smallest one first.
+#[derive(Parser, Debug)]
+#[command(
+    name = "combiner",
+    version,
+    about,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    combine: Option<CombineArgs>,
 }
 
-#[derive(Debug)]
-struct FloatingImage {
-    width: u32,
-    height: u32,
-    data: Vec<u8>,
-    name: String,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Lays a smaller image over a base image, like a logo in a corner.
+    Watermark(WatermarkArgs),
+    /// Compares two images of the same size pixel by pixel, printing how
+    /// many pixels differ and their PSNR and SSIM.
+    Diff(DiffArgs),
 }
 
-impl FloatingImage {
-    fn new(w: u32, h: u32, name: String) -> Self {
-        let buffer_capacity: u32 = h * w * 4;
-        let buffer: Vec<u8> = Vec::with_capacity(buffer_capacity.try_into().unwrap());
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// Image to compare, given once for each of the two.
+    #[arg(long = "in", value_name = "FILE", required = true)]
+    inputs: Vec<String>,
 
-        return FloatingImage {
-            width: w,
-            height: h,
-            data: buffer,
-            name: name,
-        };
-    }
+    /// File the first image is written to dimmed, with the pixels that
+    /// differ highlighted.
+    #[arg(long, value_name = "FILE")]
+    out: Option<String>,
 
-    fn set_data(&mut self, data: Vec<u8>) -> Result<(), ImageDataErrors> {
-        if data.len() > self.data.capacity() {
-            return Err(ImageDataErrors::BufferTooSmall);
-        }
+    /// Format to write the diff in instead, like png or jpg.
+    #[arg(long, value_name = "FORMAT", value_parser = parse_format)]
+    format: Option<ImageFormat>,
 
-        self.data = data;
-        return Ok(());
-    }
+    /// Most any channel of a pixel may differ by, from 0 to 255, for it to
+    /// count as the same.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    tolerance: u8,
+
+    /// Color the pixels that differ are painted in, as hex like ff00ff.
+    #[arg(long, value_name = "COLOR", default_value = "ff00ff", value_parser = parse_color)]
+    highlight: Rgba<u8>,
+
+    /// Brightness the pixels that are the same are kept at, from 0 for
+    /// black to 1 for unchanged.
+    #[arg(long, value_name = "FRACTION", default_value_t = 0.3, value_parser = parse_fraction)]
+    dim: f32,
+
+    /// Prints the results as JSON.
+    #[arg(long)]
+    json: bool,
+
+    /// Least SSIM, from 0 to 1, the images may have to pass. Less exits
+    /// with 1.
+    #[arg(long, value_name = "SSIM", value_parser = parse_fraction)]
+    threshold: Option<f32>,
 }
 
-fn main() -> Result<(), ImageDataErrors> {
-    let mut a: Args = args();
-    let first_path = a.nth(1).unwrap();
-    let second_path = a.nth(0).unwrap();
-    let third_path = a.nth(0).unwrap();
+#[derive(Args, Debug)]
+struct WatermarkArgs {
+    /// Image the watermark is laid over.
+    #[arg(long = "in", value_name = "FILE")]
+    base: String,
 
-    let (raw_im1, im1_format) = find_image(first_path);
-    let (raw_im2, im2_format) = find_image(second_path);
+    /// Image laid over the base.
+    #[arg(long, value_name = "FILE")]
+    mark: String,
 
-    if im1_format != im2_format {
-        return Err(ImageDataErrors::DifferentImageFormat);
-    }
+    /// File the watermarked image is written to, in the format of its
+    /// extension.
+    #[arg(long, value_name = "FILE")]
+    out: String,
 
-    let (im1, im2) = standardize_size(raw_im1, raw_im2);
-    let mut im_output = FloatingImage::new(im1.width(), im1.height(), third_path);
+    /// Format to write the watermarked image in instead, like png or jpg.
+    #[arg(long, value_name = "FORMAT", value_parser = parse_format)]
+    format: Option<ImageFormat>,
 
-    let combined_data = combine_images(im1, im2);
+    /// Where on the base the watermark goes.
+    #[arg(long, value_enum, default_value_t = Position::BottomRight)]
+    position: Position,
 
-    im_output.set_data(combined_data)?;
+    /// How much the watermark covers the base, from 0 to 1.
+    #[arg(long, default_value_t = 0.5, value_parser = parse_fraction)]
+    opacity: f32,
 
-    if let Err(e) = image::save_buffer_with_format(
-        im_output.name,
-        &im_output.data,
-        im_output.width,
-        im_output.height,
-        image::ColorType::Rgba8,
-        im1_format,
-    ) {
-        return Err(ImageDataErrors::ImageBufferSaveFailed(e));
-    }
+    /// Space between the watermark and the edges of the base, in pixels.
+    #[arg(long, value_name = "PIXELS", default_value_t = 16)]
+    margin: u32,
+
+    /// Largest part of the width and height of the base the watermark
+    /// takes, from 0 to 1. Bigger watermarks are scaled down to it.
+    #[arg(long, value_name = "FRACTION", default_value_t = 0.25, value_parser = parse_fraction)]
+    size: f32,
 
-    // println!("{:?}", im_output);
-    return Ok(());
+    /// Filter the watermark is scaled down with.
+    #[arg(long, value_enum, default_value_t = Filter::Triangle)]
+    filter: Filter,
 }
 
-fn find_image(filepath: String) -> (DynamicImage, ImageFormat) {
-    let image_reader: Reader<BufReader<File>> = Reader::open(filepath).unwrap();
-    let image_format = image_reader.format().unwrap();
-    let image: DynamicImage = image_reader.decode().unwrap();
+#[derive(Args, Debug)]
+struct CombineArgs {
+    /// Image to combine, given once for each of the two or more inputs.
+    #[arg(long = "in", value_name = "FILE", required_unless_present = "batch")]
+    inputs: Vec<String>,
 
-    return (image, image_format);
-}
+    /// Directory of images to combine in groups instead of --in, each
+    /// group written to --out as a directory. Exits with 5 if any group
+    /// fails.
+    #[arg(long, value_name = "DIR", conflicts_with = "inputs")]
+    batch: Option<PathBuf>,
+
+    /// How the images of the batch directory are grouped.
+    #[arg(long, value_enum, default_value_t = Pairing::Sequence, requires = "batch")]
+    pair: Pairing,
+
+    /// File the combined image is written to, in the format of its
+    /// extension. The directory the combined images are written to in a
+    /// batch, as png without --format.
+    #[arg(long, value_name = "FILE")]
+    out: PathBuf,
+
+    /// Format to write the combined image in instead, like png or jpg.
+    #[arg(long, value_name = "FORMAT", value_parser = parse_format)]
+    format: Option<ImageFormat>,
+
+    /// How the pixels of the inputs are mixed.
+    #[arg(long, value_enum, default_value_t = Mode::Checkerboard)]
+    mode: Mode,
+
+    /// Grayscale image taking pixels from the first input where it is
+    /// white and from the second where it is black, mixing them where gray.
+    /// Used instead of the mode.
+    #[arg(long, value_name = "FILE", conflicts_with = "mode")]
+    mask: Option<String>,
+
+    /// Weight of the first input in the blend mode, from 0 to 1, the second
+    /// getting the rest.
+    #[arg(long, default_value_t = 0.5, value_parser = parse_fraction)]
+    ratio: f32,
+
+    /// Direction the first input fades into the second in the gradient
+    /// mode.
+    #[arg(long, value_enum, default_value_t = Gradient::Horizontal)]
+    gradient: Gradient,
+
+    /// Fraction of the way along the gradient where the fade starts, from
+    /// 0 to 1.
+    #[arg(long, value_name = "FRACTION", default_value_t = 0., value_parser = parse_fraction)]
+    start: f32,
+
+    /// Fraction of the way along the gradient where the fade ends, from 0
+    /// to 1.
+    #[arg(long, value_name = "FRACTION", default_value_t = 1., value_parser = parse_fraction)]
+    end: f32,
+
+    /// Size of the squares of the checkerboard mode, in pixels.
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    block_size: u32,
+
+    /// Space between the images of the stitch modes, in pixels.
+    #[arg(long, value_name = "PIXELS", default_value_t = 0)]
+    gap: u32,
+
+    /// Color around the images of the stitch modes, as hex like ffffff or
+    /// ffffff80.
+    #[arg(long, value_name = "COLOR", default_value = "00000000", value_parser = parse_color)]
+    background: Rgba<u8>,
+
+    /// Side the images of the stitch-v mode line up on. They keep their
+    /// widths with it and are scaled to the narrowest without.
+    #[arg(long, value_enum)]
+    align: Option<Align>,
+
+    /// Cells in a row of the grid mode, enough for a square grid by default.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    cols: Option<u32>,
+
+    /// Size of the cells of the grid mode, like 320x240. The smallest image
+    /// is used by default.
+    #[arg(long, value_name = "WxH", value_parser = parse_size)]
+    cell_size: Option<(u32, u32)>,
+
+    /// Space around the cells of the grid mode, in pixels.
+    #[arg(long, value_name = "PIXELS", default_value_t = 0)]
+    padding: u32,
+
+    /// Filter the images are resized with, trading speed for quality.
+    #[arg(long, value_enum, default_value_t = Filter::Triangle)]
+    filter: Filter,
 
-fn get_smallest_dimension(dim1: (u32, u32), dim2: (u32, u32)) -> (u32, u32) {
-    let pixel1 = dim1.0 * dim1.1;
-    let pixel2 = dim2.0 * dim2.1;
-    return if pixel1 < pixel2 { dim1 } else { dim2 };
+    /// How the mixed images are resized to the smallest one when their
+    /// aspect ratios differ.
+    #[arg(long, value_enum, default_value_t = Fit::Stretch)]
+    fit: Fit,
+
+    /// Color around the images the pad fit leaves, as hex like ffffff.
+    #[arg(long, value_name = "COLOR", default_value = "00000000", value_parser = parse_color)]
+    pad_color: Rgba<u8>,
+
+    /// Region x,y,width,height to keep of the inputs before combining, or
+    /// of one input counting from 0 with :index, like 0,0,200,100:1. Given
+    /// once for each crop.
+    #[arg(long, value_name = "X,Y,W,H[:INDEX]", value_parser = parse_crop)]
+    crop: Vec<Crop>,
+
+    /// Degrees of 90, 180 or 270 to turn the inputs clockwise by after
+    /// cropping, or one input with :index, like 90:1.
+    #[arg(long, value_name = "DEGREES[:INDEX]", value_parser = parse_rotate)]
+    rotate: Vec<Rotate>,
+
+    /// Mirrors the inputs after rotating, left to right with h and top to
+    /// bottom with v, or one input with :index, like h:0.
+    #[arg(long, value_name = "h|v[:INDEX]", value_parser = parse_flip)]
+    flip: Vec<Flip>,
+
+    /// Text written on the combined image, like a label of the inputs.
+    #[arg(long, value_name = "TEXT")]
+    caption: Option<String>,
+
+    /// TrueType or OpenType font the caption is written in instead of the
+    /// bundled Fira Sans Bold.
+    #[arg(long, value_name = "FILE")]
+    font: Option<String>,
+
+    /// Where on the image the caption goes.
+    #[arg(long, value_enum, default_value_t = Position::Bottom)]
+    caption_position: Position,
+
+    /// Height of the caption text, in pixels.
+    #[arg(long, value_name = "PIXELS", default_value_t = 32.)]
+    caption_size: f32,
+
+    /// Color of the caption text, as hex like ffffff.
+    #[arg(long, value_name = "COLOR", default_value = "ffffff", value_parser = parse_color)]
+    caption_color: Rgba<u8>,
+
+    /// Color of a line around the letters of the caption, none by default.
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    caption_outline: Option<Rgba<u8>>,
+
+    /// Width of the line around the letters, in pixels.
+    #[arg(long, value_name = "PIXELS", default_value_t = 2)]
+    caption_outline_width: u32,
 }
 
-fn standardize_size(im1: DynamicImage, im2: DynamicImage) -> (DynamicImage, DynamicImage) {
-    let (w, h) = get_smallest_dimension(im1.dimensions(), im2.dimensions());
-    println!("width: {}, height: {}\n", w, h);
+/// Exit code of images compared less alike than the threshold.
+const EXIT_DIFFERENT: u8 = 1;
+/// Exit codes of invalid arguments, the same as clap's, of files that can't
+/// be read or written and of images that can't be decoded or encoded.
+const EXIT_USAGE: u8 = 2;
+const EXIT_IO: u8 = 3;
+const EXIT_IMAGE: u8 = 4;
+/// Exit code of a batch in which some groups couldn't be combined.
+const EXIT_BATCH: u8 = 5;
 
-    if im2.dimensions() == (w, h) {
-        return (im1.resize_exact(w, h, Triangle), im2);
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match (cli.command, cli.combine) {
+        (Some(Command::Watermark(args)), _) => run_watermark(args).map(|()| ExitCode::SUCCESS),
+        (Some(Command::Diff(args)), _) => run_diff(args),
+        (None, Some(args)) => run(args),
+        (None, None) => Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "give the images to combine with --in and --out, or a subcommand",
+            )
+            .exit(),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(exit_code(&e))
+        }
     }
+}
 
-    return (im1, im2.resize_exact(w, h, Triangle));
+fn exit_code(error: &CombineError) -> u8 {
+    match error {
+        CombineError::TooFewImages(_)
+        | CombineError::NotTwoImages(_)
+        | CombineError::UnknownOutputFormat(_)
+        | CombineError::NoSuchInput(..)
+        | CombineError::CropOutside(..) => EXIT_USAGE,
+        CombineError::Io(..) | CombineError::Encode(_, ImageError::IoError(_)) => EXIT_IO,
+        CombineError::UnknownFormat(_)
+        | CombineError::Decode(..)
+        | CombineError::Encode(..)
+        | CombineError::UnknownFont(_)
+        | CombineError::SizeMismatch(..) => EXIT_IMAGE,
+    }
 }
 
-fn combine_images(im1: DynamicImage, im2: DynamicImage) -> Vec<u8> {
-    let vec1 = im1.to_rgba8().into_vec();
-    let vec2 = im2.to_rgba8().into_vec();
+fn run(cli: CombineArgs) -> Result<ExitCode, CombineError> {
+    if let Some(dir) = &cli.batch {
+        return run_batch(&cli, dir);
+    }
+    if cli.inputs.len() < 2 {
+        Cli::command()
+            .error(
+                ErrorKind::TooFewValues,
+                format!(
+                    "--in must be given at least 2 times, got {}",
+                    cli.inputs.len()
+                ),
+            )
+            .exit();
+    }
 
-    return alternate_pixels(vec1, vec2);
+    // A bad extension of the output is refused before any work is done.
+    let format = output_format(&cli.out, cli.format)?;
+    let combined = combine_files(&cli, &cli.inputs)?;
+    save_image(&combined, &cli.out, Some(format))?;
+    Ok(ExitCode::SUCCESS)
 }
 
-fn alternate_pixels(v1: Vec<u8>, v2: Vec<u8>) -> Vec<u8> {
-    let mut combined = vec![0u8; v1.len()];
+/// Combines every group of images in `dir` into a file in the output
+/// directory, going on past failures and reporting on every group at the
+/// end. Groups of a single image, such as the last of an odd number in
+/// sequence, are skipped with a warning rather than failed.
+fn run_batch(cli: &CombineArgs, dir: &Path) -> Result<ExitCode, CombineError> {
+    let (groups, singles): (Vec<_>, Vec<_>) = group_images(dir, cli.pair)?
+        .into_iter()
+        .partition(|group| group.inputs.len() >= 2);
+    for group in &singles {
+        eprintln!("skipped: {}: only one image", group.name);
+    }
+    fs::create_dir_all(&cli.out).map_err(|e| CombineError::Io(cli.out.clone(), e))?;
+    let extension = cli
+        .format
+        .and_then(|format| format.extensions_str().first())
+        .unwrap_or(&"png");
 
-    let mut i = 0;
-    while i < v1.len() {
-        if i % 8 == 0 {
-            combined.splice(i..=i + 3, set_rgba(&v1, i, i + 3));
-        } else {
-            combined.splice(i..=i + 3, set_rgba(&v2, i, i + 3));
+    let results: Vec<_> = groups
+        .iter()
+        .map(|group| {
+            let out = cli.out.join(format!("{}.{}", group.name, extension));
+            let result = combine_files(cli, &group.inputs)
+                .and_then(|combined| save_image(&combined, &out, cli.format));
+            (out, result)
+        })
+        .collect();
+
+    let mut failures = 0;
+    for (out, result) in &results {
+        match result {
+            Ok(()) => println!("ok: {}", out.display()),
+            Err(e) => {
+                eprintln!("failed: {}: {}", out.display(), e);
+                failures += 1;
+            }
         }
-        i += 4
     }
-
-    return combined;
+    println!(
+        "\n{} combined, {} failed, {} skipped",
+        results.len() - failures,
+        failures,
+        singles.len()
+    );
+    Ok(if failures == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_BATCH)
+    })
 }
 
-fn set_rgba(v: &Vec<u8>, start: usize, end: usize) -> Vec<u8> {
-    let mut rgba: Vec<u8> = Vec::new();
-    for i in start..=end {
-        let val: u8 = match v.get(i) {
-            Some(d) => *d,
-            None => panic!("Index out of bounds"),
+/// The images at `inputs` changed and combined as the arguments say.
+fn combine_files(
+    cli: &CombineArgs,
+    inputs: &[impl AsRef<Path>],
+) -> Result<DynamicImage, CombineError> {
+    let mut images = inputs
+        .iter()
+        .map(load_image)
+        .collect::<Result<Vec<_>, _>>()?;
+    let preprocess = Preprocess {
+        crops: cli.crop.clone(),
+        rotations: cli.rotate.clone(),
+        flips: cli.flip.clone(),
+    };
+    preprocess.apply(&mut images)?;
+
+    let options = CombineOptions {
+        mode: cli.mode,
+        block_size: cli.block_size,
+        gap: cli.gap,
+        background: cli.background,
+        align: cli.align,
+        columns: cli.cols,
+        cell_size: cli.cell_size,
+        padding: cli.padding,
+        filter: cli.filter,
+        fit: cli.fit,
+        pad_color: cli.pad_color,
+        ratio: cli.ratio,
+        gradient: cli.gradient,
+        gradient_start: cli.start,
+        gradient_end: cli.end,
+    };
+    let mut combined = match &cli.mask {
+        Some(path) => combine_masked(&images, &load_image(path)?, options)?,
+        None => combine(&images, options)?,
+    };
+    if let Some(text) = &cli.caption {
+        let font = match &cli.font {
+            Some(path) => load_font(path)?,
+            None => default_font(),
+        };
+        let caption = Caption {
+            text: text.clone(),
+            position: cli.caption_position,
+            size: cli.caption_size,
+            color: cli.caption_color,
+            outline: cli.caption_outline,
+            outline_width: cli.caption_outline_width,
         };
+        let mut image = combined.to_rgba8();
+        draw_caption(&mut image, &caption, &font);
+        combined = DynamicImage::ImageRgba8(image);
+    }
+    Ok(combined)
+}
 
-        rgba.push(val);
+fn run_watermark(args: WatermarkArgs) -> Result<(), CombineError> {
+    let format = output_format(&args.out, args.format)?;
+    let base = load_image(&args.base)?;
+    let mark = load_image(&args.mark)?;
+    let options = WatermarkOptions {
+        position: args.position,
+        opacity: args.opacity,
+        margin: args.margin,
+        size: args.size,
+        filter: args.filter,
+    };
+    let watermarked = watermark(&base, &mark, options);
+    save_image(&watermarked, args.out, Some(format))
+}
+
+fn run_diff(args: DiffArgs) -> Result<ExitCode, CombineError> {
+    if args.inputs.len() != 2 {
+        let mut command = Cli::command();
+        command.build();
+        command
+            .find_subcommand_mut("diff")
+            .expect("diff should be a subcommand")
+            .error(
+                ErrorKind::WrongNumberOfValues,
+                format!("diff --in must be given 2 times, got {}", args.inputs.len()),
+            )
+            .exit();
+    }
+
+    let format = match &args.out {
+        Some(out) => Some(output_format(out, args.format)?),
+        None => None,
+    };
+    let a = load_image(&args.inputs[0])?;
+    let b = load_image(&args.inputs[1])?;
+    let options = DiffOptions {
+        tolerance: args.tolerance,
+        highlight: args.highlight,
+        dim: args.dim,
+    };
+    let diff = diff(&a, &b, options)?;
+    let passed = args
+        .threshold
+        .is_none_or(|threshold| diff.ssim >= threshold as f64);
+    if args.json {
+        // JSON has no infinity, identical images get a PSNR of null.
+        let psnr = if diff.psnr.is_finite() {
+            format!("{:.4}", diff.psnr)
+        } else {
+            "null".to_string()
+        };
+        println!(
+            "{{\"changed\": {}, \"total\": {}, \"percentage\": {:.4}, \"psnr\": {}, \"ssim\": {:.6}, \"passed\": {}}}",
+            diff.changed,
+            diff.total,
+            diff.percentage(),
+            psnr,
+            diff.ssim,
+            passed
+        );
+    } else {
+        println!(
+            "{} of {} pixels differ ({:.2}%)",
+            diff.changed,
+            diff.total,
+            diff.percentage()
+        );
+        println!("PSNR: {:.2} dB", diff.psnr);
+        println!("SSIM: {:.4}", diff.ssim);
     }
 
-    return rgba;
+    if let Some(out) = args.out {
+        save_image(&DynamicImage::ImageRgba8(diff.image), out, format)?;
+    }
+    if passed {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(EXIT_DIFFERENT))
+    }
 }
@@ -0,0 +1,122 @@
+use image::RgbaImage;
+
+/// Side of the square windows SSIM is measured in, and the step between
+/// them.
+const WINDOW: u32 = 8;
+const WINDOW_STEP: u32 = 4;
+
+/// Peak signal-to-noise ratio of `b` to `a` over their color channels, in
+/// decibels. Infinite for the same colors.
+pub(crate) fn psnr(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    let mut squares = 0.;
+    let mut count = 0u64;
+    for (pa, pb) in a.pixels().zip(b.pixels()) {
+        for i in 0..3 {
+            let error = pa[i] as f64 - pb[i] as f64;
+            squares += error * error;
+            count += 1;
+        }
+    }
+    let mean = squares / count.max(1) as f64;
+    if mean == 0. {
+        return f64::INFINITY;
+    }
+    10. * (255. * 255. / mean).log10()
+}
+
+/// Brightness of every pixel, from 0 to 255.
+fn luma(image: &RgbaImage) -> Vec<f64> {
+    image
+        .pixels()
+        .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+        .collect()
+}
+
+/// Structural similarity of the brightness of `a` and `b`, from 1 for the
+/// same down to about 0 for unrelated images. The mean over overlapping
+/// square windows.
+pub(crate) fn ssim(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    const C1: f64 = (0.01 * 255.) * (0.01 * 255.);
+    const C2: f64 = (0.03 * 255.) * (0.03 * 255.);
+
+    let (width, height) = a.dimensions();
+    let (la, lb) = (luma(a), luma(b));
+    let (window_w, window_h) = (WINDOW.min(width), WINDOW.min(height));
+    let starts = |length: u32, window: u32| (0..=length - window).step_by(WINDOW_STEP as usize);
+
+    let mut total = 0.;
+    let mut windows = 0;
+    for y0 in starts(height, window_h) {
+        for x0 in starts(width, window_w) {
+            let indices = (y0..y0 + window_h)
+                .flat_map(|y| (x0..x0 + window_w).map(move |x| (y * width + x) as usize));
+            // Images without pixels have one empty window, the same in both.
+            let n = (window_w * window_h).max(1) as f64;
+            let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0., 0., 0., 0., 0.);
+            for i in indices {
+                let (va, vb) = (la[i], lb[i]);
+                sa += va;
+                sb += vb;
+                saa += va * va;
+                sbb += vb * vb;
+                sab += va * vb;
+            }
+            let (ma, mb) = (sa / n, sb / n);
+            let (va, vb, cov) = (saa / n - ma * ma, sbb / n - mb * mb, sab / n - ma * mb);
+            total += ((2. * ma * mb + C1) * (2. * cov + C2))
+                / ((ma * ma + mb * mb + C1) * (va + vb + C2));
+            windows += 1;
+        }
+    }
+    total / windows.max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn gray(width: u32, height: u32, value: u8) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([value, value, value, 255]))
+    }
+
+    #[test]
+    fn identical_images_have_infinite_psnr_and_ssim_of_one() {
+        let image = RgbaImage::from_fn(20, 12, |x, y| Rgba([x as u8 * 10, y as u8 * 20, 7, 255]));
+        assert_eq!(psnr(&image, &image), f64::INFINITY);
+        assert!((ssim(&image, &image) - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_uniform_difference_gives_the_expected_metrics() {
+        let (a, b) = (gray(16, 16, 0), gray(16, 16, 10));
+        // A mean squared error of 100 over the color channels.
+        let expected_psnr = 10. * (255f64 * 255. / 100.).log10();
+        assert!((psnr(&a, &b) - expected_psnr).abs() < 1e-9);
+        // Flat windows only differ in their means of 0 and 10.
+        let c1 = (0.01 * 255f64).powi(2);
+        assert!((ssim(&a, &b) - c1 / (100. + c1)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn alpha_is_left_out_of_psnr() {
+        let mut b = gray(4, 4, 50);
+        b.pixels_mut().for_each(|pixel| pixel[3] = 0);
+        assert_eq!(psnr(&gray(4, 4, 50), &b), f64::INFINITY);
+    }
+
+    #[test]
+    fn images_without_pixels_count_as_the_same() {
+        let empty = RgbaImage::new(0, 0);
+        assert_eq!(psnr(&empty, &empty), f64::INFINITY);
+        assert_eq!(ssim(&empty, &empty), 1.);
+        let line = RgbaImage::new(5, 0);
+        assert_eq!(ssim(&line, &line), 1.);
+    }
+
+    #[test]
+    fn images_smaller_than_a_window_are_measured_whole() {
+        let (a, b) = (gray(3, 2, 100), gray(3, 2, 100));
+        assert!((ssim(&a, &b) - 1.).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,185 @@
+use clap::ValueEnum;
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, GenericImageView, Rgba, RgbaImage,
+};
+
+/// Filter images are resized with, from the fastest to the sharpest.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Filter {
+    /// Nearest neighbor, fast and blocky.
+    Nearest,
+    /// Linear, smooth.
+    #[default]
+    Triangle,
+    /// Cubic, sharper than linear.
+    #[value(name = "catmullrom")]
+    CatmullRom,
+    /// Gaussian, soft.
+    Gaussian,
+    /// Lanczos with a window of 3, the sharpest and slowest.
+    Lanczos3,
+}
+
+impl Filter {
+    pub fn filter_type(self) -> FilterType {
+        match self {
+            Filter::Nearest => FilterType::Nearest,
+            Filter::Triangle => FilterType::Triangle,
+            Filter::CatmullRom => FilterType::CatmullRom,
+            Filter::Gaussian => FilterType::Gaussian,
+            Filter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// How an image is resized to a size of another aspect ratio.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fit {
+    /// Stretches the image to the size, distorting it.
+    #[default]
+    Stretch,
+    /// Fits the whole image in the size, centered on a padded canvas.
+    Pad,
+    /// Covers the size with the image, cutting off what sticks out around
+    /// the center.
+    Crop,
+}
+
+/// `length` scaled by `to / from`, rounded and at least 1.
+pub(crate) fn scale(length: u32, to: u32, from: u32) -> u32 {
+    ((length as u64 * to as u64 + from as u64 / 2) / from as u64).max(1) as u32
+}
+
+/// Largest size of the aspect ratio of `(w, h)` that fits in `(width,
+/// height)`.
+pub(crate) fn contained_size((w, h): (u32, u32), (width, height): (u32, u32)) -> (u32, u32) {
+    if w as u64 * height as u64 <= h as u64 * width as u64 {
+        (scale(w, height, h), height)
+    } else {
+        (width, scale(h, width, w))
+    }
+}
+
+/// Smallest size of the aspect ratio of `(w, h)` that covers `(width,
+/// height)`.
+fn covering_size((w, h): (u32, u32), (width, height): (u32, u32)) -> (u32, u32) {
+    if w as u64 * height as u64 >= h as u64 * width as u64 {
+        (scale(w, height, h).max(width), height)
+    } else {
+        (width, scale(h, width, w).max(height))
+    }
+}
+
+/// `image` resized to `width` by `height` with `filter` as `fit` says,
+/// padded with `pad` where it doesn't fill the size.
+pub(crate) fn resize(
+    image: &DynamicImage,
+    (width, height): (u32, u32),
+    fit: Fit,
+    filter: FilterType,
+    pad: Rgba<u8>,
+) -> RgbaImage {
+    if image.dimensions() == (width, height) {
+        return image.to_rgba8();
+    }
+
+    match fit {
+        Fit::Stretch => image.resize_exact(width, height, filter).to_rgba8(),
+        Fit::Pad => {
+            let (w, h) = contained_size(image.dimensions(), (width, height));
+            let mut canvas = RgbaImage::from_pixel(width, height, pad);
+            let resized = image.resize_exact(w, h, filter).to_rgba8();
+            imageops::overlay(&mut canvas, &resized, (width - w) / 2, (height - h) / 2);
+            canvas
+        }
+        Fit::Crop => {
+            let (w, h) = covering_size(image.dimensions(), (width, height));
+            let mut resized = image.resize_exact(w, h, filter).to_rgba8();
+            imageops::crop(
+                &mut resized,
+                (w - width) / 2,
+                (h - height) / 2,
+                width,
+                height,
+            )
+            .to_image()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    /// Image red on the left half and blue on the right.
+    fn halves(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                RED
+            } else {
+                BLUE
+            }
+        }))
+    }
+
+    #[test]
+    fn scales_lengths_rounding_to_at_least_one() {
+        assert_eq!(scale(10, 3, 4), 8);
+        assert_eq!(scale(1, 1, 100), 1);
+        assert_eq!(scale(100, 1, 1), 100);
+    }
+
+    #[test]
+    fn contained_and_covering_sizes_keep_the_aspect_ratio() {
+        assert_eq!(contained_size((200, 100), (50, 50)), (50, 25));
+        assert_eq!(contained_size((100, 200), (50, 50)), (25, 50));
+        assert_eq!(covering_size((200, 100), (50, 50)), (100, 50));
+        assert_eq!(covering_size((100, 200), (50, 50)), (50, 100));
+    }
+
+    #[test]
+    fn stretching_fills_the_size() {
+        let resized = resize(
+            &halves(4, 2),
+            (2, 6),
+            Fit::Stretch,
+            FilterType::Nearest,
+            BLUE,
+        );
+        assert_eq!(resized.dimensions(), (2, 6));
+        assert_eq!(*resized.get_pixel(0, 5), RED);
+        assert_eq!(*resized.get_pixel(1, 0), BLUE);
+    }
+
+    #[test]
+    fn padding_centers_the_whole_image() {
+        let pad = Rgba([0, 255, 0, 255]);
+        let resized = resize(&halves(4, 2), (4, 4), Fit::Pad, FilterType::Nearest, pad);
+        assert_eq!(resized.dimensions(), (4, 4));
+        assert_eq!(*resized.get_pixel(0, 0), pad);
+        assert_eq!(*resized.get_pixel(0, 1), RED);
+        assert_eq!(*resized.get_pixel(3, 2), BLUE);
+        assert_eq!(*resized.get_pixel(3, 3), pad);
+    }
+
+    #[test]
+    fn cropping_cuts_off_around_the_center() {
+        let resized = resize(&halves(4, 2), (2, 2), Fit::Crop, FilterType::Nearest, BLUE);
+        assert_eq!(resized.dimensions(), (2, 2));
+        assert_eq!(*resized.get_pixel(0, 0), RED);
+        assert_eq!(*resized.get_pixel(1, 1), BLUE);
+    }
+
+    #[test]
+    fn images_of_the_size_are_kept() {
+        let image = halves(4, 2);
+        for fit in [Fit::Stretch, Fit::Pad, Fit::Crop] {
+            let resized = resize(&image, (4, 2), fit, FilterType::Lanczos3, BLUE);
+            assert_eq!(resized, image.to_rgba8());
+        }
+    }
+}
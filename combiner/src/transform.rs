@@ -0,0 +1,246 @@
+use crate::{CombineError, Result};
+use image::{DynamicImage, GenericImageView};
+use std::fmt;
+
+/// Region of an input image to keep, of the input at `index` or of every
+/// input without one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub index: Option<usize>,
+}
+
+impl fmt::Display for Crop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// Splits `text` into what comes before an optional `:index` and the index.
+fn split_index(text: &str) -> std::result::Result<(&str, Option<usize>), String> {
+    match text.rsplit_once(':') {
+        Some((value, index)) => match index.parse() {
+            Ok(index) => Ok((value, Some(index))),
+            Err(_) => Err(format!("expected an input index after :, got {}", index)),
+        },
+        None => Ok((text, None)),
+    }
+}
+
+/// Reads a crop written as `x,y,width,height`, optionally followed by
+/// `:index` to crop only that input, counting from 0.
+pub fn parse_crop(text: &str) -> std::result::Result<Crop, String> {
+    let (region, index) = split_index(text)?;
+    let numbers = region
+        .split(',')
+        .map(|number| number.trim().parse())
+        .collect::<std::result::Result<Vec<u32>, _>>();
+    match numbers.as_deref() {
+        Ok(&[x, y, width, height]) if width > 0 && height > 0 => Ok(Crop {
+            x,
+            y,
+            width,
+            height,
+            index,
+        }),
+        _ => Err(format!(
+            "expected a crop like 10,20,300,200 or 10,20,300,200:1, got {}",
+            text
+        )),
+    }
+}
+
+/// Clockwise rotation of the input at `index`, or of every input without
+/// one, by `degrees` of 90, 180 or 270.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rotate {
+    pub degrees: u32,
+    pub index: Option<usize>,
+}
+
+/// Reads a rotation of `90`, `180` or `270` degrees, optionally followed by
+/// `:index` to rotate only that input.
+pub fn parse_rotate(text: &str) -> std::result::Result<Rotate, String> {
+    let (degrees, index) = split_index(text)?;
+    match degrees.parse() {
+        Ok(degrees @ (90 | 180 | 270)) => Ok(Rotate { degrees, index }),
+        _ => Err(format!("expected 90, 180 or 270 degrees, got {}", degrees)),
+    }
+}
+
+/// Direction an image is mirrored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    /// Left to right.
+    Horizontal,
+    /// Top to bottom.
+    Vertical,
+}
+
+/// Mirroring of the input at `index`, or of every input without one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Flip {
+    pub axis: Axis,
+    pub index: Option<usize>,
+}
+
+/// Reads a flip `h` or `v`, optionally followed by `:index` to flip only
+/// that input.
+pub fn parse_flip(text: &str) -> std::result::Result<Flip, String> {
+    let (axis, index) = split_index(text)?;
+    let axis = match axis {
+        "h" => Axis::Horizontal,
+        "v" => Axis::Vertical,
+        _ => return Err(format!("expected h or v, got {}", axis)),
+    };
+    Ok(Flip { axis, index })
+}
+
+/// Changes made to the input images before they are combined: first the
+/// crops, then the rotations and then the flips, each in the order given.
+#[derive(Clone, Debug, Default)]
+pub struct Preprocess {
+    pub crops: Vec<Crop>,
+    pub rotations: Vec<Rotate>,
+    pub flips: Vec<Flip>,
+}
+
+impl Preprocess {
+    /// Applies the changes to `images`, failing on those of inputs that
+    /// aren't there or don't fit them.
+    pub fn apply(&self, images: &mut [DynamicImage]) -> Result<()> {
+        for crop in &self.crops {
+            for i in targets(crop.index, images.len())? {
+                let (w, h) = images[i].dimensions();
+                if crop.x as u64 + crop.width as u64 > w as u64
+                    || crop.y as u64 + crop.height as u64 > h as u64
+                {
+                    return Err(CombineError::CropOutside(*crop, i, (w, h)));
+                }
+                images[i] = images[i].crop_imm(crop.x, crop.y, crop.width, crop.height);
+            }
+        }
+        for rotate in &self.rotations {
+            for i in targets(rotate.index, images.len())? {
+                images[i] = match rotate.degrees {
+                    90 => images[i].rotate90(),
+                    180 => images[i].rotate180(),
+                    _ => images[i].rotate270(),
+                };
+            }
+        }
+        for flip in &self.flips {
+            for i in targets(flip.index, images.len())? {
+                images[i] = match flip.axis {
+                    Axis::Horizontal => images[i].fliph(),
+                    Axis::Vertical => images[i].flipv(),
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Indices of the images a change is applied to, that at `index` or all of
+/// the `count` without one.
+fn targets(index: Option<usize>, count: usize) -> Result<std::ops::Range<usize>> {
+    match index {
+        Some(index) if index >= count => Err(CombineError::NoSuchInput(index, count)),
+        Some(index) => Ok(index..index + 1),
+        None => Ok(0..count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([x as u8, y as u8, 0, 255])
+        }))
+    }
+
+    #[test]
+    fn reads_crops_for_all_or_one_input() {
+        assert_eq!(
+            parse_crop("10,20,30,40"),
+            Ok(Crop {
+                x: 10,
+                y: 20,
+                width: 30,
+                height: 40,
+                index: None,
+            })
+        );
+        assert_eq!(parse_crop("1, 2, 3, 4:1").unwrap().index, Some(1));
+        for text in ["1,2,3", "1,2,3,4,5", "1,2,0,4", "1,2,3,4:x", "a,b,c,d", ""] {
+            assert!(parse_crop(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn reads_rotations_and_flips() {
+        assert_eq!(
+            parse_rotate("270:0"),
+            Ok(Rotate {
+                degrees: 270,
+                index: Some(0),
+            })
+        );
+        assert!(parse_rotate("45").is_err());
+        assert!(parse_rotate("90:").is_err());
+        assert_eq!(
+            parse_flip("v"),
+            Ok(Flip {
+                axis: Axis::Vertical,
+                index: None,
+            })
+        );
+        assert_eq!(parse_flip("h:2").unwrap().axis, Axis::Horizontal);
+        assert!(parse_flip("x").is_err());
+    }
+
+    #[test]
+    fn crops_then_rotates_then_flips_the_picked_inputs() {
+        let mut images = [image(10, 8), image(10, 8)];
+        let preprocess = Preprocess {
+            crops: vec![parse_crop("2,1,4,3:1").unwrap()],
+            rotations: vec![parse_rotate("90").unwrap()],
+            flips: vec![parse_flip("h:1").unwrap()],
+        };
+        preprocess.apply(&mut images).unwrap();
+
+        assert_eq!(images[0].dimensions(), (8, 10));
+        assert_eq!(images[1].dimensions(), (3, 4));
+        // The top left pixel of the crop is turned to the top right, and
+        // flipped back to the top left.
+        assert_eq!(images[1].get_pixel(0, 0), Rgba([2, 1, 0, 255]));
+    }
+
+    #[test]
+    fn fails_on_missing_inputs_and_crops_past_the_edges() {
+        let mut images = [image(10, 8), image(4, 4)];
+        let crop = Preprocess {
+            crops: vec![parse_crop("0,0,5,5").unwrap()],
+            ..Preprocess::default()
+        };
+        assert!(matches!(
+            crop.apply(&mut images),
+            Err(CombineError::CropOutside(_, 1, (4, 4)))
+        ));
+
+        let flip = Preprocess {
+            flips: vec![parse_flip("h:2").unwrap()],
+            ..Preprocess::default()
+        };
+        assert!(matches!(
+            flip.apply(&mut images),
+            Err(CombineError::NoSuchInput(2, 2))
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{resize::contained_size, BlendMode, Filter};
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView, Rgba};
+
+/// Where on an image a watermark or caption goes.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Position {
+    TopLeft,
+    Top,
+    TopRight,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl Position {
+    /// Top left corner of something of size `(w, h)` placed here on a canvas of
+    /// `(width, height)`, `margin` pixels from the edges it is at if it fits.
+    pub(crate) fn place(
+        self,
+        (width, height): (u32, u32),
+        (w, h): (u32, u32),
+        margin: u32,
+    ) -> (u32, u32) {
+        let left = margin.min(width.saturating_sub(w));
+        let top = margin.min(height.saturating_sub(h));
+        let right = width.saturating_sub(w + margin).max(left);
+        let bottom = height.saturating_sub(h + margin).max(top);
+        let center = width.saturating_sub(w) / 2;
+        match self {
+            Position::TopLeft => (left, top),
+            Position::Top => (center, top),
+            Position::TopRight => (right, top),
+            Position::BottomLeft => (left, bottom),
+            Position::Bottom => (center, bottom),
+            Position::BottomRight => (right, bottom),
+            Position::Center => (center, height.saturating_sub(h) / 2),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WatermarkOptions {
+    pub position: Position,
+    /// How much the watermark covers the base, from 0 for not at all to 1
+    /// for as much as its own alpha.
+    pub opacity: f32,
+    /// Space between the watermark and the edges of the base, in pixels.
+    pub margin: u32,
+    /// Largest part of the width and height of the base the watermark
+    /// takes, bigger ones are scaled down to it.
+    pub size: f32,
+    pub filter: Filter,
+}
+
+impl Default for WatermarkOptions {
+    fn default() -> WatermarkOptions {
+        WatermarkOptions {
+            position: Position::default(),
+            opacity: 0.5,
+            margin: 16,
+            size: 0.25,
+            filter: Filter::default(),
+        }
+    }
+}
+
+/// `mark` laid over `base` as the options say.
+pub fn watermark(
+    base: &DynamicImage,
+    mark: &DynamicImage,
+    options: WatermarkOptions,
+) -> DynamicImage {
+    let mut canvas = base.to_rgba8();
+    let (width, height) = canvas.dimensions();
+    let fraction = |length: u32| ((length as f32 * options.size) as u32).max(1);
+    let bounds = (fraction(width), fraction(height));
+
+    let (w, h) = mark.dimensions();
+    let mark = if w <= bounds.0 && h <= bounds.1 {
+        mark.to_rgba8()
+    } else {
+        let (w, h) = contained_size((w, h), bounds);
+        mark.resize_exact(w, h, options.filter.filter_type())
+            .to_rgba8()
+    };
+
+    let (x, y) = options
+        .position
+        .place((width, height), mark.dimensions(), options.margin);
+
+    let opacity = options.opacity.clamp(0., 1.);
+    for (dx, dy, &pixel) in mark.enumerate_pixels() {
+        let (px, py) = (x + dx, y + dy);
+        if px >= width || py >= height {
+            continue;
+        }
+        let Rgba([r, g, b, a]) = pixel;
+        let top = Rgba([r, g, b, (a as f32 * opacity).round() as u8]);
+        let bottom = canvas.get_pixel_mut(px, py);
+        *bottom = BlendMode::Alpha.blend(*bottom, top);
+    }
+    DynamicImage::ImageRgba8(canvas)
+}
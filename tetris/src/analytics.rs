@@ -0,0 +1,246 @@
+//! Statistics of the games played, recorded once the player turns them on in
+//! the settings. Every game that ends is kept in `sessions.json` in the
+//! config directory, which the stats screen sums up. Nothing leaves the
+//! machine.
+
+use crate::{
+    events::{FinesseChecked, Finished, GameOver, LinesCleared},
+    game::{start_game, Autoplay},
+    records,
+    replays::Playback,
+    settings::Settings,
+    storage, GameState,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{io, mem, time::Duration};
+use tetris_core::{lines_per_minute, Game, Mode};
+
+const FILE_NAME: &str = "sessions.json";
+/// Games compared on the stats screen, the latest ones against those before.
+pub const TREND_GAMES: usize = 10;
+
+pub struct AnalyticsPlugin;
+
+impl Plugin for AnalyticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Analytics::load())
+            .init_resource::<SessionTracker>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(start_session.after(start_game)),
+            )
+            .add_system(track_session)
+            .add_system(record_session.after(track_session));
+    }
+}
+
+/// Lines cleared at once and T-spins, over one or more games.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClearCounts {
+    pub singles: u32,
+    pub doubles: u32,
+    pub triples: u32,
+    pub tetrises: u32,
+    pub t_spins: u32,
+}
+
+impl ClearCounts {
+    fn add(&mut self, other: &ClearCounts) {
+        self.singles += other.singles;
+        self.doubles += other.doubles;
+        self.triples += other.triples;
+        self.tetrises += other.tetrises;
+        self.t_spins += other.t_spins;
+    }
+}
+
+/// A game played to its end.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    /// Day the game was played, as `YYYY-MM-DD`.
+    pub date: String,
+    pub mode: Mode,
+    pub seconds: f32,
+    pub score: u32,
+    pub lines: u32,
+    pub pieces: u32,
+    pub finesse_errors: u32,
+    #[serde(default)]
+    pub clears: ClearCounts,
+}
+
+/// Every recorded game, oldest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Analytics {
+    pub sessions: Vec<Session>,
+}
+
+impl Analytics {
+    /// Loads the recorded games, starting without any when the file is
+    /// missing or unreadable.
+    pub fn load() -> Analytics {
+        let json = match storage::read(FILE_NAME) {
+            Some(json) => json,
+            None => return Analytics::default(),
+        };
+        match serde_json::from_str(&json) {
+            Ok(analytics) => analytics,
+            Err(e) => {
+                eprintln!("ignoring invalid {}: {}", FILE_NAME, e);
+                Analytics::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        storage::write(FILE_NAME, &json)
+    }
+
+    /// Sums of every recorded game.
+    pub fn lifetime(&self) -> Totals {
+        Totals::of(&self.sessions)
+    }
+
+    /// Sums of the last `TREND_GAMES` games and of as many before them, the
+    /// latter `None` until there are more games than that.
+    pub fn trend(&self) -> (Totals, Option<Totals>) {
+        let split = self.sessions.len().saturating_sub(TREND_GAMES);
+        let recent = Totals::of(&self.sessions[split..]);
+        let earlier = &self.sessions[split.saturating_sub(TREND_GAMES)..split];
+        (recent, (!earlier.is_empty()).then(|| Totals::of(earlier)))
+    }
+
+    /// Best score of each mode played, in the order they were first played.
+    /// Marathons of any start level count as one mode.
+    pub fn best_scores(&self) -> Vec<(Mode, u32)> {
+        let mut best: Vec<(Mode, u32)> = Vec::new();
+        for session in &self.sessions {
+            let kind = mem::discriminant(&session.mode);
+            match best
+                .iter_mut()
+                .find(|(mode, _)| mem::discriminant(mode) == kind)
+            {
+                Some((_, score)) => *score = (*score).max(session.score),
+                None => best.push((session.mode, session.score)),
+            }
+        }
+        best
+    }
+}
+
+/// Sums over some games.
+#[derive(Debug, Default)]
+pub struct Totals {
+    pub games: u32,
+    pub time: Duration,
+    pub score: u64,
+    pub lines: u32,
+    pub pieces: u32,
+    pub finesse_errors: u32,
+    pub clears: ClearCounts,
+}
+
+impl Totals {
+    fn of(sessions: &[Session]) -> Totals {
+        let mut totals = Totals::default();
+        for session in sessions {
+            totals.games += 1;
+            totals.time += Duration::from_secs_f32(session.seconds.max(0.));
+            totals.score += session.score as u64;
+            totals.lines += session.lines;
+            totals.pieces += session.pieces;
+            totals.finesse_errors += session.finesse_errors;
+            totals.clears.add(&session.clears);
+        }
+        totals
+    }
+
+    pub fn average_score(&self) -> u64 {
+        self.score / self.games.max(1) as u64
+    }
+
+    pub fn pieces_per_second(&self) -> f32 {
+        let seconds = self.time.as_secs_f32();
+        if seconds > 0. {
+            self.pieces as f32 / seconds
+        } else {
+            0.
+        }
+    }
+
+    pub fn lines_per_minute(&self) -> f32 {
+        lines_per_minute(self.lines, self.time)
+    }
+}
+
+/// Clears and finesse errors of the running game.
+#[derive(Default)]
+struct SessionTracker {
+    finesse_errors: u32,
+    clears: ClearCounts,
+}
+
+fn start_session(mut tracker: ResMut<SessionTracker>) {
+    *tracker = SessionTracker::default();
+}
+
+fn track_session(
+    mut cleared: EventReader<LinesCleared>,
+    mut finesse: EventReader<FinesseChecked>,
+    mut tracker: ResMut<SessionTracker>,
+) {
+    for event in cleared.iter() {
+        let clears = &mut tracker.clears;
+        if event.t_spin {
+            clears.t_spins += 1;
+        }
+        match event.rows.len() {
+            1 => clears.singles += 1,
+            2 => clears.doubles += 1,
+            3 => clears.triples += 1,
+            _ => clears.tetrises += 1,
+        }
+    }
+    for event in finesse.iter() {
+        if event.inputs > event.minimal {
+            tracker.finesse_errors += 1;
+        }
+    }
+}
+
+/// Keeps the game that just ended when the player turned statistics on.
+/// Watched replays and bot games aren't the player's and are left out.
+#[allow(clippy::too_many_arguments)]
+fn record_session(
+    mut game_over: EventReader<GameOver>,
+    mut finished: EventReader<Finished>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    tracker: Res<SessionTracker>,
+    playback: Res<Playback>,
+    autoplay: Res<Autoplay>,
+    mut analytics: ResMut<Analytics>,
+) {
+    let ended = game_over.iter().count() + finished.iter().count() > 0;
+    if !ended || !settings.analytics || playback.0.is_some() || autoplay.0.is_some() {
+        return;
+    }
+
+    analytics.sessions.push(Session {
+        date: records::today().to_string(),
+        mode: game.mode,
+        seconds: game.elapsed.as_secs_f32(),
+        score: game.score,
+        lines: game.level.lines,
+        pieces: game.stats.pieces,
+        finesse_errors: tracker.finesse_errors,
+        clears: tracker.clears,
+    });
+    if let Err(e) = analytics.save() {
+        warn!("failed to save statistics: {}", e);
+    }
+}
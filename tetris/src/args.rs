@@ -0,0 +1,76 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::network::{Mode, DEFAULT_PORT};
+
+/// Port of `--overlay` without a number.
+const OVERLAY_PORT: u16 = 7879;
+
+/// Games benchmarked by `--bench` without a number.
+const BENCH_GAMES: u32 = 100;
+
+/// Command line options.
+#[derive(Default)]
+pub struct Args {
+    /// Room to host or join, not in the web build.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub network: Option<Mode>,
+    pub seed: Option<u64>,
+    /// Play in the terminal instead of a window.
+    pub tui: bool,
+    /// Let a bot play the games.
+    pub bot: bool,
+    /// Number of games to benchmark instead of playing.
+    pub bench: Option<u32>,
+    /// Port to stream the game to overlays on.
+    pub overlay: Option<u16>,
+    /// Enable the developer time controls.
+    pub debug: bool,
+}
+
+impl Args {
+    /// Parses `--host [port]`, `--connect <host:port>`, `--seed <number>`,
+    /// `--tui`, `--bot`, `--bench [games]`, `--overlay [port]` and `--debug`.
+    /// Invalid arguments are reported and ignored.
+    pub fn parse(args: impl Iterator<Item = String>) -> Args {
+        let mut parsed = Args::default();
+        let mut args = args.peekable();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                #[cfg(not(target_arch = "wasm32"))]
+                "--host" => {
+                    let port = args.next_if(|port| port.parse::<u16>().is_ok());
+                    let port = port.map_or(DEFAULT_PORT, |port| port.parse().unwrap());
+                    parsed.network = Some(Mode::Host(port));
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                "--connect" => match args.next() {
+                    Some(address) => parsed.network = Some(Mode::Connect(address)),
+                    None => eprintln!(
+                        "--connect needs an address, e.g. --connect 127.0.0.1:{}",
+                        DEFAULT_PORT
+                    ),
+                },
+                "--seed" => match args.next().map(|seed| seed.parse()) {
+                    Some(Ok(seed)) => parsed.seed = Some(seed),
+                    _ => eprintln!("--seed needs a number"),
+                },
+                "--tui" => parsed.tui = true,
+                "--bot" => parsed.bot = true,
+                "--debug" => parsed.debug = true,
+                "--bench" => {
+                    let games = args.next_if(|games| games.parse::<u32>().is_ok());
+                    let games = games.map_or(BENCH_GAMES, |games| games.parse().unwrap());
+                    parsed.bench = Some(games);
+                }
+                "--overlay" => {
+                    let port = args.next_if(|port| port.parse::<u16>().is_ok());
+                    let port = port.map_or(OVERLAY_PORT, |port| port.parse().unwrap());
+                    parsed.overlay = Some(port);
+                }
+                other => eprintln!("ignoring unknown argument {}", other),
+            }
+        }
+
+        parsed
+    }
+}
@@ -0,0 +1,199 @@
+//! Music and sound effects. The music loops from startup and effects play on
+//! gameplay events, both at the volumes of the `AudioSettings` resource and
+//! silent while it is muted.
+
+use crate::{
+    events::{GameOver, LevelUp, LinesCleared, PieceLocked},
+    storage, GameState,
+};
+use bevy::{audio::AudioSink, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::io;
+
+const FILE_NAME: &str = "audio.toml";
+
+/// Step of the volumes on the settings screen.
+pub const VOLUME_STEP: f32 = 0.1;
+
+/// Loads the audio settings and the sounds, plays the music and the effects
+/// and toggles mute with M.
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AudioSettings::load())
+            .add_startup_system(load_sounds)
+            .add_system(toggle_mute)
+            .add_system(set_music_volume.after(toggle_mute))
+            .add_system(play_effects)
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings).with_system(preview_effects_volume),
+            );
+    }
+}
+
+/// Volume of each channel, from 0 to 1, stored in `audio.toml`. Missing
+/// entries keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub music_volume: f32,
+    pub effects_volume: f32,
+    /// Silences both channels without losing their volumes.
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> AudioSettings {
+        AudioSettings {
+            music_volume: 0.6,
+            effects_volume: 0.8,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn load() -> AudioSettings {
+        storage::load_or_init(FILE_NAME)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        storage::save(FILE_NAME, self)
+    }
+
+    /// Volume the music plays at, 0 while muted.
+    pub fn music(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.music_volume
+        }
+    }
+
+    /// Volume effects play at, 0 while muted.
+    pub fn effects(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.effects_volume
+        }
+    }
+}
+
+/// The sounds in `assets/sounds`.
+struct Sounds {
+    music: Handle<AudioSource>,
+    lock: Handle<AudioSource>,
+    clear: Handle<AudioSource>,
+    level_up: Handle<AudioSource>,
+    game_over: Handle<AudioSource>,
+}
+
+/// The looping music, kept to change its volume.
+struct Music(Handle<AudioSink>);
+
+/// Loads the sounds and starts the music.
+fn load_sounds(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    sinks: Res<Assets<AudioSink>>,
+    settings: Res<AudioSettings>,
+) {
+    let sounds = Sounds {
+        music: asset_server.load("sounds/music.wav"),
+        lock: asset_server.load("sounds/lock.wav"),
+        clear: asset_server.load("sounds/clear.wav"),
+        level_up: asset_server.load("sounds/level_up.wav"),
+        game_over: asset_server.load("sounds/game_over.wav"),
+    };
+    let sink = audio.play_with_settings(
+        sounds.music.clone(),
+        PlaybackSettings::LOOP.with_volume(settings.music()),
+    );
+    commands.insert_resource(Music(sinks.get_handle(sink)));
+    commands.insert_resource(sounds);
+}
+
+/// Follows the music volume and mute as they change.
+fn set_music_volume(
+    settings: Res<AudioSettings>,
+    music: Option<Res<Music>>,
+    sinks: Res<Assets<AudioSink>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(sink) = music.and_then(|music| sinks.get(&music.0)) {
+        sink.set_volume(settings.music());
+    }
+}
+
+/// Plays `sound` once at the effects volume, or not at all at 0.
+fn play_effect(audio: &Audio, settings: &AudioSettings, sound: &Handle<AudioSource>) {
+    let volume = settings.effects();
+    if volume > 0. {
+        audio.play_with_settings(sound.clone(), PlaybackSettings::ONCE.with_volume(volume));
+    }
+}
+
+fn play_effects(
+    audio: Res<Audio>,
+    sounds: Option<Res<Sounds>>,
+    settings: Res<AudioSettings>,
+    mut locked: EventReader<PieceLocked>,
+    mut cleared: EventReader<LinesCleared>,
+    mut level_up: EventReader<LevelUp>,
+    mut game_over: EventReader<GameOver>,
+) {
+    let sounds = match sounds {
+        Some(sounds) => sounds,
+        None => return,
+    };
+    // Only the most important sound of a frame plays, so a clear drowns
+    // out the lock of the piece that made it.
+    let events = [
+        (game_over.iter().count(), &sounds.game_over),
+        (level_up.iter().count(), &sounds.level_up),
+        (cleared.iter().count(), &sounds.clear),
+        (locked.iter().count(), &sounds.lock),
+    ];
+    let sound = match events.into_iter().find(|(count, _)| *count > 0) {
+        Some((_, sound)) => sound,
+        None => return,
+    };
+    play_effect(&audio, &settings, sound);
+}
+
+/// Plays the lock sound when the effects volume or mute is changed on the
+/// settings screen, so the new volume can be heard.
+fn preview_effects_volume(
+    audio: Res<Audio>,
+    sounds: Option<Res<Sounds>>,
+    settings: Res<AudioSettings>,
+    mut last_volume: Local<Option<f32>>,
+) {
+    let volume = settings.effects();
+    let changed = matches!(last_volume.replace(volume), Some(last) if last != volume);
+    if let (true, Some(sounds)) = (changed, sounds) {
+        play_effect(&audio, &settings, &sounds.lock);
+    }
+}
+
+/// Mutes or unmutes everything with M, except while a name is typed.
+fn toggle_mute(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut audio: ResMut<AudioSettings>,
+) {
+    if *state.current() == GameState::NameEntry || !keyboard_input.just_pressed(KeyCode::M) {
+        return;
+    }
+
+    keyboard_input.reset(KeyCode::M);
+    audio.muted = !audio.muted;
+    if let Err(e) = audio.save() {
+        warn!("failed to save audio settings: {}", e);
+    }
+}
@@ -0,0 +1,142 @@
+//! Benchmark started with `--bench [games]`. It plays headless endless
+//! games with a random bot as fast as possible and reports how fast the
+//! rules run: pieces per second, the time of ticks clearing lines and, built
+//! with the `bench` feature, the allocations made by `Game::tick`.
+
+#[cfg(feature = "bench")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+use tetris_core::{BotDriver, Config, Event, Game, Mode, RandomBot};
+
+/// Time every tick advances the games by, a frame at 60 Hz.
+const FRAME: Duration = Duration::from_micros(16_667);
+
+/// System allocator counting the allocations made through it, the global
+/// allocator only with the `bench` feature.
+#[cfg(feature = "bench")]
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "bench")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[cfg(feature = "bench")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> (usize, usize) {
+    (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    )
+}
+
+/// Time spent in a kind of tick.
+#[derive(Default)]
+struct Timing {
+    ticks: u32,
+    total: Duration,
+    max: Duration,
+}
+
+impl Timing {
+    fn add(&mut self, time: Duration) {
+        self.ticks += 1;
+        self.total += time;
+        self.max = self.max.max(time);
+    }
+
+    fn report(&self, name: &str) {
+        let mean = if self.ticks > 0 {
+            self.total / self.ticks
+        } else {
+            Duration::ZERO
+        };
+        println!(
+            "{:<16}{:>10} ticks, mean {:>8.2?}, max {:>8.2?}",
+            name, self.ticks, mean, self.max
+        );
+    }
+}
+
+/// Plays `games` games, the first on `seed` and the others on the seeds
+/// after it, and prints the results.
+pub fn run(games: u32, seed: u64) {
+    let mut pieces = 0;
+    let mut lines = 0;
+    let mut clearing = Timing::default();
+    let mut other = Timing::default();
+    let mut allocations_made = 0;
+    let mut bytes_allocated = 0;
+
+    for game_seed in (0..games as u64).map(|game| seed.wrapping_add(game)) {
+        let config = Config::default();
+        let mut driver = BotDriver::new(RandomBot::new(game_seed, config.rotation));
+        let mut game = Game::new(game_seed, Mode::Endless, config);
+        while !game.over {
+            let input = driver.input(&game);
+
+            let (count, bytes) = allocations();
+            let start = Instant::now();
+            let events = game.tick(input, FRAME);
+            let time = start.elapsed();
+            let (count_after, bytes_after) = allocations();
+            allocations_made += count_after - count;
+            bytes_allocated += bytes_after - bytes;
+
+            if events
+                .iter()
+                .any(|event| matches!(event, Event::LinesCleared { .. }))
+            {
+                clearing.add(time);
+            } else {
+                other.add(time);
+            }
+        }
+        pieces += game.stats.pieces;
+        lines += game.level.lines;
+    }
+
+    let total = clearing.total + other.total;
+    println!("{} games from seed {}", games, seed);
+    println!(
+        "{} pieces and {} lines in {:.2?} of ticks, {:.0} pieces/s",
+        pieces,
+        lines,
+        total,
+        pieces as f64 / total.as_secs_f64().max(f64::EPSILON)
+    );
+    clearing.report("line clears");
+    other.report("other ticks");
+    if cfg!(feature = "bench") {
+        println!(
+            "{} allocations, {} bytes, {:.1} allocations per piece",
+            allocations_made,
+            bytes_allocated,
+            allocations_made as f64 / pieces.max(1) as f64
+        );
+    } else {
+        println!("allocations not counted, build with --features bench to count them");
+    }
+}
@@ -0,0 +1,528 @@
+use crate::{
+    debug::GameClock,
+    events::{ClearStarted, GameOver, LinesCleared, PieceLocked},
+    game::update_game,
+    particles,
+    themes::{self, Theme},
+    GameState,
+};
+use bevy::prelude::*;
+use tetris_core::{BoardSize, Game, Mode, BOARD_HEIGHT, BOARD_WIDTH};
+
+/// Walls and locked cells of the own board, the garbage meter beside it and
+/// the effects of locks and line clears.
+pub struct BoardPlugin;
+
+impl Plugin for BoardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InvisibleStack::default())
+            .insert_resource(BoardLayout::default())
+            .add_startup_system(spawn_board)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(spawn_line_clear_flashes.after(update_game))
+                    .with_system(animate_line_clear_flashes)
+                    .with_system(fade_invisible_stack.after(update_game)),
+            )
+            .add_system(apply_theme.after(themes::select_theme))
+            .add_system(fit_board)
+            .add_system(sync_board_sprites.after(fade_invisible_stack))
+            .add_system(sync_garbage_meter)
+            .add_system(burst_on_lock)
+            .add_system(burst_on_clear);
+    }
+}
+
+#[derive(Component)]
+pub struct Wall;
+
+pub fn wall_sprite(loc: WallLocation, layout: &BoardLayout, color: Color) -> SpriteBundle {
+    SpriteBundle {
+        transform: Transform {
+            translation: loc.position(layout).extend(0.),
+            scale: loc.size(layout).extend(1.),
+            ..default()
+        },
+        sprite: Sprite { color, ..default() },
+        ..default()
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum WallLocation {
+    Bottom,
+    Left,
+    Right,
+    Top,
+}
+
+pub const WALL_THICKNESS: f32 = 10.;
+
+// Walls of the standard board, the frame other boards are fitted into and
+// the panels beside the board are laid out from.
+pub const LEFT_WALL: f32 = -(BOARD_WIDTH as f32 * SQUARE_SIZE) / 2. - WALL_THICKNESS / 2.;
+pub const RIGHT_WALL: f32 = (BOARD_WIDTH as f32 * SQUARE_SIZE) / 2. + WALL_THICKNESS / 2.;
+pub const BOTTOM_WALL: f32 = -(BOARD_HEIGHT as f32 * SQUARE_SIZE) / 2. - WALL_THICKNESS / 2.;
+pub const TOP_WALL: f32 = (BOARD_HEIGHT as f32 * SQUARE_SIZE) / 2. + WALL_THICKNESS / 2.;
+
+impl WallLocation {
+    fn position(&self, layout: &BoardLayout) -> Vec2 {
+        let distance = layout.wall_distance();
+        match self {
+            WallLocation::Left => Vec2::new(-distance.x, 0.),
+            WallLocation::Right => Vec2::new(distance.x, 0.),
+            WallLocation::Top => Vec2::new(0., distance.y),
+            WallLocation::Bottom => Vec2::new(0., -distance.y),
+        }
+    }
+
+    fn size(&self, layout: &BoardLayout) -> Vec2 {
+        let span = 2. * layout.wall_distance();
+        let (width, height) = (span.x, span.y);
+
+        match self {
+            WallLocation::Left => Vec2::new(WALL_THICKNESS, height + WALL_THICKNESS),
+            WallLocation::Right => Vec2::new(WALL_THICKNESS, height + WALL_THICKNESS),
+            WallLocation::Top => Vec2::new(width + WALL_THICKNESS, WALL_THICKNESS),
+            WallLocation::Bottom => Vec2::new(width + WALL_THICKNESS, WALL_THICKNESS),
+        }
+    }
+}
+
+pub const SQUARE_SIZE: f32 = 20.;
+
+/// Where the cells of the own board are drawn. Boards bigger than the
+/// standard one are fitted into its frame with smaller squares.
+#[derive(Clone, Copy, PartialEq)]
+pub struct BoardLayout {
+    pub width: usize,
+    pub height: usize,
+    /// Side of a square.
+    pub square: f32,
+}
+
+impl Default for BoardLayout {
+    fn default() -> BoardLayout {
+        BoardLayout::new(BoardSize::default())
+    }
+}
+
+impl BoardLayout {
+    pub fn new(size: BoardSize) -> BoardLayout {
+        let square = (SQUARE_SIZE * BOARD_WIDTH as f32 / size.width as f32)
+            .min(SQUARE_SIZE * BOARD_HEIGHT as f32 / size.height as f32)
+            .min(SQUARE_SIZE);
+        BoardLayout {
+            width: size.width,
+            height: size.height,
+            square,
+        }
+    }
+
+    /// Center of the cell `x`, `y`, row 0 at the bottom.
+    pub fn cell_to_world(&self, x: i32, y: i32) -> Vec3 {
+        Vec3::new(
+            (x as f32 + 0.5 - self.width as f32 / 2.) * self.square,
+            (y as f32 + 0.5 - self.height as f32 / 2.) * self.square,
+            0.,
+        )
+    }
+
+    /// Scale of the square sprites.
+    pub fn square_scale(&self) -> Vec3 {
+        Vec3::new(self.square, self.square, 0.)
+    }
+
+    /// Distances from the center of the board to the centers of its side
+    /// and its top walls.
+    fn wall_distance(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32) * self.square / 2. + WALL_THICKNESS / 2.
+    }
+}
+
+pub fn square_sprite(color: Color, translation: Vec3) -> SpriteBundle {
+    SpriteBundle {
+        transform: Transform {
+            translation,
+            scale: Vec3::new(SQUARE_SIZE, SQUARE_SIZE, 0.),
+            ..default()
+        },
+        sprite: Sprite {
+            color,
+            // Sized by the scale whatever the size of the skin texture.
+            custom_size: Some(Vec2::ONE),
+            ..default()
+        },
+        ..default()
+    }
+}
+
+/// Sprite showing the locked content of a board cell.
+#[derive(Component)]
+pub struct BoardCell {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Segment of the bar next to the playfield growing with the pending
+/// garbage. Rows ready to enter the board are stacked at the bottom, those
+/// still waiting for their delay above them.
+#[derive(Component)]
+struct GarbageMeter {
+    ready: bool,
+}
+
+const GARBAGE_METER_WIDTH: f32 = 6.;
+const GARBAGE_METER_COLOR: Color = Color::rgb(0.9, 0.2, 0.2);
+const WAITING_GARBAGE_COLOR: Color = Color::rgb(0.9, 0.6, 0.2);
+
+/// Time received garbage waits before it can enter the board, giving the
+/// player a moment to cancel it.
+pub const GARBAGE_DELAY_SECONDS: f32 = 1.;
+
+/// White bar over a row being cleared, flashing and shrinking to nothing
+/// while the game waits for the row to be removed.
+#[derive(Component)]
+struct LineClearFlash(Timer);
+
+/// Length of the line clear animation, the game waits for it before removing
+/// the rows.
+pub const LINE_CLEAR_SECONDS: f32 = 0.25;
+
+/// Seconds a locked block stays visible in the invisible mode, the last
+/// `FADE_SECONDS` of them fading out.
+const INVISIBLE_DELAY_SECONDS: f32 = 1.;
+const FADE_SECONDS: f32 = 0.5;
+/// Seconds the whole stack is shown again after a line clear.
+const REVEAL_SECONDS: f32 = 1.;
+
+/// Seconds each cell of the playfield stays visible for in the invisible
+/// mode, `shown[y][x]`.
+struct InvisibleStack {
+    shown: Vec<Vec<f32>>,
+}
+
+impl Default for InvisibleStack {
+    fn default() -> InvisibleStack {
+        InvisibleStack::new(BoardSize::default())
+    }
+}
+
+impl InvisibleStack {
+    fn new(size: BoardSize) -> InvisibleStack {
+        InvisibleStack {
+            shown: vec![vec![0.; size.width]; size.height],
+        }
+    }
+
+    fn shown(&self, x: usize, y: usize) -> f32 {
+        self.shown
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn reveal(&mut self, seconds: f32) {
+        self.shown
+            .iter_mut()
+            .flatten()
+            .for_each(|shown| *shown = seconds);
+    }
+}
+
+/// Hides locked blocks a moment after they lock. Line clears reveal the
+/// whole stack briefly and it stays revealed once the game is over.
+fn fade_invisible_stack(
+    time: Res<Time>,
+    game: Res<Game>,
+    mut stack: ResMut<InvisibleStack>,
+    mut locked: EventReader<PieceLocked>,
+    mut cleared: EventReader<LinesCleared>,
+    mut game_over: EventReader<GameOver>,
+) {
+    if game.mode != Mode::Invisible {
+        return;
+    }
+
+    let size = game.board.size();
+    if stack.shown.len() != size.height || stack.shown[0].len() != size.width {
+        *stack = InvisibleStack::new(size);
+    }
+    let delta = time.delta_seconds();
+    for shown in stack.shown.iter_mut().flatten() {
+        *shown = (*shown - delta).max(0.);
+    }
+    for event in locked.iter() {
+        for &(x, y) in &event.cells {
+            if let Some(shown) = stack.shown.get_mut(y as usize) {
+                shown[x as usize] = INVISIBLE_DELAY_SECONDS;
+            }
+        }
+    }
+    if cleared.iter().count() > 0 {
+        stack.reveal(REVEAL_SECONDS);
+    }
+    if game_over.iter().count() > 0 {
+        stack.reveal(f32::INFINITY);
+    }
+}
+
+/// Shows the locked blocks in the cells of the board, cells past its size
+/// stay hidden.
+fn sync_board_sprites(
+    game: Res<Game>,
+    theme: Res<Theme>,
+    stack: Res<InvisibleStack>,
+    mut query: Query<(&BoardCell, &mut Sprite, &mut Visibility)>,
+) {
+    if !game.is_changed() && !theme.is_changed() && !stack.is_changed() {
+        return;
+    }
+
+    let size = game.board.size();
+    for (cell, mut sprite, mut visibility) in query.iter_mut() {
+        // Rows being cleared are drawn by their flash.
+        if game.clearing_rows().contains(&cell.y) {
+            visibility.is_visible = false;
+            continue;
+        }
+
+        let block = if cell.x < size.width && cell.y < size.height {
+            game.board.cells[cell.y][cell.x]
+        } else {
+            None
+        };
+        match block {
+            Some(block) => {
+                sprite.color = theme.block_color(block);
+                if game.mode == Mode::Invisible {
+                    let shown = stack.shown(cell.x, cell.y);
+                    sprite.color.set_a((shown / FADE_SECONDS).min(1.));
+                }
+                visibility.is_visible = true;
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+}
+
+/// Sizes the segments of the garbage meter. The waiting segment fades in
+/// as the oldest waiting attack gets ready to land.
+fn sync_garbage_meter(
+    game: Res<Game>,
+    layout: Res<BoardLayout>,
+    mut query: Query<(&GarbageMeter, &mut Transform, &mut Sprite, &mut Visibility)>,
+) {
+    if !game.is_changed() && !layout.is_changed() {
+        return;
+    }
+
+    let ready = (game.ready_garbage() as usize).min(layout.height);
+    let waiting = (game.pending_garbage() as usize).min(layout.height) - ready;
+    let next_delay = game
+        .incoming_garbage
+        .iter()
+        .map(|garbage| garbage.delay)
+        .find(|delay| !delay.is_zero())
+        .map_or(0., |delay| delay.as_secs_f32());
+    let bottom = -(layout.height as f32 * layout.square) / 2.;
+    let x = -layout.wall_distance().x - WALL_THICKNESS / 2. - 2. * GARBAGE_METER_WIDTH;
+
+    for (meter, mut transform, mut sprite, mut visibility) in query.iter_mut() {
+        let (start, rows) = if meter.ready {
+            (0, ready)
+        } else {
+            sprite
+                .color
+                .set_a(1. - 0.6 * (next_delay / GARBAGE_DELAY_SECONDS).min(1.));
+            (ready, waiting)
+        };
+        let height = rows as f32 * layout.square;
+        transform.translation.x = x;
+        transform.translation.y = bottom + start as f32 * layout.square + height / 2.;
+        transform.scale.y = height;
+        visibility.is_visible = rows > 0;
+    }
+}
+
+/// Particles per square of a locked piece.
+const LOCK_PARTICLES: usize = 3;
+/// Particles per cell of a cleared row, multiplied for bigger clears.
+const CLEAR_PARTICLES: usize = 2;
+
+fn burst_on_lock(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    layout: Res<BoardLayout>,
+    mut locked: EventReader<PieceLocked>,
+) {
+    for event in locked.iter() {
+        for &(x, y) in &event.cells {
+            particles::spawn_burst(
+                &mut commands,
+                layout.cell_to_world(x, y),
+                theme.block_color(event.block),
+                LOCK_PARTICLES,
+                60.,
+            );
+        }
+    }
+}
+
+/// Bursts from every cell of the cleared rows, a tetris throws three times
+/// as many particles twice as fast as a single.
+fn burst_on_clear(
+    mut commands: Commands,
+    game: Res<Game>,
+    theme: Res<Theme>,
+    layout: Res<BoardLayout>,
+    mut started: EventReader<ClearStarted>,
+) {
+    for event in started.iter() {
+        let intensity = match event.rows.len() {
+            4 => 3,
+            3 => 2,
+            _ => 1,
+        };
+        for &row in &event.rows {
+            for (x, cell) in game.board.cells[row].iter().enumerate() {
+                let color = cell
+                    .map(|block| theme.block_color(block))
+                    .unwrap_or(Color::WHITE);
+                particles::spawn_burst(
+                    &mut commands,
+                    layout.cell_to_world(x as i32, row as i32),
+                    color,
+                    CLEAR_PARTICLES * intensity,
+                    100. + 50. * (intensity - 1) as f32,
+                );
+            }
+        }
+    }
+}
+
+fn spawn_line_clear_flashes(
+    mut commands: Commands,
+    layout: Res<BoardLayout>,
+    mut started: EventReader<ClearStarted>,
+) {
+    for event in started.iter() {
+        for &row in &event.rows {
+            let mut sprite = square_sprite(
+                Color::WHITE,
+                Vec3::new(0., layout.cell_to_world(0, row as i32).y, 0.5),
+            );
+            sprite.transform.scale = layout.square_scale();
+            sprite.transform.scale.x = layout.width as f32 * layout.square;
+            commands
+                .spawn_bundle(sprite)
+                .insert(LineClearFlash(Timer::from_seconds(
+                    LINE_CLEAR_SECONDS,
+                    false,
+                )));
+        }
+    }
+}
+
+/// Fades and shrinks the flashes over the line clear delay. They only run
+/// while playing and on the game's clock so they stay in step with the
+/// paused or slowed game.
+fn animate_line_clear_flashes(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    layout: Res<BoardLayout>,
+    mut query: Query<(Entity, &mut LineClearFlash, &mut Transform, &mut Sprite)>,
+) {
+    let delta = clock.delta().unwrap_or_default();
+    for (entity, mut flash, mut transform, mut sprite) in query.iter_mut() {
+        if flash.0.tick(delta).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let remaining = 1. - flash.0.percent();
+        transform.scale.y = layout.square * remaining;
+        sprite.color.set_a(remaining);
+    }
+}
+
+/// Spawns the walls and the cells of the biggest board, those past the size
+/// of the current one hidden.
+fn spawn_board(mut commands: Commands, theme: Res<Theme>, layout: Res<BoardLayout>) {
+    for location in [
+        WallLocation::Left,
+        WallLocation::Right,
+        WallLocation::Bottom,
+        WallLocation::Top,
+    ] {
+        commands
+            .spawn_bundle(wall_sprite(location, &layout, theme.wall))
+            .insert(Wall)
+            .insert(location);
+    }
+
+    for y in 0..*BoardSize::HEIGHTS.end() {
+        for x in 0..*BoardSize::WIDTHS.end() {
+            let position = layout.cell_to_world(x as i32, y as i32);
+            let mut sprite = square_sprite(Color::NONE, position);
+            sprite.visibility.is_visible = false;
+            commands.spawn_bundle(sprite).insert(BoardCell { x, y });
+        }
+    }
+
+    for (ready, color) in [(true, GARBAGE_METER_COLOR), (false, WAITING_GARBAGE_COLOR)] {
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: Transform {
+                    scale: Vec3::new(GARBAGE_METER_WIDTH, 0., 1.),
+                    ..default()
+                },
+                sprite: Sprite { color, ..default() },
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(GarbageMeter { ready });
+    }
+}
+
+/// Lays the board out for the size of the game's board, or for the standard
+/// board the editor works on, moving its cells and walls.
+fn fit_board(
+    game: Res<Game>,
+    state: Res<State<GameState>>,
+    mut layout: ResMut<BoardLayout>,
+    mut cells: Query<(&BoardCell, &mut Transform)>,
+    mut walls: Query<(&WallLocation, &mut Transform), Without<BoardCell>>,
+) {
+    let size = if *state.current() == GameState::Editor {
+        BoardSize::default()
+    } else {
+        game.board.size()
+    };
+    let fitted = BoardLayout::new(size);
+    if fitted == *layout {
+        return;
+    }
+
+    *layout = fitted;
+    for (cell, mut transform) in cells.iter_mut() {
+        transform.translation = layout.cell_to_world(cell.x as i32, cell.y as i32);
+        transform.scale = layout.square_scale();
+    }
+    for (location, mut transform) in walls.iter_mut() {
+        transform.translation = location.position(&layout).extend(0.);
+        transform.scale = location.size(&layout).extend(1.);
+    }
+}
+
+/// Recolors the walls, of the opponent's board too.
+fn apply_theme(theme: Res<Theme>, mut walls: Query<&mut Sprite, With<Wall>>) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    for mut sprite in walls.iter_mut() {
+        sprite.color = theme.wall;
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{
+    board::{BOTTOM_WALL, TOP_WALL},
+    ui::STATS_LEFT,
+};
+use bevy::{
+    prelude::*,
+    window::{WindowCreated, WindowId, WindowResized},
+};
+
+/// Cameras of the playfield and the UI, the first kept fitted to the window.
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup).add_system(fit_camera);
+    }
+}
+
+/// Size of the world area with the playfield and its side panels that the
+/// camera keeps in view, margins included. It is centered on the own board
+/// and wide enough for the statistics panel on the left and the opponent's
+/// board on the right.
+const SCENE_WIDTH: f32 = -2. * STATS_LEFT + 80.;
+const SCENE_HEIGHT: f32 = TOP_WALL - BOTTOM_WALL + 80.;
+
+/// Camera rendering the playfield, as opposed to the UI camera.
+#[derive(Component)]
+pub struct GameCamera;
+
+fn setup(mut commands: Commands) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(GameCamera);
+    commands.spawn_bundle(UiCameraBundle::default());
+}
+
+/// Scales the game camera whenever the window is created or resized so the
+/// whole scene stays visible and centered, whatever the aspect ratio.
+fn fit_camera(
+    mut created: EventReader<WindowCreated>,
+    mut resized: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut query: Query<&mut OrthographicProjection, With<GameCamera>>,
+) {
+    // Not short-circuiting so both readers are drained.
+    let primary_changed = created.iter().any(|event| event.id == WindowId::primary())
+        | resized.iter().any(|event| event.id == WindowId::primary());
+    if !primary_changed {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) if window.width() > 0. && window.height() > 0. => window,
+        // Minimized windows have no size to fit the scene into.
+        _ => return,
+    };
+
+    let scale = (SCENE_WIDTH / window.width()).max(SCENE_HEIGHT / window.height());
+    for mut projection in query.iter_mut() {
+        projection.scale = scale;
+    }
+}
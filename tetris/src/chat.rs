@@ -0,0 +1,321 @@
+//! Text chat between the players of a room. Enter starts typing during a
+//! match, Enter again sends and Esc cancels. Messages are cut to a maximum
+//! length and each side sends and accepts only a few per moment, so a
+//! flooding player can't bury the panel or the connection.
+
+use crate::{
+    controls::{update_actions, Actions},
+    lobby::Room,
+    locale::Locale,
+    menu::update_menu_input,
+    network::{Connection, Message, NetEvent},
+    GameState,
+};
+use bevy::{input::InputSystem, prelude::*, window::ReceivedCharacter};
+use std::collections::VecDeque;
+
+/// Characters of a message, longer ones are cut.
+const MAX_CHAT_LENGTH: usize = 80;
+/// Messages a player may send within `RATE_WINDOW_SECONDS`.
+const RATE_LIMIT: usize = 3;
+const RATE_WINDOW_SECONDS: f64 = 5.;
+/// Lines kept in the history panel.
+const HISTORY_LINES: usize = 8;
+
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Chat>()
+            .add_startup_system(spawn_chat_panel)
+            // Typing takes the keys before menus and gameplay see them.
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                type_message
+                    .after(InputSystem)
+                    .before(update_actions)
+                    .before(update_menu_input),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, release_actions.after(update_actions))
+            .add_system(receive_messages)
+            .add_system(sync_chat_panel.after(receive_messages));
+    }
+}
+
+/// Lines of the chat and the message being typed, if any.
+#[derive(Default)]
+pub struct Chat {
+    history: VecDeque<ChatLine>,
+    typing: Option<String>,
+    sent: RateLimit,
+    received: RateLimit,
+}
+
+impl Chat {
+    fn push(&mut self, line: ChatLine) {
+        if self.history.len() == HISTORY_LINES {
+            self.history.pop_front();
+        }
+        self.history.push_back(line);
+    }
+}
+
+enum ChatLine {
+    /// Message of the player at this index of the room.
+    Player { index: usize, text: String },
+    /// Note of the game, a locale key.
+    Notice(&'static str),
+}
+
+/// Times of the last messages, to let through at most `RATE_LIMIT` within
+/// `RATE_WINDOW_SECONDS`.
+#[derive(Default)]
+struct RateLimit(VecDeque<f64>);
+
+impl RateLimit {
+    /// Whether a message may pass now, counting it if so.
+    fn allow(&mut self, now: f64) -> bool {
+        while self
+            .0
+            .front()
+            .is_some_and(|&time| now - time >= RATE_WINDOW_SECONDS)
+        {
+            self.0.pop_front();
+        }
+        if self.0.len() == RATE_LIMIT {
+            return false;
+        }
+        self.0.push_back(now);
+        true
+    }
+}
+
+/// Text cut to `MAX_CHAT_LENGTH` characters without control characters.
+fn clean(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_CHAT_LENGTH)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[derive(Component)]
+struct ChatPanel;
+
+fn spawn_chat_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 16.,
+                    color: Color::WHITE,
+                },
+                default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(ChatPanel);
+}
+
+/// Enter starts typing during a match in a room. While typing, characters
+/// go into the message, Backspace removes the last one, Enter sends it and
+/// Esc drops it, and no other system sees the keys.
+fn type_message(
+    mut characters: EventReader<ReceivedCharacter>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    room: Option<Res<Room>>,
+    connection: Option<Res<Connection>>,
+    mut chat: ResMut<Chat>,
+) {
+    let (room, connection) = match (room, connection) {
+        (Some(room), Some(connection)) if room.joined => (room, connection),
+        _ => {
+            if chat.typing.is_some() {
+                chat.typing = None;
+            }
+            return;
+        }
+    };
+
+    let chat = &mut *chat;
+    let typing = match &mut chat.typing {
+        Some(typing) => typing,
+        None => {
+            if *state.current() == GameState::Playing
+                && keyboard_input.just_pressed(KeyCode::Return)
+            {
+                chat.typing = Some(String::new());
+                keyboard_input.clear();
+            }
+            return;
+        }
+    };
+
+    for event in characters.iter() {
+        if !event.char.is_control() && typing.chars().count() < MAX_CHAT_LENGTH {
+            typing.push(event.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        typing.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        let text = clean(typing);
+        chat.typing = None;
+        if !text.is_empty() {
+            if chat.sent.allow(time.seconds_since_startup()) {
+                connection.send(Message::Chat { text: text.clone() });
+                chat.push(ChatLine::Player {
+                    index: room.own_index(&connection),
+                    text,
+                });
+            } else {
+                chat.push(ChatLine::Notice("chat.too_fast"));
+            }
+        }
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        chat.typing = None;
+    }
+    keyboard_input.clear();
+}
+
+/// Keeps the gameplay actions released while typing.
+fn release_actions(chat: Res<Chat>, mut actions: ResMut<Actions>) {
+    if chat.typing.is_some() {
+        actions.release_all();
+    }
+}
+
+/// Adds the opponent's messages to the history, dropping those past the
+/// rate limit. Leaving the room clears it.
+fn receive_messages(
+    mut events: EventReader<NetEvent>,
+    time: Res<Time>,
+    room: Option<Res<Room>>,
+    connection: Option<Res<Connection>>,
+    mut chat: ResMut<Chat>,
+) {
+    let (room, connection) = match (room, connection) {
+        (Some(room), Some(connection)) => (room, connection),
+        _ => {
+            if !chat.history.is_empty() {
+                *chat = Chat::default();
+            }
+            return;
+        }
+    };
+
+    for event in events.iter() {
+        if let NetEvent::Received(Message::Chat { text }) = event {
+            let text = clean(text);
+            if !text.is_empty() && chat.received.allow(time.seconds_since_startup()) {
+                chat.push(ChatLine::Player {
+                    index: 1 - room.own_index(&connection),
+                    text,
+                });
+            }
+        }
+    }
+}
+
+/// Shows the history and the message being typed while in a room.
+fn sync_chat_panel(
+    chat: Res<Chat>,
+    room: Option<Res<Room>>,
+    locale: Res<Locale>,
+    mut in_room: Local<bool>,
+    mut query: Query<(&mut Text, &mut Visibility), With<ChatPanel>>,
+) {
+    let room_changed =
+        room.as_ref().is_some_and(|room| room.is_changed()) || room.is_some() != *in_room;
+    *in_room = room.is_some();
+    if !chat.is_changed() && !room_changed && !locale.is_changed() {
+        return;
+    }
+
+    let mut lines: Vec<_> = chat
+        .history
+        .iter()
+        .map(|line| match line {
+            ChatLine::Player { index, text } => {
+                let name = room
+                    .as_ref()
+                    .and_then(|room| room.players.get(*index))
+                    .map(|player| player.name.trim())
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| locale.get("name_entry.default_name"));
+                format!("{}: {}", name, text)
+            }
+            ChatLine::Notice(key) => locale.get(key).to_string(),
+        })
+        .collect();
+    match &chat.typing {
+        Some(typing) => lines.push(format!("{}{}_", locale.get("chat.prompt"), typing)),
+        None if room.is_some() => lines.push(locale.get("chat.hint").to_string()),
+        None => {}
+    }
+    for (mut text, mut visibility) in query.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+        visibility.is_visible = room.is_some();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_beyond_the_limit_wait_for_the_window_to_pass() {
+        let mut limit = RateLimit::default();
+        assert!(limit.allow(0.));
+        assert!(limit.allow(1.));
+        assert!(limit.allow(2.));
+        assert!(!limit.allow(4.9));
+
+        // The first message leaves the window exactly `RATE_WINDOW_SECONDS`
+        // after it was sent.
+        assert!(limit.allow(5.));
+        assert!(!limit.allow(5.5));
+        assert!(limit.allow(6.));
+    }
+
+    #[test]
+    fn refused_messages_dont_count() {
+        let mut limit = RateLimit::default();
+        for _ in 0..RATE_LIMIT {
+            assert!(limit.allow(0.));
+        }
+        for _ in 0..10 {
+            assert!(!limit.allow(1.));
+        }
+
+        assert!(limit.allow(5.));
+    }
+
+    #[test]
+    fn messages_are_cut_to_the_maximum_length() {
+        let text = "é".repeat(MAX_CHAT_LENGTH + 20);
+
+        assert_eq!(clean(&text), "é".repeat(MAX_CHAT_LENGTH));
+    }
+
+    #[test]
+    fn control_characters_and_surrounding_spaces_are_removed() {
+        assert_eq!(clean("  hi\u{7}\nthere\u{1b}[2J\t "), "hithere[2J");
+    }
+}
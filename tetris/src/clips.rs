@@ -0,0 +1,187 @@
+//! Clips of the last seconds of play, saved as animated GIFs with F9. The
+//! board is sampled a few times a second into a ring buffer of small frames,
+//! which are scaled up and encoded on a background thread.
+
+use crate::{
+    game::update_game,
+    storage,
+    themes::{Theme, ITEM_COLOR},
+    GameState,
+};
+use bevy::prelude::*;
+use gif::{Encoder, Frame, Repeat};
+use std::{borrow::Cow, collections::VecDeque, io, thread};
+use tetris_core::{Block, BoardSize, Game, ShapeTypes};
+
+const CLIP_SECONDS: usize = 10;
+const FRAMES_PER_SECOND: usize = 10;
+/// Pixels per side of a cell in the saved GIF.
+const CELL_PIXELS: usize = 8;
+
+// Palette indices of the frames, followed by the pieces in the order of
+// `ShapeTypes::ALL`.
+const BACKGROUND: u8 = 0;
+const WALL: u8 = 1;
+const GARBAGE: u8 = 2;
+const ITEM: u8 = 3;
+const CLEARING: u8 = 4;
+const PIECES: u8 = 5;
+
+/// Samples the board while playing and saves a clip on F9.
+pub struct ClipPlugin;
+
+impl Plugin for ClipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClipRecorder>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(capture_frame.after(update_game)),
+            )
+            .add_system(save_clip);
+    }
+}
+
+/// Last `CLIP_SECONDS` of frames, one palette index per cell, oldest first.
+/// A board of another size starts the clip over.
+pub struct ClipRecorder {
+    timer: Timer,
+    size: BoardSize,
+    frames: VecDeque<Vec<u8>>,
+}
+
+impl Default for ClipRecorder {
+    fn default() -> ClipRecorder {
+        ClipRecorder {
+            timer: Timer::from_seconds(1. / FRAMES_PER_SECOND as f32, true),
+            size: BoardSize::default(),
+            frames: VecDeque::with_capacity(CLIP_SECONDS * FRAMES_PER_SECOND),
+        }
+    }
+}
+
+fn capture_frame(time: Res<Time>, game: Res<Game>, mut recorder: ResMut<ClipRecorder>) {
+    if !recorder.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    if game.board.size() != recorder.size {
+        recorder.size = game.board.size();
+        recorder.frames.clear();
+    }
+    if recorder.frames.len() == CLIP_SECONDS * FRAMES_PER_SECOND {
+        recorder.frames.pop_front();
+    }
+    recorder.frames.push_back(frame(&game));
+}
+
+/// Cells across and down a frame: the board with a wall on the left, right
+/// and bottom.
+fn frame_size(size: BoardSize) -> (usize, usize) {
+    (size.width + 2, size.height + 1)
+}
+
+/// Cells of the board, the active piece and the walls, top row first.
+fn frame(game: &Game) -> Vec<u8> {
+    let piece = game.piece.cells();
+    let size = game.board.size();
+    let (frame_width, frame_height) = frame_size(size);
+    let mut frame = vec![BACKGROUND; frame_width * frame_height];
+    for row in 0..frame_height {
+        for column in 0..frame_width {
+            let index = if row == size.height || column == 0 || column == size.width + 1 {
+                WALL
+            } else {
+                let (x, y) = (column - 1, size.height - 1 - row);
+                if game.clearing_rows().contains(&y) {
+                    CLEARING
+                } else if let Some(block) = game.board.cells[y][x] {
+                    match block {
+                        Block::Piece(shape_type) => PIECES + shape_type as u8,
+                        Block::Garbage => GARBAGE,
+                        Block::Item(_) => ITEM,
+                    }
+                } else if piece.contains(&(x as i32, y as i32)) && !game.over {
+                    PIECES + game.piece.shape_type as u8
+                } else {
+                    BACKGROUND
+                }
+            };
+            frame[row * frame_width + column] = index;
+        }
+    }
+    frame
+}
+
+/// Colors of the palette indices as RGB bytes.
+fn palette(theme: &Theme) -> Vec<u8> {
+    [
+        theme.background,
+        theme.wall,
+        theme.garbage,
+        ITEM_COLOR,
+        Color::WHITE,
+    ]
+    .into_iter()
+    .chain(
+        ShapeTypes::ALL
+            .iter()
+            .map(|&shape_type| theme.piece_color(shape_type)),
+    )
+    .flat_map(|color| {
+        let [r, g, b, _] = color.as_rgba_f32();
+        [r, g, b].map(|channel| (channel.clamp(0., 1.) * 255.).round() as u8)
+    })
+    .collect()
+}
+
+/// Encodes the recorded frames to `clips/` in the config directory on F9,
+/// without holding up the game.
+fn save_clip(keyboard_input: Res<Input<KeyCode>>, recorder: Res<ClipRecorder>, theme: Res<Theme>) {
+    if !keyboard_input.just_pressed(KeyCode::F9) || recorder.frames.is_empty() {
+        return;
+    }
+
+    let frames: Vec<_> = recorder.frames.iter().cloned().collect();
+    let size = recorder.size;
+    let palette = palette(&theme);
+    let file_name = format!(
+        "clips/clip-{}.gif",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    thread::spawn(move || {
+        match encode(&frames, size, &palette).and_then(|gif| storage::write_bytes(&file_name, &gif))
+        {
+            Ok(()) => info!("saved clip {}", file_name),
+            Err(e) => warn!("failed to save clip: {}", e),
+        }
+    });
+}
+
+fn encode(frames: &[Vec<u8>], size: BoardSize, palette: &[u8]) -> io::Result<Vec<u8>> {
+    let (frame_width, frame_height) = frame_size(size);
+    let (width, height) = (frame_width * CELL_PIXELS, frame_height * CELL_PIXELS);
+    let mut gif = Vec::new();
+    {
+        let mut encoder = Encoder::new(&mut gif, width as u16, height as u16, palette)
+            .map_err(io::Error::other)?;
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(io::Error::other)?;
+        for cells in frames {
+            let pixels: Vec<u8> = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| cells[y / CELL_PIXELS * frame_width + x / CELL_PIXELS])
+                .collect();
+            let frame = Frame {
+                width: width as u16,
+                height: height as u16,
+                // In hundredths of a second.
+                delay: (100 / FRAMES_PER_SECOND) as u16,
+                buffer: Cow::Owned(pixels),
+                ..Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(io::Error::other)?;
+        }
+    }
+    Ok(gif)
+}
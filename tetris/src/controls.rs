@@ -0,0 +1,452 @@
+use crate::storage;
+use bevy::{
+    input::{
+        gamepad::{GamepadAxisType, GamepadButtonType, GamepadEvent, GamepadEventType},
+        keyboard::KeyCode,
+        InputSystem,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+use tetris_core::Button;
+
+const FILE_NAME: &str = "bindings.toml";
+const GAMEPAD_FILE_NAME: &str = "gamepad.toml";
+
+/// Loads the key and gamepad bindings and turns keys, gamepad buttons and
+/// touches into `Actions` before the frame's systems run.
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(KeyBindings::load())
+            .insert_resource(GamepadBindings::load())
+            .insert_resource(Actions::default())
+            .add_system_to_stage(CoreStage::PreUpdate, update_actions.after(InputSystem))
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_touch_actions.after(update_actions),
+            )
+            .add_system(log_gamepad_connections);
+    }
+}
+
+/// Abstract gameplay inputs. Gameplay systems read these from `Actions`
+/// instead of looking at physical keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+    Hold,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::RotateClockwise,
+        Action::RotateCounterClockwise,
+        Action::Rotate180,
+        Action::Hold,
+        Action::Pause,
+    ];
+
+    /// Button of the game driven by this action, `None` for actions handled
+    /// by the frontend.
+    pub fn button(self) -> Option<Button> {
+        match self {
+            Action::MoveLeft => Some(Button::MoveLeft),
+            Action::MoveRight => Some(Button::MoveRight),
+            Action::SoftDrop => Some(Button::SoftDrop),
+            Action::HardDrop => Some(Button::HardDrop),
+            Action::RotateClockwise => Some(Button::RotateClockwise),
+            Action::RotateCounterClockwise => Some(Button::RotateCounterClockwise),
+            Action::Rotate180 => Some(Button::Rotate180),
+            Action::Hold => Some(Button::Hold),
+            Action::Pause => None,
+        }
+    }
+}
+
+/// Keys bound to each action, loaded from `bindings.toml` in the config
+/// directory. Missing entries keep their default keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_left: Vec<KeyCode>,
+    pub move_right: Vec<KeyCode>,
+    pub soft_drop: Vec<KeyCode>,
+    pub hard_drop: Vec<KeyCode>,
+    pub rotate_clockwise: Vec<KeyCode>,
+    pub rotate_counter_clockwise: Vec<KeyCode>,
+    pub rotate_180: Vec<KeyCode>,
+    pub hold: Vec<KeyCode>,
+    pub pause: Vec<KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            move_left: vec![KeyCode::Left],
+            move_right: vec![KeyCode::Right],
+            soft_drop: vec![KeyCode::Down],
+            hard_drop: vec![KeyCode::Space],
+            rotate_clockwise: vec![KeyCode::Up, KeyCode::X],
+            rotate_counter_clockwise: vec![KeyCode::Z, KeyCode::LControl, KeyCode::RControl],
+            rotate_180: vec![KeyCode::A],
+            hold: vec![KeyCode::C],
+            pause: vec![KeyCode::Escape, KeyCode::P],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Loads the bindings, writing the defaults to disk on first start so
+    /// players have a file to edit.
+    pub fn load() -> KeyBindings {
+        storage::load_or_init(FILE_NAME)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        storage::save(FILE_NAME, self)
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        match action {
+            Action::MoveLeft => &self.move_left,
+            Action::MoveRight => &self.move_right,
+            Action::SoftDrop => &self.soft_drop,
+            Action::HardDrop => &self.hard_drop,
+            Action::RotateClockwise => &self.rotate_clockwise,
+            Action::RotateCounterClockwise => &self.rotate_counter_clockwise,
+            Action::Rotate180 => &self.rotate_180,
+            Action::Hold => &self.hold,
+            Action::Pause => &self.pause,
+        }
+    }
+
+    pub fn keys_mut(&mut self, action: Action) -> &mut Vec<KeyCode> {
+        match action {
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::SoftDrop => &mut self.soft_drop,
+            Action::HardDrop => &mut self.hard_drop,
+            Action::RotateClockwise => &mut self.rotate_clockwise,
+            Action::RotateCounterClockwise => &mut self.rotate_counter_clockwise,
+            Action::Rotate180 => &mut self.rotate_180,
+            Action::Hold => &mut self.hold,
+            Action::Pause => &mut self.pause,
+        }
+    }
+}
+
+/// Gamepad buttons bound to each action, loaded from `gamepad.toml`. The left
+/// stick and d-pads reporting axes always move and soft drop, ignoring
+/// deflections below their deadzone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadBindings {
+    pub move_left: Vec<GamepadButtonType>,
+    pub move_right: Vec<GamepadButtonType>,
+    pub soft_drop: Vec<GamepadButtonType>,
+    pub hard_drop: Vec<GamepadButtonType>,
+    pub rotate_clockwise: Vec<GamepadButtonType>,
+    pub rotate_counter_clockwise: Vec<GamepadButtonType>,
+    pub rotate_180: Vec<GamepadButtonType>,
+    pub hold: Vec<GamepadButtonType>,
+    pub pause: Vec<GamepadButtonType>,
+    pub stick_deadzone: f32,
+    pub dpad_deadzone: f32,
+}
+
+impl Default for GamepadBindings {
+    fn default() -> GamepadBindings {
+        GamepadBindings {
+            move_left: vec![GamepadButtonType::DPadLeft],
+            move_right: vec![GamepadButtonType::DPadRight],
+            soft_drop: vec![GamepadButtonType::DPadDown],
+            hard_drop: vec![GamepadButtonType::DPadUp],
+            rotate_clockwise: vec![GamepadButtonType::South],
+            rotate_counter_clockwise: vec![GamepadButtonType::East],
+            rotate_180: vec![GamepadButtonType::North],
+            hold: vec![
+                GamepadButtonType::LeftTrigger,
+                GamepadButtonType::RightTrigger,
+            ],
+            pause: vec![GamepadButtonType::Start],
+            stick_deadzone: 0.5,
+            dpad_deadzone: 0.5,
+        }
+    }
+}
+
+impl GamepadBindings {
+    pub fn load() -> GamepadBindings {
+        storage::load_or_init(GAMEPAD_FILE_NAME)
+    }
+
+    pub fn buttons(&self, action: Action) -> &[GamepadButtonType] {
+        match action {
+            Action::MoveLeft => &self.move_left,
+            Action::MoveRight => &self.move_right,
+            Action::SoftDrop => &self.soft_drop,
+            Action::HardDrop => &self.hard_drop,
+            Action::RotateClockwise => &self.rotate_clockwise,
+            Action::RotateCounterClockwise => &self.rotate_counter_clockwise,
+            Action::Rotate180 => &self.rotate_180,
+            Action::Hold => &self.hold,
+            Action::Pause => &self.pause,
+        }
+    }
+
+    /// Action of an axis deflected past its deadzone, if any.
+    fn axis_action(&self, axis: GamepadAxisType, value: f32) -> Option<Action> {
+        let deadzone = match axis {
+            GamepadAxisType::LeftStickX | GamepadAxisType::LeftStickY => self.stick_deadzone,
+            GamepadAxisType::DPadX | GamepadAxisType::DPadY => self.dpad_deadzone,
+            _ => return None,
+        };
+        if value.abs() < deadzone {
+            return None;
+        }
+
+        match axis {
+            GamepadAxisType::LeftStickX | GamepadAxisType::DPadX if value < 0. => {
+                Some(Action::MoveLeft)
+            }
+            GamepadAxisType::LeftStickX | GamepadAxisType::DPadX => Some(Action::MoveRight),
+            GamepadAxisType::LeftStickY | GamepadAxisType::DPadY if value < 0. => {
+                Some(Action::SoftDrop)
+            }
+            _ => None,
+        }
+    }
+}
+
+const MOVEMENT_AXES: [GamepadAxisType; 4] = [
+    GamepadAxisType::LeftStickX,
+    GamepadAxisType::LeftStickY,
+    GamepadAxisType::DPadX,
+    GamepadAxisType::DPadY,
+];
+
+/// Actions held and newly pressed this frame, collected from every input
+/// device before the gameplay systems run.
+#[derive(Default)]
+pub struct Actions {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    previously_pressed: HashSet<Action>,
+}
+
+impl Actions {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Clears a press so that systems running later in the frame, for
+    /// example those of a state entered in response, don't handle it again.
+    pub fn consume(&mut self, action: Action) {
+        self.just_pressed.remove(&action);
+    }
+
+    /// Clears every press of this frame, when a menu handled it.
+    pub fn consume_all(&mut self) {
+        self.just_pressed.clear();
+    }
+
+    /// Releases every action for this frame, while the keys type text.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn release_all(&mut self) {
+        self.pressed.clear();
+        self.just_pressed.clear();
+    }
+
+    /// Input of this frame for `Game::tick`.
+    pub fn input(&self) -> tetris_core::Input {
+        let mut input = tetris_core::Input::default();
+        for action in Action::ALL {
+            if let Some(button) = action.button() {
+                if self.pressed(action) {
+                    input.held.insert(button);
+                }
+                if self.just_pressed(action) {
+                    input.pressed.insert(button);
+                }
+            }
+        }
+        input
+    }
+}
+
+pub fn update_actions(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    bindings: Res<KeyBindings>,
+    gamepad_bindings: Res<GamepadBindings>,
+    mut actions: ResMut<Actions>,
+) {
+    let actions = &mut *actions;
+    actions.previously_pressed = std::mem::take(&mut actions.pressed);
+    actions.just_pressed.clear();
+
+    for action in Action::ALL {
+        let keys = bindings.keys(action);
+        if keyboard_input.any_pressed(keys.iter().copied()) {
+            actions.pressed.insert(action);
+        }
+        if keyboard_input.any_just_pressed(keys.iter().copied()) {
+            actions.just_pressed.insert(action);
+        }
+
+        for gamepad in gamepads.iter() {
+            let buttons = gamepad_bindings
+                .buttons(action)
+                .iter()
+                .map(|button| GamepadButton(*gamepad, *button));
+            if gamepad_input.any_pressed(buttons.clone()) {
+                actions.pressed.insert(action);
+            }
+            if gamepad_input.any_just_pressed(buttons) {
+                actions.just_pressed.insert(action);
+            }
+        }
+    }
+
+    for gamepad in gamepads.iter() {
+        for axis in MOVEMENT_AXES {
+            let value = gamepad_axes.get(GamepadAxis(*gamepad, axis)).unwrap_or(0.);
+            if let Some(action) = gamepad_bindings.axis_action(axis, value) {
+                actions.pressed.insert(action);
+            }
+        }
+    }
+
+    // Axes have no press events, a newly held action counts as a press.
+    let newly_pressed: Vec<Action> = actions
+        .pressed
+        .difference(&actions.previously_pressed)
+        .copied()
+        .collect();
+    actions.just_pressed.extend(newly_pressed);
+}
+
+/// Horizontal distance a finger travels to move the piece by one cell.
+const SWIPE_STEP: f32 = 40.;
+/// Distance under which a touch is a tap.
+const TAP_DISTANCE: f32 = 15.;
+/// Downward distance from which a touch soft drops while it is held.
+const SOFT_DROP_DISTANCE: f32 = 40.;
+/// Downward distance of a swipe that hard drops when the finger is lifted.
+const HARD_DROP_DISTANCE: f32 = 150.;
+
+/// Progress of a finger on the screen.
+pub struct Swipe {
+    /// Horizontal position of the last move step.
+    anchor_x: f32,
+    /// Whether the finger left the tap distance at some point.
+    moved: bool,
+}
+
+/// Downward component of a touch movement. Touch positions grow upwards
+/// like the cursor's, except on mobile platforms.
+fn downward(delta: Vec2) -> f32 {
+    if cfg!(any(target_os = "android", target_os = "ios")) {
+        delta.y
+    } else {
+        -delta.y
+    }
+}
+
+/// Adds touch gestures to the actions of this frame: a tap rotates, swiping
+/// sideways moves a cell per `SWIPE_STEP`, swiping down soft drops and a
+/// long swipe down hard drops.
+pub fn update_touch_actions(
+    touches: Res<Touches>,
+    mut swipes: Local<HashMap<u64, Swipe>>,
+    mut actions: ResMut<Actions>,
+) {
+    let actions = &mut *actions;
+
+    for touch in touches.iter_just_pressed() {
+        swipes.insert(
+            touch.id(),
+            Swipe {
+                anchor_x: touch.position().x,
+                moved: false,
+            },
+        );
+    }
+
+    for touch in touches.iter() {
+        let swipe = match swipes.get_mut(&touch.id()) {
+            Some(swipe) => swipe,
+            None => continue,
+        };
+        let delta = touch.position() - touch.start_position();
+        swipe.moved |= delta.length() > TAP_DISTANCE;
+
+        let steps = ((touch.position().x - swipe.anchor_x) / SWIPE_STEP).trunc();
+        if steps != 0. {
+            swipe.anchor_x += steps * SWIPE_STEP;
+            let action = if steps > 0. {
+                Action::MoveRight
+            } else {
+                Action::MoveLeft
+            };
+            actions.just_pressed.insert(action);
+        }
+
+        let down = downward(delta);
+        if down > SOFT_DROP_DISTANCE && down > delta.x.abs() {
+            actions.pressed.insert(Action::SoftDrop);
+            if !actions.previously_pressed.contains(&Action::SoftDrop) {
+                actions.just_pressed.insert(Action::SoftDrop);
+            }
+        }
+    }
+
+    for touch in touches.iter_just_released() {
+        let swipe = match swipes.remove(&touch.id()) {
+            Some(swipe) => swipe,
+            None => continue,
+        };
+        let delta = touch.position() - touch.start_position();
+        if !swipe.moved {
+            actions.just_pressed.insert(Action::RotateClockwise);
+        } else if downward(delta) > HARD_DROP_DISTANCE && downward(delta) > delta.x.abs() {
+            actions.just_pressed.insert(Action::HardDrop);
+        }
+    }
+    for touch in touches.iter_just_cancelled() {
+        swipes.remove(&touch.id());
+    }
+}
+
+pub fn log_gamepad_connections(mut events: EventReader<GamepadEvent>) {
+    for GamepadEvent(gamepad, event_type) in events.iter() {
+        match event_type {
+            GamepadEventType::Connected => info!("gamepad {} connected", gamepad.0),
+            GamepadEventType::Disconnected => info!("gamepad {} disconnected", gamepad.0),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use bevy::{
+    diagnostic::{Diagnostics, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+use std::time::Duration;
+use tetris_core::Game;
+
+/// Time a frame step advances the game by, a frame at 60 Hz.
+const STEP_FRAME: Duration = Duration::from_micros(16_667);
+/// Speed of the game in slow motion.
+const SLOW_MOTION_FACTOR: u32 = 4;
+
+/// Developer overlay toggled with F3: frame rate, entity count, the seed, the
+/// active piece, the game's timers and the board as text. With
+/// `time_controls`, F5 toggles slow motion, F6 pauses and F7 advances the
+/// paused game a frame.
+pub struct DebugPlugin {
+    pub time_controls: bool,
+}
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .add_plugin(EntityCountDiagnosticsPlugin)
+            .init_resource::<GameClock>()
+            .add_system_to_stage(CoreStage::PreUpdate, advance_clock)
+            .add_startup_system(spawn_debug_text)
+            .add_system(toggle_debug_text)
+            .add_system(sync_debug_text);
+        if self.time_controls {
+            app.add_system_to_stage(CoreStage::PreUpdate, control_clock.before(advance_clock));
+        }
+    }
+}
+
+/// Time the game runs on. It follows the real time unless the time controls
+/// slow it down, pause it or step it.
+#[derive(Default)]
+pub struct GameClock {
+    /// Time the game advances by this frame, `None` while paused.
+    delta: Option<Duration>,
+    slow_motion: bool,
+    paused: bool,
+    /// Whether to advance the paused game a frame.
+    step: bool,
+}
+
+impl GameClock {
+    pub fn delta(&self) -> Option<Duration> {
+        self.delta
+    }
+
+    fn name(&self) -> &'static str {
+        if self.paused {
+            "paused"
+        } else if self.slow_motion {
+            "25%"
+        } else {
+            "100%"
+        }
+    }
+}
+
+fn control_clock(keyboard_input: Res<Input<KeyCode>>, mut clock: ResMut<GameClock>) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        clock.slow_motion = !clock.slow_motion;
+    }
+    if keyboard_input.just_pressed(KeyCode::F6) {
+        clock.paused = !clock.paused;
+    }
+    if keyboard_input.just_pressed(KeyCode::F7) {
+        clock.paused = true;
+        clock.step = true;
+    }
+}
+
+fn advance_clock(time: Res<Time>, mut clock: ResMut<GameClock>) {
+    clock.delta = if clock.paused {
+        std::mem::take(&mut clock.step).then_some(STEP_FRAME)
+    } else if clock.slow_motion {
+        Some(time.delta() / SLOW_MOTION_FACTOR)
+    } else {
+        Some(time.delta())
+    };
+}
+
+#[derive(Component)]
+struct DebugText;
+
+fn spawn_debug_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 14.,
+                    color: Color::WHITE,
+                },
+                default(),
+            ),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(DebugText);
+}
+
+fn toggle_debug_text(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<&mut Visibility, With<DebugText>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        for mut visibility in query.iter_mut() {
+            visibility.is_visible = !visibility.is_visible;
+        }
+    }
+}
+
+fn sync_debug_text(
+    game: Res<Game>,
+    clock: Res<GameClock>,
+    diagnostics: Res<Diagnostics>,
+    mut query: Query<(&mut Text, &Visibility), With<DebugText>>,
+) {
+    let average = |id| {
+        diagnostics
+            .get(id)
+            .and_then(|diagnostic| diagnostic.average())
+            .unwrap_or(0.)
+    };
+    let fps = average(FrameTimeDiagnosticsPlugin::FPS);
+    let entities = average(EntityCountDiagnosticsPlugin::ENTITY_COUNT);
+
+    for (mut text, visibility) in query.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+
+        let piece = &game.piece;
+        text.sections[0].value = format!(
+            "FPS {:.0}\nEntities {:.0}\nClock {}\nSeed {}\nPiece {} at ({}, {}), ghost at {}\nGravity {:.3} s\nLine clear {:.3} s\n\n{}",
+            fps,
+            entities,
+            clock.name(),
+            game.seed(),
+            piece.shape_type.letter(),
+            piece.x,
+            piece.y,
+            game.ghost_y(),
+            game.gravity_remaining().as_secs_f32(),
+            game.line_clear_remaining().as_secs_f32(),
+            board_text(&game)
+        );
+    }
+}
+
+/// The board top row first, `#` for locked blocks and `@` for the active
+/// piece.
+fn board_text(game: &Game) -> String {
+    let piece = game.piece.cells();
+    (0..game.board.height())
+        .rev()
+        .map(|y| {
+            let row: String = (0..game.board.width())
+                .map(|x| {
+                    if game.board.cells[y][x].is_some() {
+                        '#'
+                    } else if piece.contains(&(x as i32, y as i32)) {
+                        '@'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("|{}|", row)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
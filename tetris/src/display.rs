@@ -0,0 +1,157 @@
+//! Fullscreen, vsync and window size, changed on the settings screen or
+//! with F11 and Alt+Enter and restored at startup.
+
+use crate::{menu, storage};
+use bevy::{
+    input::InputSystem,
+    prelude::*,
+    window::{PresentMode, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+use std::io;
+
+const FILE_NAME: &str = "display.toml";
+
+/// Window sizes offered on the settings screen.
+const RESOLUTIONS: [(u32, u32); 5] = [
+    (960, 540),
+    (1280, 720),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1440),
+];
+
+/// Applies the display settings to the window and toggles fullscreen.
+pub struct DisplayPlugin;
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        // Alt+Enter is taken before the menus see an Enter press.
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            toggle_fullscreen
+                .after(InputSystem)
+                .before(menu::update_menu_input),
+        )
+        .add_system(apply_display_settings);
+    }
+}
+
+/// Display configuration, stored in `display.toml`. Missing entries keep
+/// their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub fullscreen: bool,
+    /// Waits for the display's refresh to present frames, capping the frame
+    /// rate and avoiding tearing.
+    pub vsync: bool,
+    /// Size of the window when not fullscreen, in logical pixels.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> DisplaySettings {
+        DisplaySettings {
+            fullscreen: false,
+            vsync: true,
+            width: 1280,
+            height: 720,
+        }
+    }
+}
+
+impl DisplaySettings {
+    pub fn load() -> DisplaySettings {
+        storage::load_or_init(FILE_NAME)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        storage::save(FILE_NAME, self)
+    }
+
+    /// Window of a desktop build opened with these settings.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            title: String::from("Tetris"),
+            width: self.width as f32,
+            height: self.height as f32,
+            present_mode: self.present_mode(),
+            mode: self.window_mode(),
+            ..default()
+        }
+    }
+
+    /// Steps the window size through `RESOLUTIONS`, wrapping around. A size
+    /// not in the list starts from the first one.
+    pub fn cycle_resolution(&mut self, step: isize) {
+        let index = RESOLUTIONS
+            .iter()
+            .position(|&size| size == (self.width, self.height))
+            .map_or(0, |index| {
+                (index as isize + step).rem_euclid(RESOLUTIONS.len() as isize) as usize
+            });
+        (self.width, self.height) = RESOLUTIONS[index];
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::Fifo
+        } else {
+            PresentMode::Immediate
+        }
+    }
+
+    fn window_mode(&self) -> WindowMode {
+        if self.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        }
+    }
+}
+
+/// Switches between fullscreen and a window with F11 or Alt+Enter.
+fn toggle_fullscreen(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut display: ResMut<DisplaySettings>,
+) {
+    let alt = keyboard_input.any_pressed([KeyCode::LAlt, KeyCode::RAlt]);
+    let key = if keyboard_input.just_pressed(KeyCode::F11) {
+        KeyCode::F11
+    } else if alt && keyboard_input.just_pressed(KeyCode::Return) {
+        KeyCode::Return
+    } else {
+        return;
+    };
+
+    keyboard_input.reset(key);
+    display.fullscreen = !display.fullscreen;
+    if let Err(e) = display.save() {
+        warn!("failed to save display settings: {}", e);
+    }
+}
+
+/// Applies changed display settings to the window.
+fn apply_display_settings(display: Res<DisplaySettings>, mut windows: ResMut<Windows>) {
+    if !display.is_changed() {
+        return;
+    }
+
+    let window = match windows.get_primary_mut() {
+        Some(window) => window,
+        None => return,
+    };
+    if window.mode() != display.window_mode() {
+        window.set_mode(display.window_mode());
+    }
+    if window.present_mode() != display.present_mode() {
+        window.set_present_mode(display.present_mode());
+    }
+    // The canvas of the web build follows the browser window instead.
+    if cfg!(not(target_arch = "wasm32")) && !display.fullscreen {
+        window.set_resolution(display.width as f32, display.height as f32);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    board::{BoardCell, BOTTOM_WALL, LEFT_WALL, SQUARE_SIZE, TOP_WALL, WALL_THICKNESS},
+    camera::GameCamera,
+    locale::Locale,
+    piece::{GhostSquare, HoldSquare, PieceSquare, PreviewSquare},
+    puzzles::{self, Puzzles},
+    themes::Theme,
+    ui::{StatsText, STATS_LEFT},
+    GameState,
+};
+use bevy::prelude::*;
+use tetris_core::{Block, Cells, Game, ShapeTypes, BOARD_HEIGHT, BOARD_WIDTH};
+
+/// Puzzle editor opened from the menu: cells are painted on the board, the
+/// piece sequence typed in and the result exported as a puzzle.
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Editor>()
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(open_editor))
+            .add_system_set(SystemSet::on_enter(GameState::Editor).with_system(enter_editor))
+            .add_system_set(
+                SystemSet::on_update(GameState::Editor)
+                    .with_system(paint_cells)
+                    .with_system(edit_puzzle)
+                    .with_system(sync_editor.after(paint_cells).after(edit_puzzle)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Editor).with_system(exit_editor));
+    }
+}
+
+/// Board and piece sequence being edited, kept between visits of the editor.
+pub struct Editor {
+    cells: Cells,
+    pieces: Vec<ShapeTypes>,
+    brush: Block,
+    /// Result of the last export.
+    status: String,
+}
+
+impl Default for Editor {
+    fn default() -> Editor {
+        Editor {
+            cells: vec![vec![None; BOARD_WIDTH]; BOARD_HEIGHT],
+            pieces: Vec::new(),
+            brush: Block::Garbage,
+            status: String::new(),
+        }
+    }
+}
+
+/// Brush, piece sequence and help of the editor, in place of the statistics.
+#[derive(Component)]
+pub struct EditorText;
+
+fn open_editor(mut keyboard_input: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::E) {
+        keyboard_input.reset(KeyCode::E);
+        let _ = state.set(GameState::Editor);
+    }
+}
+
+/// Hides everything of the last game but its board, which shows the edited
+/// cells instead.
+#[allow(clippy::type_complexity)]
+fn enter_editor(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut editor: ResMut<Editor>,
+    mut query: Query<
+        &mut Visibility,
+        Or<(
+            With<PieceSquare>,
+            With<GhostSquare>,
+            With<PreviewSquare>,
+            With<HoldSquare>,
+            With<StatsText>,
+        )>,
+    >,
+) {
+    for mut visibility in query.iter_mut() {
+        visibility.is_visible = false;
+    }
+    editor.status.clear();
+
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 16.,
+                    color: Color::WHITE,
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Top,
+                    horizontal: HorizontalAlign::Left,
+                },
+            ),
+            transform: Transform::from_translation(Vec3::new(STATS_LEFT, TOP_WALL - 10., 0.)),
+            ..default()
+        })
+        .insert(EditorText);
+}
+
+/// Gives the board back to the game, which redraws it.
+fn exit_editor(
+    mut commands: Commands,
+    mut game: ResMut<Game>,
+    text: Query<Entity, With<EditorText>>,
+    mut stats: Query<&mut Visibility, With<StatsText>>,
+) {
+    for entity in text.iter() {
+        commands.entity(entity).despawn();
+    }
+    for mut visibility in stats.iter_mut() {
+        visibility.is_visible = true;
+    }
+    game.set_changed();
+}
+
+/// Board cell under a world position.
+fn world_to_cell(position: Vec2) -> Option<(usize, usize)> {
+    let x = ((position.x - LEFT_WALL - WALL_THICKNESS / 2.) / SQUARE_SIZE).floor();
+    let y = ((position.y - BOTTOM_WALL - WALL_THICKNESS / 2.) / SQUARE_SIZE).floor();
+    let on_board = x >= 0. && y >= 0. && x < BOARD_WIDTH as f32 && y < BOARD_HEIGHT as f32;
+    on_board.then_some((x as usize, y as usize))
+}
+
+/// Paints the cell under the cursor with the brush while the left button is
+/// held and clears it with the right one.
+fn paint_cells(
+    buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    camera: Query<(&GlobalTransform, &OrthographicProjection), With<GameCamera>>,
+    mut editor: ResMut<Editor>,
+) {
+    let block = if buttons.pressed(MouseButton::Left) {
+        Some(editor.brush)
+    } else if buttons.pressed(MouseButton::Right) {
+        None
+    } else {
+        return;
+    };
+    let (window, cursor) = match windows.get_primary() {
+        Some(window) => match window.cursor_position() {
+            Some(cursor) => (window, cursor),
+            None => return,
+        },
+        None => return,
+    };
+
+    for (transform, projection) in camera.iter() {
+        let center = Vec2::new(window.width(), window.height()) / 2.;
+        let position = transform.translation.truncate() + (cursor - center) * projection.scale;
+        if let Some((x, y)) = world_to_cell(position) {
+            if editor.cells[y][x] != block {
+                editor.cells[y][x] = block;
+            }
+        }
+    }
+}
+
+/// Digits pick the brush, piece letters add to the sequence. Enter exports
+/// the puzzle and Escape goes back to the menu.
+fn edit_puzzle(
+    mut characters: EventReader<ReceivedCharacter>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    mut puzzles: ResMut<Puzzles>,
+    locale: Res<Locale>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for event in characters.iter() {
+        let c = event.char.to_ascii_uppercase();
+        match c.to_digit(10) {
+            Some(0) => editor.brush = Block::Garbage,
+            Some(digit) if digit as usize <= ShapeTypes::ALL.len() => {
+                editor.brush = Block::Piece(ShapeTypes::ALL[digit as usize - 1]);
+            }
+            _ => {
+                if let Some(shape_type) = ShapeTypes::from_letter(c) {
+                    editor.pieces.push(shape_type);
+                }
+            }
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        editor.pieces.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Delete) {
+        editor.cells = Editor::default().cells;
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        editor.status = match puzzles::export(&editor.cells, &editor.pieces, &locale) {
+            Ok(path) => {
+                *puzzles = Puzzles::discover();
+                let file = path.file_name().unwrap().to_string_lossy();
+                locale.format("editor.saved", &[("file", &file)])
+            }
+            Err(e) => locale.format("editor.not_saved", &[("error", &e)]),
+        };
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        keyboard_input.reset(KeyCode::Escape);
+        let _ = state.set(GameState::Menu);
+    }
+}
+
+fn sync_editor(
+    editor: Res<Editor>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    mut cells: Query<(&BoardCell, &mut Sprite, &mut Visibility)>,
+    mut text: Query<&mut Text, With<EditorText>>,
+) {
+    if !editor.is_changed() && !theme.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    for (cell, mut sprite, mut visibility) in cells.iter_mut() {
+        // The board has cells for the biggest size, the editor works on the
+        // standard one.
+        let block = editor.cells.get(cell.y).and_then(|row| row.get(cell.x));
+        match block.copied().flatten() {
+            Some(block) => {
+                sprite.color = theme.block_color(block);
+                visibility.is_visible = true;
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+
+    let brush = match editor.brush {
+        Block::Garbage => locale.get("editor.garbage").to_string(),
+        Block::Piece(shape_type) => shape_type.letter().to_string(),
+        Block::Item(item) => locale.get(item.key()).to_lowercase(),
+    };
+    let pieces: String = editor
+        .pieces
+        .iter()
+        .map(|shape_type| shape_type.letter())
+        .collect();
+    for mut text in text.iter_mut() {
+        text.sections[0].value = format!(
+            "{}\n\n{}\n{}\n\n{}\n\n{}",
+            locale.get("editor.title"),
+            locale.format("editor.brush", &[("brush", &brush)]),
+            locale.format("editor.pieces", &[("pieces", &pieces)]),
+            locale.get("editor.keys"),
+            editor.status
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use tetris_core::{Block, Event as GameEvent, Item, ShapeTypes};
+
+/// A new active piece entered the board.
+pub struct PieceSpawned {
+    pub shape_type: ShapeTypes,
+}
+
+/// The active piece was written into the board.
+pub struct PieceLocked {
+    pub cells: Vec<(i32, i32)>,
+    pub block: Block,
+}
+
+/// The locked piece completed rows, they are removed once their animation
+/// is over.
+pub struct ClearStarted {
+    pub rows: Vec<usize>,
+}
+
+/// Full rows were removed from the board, bottom first.
+pub struct LinesCleared {
+    pub rows: Vec<usize>,
+    pub t_spin: bool,
+    pub combo: u32,
+    pub back_to_back: u32,
+}
+
+pub struct LevelUp {
+    pub level: u32,
+}
+
+/// The game was lost, `Game::game_over_cause` tells why.
+pub struct GameOver;
+
+/// The goal of the mode was reached.
+pub struct Finished;
+
+/// The stack topped out in zen and the board was emptied.
+pub struct BoardCleared;
+
+/// A cleared row held an item, whose effect was applied to the own game.
+pub struct ItemTriggered {
+    pub item: Item,
+}
+
+/// A placement in finesse mode took `inputs` presses where `minimal` would
+/// have done.
+pub struct FinesseChecked {
+    pub inputs: u32,
+    pub minimal: u32,
+}
+
+/// Writers of every gameplay event, so the events returned by the game can
+/// be sent to the systems reacting to them.
+#[derive(SystemParam)]
+pub struct GameEvents<'w, 's> {
+    spawned: EventWriter<'w, 's, PieceSpawned>,
+    locked: EventWriter<'w, 's, PieceLocked>,
+    clear_started: EventWriter<'w, 's, ClearStarted>,
+    cleared: EventWriter<'w, 's, LinesCleared>,
+    level_up: EventWriter<'w, 's, LevelUp>,
+    game_over: EventWriter<'w, 's, GameOver>,
+    finished: EventWriter<'w, 's, Finished>,
+    board_cleared: EventWriter<'w, 's, BoardCleared>,
+    finesse_checked: EventWriter<'w, 's, FinesseChecked>,
+    item_triggered: EventWriter<'w, 's, ItemTriggered>,
+}
+
+impl<'w, 's> GameEvents<'w, 's> {
+    pub fn send(&mut self, event: GameEvent) {
+        match event {
+            GameEvent::PieceSpawned { shape_type } => {
+                self.spawned.send(PieceSpawned { shape_type })
+            }
+            GameEvent::PieceLocked { cells, block } => {
+                self.locked.send(PieceLocked { cells, block })
+            }
+            GameEvent::ClearStarted { rows } => self.clear_started.send(ClearStarted { rows }),
+            GameEvent::LinesCleared {
+                rows,
+                t_spin,
+                combo,
+                back_to_back,
+            } => self.cleared.send(LinesCleared {
+                rows,
+                t_spin,
+                combo,
+                back_to_back,
+            }),
+            GameEvent::LevelUp { level } => self.level_up.send(LevelUp { level }),
+            GameEvent::GameOver { .. } => self.game_over.send(GameOver),
+            GameEvent::Finished => self.finished.send(Finished),
+            GameEvent::BoardCleared => self.board_cleared.send(BoardCleared),
+            GameEvent::FinesseChecked { inputs, minimal } => self
+                .finesse_checked
+                .send(FinesseChecked { inputs, minimal }),
+            GameEvent::ItemTriggered { item } => self.item_triggered.send(ItemTriggered { item }),
+            // The tutorial prompt follows `Game::tutorial` instead, and both
+            // games of a versus match are simulated on each side, attacks
+            // included.
+            GameEvent::TutorialStepDone { .. }
+            | GameEvent::GarbageSent { .. }
+            | GameEvent::GarbageInserted { .. } => {}
+        }
+    }
+}
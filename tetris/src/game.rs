@@ -0,0 +1,377 @@
+use crate::{
+    board::{GARBAGE_DELAY_SECONDS, LINE_CLEAR_SECONDS},
+    controls::{Action, Actions},
+    debug::GameClock,
+    events::{
+        BoardCleared, ClearStarted, FinesseChecked, Finished, GameEvents, GameOver, ItemTriggered,
+        LevelUp, LinesCleared, PieceLocked, PieceSpawned,
+    },
+    high_scores::HighScores,
+    puzzles::Puzzles,
+    records::{self, Records},
+    replays::{Playback, Recording},
+    settings::{self, Settings, SettingsWatcher},
+    GameState,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{lobby::Room, versus::Versus};
+use bevy::prelude::*;
+use tetris_core::{
+    BoardSize, BotDriver, Buttons, Config, Game, Handicap, HeuristicBot, Mode, Replay,
+};
+
+/// Playing a game: the countdown, feeding the actions of each frame to the
+/// game and sending what happened as events, pausing, ending the game and
+/// applying changed settings to it.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SelectedMode(Mode::Endless))
+            .insert_resource(Puzzles::discover())
+            .init_resource::<SelectedPuzzle>()
+            .init_resource::<Countdown>()
+            .init_resource::<InputBuffer>()
+            .insert_resource(HighScores::load())
+            .insert_resource(Records::load())
+            .init_resource::<SettingsWatcher>()
+            .add_event::<PieceSpawned>()
+            .add_event::<PieceLocked>()
+            .add_event::<ClearStarted>()
+            .add_event::<LinesCleared>()
+            .add_event::<LevelUp>()
+            .add_event::<GameOver>()
+            .add_event::<Finished>()
+            .add_event::<BoardCleared>()
+            .add_event::<FinesseChecked>()
+            .add_event::<ItemTriggered>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(start_game))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(pause)
+                    .with_system(pause_on_gamepad_disconnect)
+                    .with_system(update_game)
+                    .with_system(end_game.after(update_game)),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Paused).with_system(resume))
+            .add_system(settings::reload_settings)
+            // Before the game ticks, so a change lands between the same ticks in
+            // the replay as in the game.
+            .add_system(
+                apply_settings
+                    .after(settings::reload_settings)
+                    .before(update_game),
+            );
+    }
+}
+
+/// Seed chosen on the command line or in the menu for the next games, a
+/// fresh random seed is used for every game without one.
+pub struct Seed(pub Option<u64>);
+
+/// Mode picked in the menu for the next games. The level cap of marathons
+/// comes from the settings when a game starts.
+pub struct SelectedMode(pub Mode);
+
+/// Index of the puzzle picked on the puzzle select screen.
+#[derive(Default)]
+pub struct SelectedPuzzle(pub usize);
+
+/// Counts 3, 2, 1 before a game starts, then shows GO for a moment. The
+/// game isn't updated until the numbers are over, so gravity and input wait
+/// for the player.
+pub struct Countdown(pub Timer);
+
+impl Countdown {
+    pub fn start() -> Countdown {
+        Countdown(Timer::from_seconds(COUNTDOWN_SECONDS + GO_SECONDS, false))
+    }
+
+    pub fn is_counting(&self) -> bool {
+        self.0.elapsed_secs() < COUNTDOWN_SECONDS
+    }
+}
+
+impl Default for Countdown {
+    /// A countdown that is already over.
+    fn default() -> Countdown {
+        Countdown(Timer::from_seconds(0., false))
+    }
+}
+
+pub const COUNTDOWN_SECONDS: f32 = 3.;
+const GO_SECONDS: f32 = 0.5;
+
+/// Rotations and holds pressed during the countdown, applied to the first
+/// piece at GO together with those still held.
+#[derive(Default)]
+pub struct InputBuffer(pub Buttons);
+
+/// Bot playing in place of the player, with `--bot`. Every game gets a new
+/// one.
+pub struct Autoplay(pub Option<BotDriver>);
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_game(
+    mut game: ResMut<Game>,
+    seed: Res<Seed>,
+    mode: Res<SelectedMode>,
+    settings: Res<Settings>,
+    puzzles: Res<Puzzles>,
+    puzzle: Res<SelectedPuzzle>,
+    mut countdown: ResMut<Countdown>,
+    mut buffer: ResMut<InputBuffer>,
+    mut spawned: EventWriter<PieceSpawned>,
+    #[cfg(not(target_arch = "wasm32"))] room: Option<Res<Room>>,
+    mut recording: ResMut<Recording>,
+    mut playback: ResMut<Playback>,
+    mut autoplay: ResMut<Autoplay>,
+) {
+    if autoplay.0.is_some() {
+        autoplay.0 = Some(BotDriver::new(HeuristicBot::new(settings.rotation)));
+    }
+    // A replay picked on the game over or results screen is watched,
+    // restarting from there plays a new game.
+    if let Some((replay, 0)) = &playback.0 {
+        *game = replay.start();
+        recording.0 = None;
+    } else {
+        playback.0 = None;
+        // Matches in a room are played in the mode and with the seed the
+        // host picked, with the player's handicap. The web build has no
+        // rooms. Every player gets the same pieces in the daily challenge.
+        #[cfg(not(target_arch = "wasm32"))]
+        let room = room.map(|room| (room.mode, room.seed, room.handicap));
+        #[cfg(target_arch = "wasm32")]
+        let room: Option<(Mode, u64, Handicap)> = None;
+        let mode = room.map_or(mode.0, |(mode, _, _)| mode);
+        let seed = match room {
+            Some((_, seed, _)) => seed,
+            None if mode == Mode::Daily => records::daily_seed(records::today()),
+            None => seed.0.unwrap_or_else(rand::random),
+        };
+        let config = match_config(&settings, room.map(|(_, _, handicap)| handicap));
+        *game = match mode {
+            Mode::Marathon { start_level, .. } => Game::new(
+                seed,
+                Mode::Marathon {
+                    start_level: start_level.min(settings.marathon_level_cap),
+                    level_cap: settings.marathon_level_cap,
+                },
+                config,
+            ),
+            Mode::Puzzle => Game::puzzle(seed, &puzzles.list[puzzle.0], config),
+            Mode::Items { .. } => Game::new(
+                seed,
+                Mode::Items {
+                    versus: room.is_some(),
+                },
+                config,
+            ),
+            mode => Game::new(seed, mode, config),
+        };
+        // Puzzles, practice and versus games depend on more than the seed.
+        recording.0 = (!matches!(game.mode, Mode::Puzzle | Mode::Practice) && room.is_none())
+            .then(|| Replay::new(seed, game.mode, game_config(&settings)));
+    }
+    *countdown = Countdown::start();
+    *buffer = InputBuffer::default();
+    spawned.send(PieceSpawned {
+        shape_type: game.piece.shape_type,
+    });
+}
+
+/// Feeds the actions of this frame to the game and sends what happened as
+/// events. The game runs on the clock of the time controls, and doesn't
+/// tick at all while they pause it.
+#[allow(clippy::too_many_arguments)]
+pub fn update_game(
+    clock: Res<GameClock>,
+    actions: Res<Actions>,
+    mut countdown: ResMut<Countdown>,
+    mut buffer: ResMut<InputBuffer>,
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+    mut recording: ResMut<Recording>,
+    mut playback: ResMut<Playback>,
+    mut autoplay: ResMut<Autoplay>,
+    #[cfg(not(target_arch = "wasm32"))] versus: Res<Versus>,
+) {
+    // Versus games run in fixed frames in `update_versus`.
+    #[cfg(not(target_arch = "wasm32"))]
+    if versus.is_running() {
+        return;
+    }
+    let delta = match clock.delta() {
+        Some(delta) => delta,
+        None => return,
+    };
+
+    let input = actions.input();
+    if countdown.is_counting() {
+        buffer.0 = buffer.0.union(input.pressed);
+        countdown.0.tick(delta);
+        if countdown.is_counting() {
+            return;
+        }
+
+        let initial = match &playback.0 {
+            Some((replay, _)) => {
+                replay.apply_config_changes(&mut game, 0);
+                replay.initial
+            }
+            None => std::mem::take(&mut buffer.0).union(input.held),
+        };
+        if let Some(replay) = &mut recording.0 {
+            replay.record_initial(initial);
+        }
+        for event in game.apply_initial_input(initial) {
+            events.send(event);
+        }
+        return;
+    }
+    if !countdown.0.finished() {
+        countdown.0.tick(delta);
+    }
+
+    // A watched replay runs on its recorded input and frame times.
+    let (input, delta) = match (&playback.0, &mut autoplay.0) {
+        (Some(_), _) => match playback.next() {
+            Some(tick) => tick,
+            None => return,
+        },
+        (None, Some(bot)) => (bot.input(&game), delta),
+        (None, None) => (input, delta),
+    };
+    if let Some(replay) = &mut recording.0 {
+        replay.record_tick(input, delta);
+    }
+    for event in game.tick(input, delta) {
+        events.send(event);
+    }
+    if let Some((replay, index)) = &playback.0 {
+        replay.apply_config_changes(&mut game, *index);
+    }
+}
+
+/// Leaves the game when it is over: to the results of a finished mode, or
+/// to the name entry or game over screen after topping out. Only endless
+/// games make it into the high scores.
+#[allow(clippy::too_many_arguments)]
+fn end_game(
+    mut game_over: EventReader<GameOver>,
+    mut finished: EventReader<Finished>,
+    game: Res<Game>,
+    high_scores: Res<HighScores>,
+    mut state: ResMut<State<GameState>>,
+    mut recording: ResMut<Recording>,
+    playback: Res<Playback>,
+    autoplay: Res<Autoplay>,
+) {
+    let finished = finished.iter().count() > 0;
+    if !finished && game_over.iter().count() == 0 {
+        return;
+    }
+    if let Some(replay) = &mut recording.0 {
+        replay.finish(&game);
+    }
+    if finished {
+        let _ = state.set(GameState::Finished);
+        return;
+    }
+
+    // Watched replays and bot games don't make it into the high scores.
+    let next = if game.mode == Mode::Endless
+        && playback.0.is_none()
+        && autoplay.0.is_none()
+        && high_scores.qualifies(game.score)
+    {
+        GameState::NameEntry
+    } else {
+        GameState::GameOver
+    };
+    let _ = state.set(next);
+}
+
+/// Pushes the Paused state on top of Playing, which stops every gameplay
+/// system and timer until it is popped again.
+fn pause(mut actions: ResMut<Actions>, mut state: ResMut<State<GameState>>) {
+    if actions.just_pressed(Action::Pause) {
+        // Consume the press so the Paused state doesn't pop itself this frame.
+        actions.consume(Action::Pause);
+        let _ = state.push(GameState::Paused);
+    }
+}
+
+/// Pauses when a gamepad is unplugged mid-game so the player doesn't lose
+/// control of the falling piece.
+fn pause_on_gamepad_disconnect(
+    mut events: EventReader<GamepadEvent>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let disconnected = events
+        .iter()
+        .any(|event| event.1 == GamepadEventType::Disconnected);
+    if disconnected {
+        let _ = state.push(GameState::Paused);
+    }
+}
+
+fn resume(mut actions: ResMut<Actions>, mut state: ResMut<State<GameState>>) {
+    if actions.just_pressed(Action::Pause) {
+        actions.consume(Action::Pause);
+        let _ = state.pop();
+    }
+}
+
+pub fn game_config(settings: &Settings) -> Config {
+    Config {
+        das: settings.das,
+        arr: settings.arr,
+        line_clear_delay: LINE_CLEAR_SECONDS,
+        garbage_delay: GARBAGE_DELAY_SECONDS,
+        rotation: settings.rotation,
+        kicks: settings.kicks.clone(),
+        previews: settings.previews,
+        scoring: settings.scoring,
+        board_size: settings.board_size(),
+        attack: settings.attack.clone(),
+        speed: match settings.lock_delay {
+            Some(lock_delay) => settings.speed.clone().with_lock_delay(lock_delay),
+            None => settings.speed.clone(),
+        },
+        ..default()
+    }
+}
+
+/// Config of a game, with the player's handicap in a room, where both
+/// players are on the standard board.
+pub fn match_config(settings: &Settings, handicap: Option<Handicap>) -> Config {
+    match handicap {
+        Some(handicap) => Config {
+            board_size: BoardSize::default(),
+            ..game_config(settings).with_handicap(handicap)
+        },
+        None => game_config(settings),
+    }
+}
+
+/// Applies changed settings to the running game. The change is recorded
+/// into the replay so it plays back the same, and a watched replay keeps
+/// the configs it recorded.
+fn apply_settings(
+    settings: Res<Settings>,
+    mut game: ResMut<Game>,
+    mut recording: ResMut<Recording>,
+    playback: Res<Playback>,
+) {
+    if !settings.is_changed() || playback.0.is_some() {
+        return;
+    }
+
+    let config = game_config(&settings);
+    if let Some(replay) = &mut recording.0 {
+        replay.record_config(config.clone());
+    }
+    game.set_config(config);
+}
@@ -0,0 +1,159 @@
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+const MAX_ENTRIES: usize = 10;
+const FILE_NAME: &str = "high_scores.toml";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub lines: u32,
+    pub date: String,
+}
+
+/// Top scores, best first, stored in the platform config directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    #[serde(default)]
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// Loads the table from disk. A missing or unreadable file gives an
+    /// empty table so a broken file never prevents the game from starting.
+    pub fn load() -> HighScores {
+        storage::load(FILE_NAME).unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        storage::save(FILE_NAME, self)
+    }
+
+    /// Whether a score would make it into the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_ENTRIES
+                || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    pub fn insert(&mut self, entry: HighScore) {
+        let position = self
+            .entries
+            .iter()
+            .position(|existing| entry.score > existing.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(position, entry);
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    /// The table as aligned text lines for the menu and game over screens,
+    /// `empty` when there are no entries yet.
+    pub fn to_text(&self, empty: &str) -> String {
+        if self.entries.is_empty() {
+            return empty.to_string();
+        }
+
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                format!(
+                    "{:>2}. {:<10} {:>7} {:>4}  {}",
+                    i + 1,
+                    entry.name,
+                    entry.score,
+                    entry.lines,
+                    entry.date
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            score,
+            lines: 0,
+            date: String::new(),
+        }
+    }
+
+    /// Full table with the scores 100, 90, ... 10.
+    fn full_table() -> HighScores {
+        HighScores {
+            entries: (0..MAX_ENTRIES as u32)
+                .map(|i| entry("AAA", 100 - 10 * i))
+                .collect(),
+        }
+    }
+
+    fn scores(high_scores: &HighScores) -> Vec<u32> {
+        high_scores
+            .entries
+            .iter()
+            .map(|entry| entry.score)
+            .collect()
+    }
+
+    #[test]
+    fn any_score_qualifies_until_the_table_is_full() {
+        let mut high_scores = HighScores::default();
+        assert!(!high_scores.qualifies(0));
+        assert!(high_scores.qualifies(1));
+
+        high_scores.insert(entry("AAA", 5));
+        assert!(high_scores.qualifies(1));
+    }
+
+    #[test]
+    fn entries_are_kept_best_first() {
+        let mut high_scores = HighScores::default();
+        for score in [30, 50, 10, 40] {
+            high_scores.insert(entry("AAA", score));
+        }
+
+        assert_eq!(scores(&high_scores), [50, 40, 30, 10]);
+    }
+
+    #[test]
+    fn a_full_table_drops_its_lowest_entry() {
+        let mut high_scores = full_table();
+        assert!(!high_scores.qualifies(5));
+        assert!(high_scores.qualifies(15));
+
+        high_scores.insert(entry("BBB", 15));
+
+        assert_eq!(high_scores.entries.len(), MAX_ENTRIES);
+        assert_eq!(high_scores.entries[MAX_ENTRIES - 2].score, 20);
+        assert_eq!(high_scores.entries[MAX_ENTRIES - 1].name, "BBB");
+    }
+
+    #[test]
+    fn ties_rank_below_the_older_entry() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("AAA", 50));
+        high_scores.insert(entry("BBB", 50));
+
+        assert_eq!(high_scores.entries[0].name, "AAA");
+        assert_eq!(high_scores.entries[1].name, "BBB");
+    }
+
+    #[test]
+    fn tying_the_lowest_score_of_a_full_table_doesnt_qualify() {
+        let mut high_scores = full_table();
+        assert!(!high_scores.qualifies(10));
+
+        high_scores.insert(entry("BBB", 10));
+
+        assert_eq!(high_scores.entries.len(), MAX_ENTRIES);
+        assert!(high_scores.entries.iter().all(|entry| entry.name == "AAA"));
+    }
+}
@@ -0,0 +1,246 @@
+//! Rooms of networked games. A player hosts a room, the other joins it by
+//! address, both mark themselves ready and the host starts each match. The
+//! host keeps the state of the room and sends it to the other player
+//! whenever it changes.
+
+use crate::{
+    network::{self, Connection, Message, NetEvent, RoomPlayer, PROTOCOL_VERSION},
+    GameState,
+};
+use bevy::prelude::*;
+use tetris_core::{Handicap, Mode};
+
+/// Modes a match can be played in, picked by the host.
+pub const VERSUS_MODES: [Mode; 5] = [
+    Mode::Endless,
+    Mode::Items { versus: true },
+    Mode::Sprint,
+    Mode::Ultra,
+    Mode::Invisible,
+];
+
+/// Turns what the connection received into `NetEvent`s and keeps the room
+/// up to date with them.
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LobbyNotice>()
+            .add_event::<NetEvent>()
+            .add_system_to_stage(CoreStage::PreUpdate, poll_connection)
+            .add_system(update_room);
+    }
+}
+
+/// Room hosted or joined, present as long as the connection is.
+pub struct Room {
+    /// Name this player goes by in the room.
+    pub name: String,
+    /// Whether the other player's socket is connected.
+    pub connected: bool,
+    /// Whether the host took this player in, from the start for the host.
+    pub joined: bool,
+    pub players: Vec<RoomPlayer>,
+    pub mode: Mode,
+    /// Seed of the running or last match.
+    pub seed: u64,
+    /// Handicap this player takes in the matches.
+    pub handicap: Handicap,
+}
+
+impl Room {
+    /// Index of this player in `players`, the host being the first.
+    pub fn own_index(&self, connection: &Connection) -> usize {
+        if connection.is_host() {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Whether the host can start a match: both players are there and
+    /// ready.
+    pub fn can_start(&self) -> bool {
+        self.players.len() == 2 && self.players.iter().all(|player| player.ready)
+    }
+
+    /// Marks this player ready or not and tells the other.
+    pub fn set_ready(&mut self, connection: &Connection, ready: bool) {
+        let index = self.own_index(connection);
+        if let Some(player) = self.players.get_mut(index) {
+            player.ready = ready;
+        }
+        if connection.is_host() {
+            self.send(connection);
+        } else {
+            connection.send(Message::Ready { ready });
+        }
+    }
+
+    /// Changes the handicap of this player and tells the other.
+    pub fn set_handicap(&mut self, connection: &Connection, handicap: Handicap) {
+        self.handicap = handicap;
+        let index = self.own_index(connection);
+        if let Some(player) = self.players.get_mut(index) {
+            player.handicap = handicap;
+        }
+        if connection.is_host() {
+            self.send(connection);
+        } else {
+            connection.send(Message::Handicap { handicap });
+        }
+    }
+
+    /// Starts a match for both players, called by the host.
+    pub fn start(&mut self, connection: &Connection, seed: u64) {
+        self.seed = seed;
+        for player in &mut self.players {
+            player.ready = false;
+        }
+        connection.send(Message::Start { seed });
+        self.send(connection);
+    }
+
+    /// Sends the players and mode to the other player, from the host.
+    pub fn send(&self, connection: &Connection) {
+        connection.send(Message::Room {
+            players: self.players.clone(),
+            mode: self.mode,
+        });
+    }
+}
+
+/// Why the last room was left, as a locale key, shown on the lobby screen.
+#[derive(Default)]
+pub struct LobbyNotice(pub Option<&'static str>);
+
+/// Hosts or joins a room, leaving the current one. The handicap is kept
+/// from the last room.
+pub fn open_room(commands: &mut Commands, mode: network::Mode, name: String, handicap: Handicap) {
+    let is_host = matches!(mode, network::Mode::Host(_));
+    commands.insert_resource(Room {
+        players: if is_host {
+            vec![RoomPlayer {
+                name: name.clone(),
+                ready: false,
+                handicap,
+            }]
+        } else {
+            Vec::new()
+        },
+        name,
+        connected: false,
+        joined: is_host,
+        mode: Mode::Endless,
+        seed: 0,
+        handicap,
+    });
+    commands.insert_resource(Connection::start(mode));
+}
+
+/// Closes the connection, which the other player sees as a disconnect.
+pub fn leave_room(commands: &mut Commands) {
+    commands.remove_resource::<Room>();
+    commands.remove_resource::<Connection>();
+}
+
+fn poll_connection(connection: Option<Res<Connection>>, mut events: EventWriter<NetEvent>) {
+    if let Some(connection) = connection {
+        for event in connection.poll() {
+            events.send(event);
+        }
+    }
+}
+
+/// Handshake and room messages. A joining player introduces themselves,
+/// the host takes them in if they run the same version and sends the room.
+/// A host whose opponent left waits for the next one.
+fn update_room(
+    mut commands: Commands,
+    mut events: EventReader<NetEvent>,
+    connection: Option<Res<Connection>>,
+    room: Option<ResMut<Room>>,
+    mut notice: ResMut<LobbyNotice>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let (connection, mut room) = match (connection, room) {
+        (Some(connection), Some(room)) => (connection, room),
+        _ => return,
+    };
+
+    for event in events.iter() {
+        match event {
+            NetEvent::Connected => {
+                room.connected = true;
+                if !connection.is_host() {
+                    connection.send(Message::Hello {
+                        name: room.name.clone(),
+                        version: PROTOCOL_VERSION,
+                    });
+                    connection.send(Message::Handicap {
+                        handicap: room.handicap,
+                    });
+                }
+            }
+            NetEvent::Received(Message::Hello { name, version }) if connection.is_host() => {
+                if *version != PROTOCOL_VERSION {
+                    connection.send(Message::Refused {
+                        version: PROTOCOL_VERSION,
+                    });
+                    // The refusal is still sent before the socket closes.
+                    room.connected = false;
+                    commands.insert_resource(Connection::start(connection.mode.clone()));
+                    continue;
+                }
+                room.players.truncate(1);
+                room.players.push(RoomPlayer {
+                    name: name.clone(),
+                    ready: false,
+                    handicap: Handicap::default(),
+                });
+                notice.0 = None;
+                room.send(&connection);
+            }
+            NetEvent::Received(Message::Refused { .. }) => {
+                notice.0 = Some("lobby.refused");
+                leave_room(&mut commands);
+            }
+            NetEvent::Received(Message::Room { players, mode }) if !connection.is_host() => {
+                room.joined = true;
+                room.players = players.clone();
+                room.mode = *mode;
+            }
+            NetEvent::Received(Message::Ready { ready }) if connection.is_host() => {
+                if let Some(player) = room.players.get_mut(1) {
+                    player.ready = *ready;
+                }
+                room.send(&connection);
+            }
+            NetEvent::Received(Message::Handicap { handicap }) if connection.is_host() => {
+                if let Some(player) = room.players.get_mut(1) {
+                    player.handicap = *handicap;
+                }
+                room.send(&connection);
+            }
+            NetEvent::Received(Message::Start { seed }) if !connection.is_host() => {
+                room.seed = *seed;
+                let _ = state.set(GameState::Playing);
+            }
+            NetEvent::Disconnected if connection.is_host() && room.connected => {
+                room.players.truncate(1);
+                room.connected = false;
+                notice.0 = Some("lobby.opponent_left");
+                commands.insert_resource(Connection::start(connection.mode.clone()));
+            }
+            NetEvent::Disconnected => {
+                notice.0 = Some(if room.connected {
+                    "lobby.disconnected"
+                } else {
+                    "lobby.connection_failed"
+                });
+                leave_room(&mut commands);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Text of the interface in the language picked on the settings screen.
+//! Each language is a TOML file in `assets/locales` mapping keys such as
+//! `menu.play` to text, built into the game so the web build has them too.
+
+use crate::settings::Settings;
+use bevy::prelude::*;
+use std::{collections::HashMap, fmt::Display};
+
+/// Text showing the text of a key, kept in the current language.
+#[derive(Component)]
+pub struct Translated(pub &'static str);
+
+/// Code and file of each language, English first. Text missing from a
+/// language falls back to English.
+const LANGUAGES: [(&str, &str); 2] = [
+    ("en", include_str!("../assets/locales/en.toml")),
+    ("de", include_str!("../assets/locales/de.toml")),
+];
+
+/// Follows the language of the settings.
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(sync_locale)
+            .add_system(translate_labels.after(sync_locale));
+    }
+}
+
+/// Text of the current language by key.
+pub struct Locale {
+    language: String,
+    strings: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+impl Locale {
+    /// Locale of a language code, English for an unknown code.
+    pub fn load(language: &str) -> Locale {
+        let (code, source) = LANGUAGES
+            .iter()
+            .find(|(code, _)| *code == language)
+            .unwrap_or(&LANGUAGES[0]);
+        Locale {
+            language: code.to_string(),
+            strings: parse(code, source),
+            fallback: parse(LANGUAGES[0].0, LANGUAGES[0].1),
+        }
+    }
+
+    /// Text of a key, the key itself when no language has it.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    /// Text of a key with its `{name}` placeholders replaced by the values.
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        args.iter()
+            .fold(self.get(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), &value.to_string())
+            })
+    }
+}
+
+/// Language code `step` places after this one, wrapping around.
+pub fn cycle_language(language: &str, step: isize) -> &'static str {
+    let index = LANGUAGES
+        .iter()
+        .position(|(code, _)| *code == language)
+        .unwrap_or(0) as isize;
+    LANGUAGES[(index + step).rem_euclid(LANGUAGES.len() as isize) as usize].0
+}
+
+/// Flattens a language file into keys joined with dots.
+fn parse(code: &str, source: &str) -> HashMap<String, String> {
+    let mut strings = HashMap::new();
+    match source.parse::<toml::Value>() {
+        Ok(toml::Value::Table(table)) => flatten("", table, &mut strings),
+        Ok(_) => unreachable!(),
+        Err(e) => warn!("failed to parse locale {}: {}", code, e),
+    }
+    strings
+}
+
+fn flatten(prefix: &str, table: toml::value::Table, strings: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            toml::Value::String(text) => {
+                strings.insert(key, text);
+            }
+            toml::Value::Table(table) => flatten(&key, table, strings),
+            _ => warn!("ignoring locale entry {}, which is not text", key),
+        }
+    }
+}
+
+/// Switches the text to the language of the settings once it changes.
+fn sync_locale(settings: Res<Settings>, mut locale: ResMut<Locale>) {
+    if settings.is_changed() && settings.language != locale.language {
+        *locale = Locale::load(&settings.language);
+    }
+}
+
+fn translate_labels(locale: Res<Locale>, mut query: Query<(&Translated, &mut Text)>) {
+    if !locale.is_changed() {
+        return;
+    }
+
+    for (translated, mut text) in query.iter_mut() {
+        text.sections[0].value = locale.get(translated.0).to_string();
+    }
+}
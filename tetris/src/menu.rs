@@ -0,0 +1,254 @@
+//! Menus navigable with the keyboard or a gamepad. `MenuInput` collects the
+//! menu commands of a frame from both, `MenuControls` hands them to the menu
+//! systems and a `Menu` moves its focus with them and lays out its items.
+
+use crate::controls::Actions;
+use bevy::{
+    ecs::system::SystemParam,
+    input::{
+        gamepad::{GamepadAxisType, GamepadButtonType},
+        InputSystem,
+    },
+    prelude::*,
+};
+
+/// Color of the focused menu item.
+const FOCUS_COLOR: Color = Color::rgb(1., 0.85, 0.3);
+/// Deflection of the left stick or an axis d-pad that counts as a press.
+const STICK_DEADZONE: f32 = 0.5;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuInput>()
+            .add_system_to_stage(CoreStage::PreUpdate, update_menu_input.after(InputSystem));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuCommand {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Back,
+}
+
+impl MenuCommand {
+    const ALL: [MenuCommand; 6] = [
+        MenuCommand::Up,
+        MenuCommand::Down,
+        MenuCommand::Left,
+        MenuCommand::Right,
+        MenuCommand::Confirm,
+        MenuCommand::Back,
+    ];
+
+    fn key(self) -> KeyCode {
+        match self {
+            MenuCommand::Up => KeyCode::Up,
+            MenuCommand::Down => KeyCode::Down,
+            MenuCommand::Left => KeyCode::Left,
+            MenuCommand::Right => KeyCode::Right,
+            MenuCommand::Confirm => KeyCode::Return,
+            MenuCommand::Back => KeyCode::Escape,
+        }
+    }
+
+    fn gamepad_button(self) -> GamepadButtonType {
+        match self {
+            MenuCommand::Up => GamepadButtonType::DPadUp,
+            MenuCommand::Down => GamepadButtonType::DPadDown,
+            MenuCommand::Left => GamepadButtonType::DPadLeft,
+            MenuCommand::Right => GamepadButtonType::DPadRight,
+            MenuCommand::Confirm => GamepadButtonType::South,
+            MenuCommand::Back => GamepadButtonType::East,
+        }
+    }
+
+    /// Command of an axis deflected past the deadzone, if any.
+    fn of_axis(axis: GamepadAxisType, value: f32) -> Option<MenuCommand> {
+        if value.abs() < STICK_DEADZONE {
+            return None;
+        }
+
+        match axis {
+            GamepadAxisType::LeftStickX | GamepadAxisType::DPadX if value < 0. => {
+                Some(MenuCommand::Left)
+            }
+            GamepadAxisType::LeftStickX | GamepadAxisType::DPadX => Some(MenuCommand::Right),
+            GamepadAxisType::LeftStickY | GamepadAxisType::DPadY if value < 0. => {
+                Some(MenuCommand::Down)
+            }
+            GamepadAxisType::LeftStickY | GamepadAxisType::DPadY => Some(MenuCommand::Up),
+            _ => None,
+        }
+    }
+}
+
+/// Menu commands pressed this frame and the axis commands held, to tell
+/// when the stick is newly pushed.
+#[derive(Default)]
+pub struct MenuInput {
+    just_pressed: Vec<MenuCommand>,
+    held_axes: Vec<MenuCommand>,
+}
+
+const MENU_AXES: [GamepadAxisType; 4] = [
+    GamepadAxisType::LeftStickX,
+    GamepadAxisType::LeftStickY,
+    GamepadAxisType::DPadX,
+    GamepadAxisType::DPadY,
+];
+
+/// Reads the arrows, Enter and Esc, and on gamepads the d-pad, the left
+/// stick, South to confirm and East to go back.
+pub fn update_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut menu_input: ResMut<MenuInput>,
+) {
+    let menu_input = &mut *menu_input;
+    menu_input.just_pressed.clear();
+
+    for command in MenuCommand::ALL {
+        let pressed = keyboard_input.just_pressed(command.key())
+            || gamepads.iter().any(|gamepad| {
+                gamepad_input.just_pressed(GamepadButton(*gamepad, command.gamepad_button()))
+            });
+        if pressed {
+            menu_input.just_pressed.push(command);
+        }
+    }
+
+    let mut held_axes = Vec::new();
+    for gamepad in gamepads.iter() {
+        for axis in MENU_AXES {
+            let value = gamepad_axes.get(GamepadAxis(*gamepad, axis)).unwrap_or(0.);
+            held_axes.extend(MenuCommand::of_axis(axis, value));
+        }
+    }
+    for &command in &held_axes {
+        if !menu_input.held_axes.contains(&command) && !menu_input.just_pressed.contains(&command) {
+            menu_input.just_pressed.push(command);
+        }
+    }
+    menu_input.held_axes = held_axes;
+}
+
+/// Menu commands for the systems of a menu. Taking a command also hides
+/// its press from the gameplay actions and keys, so a menu leading into a
+/// game or another screen doesn't pass the press on.
+#[derive(SystemParam)]
+pub struct MenuControls<'w, 's> {
+    input: ResMut<'w, MenuInput>,
+    keyboard_input: ResMut<'w, Input<KeyCode>>,
+    actions: ResMut<'w, Actions>,
+    #[system_param(ignore)]
+    marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl<'w, 's> MenuControls<'w, 's> {
+    /// Whether `command` was pressed this frame, consuming it.
+    pub fn take(&mut self, command: MenuCommand) -> bool {
+        let index = match self.input.just_pressed.iter().position(|&c| c == command) {
+            Some(index) => index,
+            None => return false,
+        };
+        self.input.just_pressed.remove(index);
+        self.keyboard_input.reset(command.key());
+        self.actions.consume_all();
+        true
+    }
+
+    /// Any key pressed this frame, consuming it along with the menu commands,
+    /// for menus waiting for a key to bind.
+    pub fn take_key(&mut self) -> Option<KeyCode> {
+        let key = self.keyboard_input.get_just_pressed().next().copied()?;
+        self.keyboard_input.reset(key);
+        self.input.just_pressed.clear();
+        self.actions.consume_all();
+        Some(key)
+    }
+
+    /// Whether a key was pressed this frame, for text entry next to a menu.
+    pub fn key_just_pressed(&self, key: KeyCode) -> bool {
+        self.keyboard_input.just_pressed(key)
+    }
+}
+
+/// What the commands of a frame ask of a menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuEvent {
+    /// The focus moved to the item at this index.
+    Focus(usize),
+    /// The focused item's value is stepped left (-1) or right (1).
+    Change(i32),
+    Confirm,
+    Back,
+}
+
+/// Vertical list of selectable items with one of them focused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Menu {
+    pub focused: usize,
+}
+
+impl Menu {
+    pub fn focused_on(focused: usize) -> Menu {
+        Menu { focused }
+    }
+
+    /// Handles the commands of this frame for a menu of `len` items: Up and
+    /// Down move the focus, wrapping around, Left and Right change the
+    /// focused item and Confirm and Back are passed on.
+    pub fn navigate(&self, controls: &mut MenuControls, len: usize) -> Option<MenuEvent> {
+        if len == 0 {
+            return controls.take(MenuCommand::Back).then_some(MenuEvent::Back);
+        }
+
+        if controls.take(MenuCommand::Up) {
+            Some(MenuEvent::Focus((self.focused + len - 1) % len))
+        } else if controls.take(MenuCommand::Down) {
+            Some(MenuEvent::Focus((self.focused + 1) % len))
+        } else if controls.take(MenuCommand::Left) {
+            Some(MenuEvent::Change(-1))
+        } else if controls.take(MenuCommand::Right) {
+            Some(MenuEvent::Change(1))
+        } else if controls.take(MenuCommand::Confirm) {
+            Some(MenuEvent::Confirm)
+        } else if controls.take(MenuCommand::Back) {
+            Some(MenuEvent::Back)
+        } else {
+            None
+        }
+    }
+
+    /// Text of the menu in `style`, one item per line with the focused one
+    /// marked and highlighted, followed by `footer`.
+    pub fn sections(&self, style: &TextStyle, items: &[String], footer: &str) -> Vec<TextSection> {
+        let mut sections: Vec<_> = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let focused = i == self.focused;
+                TextSection {
+                    value: format!("{} {}\n", if focused { '>' } else { ' ' }, item),
+                    style: TextStyle {
+                        color: if focused { FOCUS_COLOR } else { style.color },
+                        ..style.clone()
+                    },
+                }
+            })
+            .collect();
+        sections.push(TextSection {
+            value: footer.to_string(),
+            style: style.clone(),
+        });
+        sections
+    }
+}
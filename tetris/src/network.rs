@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tetris_core::{Config, Handicap, Input, Mode as GameMode};
+
+pub const DEFAULT_PORT: u16 = 7878;
+/// Version of the messages below. Players with different versions can't
+/// play together, the host refuses the other.
+pub const PROTOCOL_VERSION: u32 = 3;
+/// How often a waiting host checks whether it should stop listening.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+/// Bytes of the longest line read from the opponent, far more than any
+/// message takes. A longer one closes the connection rather than growing
+/// the buffer without bound.
+const MAX_LINE: u64 = 64 * 1024;
+
+/// How to reach the opponent, chosen on the command line or in the lobby.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// Wait for the opponent to connect on the given port.
+    Host(u16),
+    /// Connect to a hosting opponent at `host:port`.
+    Connect(String),
+}
+
+/// Messages to the opponent, one JSON object per line. Both sides simulate
+/// both games, so only how a game starts and the inputs of every frame are
+/// exchanged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Message {
+    /// First message of a joining player, answered by the host with the
+    /// room or a refusal.
+    Hello { name: String, version: u32 },
+    /// The host runs another version, the connection is closed.
+    Refused { version: u32 },
+    /// Players of the room and the mode of the next match, sent by the host
+    /// whenever they change.
+    Room {
+        players: Vec<RoomPlayer>,
+        mode: GameMode,
+    },
+    /// The sender is ready for the next match, or not anymore.
+    Ready { ready: bool },
+    /// The sender takes this handicap in the next matches.
+    Handicap { handicap: Handicap },
+    /// The host starts a match of the room's mode, with a seed for the
+    /// pieces so both players get the same ones.
+    Start { seed: u64 },
+    /// The sender started a game, which the receiver creates from the same
+    /// settings.
+    NewGame {
+        seed: u64,
+        mode: GameMode,
+        config: Box<Config>,
+    },
+    /// Input of the sender in a frame of their game, frames counted from 0
+    /// at every new game.
+    Input { frame: u32, input: Input },
+    /// Line of the chat.
+    Chat { text: String },
+}
+
+/// Player in a room, the host first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomPlayer {
+    pub name: String,
+    pub ready: bool,
+    pub handicap: Handicap,
+}
+
+pub enum NetEvent {
+    Connected,
+    Received(Message),
+    Disconnected,
+}
+
+/// Link to the opponent. Sockets live on background threads so the game
+/// never blocks on the network; messages go through channels. Dropping it
+/// closes the socket or stops listening.
+pub struct Connection {
+    pub mode: Mode,
+    outgoing: Sender<Message>,
+    incoming: Mutex<Receiver<NetEvent>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Connection {
+    pub fn start(mode: Mode) -> Connection {
+        let (outgoing, outgoing_rx) = mpsc::channel();
+        let (incoming_tx, incoming) = mpsc::channel();
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let (run_mode, run_closed) = (mode.clone(), closed.clone());
+        thread::spawn(move || {
+            if let Err(e) = run(run_mode, &run_closed, outgoing_rx, &incoming_tx) {
+                eprintln!("network error: {}", e);
+            }
+            let _ = incoming_tx.send(NetEvent::Disconnected);
+        });
+
+        Connection {
+            mode,
+            outgoing,
+            incoming: Mutex::new(incoming),
+            closed,
+        }
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.mode, Mode::Host(_))
+    }
+
+    /// Queues a message, it is sent as soon as the opponent is connected.
+    pub fn send(&self, message: Message) {
+        let _ = self.outgoing.send(message);
+    }
+
+    /// Events received since the last call.
+    pub fn poll(&self) -> Vec<NetEvent> {
+        match self.incoming.lock() {
+            Ok(incoming) => incoming.try_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+fn run(
+    mode: Mode,
+    closed: &AtomicBool,
+    outgoing: Receiver<Message>,
+    incoming: &Sender<NetEvent>,
+) -> io::Result<()> {
+    let stream = match mode {
+        Mode::Host(port) => match accept(port, closed)? {
+            Some(stream) => stream,
+            None => return Ok(()),
+        },
+        Mode::Connect(address) => TcpStream::connect(address)?,
+    };
+    stream.set_nodelay(true)?;
+    let _ = incoming.send(NetEvent::Connected);
+
+    let writer = stream.try_clone()?;
+    thread::spawn(move || write_messages(writer, outgoing));
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        let more = read_line(&mut reader, &mut line).inspect_err(|_| {
+            // Ends the writing side too, the opponent is dropped.
+            let _ = reader.get_ref().shutdown(Shutdown::Both);
+        })?;
+        if !more {
+            break;
+        }
+        let message = serde_json::from_str(&line)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if incoming.send(NetEvent::Received(message)).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the next line into `line`, `false` at the end of the stream. Lines
+/// longer than `MAX_LINE` are an error.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<bool> {
+    line.clear();
+    let read = reader.take(MAX_LINE + 1).read_line(line)?;
+    if read as u64 > MAX_LINE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line longer than {} bytes", MAX_LINE),
+        ));
+    }
+    Ok(read > 0)
+}
+
+/// Waits for an opponent on `port`, `None` once the connection is closed
+/// before anyone joined.
+fn accept(port: u16, closed: &AtomicBool) -> io::Result<Option<TcpStream>> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    listener.set_nonblocking(true)?;
+    println!("waiting for an opponent on port {}", port);
+    while !closed.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                return Ok(Some(stream));
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+/// Writes the queued messages until the connection is dropped, then shuts
+/// the socket down, which also ends the reading side.
+fn write_messages(mut stream: TcpStream, outgoing: Receiver<Message>) {
+    for message in outgoing {
+        let line = match serde_json::to_string(&message) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("failed to encode {:?}: {}", message, e);
+                continue;
+            }
+        };
+        if writeln!(stream, "{}", line).is_err() {
+            break;
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn lines_are_read_until_the_end_of_the_stream() {
+        let mut reader = Cursor::new("{\"Ready\":{\"ready\":true}}\nlast");
+        let mut line = String::new();
+
+        assert!(read_line(&mut reader, &mut line).unwrap());
+        assert!(serde_json::from_str::<Message>(&line).is_ok());
+        assert!(read_line(&mut reader, &mut line).unwrap());
+        assert_eq!(line, "last");
+        assert!(!read_line(&mut reader, &mut line).unwrap());
+    }
+
+    #[test]
+    fn lines_over_the_limit_are_refused() {
+        let longest = "x".repeat(MAX_LINE as usize - 1) + "\n";
+        let mut line = String::new();
+        assert!(read_line(&mut Cursor::new(&longest), &mut line).unwrap());
+
+        let endless = io::repeat(b'x');
+        let result = read_line(&mut BufReader::new(endless), &mut line);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(line.len() as u64 <= MAX_LINE + 1);
+    }
+}
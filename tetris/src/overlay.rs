@@ -0,0 +1,230 @@
+//! Live state of the game streamed as JSON over a local WebSocket with
+//! `--overlay [port]`, for browser sources of streaming software. Clients get
+//! the board and numbers a few times a second and the gameplay events as they
+//! happen, each as a text message with a `type` field.
+
+use crate::events::{Finished, GameOver, LevelUp, LinesCleared};
+use bevy::prelude::*;
+use serde::Serialize;
+use std::{
+    io,
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+use tetris_core::{Block, Game, Mode};
+use tungstenite::WebSocket;
+
+const STATES_PER_SECOND: f32 = 10.;
+/// Time a client may take to read a message before it is dropped, so a
+/// stuck browser doesn't hold up the others.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Time a client may take to send its handshake before it is dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Streams the game to the overlay clients connected on `port`.
+pub struct OverlayPlugin {
+    pub port: u16,
+}
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(OverlayServer::start(self.port))
+            .insert_resource(StateTimer(Timer::from_seconds(
+                1. / STATES_PER_SECOND,
+                true,
+            )))
+            .add_system(stream_state)
+            .add_system(stream_events);
+    }
+}
+
+/// Messages sent to every client.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OverlayMessage {
+    State {
+        mode: Mode,
+        score: u32,
+        level: u32,
+        lines: u32,
+        pieces: u32,
+        seconds: f32,
+        /// Rows of the board with the active piece, top first: a piece
+        /// letter, `#` for garbage, `*` for items and `.` for empty cells.
+        board: Vec<String>,
+        piece: char,
+        hold: Option<char>,
+        next: Vec<char>,
+        /// Garbage rows waiting to enter the board.
+        garbage: u32,
+        over: bool,
+    },
+    LinesCleared {
+        lines: usize,
+        t_spin: bool,
+        combo: u32,
+        back_to_back: u32,
+    },
+    LevelUp {
+        level: u32,
+    },
+    GameOver,
+    Finished,
+}
+
+/// Hands encoded messages to the thread writing them to the clients.
+struct OverlayServer {
+    outgoing: Sender<String>,
+}
+
+impl OverlayServer {
+    /// Accepts clients on `port` of this machine and broadcasts to them, both
+    /// on threads of their own.
+    fn start(port: u16) -> OverlayServer {
+        let (outgoing, messages) = mpsc::channel();
+        let (clients_tx, clients) = mpsc::channel();
+        thread::spawn(move || {
+            if let Err(e) = accept_clients(port, clients_tx) {
+                warn!("failed to open the overlay port {}: {}", port, e);
+            }
+        });
+        thread::spawn(move || broadcast(messages, clients));
+        OverlayServer { outgoing }
+    }
+
+    fn send(&self, message: &OverlayMessage) {
+        match serde_json::to_string(message) {
+            Ok(text) => {
+                let _ = self.outgoing.send(text);
+            }
+            Err(e) => warn!("failed to encode an overlay message: {}", e),
+        }
+    }
+}
+
+/// Accepts connections on `port` for as long as the game runs, shaking hands
+/// with each on a thread of its own so a silent client doesn't keep the
+/// others out.
+fn accept_clients(port: u16, clients: Sender<WebSocket<TcpStream>>) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    info!("streaming the game to overlays on ws://127.0.0.1:{}", port);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("failed to accept an overlay client: {}", e);
+                continue;
+            }
+        };
+        let clients = clients.clone();
+        thread::spawn(move || {
+            if let Err(e) = stream
+                .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+                .and_then(|()| stream.set_write_timeout(Some(WRITE_TIMEOUT)))
+            {
+                warn!("failed to set up an overlay client: {}", e);
+                return;
+            }
+            match tungstenite::accept(stream) {
+                Ok(client) => {
+                    let _ = clients.send(client);
+                }
+                Err(e) => warn!("overlay handshake failed: {}", e),
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Writes every message to the clients connected so far, dropping those
+/// that went away.
+fn broadcast(messages: Receiver<String>, new_clients: Receiver<WebSocket<TcpStream>>) {
+    let mut clients = Vec::new();
+    for text in messages {
+        clients.extend(new_clients.try_iter());
+        clients.retain_mut(|client: &mut WebSocket<TcpStream>| {
+            client
+                .write_message(tungstenite::Message::Text(text.clone()))
+                .is_ok()
+        });
+    }
+}
+
+struct StateTimer(Timer);
+
+fn stream_state(
+    time: Res<Time>,
+    game: Res<Game>,
+    server: Res<OverlayServer>,
+    mut timer: ResMut<StateTimer>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        server.send(&state(&game));
+    }
+}
+
+fn state(game: &Game) -> OverlayMessage {
+    let piece = game.piece.cells();
+    let board = (0..game.board.height())
+        .rev()
+        .map(|y| {
+            (0..game.board.width())
+                .map(|x| match game.board.cells[y][x] {
+                    Some(Block::Piece(shape_type)) => shape_type.letter(),
+                    Some(Block::Garbage) => '#',
+                    Some(Block::Item(_)) => '*',
+                    None if piece.contains(&(x as i32, y as i32)) && !game.over => {
+                        game.piece.shape_type.letter()
+                    }
+                    None => '.',
+                })
+                .collect()
+        })
+        .collect();
+    OverlayMessage::State {
+        mode: game.mode,
+        score: game.score,
+        level: game.level.level,
+        lines: game.level.lines,
+        pieces: game.stats.pieces,
+        seconds: game.elapsed.as_secs_f32(),
+        board,
+        piece: game.piece.shape_type.letter(),
+        hold: game.hold.map(|shape_type| shape_type.letter()),
+        next: game
+            .queue
+            .previews()
+            .map(|shape_type| shape_type.letter())
+            .collect(),
+        garbage: game.pending_garbage(),
+        over: game.over,
+    }
+}
+
+fn stream_events(
+    mut cleared: EventReader<LinesCleared>,
+    mut level_up: EventReader<LevelUp>,
+    mut game_over: EventReader<GameOver>,
+    mut finished: EventReader<Finished>,
+    server: Res<OverlayServer>,
+) {
+    for event in cleared.iter() {
+        server.send(&OverlayMessage::LinesCleared {
+            lines: event.rows.len(),
+            t_spin: event.t_spin,
+            combo: event.combo,
+            back_to_back: event.back_to_back,
+        });
+    }
+    for event in level_up.iter() {
+        server.send(&OverlayMessage::LevelUp { level: event.level });
+    }
+    for _ in game_over.iter() {
+        server.send(&OverlayMessage::GameOver);
+    }
+    for _ in finished.iter() {
+        server.send(&OverlayMessage::Finished);
+    }
+}
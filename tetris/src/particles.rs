@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+const PARTICLE_SIZE: f32 = 4.;
+/// Downward acceleration of particles, in world units per second squared.
+const GRAVITY: f32 = 400.;
+
+/// Moves and fades the particles of `spawn_burst`.
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(update_particles);
+    }
+}
+
+#[derive(Component)]
+pub struct Velocity(pub Vec2);
+
+/// Time left before a particle disappears, it fades out meanwhile.
+#[derive(Component)]
+pub struct Lifetime(pub Timer);
+
+/// Spawns `count` particles flying out of `position` in random directions
+/// at up to `speed` world units per second.
+pub fn spawn_burst(
+    commands: &mut Commands,
+    position: Vec3,
+    color: Color,
+    count: usize,
+    speed: f32,
+) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..count {
+        let angle = rng.gen_range(0. ..std::f32::consts::TAU);
+        let velocity = Vec2::new(angle.cos(), angle.sin()) * rng.gen_range(0.3..1.) * speed;
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: Transform {
+                    // In front of the board and the pieces.
+                    translation: position + Vec3::new(0., 0., 1.),
+                    scale: Vec3::new(PARTICLE_SIZE, PARTICLE_SIZE, 1.),
+                    ..default()
+                },
+                sprite: Sprite { color, ..default() },
+                ..default()
+            })
+            .insert(Velocity(velocity))
+            .insert(Lifetime(Timer::from_seconds(
+                rng.gen_range(0.3..0.8),
+                false,
+            )));
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &mut Velocity,
+        &mut Lifetime,
+        &mut Transform,
+        &mut Sprite,
+    )>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut velocity, mut lifetime, mut transform, mut sprite) in query.iter_mut() {
+        if lifetime.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        velocity.0.y -= GRAVITY * delta;
+        transform.translation += velocity.0.extend(0.) * delta;
+        sprite.color.set_a(1. - lifetime.0.percent());
+    }
+}
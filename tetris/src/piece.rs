@@ -0,0 +1,413 @@
+use crate::{
+    board::{
+        square_sprite, BoardLayout, LEFT_WALL, RIGHT_WALL, SQUARE_SIZE, TOP_WALL, WALL_THICKNESS,
+    },
+    events::PieceSpawned,
+    locale::{Locale, Translated},
+    settings::Settings,
+    themes::Theme,
+    ui::label,
+};
+use bevy::prelude::*;
+use tetris_core::{Block, Game, ShapeTypes, MAX_PREVIEWS};
+
+/// The active piece, its ghost and the mini-boards of the hold and preview
+/// panels.
+pub struct PiecePlugin;
+
+impl Plugin for PiecePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_pieces)
+            .add_system(sync_piece_sprites)
+            .add_system(sync_ghost_sprites)
+            .add_system(
+                tween_sprites
+                    .after(sync_piece_sprites)
+                    .after(sync_ghost_sprites),
+            )
+            .add_system(sync_preview_sprites)
+            .add_system(sync_hold_sprites)
+            .add_system(sync_mini_boards);
+    }
+}
+
+/// One of the four sprites showing the active piece, indexed into its squares.
+#[derive(Component)]
+pub struct PieceSquare(usize);
+
+/// Slides a sprite to the position of its cell over `TWEEN_SECONDS` instead
+/// of jumping there, the game itself still moves cell by cell.
+#[derive(Component)]
+struct Tween {
+    start: Vec3,
+    target: Vec3,
+    timer: Timer,
+}
+
+const TWEEN_SECONDS: f32 = 0.05;
+
+impl Tween {
+    fn new() -> Tween {
+        Tween {
+            start: Vec3::ZERO,
+            target: Vec3::ZERO,
+            timer: Timer::from_seconds(TWEEN_SECONDS, false),
+        }
+    }
+
+    /// Starts sliding from `current` if the target moved.
+    fn set_target(&mut self, current: Vec3, target: Vec3) {
+        if target != self.target {
+            self.start = current;
+            self.target = target;
+            self.timer.reset();
+        }
+    }
+
+    /// Moves straight to `target`, for pieces appearing somewhere new.
+    fn snap(&mut self, target: Vec3) {
+        self.start = target;
+        self.target = target;
+    }
+}
+
+/// One of the four translucent sprites marking where the active piece would
+/// land.
+#[derive(Component)]
+pub struct GhostSquare(usize);
+
+const GHOST_ALPHA: f32 = 0.25;
+
+/// Square of the held piece on the hold mini-board.
+#[derive(Component)]
+pub struct HoldSquare(usize);
+
+/// Alpha of the held piece while it can't be swapped back in, after a hold
+/// and until the next piece spawns.
+const USED_HOLD_ALPHA: f32 = 0.4;
+
+/// Square of the piece on the mini-board of the given slot of the preview
+/// panel.
+#[derive(Component)]
+pub struct PreviewSquare {
+    slot: usize,
+    index: usize,
+}
+
+/// The NEXT label over the preview panel, hidden without previews.
+#[derive(Component)]
+struct NextLabel;
+
+/// Cell of the empty grid of a mini-board.
+#[derive(Component)]
+struct MiniCell(Panel);
+
+/// Cells across and down a mini-board, enough for any piece.
+const MINI_CELLS: i32 = 4;
+/// Size of the mini-board cells against those of the playfield.
+const MINI_SCALE: f32 = 0.6;
+const MINI_SQUARE: f32 = SQUARE_SIZE * MINI_SCALE;
+const MINI_BOARD_SIZE: f32 = MINI_CELLS as f32 * MINI_SQUARE;
+const MINI_GRID_ALPHA: f32 = 0.08;
+/// Space between the playfield and the mini-boards beside it, and under the
+/// labels over them.
+const PANEL_GAP: f32 = SQUARE_SIZE;
+const PANEL_TOP: f32 = TOP_WALL - WALL_THICKNESS / 2. - 2. * PANEL_GAP;
+
+/// Centers of the hold and preview panels, a gap away from the walls.
+pub const HOLD_X: f32 = LEFT_WALL - WALL_THICKNESS / 2. - PANEL_GAP - MINI_BOARD_SIZE / 2.;
+pub const PREVIEW_X: f32 = RIGHT_WALL + WALL_THICKNESS / 2. + PANEL_GAP + MINI_BOARD_SIZE / 2.;
+/// Bottom of the hold mini-board.
+pub const HOLD_BOTTOM: f32 = PANEL_TOP - MINI_BOARD_SIZE;
+
+/// Mini-boards beside the playfield, laid out from the walls of the standard
+/// board.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    Hold,
+    /// Slot of the preview panel, 0 being the next piece. Slots are stacked
+    /// a mini-board cell apart.
+    Preview(usize),
+}
+
+impl Panel {
+    /// Center of the cell `x`, `y` of the mini-board, row 0 at the bottom.
+    fn cell_to_world(self, x: i32, y: i32) -> Vec3 {
+        let (center_x, top) = match self {
+            Panel::Hold => (HOLD_X, PANEL_TOP),
+            Panel::Preview(slot) => (
+                PREVIEW_X,
+                PANEL_TOP - slot as f32 * (MINI_BOARD_SIZE + MINI_SQUARE),
+            ),
+        };
+        Vec3::new(
+            center_x - MINI_BOARD_SIZE / 2. + (x as f32 + 0.5) * MINI_SQUARE,
+            top - MINI_BOARD_SIZE + (y as f32 + 0.5) * MINI_SQUARE,
+            0.,
+        )
+    }
+}
+
+fn mini_sprite(position: Vec3) -> SpriteBundle {
+    let mut sprite = square_sprite(Color::NONE, position);
+    sprite.transform.scale *= MINI_SCALE;
+    sprite
+}
+
+fn sync_piece_sprites(
+    game: Res<Game>,
+    theme: Res<Theme>,
+    layout: Res<BoardLayout>,
+    mut spawned: EventReader<PieceSpawned>,
+    mut query: Query<(
+        &PieceSquare,
+        &mut Tween,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+) {
+    let spawned = spawned.iter().last().map(|event| event.shape_type);
+    if !game.is_changed() && !theme.is_changed() && !layout.is_changed() && spawned.is_none() {
+        return;
+    }
+
+    // While rows are cleared the piece is part of the board.
+    let clearing = !game.clearing_rows().is_empty();
+    let piece = &game.piece;
+    for (square, mut tween, mut transform, mut sprite, mut visibility) in query.iter_mut() {
+        let offset = piece.shape.squares[square.0];
+        let target = layout.cell_to_world(piece.x + offset.x, piece.y + offset.y);
+        transform.scale = layout.square_scale();
+        // The square carrying an item is drawn like the locked item block.
+        let color = |shape_type| match piece.item {
+            Some((index, item)) if index == square.0 => theme.block_color(Block::Item(item)),
+            _ => theme.piece_color(shape_type),
+        };
+        if let Some(shape_type) = spawned {
+            sprite.color = color(shape_type);
+            tween.snap(target);
+        } else {
+            if theme.is_changed() {
+                sprite.color = color(piece.shape_type);
+            }
+            tween.set_target(transform.translation, target);
+        }
+        visibility.is_visible = !clearing;
+    }
+}
+
+fn sync_ghost_sprites(
+    game: Res<Game>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    layout: Res<BoardLayout>,
+    mut spawned: EventReader<PieceSpawned>,
+    mut query: Query<(
+        &GhostSquare,
+        &mut Tween,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+) {
+    let new_piece = spawned.iter().count() > 0;
+    let changed = game.is_changed() || settings.is_changed() || theme.is_changed();
+    if !changed && !layout.is_changed() && !new_piece {
+        return;
+    }
+
+    let piece = &game.piece;
+    let drop_y = game.ghost_y();
+    let mut color = theme.piece_color(piece.shape_type);
+    color.set_a(GHOST_ALPHA);
+
+    for (square, mut tween, mut transform, mut sprite, mut visibility) in query.iter_mut() {
+        let offset = piece.shape.squares[square.0];
+        // Keep the ghost behind the active piece where they overlap.
+        let target =
+            layout.cell_to_world(piece.x + offset.x, drop_y + offset.y) - Vec3::new(0., 0., 0.1);
+        transform.scale = layout.square_scale();
+        if new_piece {
+            tween.snap(target);
+        } else {
+            tween.set_target(transform.translation, target);
+        }
+        sprite.color = color;
+        visibility.is_visible = settings.ghost_piece && game.clearing_rows().is_empty();
+    }
+}
+
+fn tween_sprites(time: Res<Time>, mut query: Query<(&mut Tween, &mut Transform)>) {
+    for (mut tween, mut transform) in query.iter_mut() {
+        tween.timer.tick(time.delta());
+        transform.translation = tween.start.lerp(tween.target, tween.timer.percent());
+    }
+}
+
+fn sync_preview_sprites(
+    game: Res<Game>,
+    theme: Res<Theme>,
+    mut shown: Local<Option<Vec<ShapeTypes>>>,
+    mut query: Query<(&PreviewSquare, &mut Transform, &mut Sprite, &mut Visibility)>,
+    mut label: Query<&mut Visibility, (With<NextLabel>, Without<PreviewSquare>)>,
+) {
+    // The queue moves when a piece spawns, is reordered by an opponent's
+    // shuffle item and grows or shrinks with the previews setting.
+    let previews: Vec<_> = game.queue.previews().collect();
+    if shown.as_ref() == Some(&previews) && !theme.is_changed() {
+        return;
+    }
+
+    for mut visibility in label.iter_mut() {
+        visibility.is_visible = !previews.is_empty();
+    }
+    for (preview, mut transform, mut sprite, mut visibility) in query.iter_mut() {
+        // The queue of a puzzle runs out.
+        match previews.get(preview.slot) {
+            Some(&shape_type) => {
+                transform.translation =
+                    mini_position(Panel::Preview(preview.slot), shape_type, preview.index);
+                sprite.color = theme.piece_color(shape_type);
+                visibility.is_visible = true;
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+    *shown = Some(previews);
+}
+
+fn sync_hold_sprites(
+    game: Res<Game>,
+    theme: Res<Theme>,
+    mut spawned: EventReader<PieceSpawned>,
+    mut query: Query<(&HoldSquare, &mut Transform, &mut Sprite, &mut Visibility)>,
+) {
+    // Holding spawns a piece, and so does starting a game without one held.
+    if spawned.iter().count() == 0 && !theme.is_changed() {
+        return;
+    }
+
+    for (square, mut transform, mut sprite, mut visibility) in query.iter_mut() {
+        match game.hold {
+            Some(shape_type) => {
+                transform.translation = mini_position(Panel::Hold, shape_type, square.0);
+                sprite.color = theme.piece_color(shape_type);
+                if !game.can_hold() {
+                    sprite.color.set_a(USED_HOLD_ALPHA);
+                }
+                visibility.is_visible = true;
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+}
+
+/// Position of a square of a piece on the grid of a mini-board, the piece
+/// placed in the middle of the grid like it spawns: leaning left and down
+/// where it can't be centered.
+fn mini_position(panel: Panel, shape_type: ShapeTypes, index: usize) -> Vec3 {
+    let shape = shape_type.build();
+    let (min_x, max_x) = bounds(shape.squares.iter().map(|square| square.x));
+    let (min_y, max_y) = bounds(shape.squares.iter().map(|square| square.y));
+    let x = (MINI_CELLS - (max_x - min_x + 1)) / 2 - min_x;
+    let y = (MINI_CELLS - (max_y - min_y + 1)) / 2 - min_y;
+
+    let square = shape.squares[index];
+    panel.cell_to_world(x + square.x, y + square.y)
+}
+
+/// Shows the grids of the hold panel and of the preview slots in use, in
+/// the color of the walls.
+fn sync_mini_boards(
+    game: Res<Game>,
+    theme: Res<Theme>,
+    mut shown: Local<Option<usize>>,
+    mut query: Query<(&MiniCell, &mut Sprite, &mut Visibility)>,
+) {
+    let previews = game.queue.previews().count();
+    if *shown == Some(previews) && !theme.is_changed() {
+        return;
+    }
+
+    let mut color = theme.wall;
+    color.set_a(MINI_GRID_ALPHA);
+    for (cell, mut sprite, mut visibility) in query.iter_mut() {
+        sprite.color = color;
+        visibility.is_visible = match cell.0 {
+            Panel::Hold => true,
+            Panel::Preview(slot) => slot < previews,
+        };
+    }
+    *shown = Some(previews);
+}
+
+fn bounds(values: impl Iterator<Item = i32>) -> (i32, i32) {
+    values.fold((i32::MAX, i32::MIN), |(min, max), value| {
+        (min.min(value), max.max(value))
+    })
+}
+
+fn spawn_pieces(
+    mut commands: Commands,
+    game: Res<Game>,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+) {
+    let squares = game.piece.shape.squares.len();
+
+    for index in 0..squares {
+        commands
+            .spawn_bundle(square_sprite(Color::NONE, Vec3::ZERO))
+            .insert(PieceSquare(index))
+            .insert(Tween::new());
+        commands
+            .spawn_bundle(square_sprite(Color::NONE, Vec3::ZERO))
+            .insert(GhostSquare(index))
+            .insert(Tween::new());
+    }
+
+    commands
+        .spawn_bundle(label(
+            &asset_server,
+            locale.get("hud.next"),
+            Vec3::new(PREVIEW_X, PANEL_TOP + PANEL_GAP, 0.),
+        ))
+        .insert(NextLabel)
+        .insert(Translated("hud.next"));
+    commands
+        .spawn_bundle(label(
+            &asset_server,
+            locale.get("hud.hold"),
+            Vec3::new(HOLD_X, PANEL_TOP + PANEL_GAP, 0.),
+        ))
+        .insert(Translated("hud.hold"));
+
+    // Slots past the chosen number of previews stay hidden.
+    let panels = std::iter::once(Panel::Hold).chain((0..MAX_PREVIEWS).map(Panel::Preview));
+    for panel in panels {
+        for x in 0..MINI_CELLS {
+            for y in 0..MINI_CELLS {
+                // Behind the squares of the pieces.
+                let position = panel.cell_to_world(x, y) - Vec3::new(0., 0., 0.1);
+                commands
+                    .spawn_bundle(mini_sprite(position))
+                    .insert(MiniCell(panel));
+            }
+        }
+    }
+
+    for slot in 0..MAX_PREVIEWS {
+        for index in 0..squares {
+            commands
+                .spawn_bundle(mini_sprite(Vec3::ZERO))
+                .insert(PreviewSquare { slot, index });
+        }
+    }
+
+    for index in 0..squares {
+        let mut sprite = mini_sprite(Vec3::ZERO);
+        sprite.visibility.is_visible = false;
+        commands.spawn_bundle(sprite).insert(HoldSquare(index));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{
+    events::GameEvents,
+    game::{update_game, Countdown},
+    GameState,
+};
+use bevy::prelude::*;
+use tetris_core::{Game, Mode, ShapeTypes};
+
+/// Controls of practice mode besides those of the game.
+pub struct PracticePlugin;
+
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(practice_controls.after(update_game)),
+        );
+    }
+}
+
+/// Keys choosing the next piece in practice mode, in the order of
+/// `ShapeTypes::ALL`.
+const PALETTE_KEYS: [KeyCode; 7] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+];
+
+/// Palette and reset of practice mode: the digit keys choose the next piece
+/// and R empties the board.
+fn practice_controls(
+    keyboard_input: Res<Input<KeyCode>>,
+    countdown: Res<Countdown>,
+    mut game: ResMut<Game>,
+    mut events: GameEvents,
+) {
+    if game.mode != Mode::Practice || countdown.is_counting() {
+        return;
+    }
+
+    for (&key, &shape_type) in PALETTE_KEYS.iter().zip(ShapeTypes::ALL.iter()) {
+        if keyboard_input.just_pressed(key) {
+            game.choose_next(shape_type);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::R) {
+        for event in game.reset_board() {
+            events.send(event);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::locale::Locale;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, path::PathBuf};
+use tetris_core::{Cells, Puzzle, ShapeTypes};
+
+/// Puzzle file in `assets/puzzles`, e.g.
+///
+/// ```toml
+/// name = "Tetris"
+/// board = [
+///     "#########.",
+///     "#########.",
+/// ]
+/// pieces = "I"
+/// ```
+#[derive(Serialize, Deserialize)]
+struct PuzzleFile {
+    name: String,
+    /// Bottom rows of the board, see `Puzzle::parse`.
+    #[serde(default)]
+    board: Vec<String>,
+    pieces: String,
+}
+
+/// Puzzles found in `assets/puzzles`, ordered by file name. Files that can't
+/// be read are skipped.
+pub struct Puzzles {
+    pub list: Vec<Puzzle>,
+}
+
+/// Puzzles shipped with the game, the browser can't list the asset folder.
+#[cfg(target_arch = "wasm32")]
+const BUNDLED: [(&str, &str); 3] = [
+    (
+        "01-tetris.toml",
+        include_str!("../assets/puzzles/01-tetris.toml"),
+    ),
+    (
+        "02-t-slot.toml",
+        include_str!("../assets/puzzles/02-t-slot.toml"),
+    ),
+    (
+        "03-bricks.toml",
+        include_str!("../assets/puzzles/03-bricks.toml"),
+    ),
+];
+
+impl Puzzles {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn discover() -> Puzzles {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(puzzles_dir())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+
+        let list = paths
+            .iter()
+            .filter_map(|path| {
+                match std::fs::read_to_string(path)
+                    .map_err(Box::from)
+                    .and_then(|content| parse(&content))
+                {
+                    Ok(puzzle) => Some(puzzle),
+                    Err(e) => {
+                        warn!("skipping puzzle {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Puzzles { list }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn discover() -> Puzzles {
+        let list = BUNDLED
+            .iter()
+            .filter_map(|(file_name, content)| match parse(content) {
+                Ok(puzzle) => Some(puzzle),
+                Err(e) => {
+                    warn!("skipping puzzle {}: {}", file_name, e);
+                    None
+                }
+            })
+            .collect();
+
+        Puzzles { list }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn puzzles_dir() -> PathBuf {
+    bevy::asset::FileAssetIo::get_root_path()
+        .join("assets")
+        .join("puzzles")
+}
+
+fn parse(content: &str) -> Result<Puzzle, Box<dyn Error>> {
+    let file: PuzzleFile = toml::from_str(content)?;
+    Ok(Puzzle::parse(&file.name, &file.board, &file.pieces)?)
+}
+
+/// Writes a puzzle with the given board and pieces to the first free
+/// `custom-N.toml` file of `assets/puzzles` and returns its path. The puzzle
+/// is named in the language of the locale.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export(
+    cells: &Cells,
+    pieces: &[ShapeTypes],
+    locale: &Locale,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = puzzles_dir();
+    std::fs::create_dir_all(&dir)?;
+    let (n, path) = (1..)
+        .map(|n| (n, dir.join(format!("custom-{}.toml", n))))
+        .find(|(_, path)| !path.exists())
+        .unwrap();
+
+    let file = PuzzleFile {
+        name: locale.format("editor.custom_name", &[("number", &n)]),
+        board: layout_rows(cells),
+        pieces: pieces
+            .iter()
+            .map(|shape_type| shape_type.letter())
+            .collect(),
+    };
+    // Only write puzzles that load again.
+    Puzzle::parse(&file.name, &file.board, &file.pieces)?;
+    std::fs::write(&path, toml::to_string_pretty(&file)?)?;
+
+    Ok(path)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn export(
+    _cells: &Cells,
+    _pieces: &[ShapeTypes],
+    locale: &Locale,
+) -> Result<PathBuf, Box<dyn Error>> {
+    Err(locale.get("editor.browser").into())
+}
+
+/// Rows of the board up to the highest block, top to bottom, in the format
+/// of `Puzzle::parse`.
+#[cfg(not(target_arch = "wasm32"))]
+fn layout_rows(cells: &Cells) -> Vec<String> {
+    use tetris_core::Block;
+
+    let height = cells
+        .iter()
+        .rposition(|row| row.iter().any(Option::is_some))
+        .map_or(0, |top| top + 1);
+
+    cells[..height]
+        .iter()
+        .rev()
+        .map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    None => '.',
+                    // Puzzles have no items, they are kept as garbage.
+                    Some(Block::Garbage | Block::Item(_)) => '#',
+                    Some(Block::Piece(shape_type)) => shape_type.letter(),
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,151 @@
+use crate::storage;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::{io, time::Duration};
+
+const FILE_NAME: &str = "records.toml";
+
+/// Personal bests of the timed modes, stored next to the high scores.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Records {
+    /// Fastest sprint, in milliseconds.
+    pub sprint: Option<u64>,
+    /// Best score of the latest daily challenge played.
+    pub daily: Option<DailyBest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyBest {
+    /// Day of the challenge, as `YYYY-MM-DD`.
+    pub date: String,
+    pub score: u32,
+}
+
+/// Date of today's daily challenge, in the player's time zone.
+pub fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+/// Seed of the daily challenge of `date`, the same for every player: the
+/// date written as a number, like 20261016.
+pub fn daily_seed(date: NaiveDate) -> u64 {
+    date.year() as u64 * 10_000 + date.month() as u64 * 100 + date.day() as u64
+}
+
+impl Records {
+    /// Loads the records, starting without any when the file is missing or
+    /// unreadable.
+    pub fn load() -> Records {
+        storage::load(FILE_NAME).unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        storage::save(FILE_NAME, self)
+    }
+
+    /// Keeps a sprint time if it beats the best one, returns whether it did.
+    pub fn record_sprint(&mut self, time: Duration) -> bool {
+        let millis = time.as_millis() as u64;
+        if self.sprint.is_some_and(|best| best <= millis) {
+            return false;
+        }
+
+        self.sprint = Some(millis);
+        true
+    }
+
+    /// Keeps a daily challenge score if it beats the best one of that day,
+    /// returns whether it did. A new day starts without a best.
+    pub fn record_daily(&mut self, date: NaiveDate, score: u32) -> bool {
+        let date = date.to_string();
+        if matches!(&self.daily, Some(best) if best.date == date && best.score >= score) {
+            return false;
+        }
+
+        self.daily = Some(DailyBest { date, score });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn the_first_sprint_is_a_best() {
+        let mut records = Records::default();
+
+        assert!(records.record_sprint(Duration::from_millis(61_234)));
+        assert_eq!(records.sprint, Some(61_234));
+    }
+
+    #[test]
+    fn only_faster_sprints_replace_the_best() {
+        let mut records = Records::default();
+        records.record_sprint(Duration::from_secs(60));
+
+        assert!(!records.record_sprint(Duration::from_secs(61)));
+        assert!(!records.record_sprint(Duration::from_secs(60)));
+        assert_eq!(records.sprint, Some(60_000));
+
+        assert!(records.record_sprint(Duration::from_millis(59_999)));
+        assert_eq!(records.sprint, Some(59_999));
+    }
+
+    #[test]
+    fn sprints_are_compared_in_whole_milliseconds() {
+        let mut records = Records::default();
+        records.record_sprint(Duration::from_micros(60_000_900));
+
+        assert!(!records.record_sprint(Duration::from_micros(60_000_100)));
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn the_daily_seed_is_the_date_as_a_number() {
+        assert_eq!(daily_seed(date(2026, 10, 16)), 20261016);
+        assert_eq!(daily_seed(date(2026, 1, 2)), 20260102);
+    }
+
+    #[test]
+    fn every_day_has_its_own_seed() {
+        let mut day = date(2025, 1, 1);
+        let mut seeds = HashSet::new();
+        while day < date(2027, 1, 1) {
+            assert!(seeds.insert(daily_seed(day)));
+            day = day.succ_opt().unwrap();
+        }
+    }
+
+    #[test]
+    fn only_better_scores_of_the_same_day_replace_the_daily_best() {
+        let mut records = Records::default();
+        let day = date(2026, 10, 16);
+        assert!(records.record_daily(day, 500));
+
+        assert!(!records.record_daily(day, 400));
+        assert!(!records.record_daily(day, 500));
+        assert!(records.record_daily(day, 600));
+
+        let best = records.daily.unwrap();
+        assert_eq!(best.date, "2026-10-16");
+        assert_eq!(best.score, 600);
+    }
+
+    #[test]
+    fn a_new_day_starts_without_a_best() {
+        let mut records = Records::default();
+        records.record_daily(date(2026, 10, 16), 5000);
+
+        assert!(records.record_daily(date(2026, 10, 17), 10));
+
+        let best = records.daily.unwrap();
+        assert_eq!(best.date, "2026-10-17");
+        assert_eq!(best.score, 10);
+    }
+}
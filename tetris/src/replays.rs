@@ -0,0 +1,100 @@
+use crate::{locale::Locale, storage, ui::OverlayBody, CurrentRoom, GameState};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::{io, time::Duration};
+use tetris_core::{Input as GameInput, Replay, ReplayError, REPLAY_VERSION};
+
+const FILE_NAME: &str = "replay.json";
+
+/// Recording of the games played and the replay being watched, saved and
+/// loaded on the game over and results screens.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recording>()
+            .init_resource::<Playback>()
+            .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(replay_keys))
+            .add_system_set(SystemSet::on_update(GameState::Finished).with_system(replay_keys));
+    }
+}
+
+/// Replay of the game being played. Puzzles and versus games depend on more
+/// than their seed and aren't recorded.
+#[derive(Default)]
+pub struct Recording(pub Option<Replay>);
+
+/// Replay being watched and the index of its next tick.
+#[derive(Default)]
+pub struct Playback(pub Option<(Replay, usize)>);
+
+impl Playback {
+    /// Input and time of the next tick of the watched replay.
+    pub fn next(&mut self) -> Option<(GameInput, Duration)> {
+        let (replay, index) = self.0.as_mut()?;
+        let tick = replay.tick(*index)?;
+        *index += 1;
+        Some(tick)
+    }
+}
+
+/// The version alone, readable whatever the layout of the rest.
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+pub fn save(replay: &Replay) -> io::Result<()> {
+    let json =
+        serde_json::to_string(replay).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    storage::write(FILE_NAME, &json)
+}
+
+pub fn load() -> Result<Replay, String> {
+    let json = storage::read(FILE_NAME).ok_or_else(|| format!("no {} saved yet", FILE_NAME))?;
+    // Replays of newer versions are refused before their layout is parsed.
+    let header: Header = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    if header.version > REPLAY_VERSION {
+        return Err(ReplayError::UnsupportedVersion(header.version).to_string());
+    }
+    let replay: Replay = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    replay.upgrade().map_err(|e| e.to_string())
+}
+
+/// On the game over and results screens, R saves the replay of the last game
+/// and L starts watching the saved one, except in a room where the host
+/// starts what is played next.
+fn replay_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    recording: Res<Recording>,
+    room: CurrentRoom,
+    locale: Res<Locale>,
+    mut playback: ResMut<Playback>,
+    mut state: ResMut<State<GameState>>,
+    mut query: Query<&mut Text, With<OverlayBody>>,
+) {
+    let status = if keyboard_input.just_pressed(KeyCode::R) {
+        match &recording.0 {
+            Some(replay) => match save(replay) {
+                Ok(()) => locale.format("replays.saved", &[("file", &FILE_NAME)]),
+                Err(e) => locale.format("replays.save_failed", &[("error", &e)]),
+            },
+            None => locale.get("replays.none").to_string(),
+        }
+    } else if keyboard_input.just_pressed(KeyCode::L) && room.is_none() {
+        match load() {
+            Ok(replay) => {
+                playback.0 = Some((replay, 0));
+                let _ = state.set(GameState::Playing);
+                return;
+            }
+            Err(e) => locale.format("replays.load_failed", &[("error", &e)]),
+        }
+    } else {
+        return;
+    };
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value.push_str(&format!("\n\n{}", status));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{skins, storage, themes};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Display, io, time::SystemTime};
+use tetris_core::{
+    AttackTable, BoardSize, KickTable, Rotation, Scoring, SpeedCurves, BOARD_HEIGHT, BOARD_WIDTH,
+    MAX_PREVIEWS, NEXT_PIECES,
+};
+
+const FILE_NAME: &str = "tetris.toml";
+/// File the settings were stored in before, read when `tetris.toml` doesn't
+/// exist yet.
+const LEGACY_FILE_NAME: &str = "settings.toml";
+const KICKS_FILE_NAME: &str = "kicks.toml";
+const ATTACK_FILE_NAME: &str = "attack.toml";
+const SPEED_FILE_NAME: &str = "speed.toml";
+
+/// Player preferences and tunables, edited on the settings screen and stored
+/// in `tetris.toml`. Missing entries keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Code of the language of the interface, see `locale::LANGUAGES`.
+    pub language: String,
+    /// Delayed Auto Shift: seconds a horizontal move has to be held before
+    /// the piece starts moving on its own.
+    pub das: f32,
+    /// Auto Repeat Rate: seconds between moves once DAS has charged.
+    pub arr: f32,
+    pub ghost_piece: bool,
+    /// Whether the games played are recorded for the stats screen, off
+    /// until the player turns it on.
+    pub analytics: bool,
+    /// Strength of the camera shake on tetrises and T-spins, from 0 (off)
+    /// to 1.
+    pub screen_shake: f32,
+    /// Seconds the strongest shake takes to settle.
+    pub shake_duration: f32,
+    /// Rotation system pieces turn with.
+    pub rotation: Rotation,
+    /// Wall kicks from `kicks.toml` replacing those of the rotation system,
+    /// when that file exists.
+    #[serde(skip)]
+    pub kicks: Option<KickTable>,
+    /// Garbage sent for each kind of clear, from `attack.toml`.
+    #[serde(skip)]
+    pub attack: AttackTable,
+    /// Gravity and lock delay by level of each mode, from `speed.toml`.
+    #[serde(skip)]
+    pub speed: SpeedCurves,
+    /// Seconds a piece rests on the stack before it locks, on every level,
+    /// instead of the lock delays of `speed.toml`.
+    pub lock_delay: Option<f64>,
+    /// How locks and drops score.
+    pub scoring: Scoring,
+    /// Number of upcoming pieces shown, from 0 to `MAX_PREVIEWS`.
+    pub previews: usize,
+    /// Folder in `assets/skins` the block texture is taken from, or `flat`.
+    pub skin: String,
+    /// Name of the color theme, see `themes::THEMES`.
+    pub theme: String,
+    /// Level whose completion wins a marathon.
+    pub marathon_level_cap: u32,
+    /// Columns of the board, from 4 to 20, applied from the next game.
+    pub board_width: usize,
+    /// Visible rows of the board, from 8 to 40, applied from the next game.
+    pub board_height: usize,
+    /// Colors replacing those of the theme, see `Theme::with_colors`.
+    pub colors: BTreeMap<String, String>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            language: String::from("en"),
+            das: 0.17,
+            arr: 0.05,
+            ghost_piece: true,
+            analytics: false,
+            screen_shake: 1.,
+            shake_duration: 0.8,
+            rotation: Rotation::Srs,
+            kicks: None,
+            attack: AttackTable::default(),
+            speed: SpeedCurves::default(),
+            lock_delay: None,
+            scoring: Scoring::Guideline,
+            previews: NEXT_PIECES,
+            skin: skins::FLAT.to_string(),
+            theme: themes::THEMES[0].name.to_string(),
+            marathon_level_cap: DEFAULT_MARATHON_LEVEL_CAP,
+            board_width: BOARD_WIDTH,
+            board_height: BOARD_HEIGHT,
+            colors: BTreeMap::new(),
+        }
+    }
+}
+
+pub const SCREEN_SHAKE_STEP: f32 = 0.25;
+pub const SCREEN_SHAKE_RANGE: (f32, f32) = (0., 1.);
+pub const SHAKE_DURATION_RANGE: (f32, f32) = (0., 5.);
+pub const DAS_RANGE: (f32, f32) = (0.05, 0.5);
+pub const ARR_RANGE: (f32, f32) = (0.01, 0.2);
+/// Step of the DAS and ARR values on the settings screen.
+pub const TIMING_STEP: f32 = 0.01;
+pub const LOCK_DELAY_RANGE: (f64, f64) = (0., 5.);
+pub const DEFAULT_MARATHON_LEVEL_CAP: u32 = 15;
+pub const MARATHON_LEVEL_CAP_RANGE: (u32, u32) = (1, 30);
+
+impl Settings {
+    pub fn load() -> Settings {
+        let mut settings: Settings = storage::load(FILE_NAME)
+            .or_else(|| storage::load(LEGACY_FILE_NAME))
+            .unwrap_or_else(|| storage::load_or_init(FILE_NAME));
+        settings.kicks = storage::load(KICKS_FILE_NAME);
+        settings.attack = storage::load_or_init(ATTACK_FILE_NAME);
+        settings.speed = storage::load_or_init(SPEED_FILE_NAME);
+        settings.clamp_tunables();
+        settings
+    }
+
+    /// Brings the values edited by hand in `tetris.toml` into the ranges
+    /// the settings screen allows, warning about each one that was out.
+    fn clamp_tunables(&mut self) {
+        self.das = clamp_setting("das", self.das, DAS_RANGE);
+        self.arr = clamp_setting("arr", self.arr, ARR_RANGE);
+        self.lock_delay = self
+            .lock_delay
+            .map(|lock_delay| clamp_setting("lock_delay", lock_delay, LOCK_DELAY_RANGE));
+        self.screen_shake = clamp_setting("screen_shake", self.screen_shake, SCREEN_SHAKE_RANGE);
+        self.shake_duration =
+            clamp_setting("shake_duration", self.shake_duration, SHAKE_DURATION_RANGE);
+        self.previews = clamp_setting("previews", self.previews, (0, MAX_PREVIEWS));
+        self.marathon_level_cap = clamp_setting(
+            "marathon_level_cap",
+            self.marathon_level_cap,
+            MARATHON_LEVEL_CAP_RANGE,
+        );
+
+        let size = self.board_size();
+        if size.clamped() != size {
+            let clamped = size.clamped();
+            warn!(
+                "board size {}x{} is out of range, using {}x{}",
+                size.width, size.height, clamped.width, clamped.height
+            );
+            self.board_width = clamped.width;
+            self.board_height = clamped.height;
+        }
+    }
+
+    pub fn board_size(&self) -> BoardSize {
+        BoardSize {
+            width: self.board_width,
+            height: self.board_height,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        storage::save(FILE_NAME, self)
+    }
+}
+
+/// `value` brought into `(min, max)`, `min` when it isn't a number.
+fn clamp_setting<T: PartialOrd + Copy + Display>(name: &str, value: T, (min, max): (T, T)) -> T {
+    let clamped = match (value.partial_cmp(&min), value.partial_cmp(&max)) {
+        (Some(Ordering::Less) | None, _) => min,
+        (_, Some(Ordering::Greater)) => max,
+        _ => value,
+    };
+    if clamped != value {
+        warn!("{} {} is out of range, using {}", name, value, clamped);
+    }
+    clamped
+}
+
+/// Files the settings are loaded from, watched for changes.
+const WATCHED_FILES: [&str; 4] = [
+    FILE_NAME,
+    KICKS_FILE_NAME,
+    ATTACK_FILE_NAME,
+    SPEED_FILE_NAME,
+];
+/// Seconds between checks of the watched files.
+const WATCH_INTERVAL: f32 = 1.;
+
+/// Last modification times of the watched files.
+pub struct SettingsWatcher {
+    timer: Timer,
+    modified: Vec<Option<SystemTime>>,
+}
+
+impl Default for SettingsWatcher {
+    fn default() -> SettingsWatcher {
+        SettingsWatcher {
+            timer: Timer::from_seconds(WATCH_INTERVAL, true),
+            modified: modification_times(),
+        }
+    }
+}
+
+fn modification_times() -> Vec<Option<SystemTime>> {
+    WATCHED_FILES
+        .iter()
+        .map(|file_name| storage::modified(file_name))
+        .collect()
+}
+
+/// Reloads the settings when one of their files was edited, which applies
+/// them to the running game like a change on the settings screen.
+pub fn reload_settings(
+    time: Res<Time>,
+    mut watcher: ResMut<SettingsWatcher>,
+    mut settings: ResMut<Settings>,
+) {
+    if !watcher.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let modified = modification_times();
+    if modified != watcher.modified {
+        watcher.modified = modified;
+        *settings = Settings::load();
+        // Files written with defaults by the load are not edits.
+        watcher.modified = modification_times();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let mut settings: Settings = toml::from_str(
+            "das = -0.1\n\
+             arr = 3.0\n\
+             lock_delay = -1.0\n\
+             screen_shake = nan\n\
+             shake_duration = inf\n\
+             previews = 100\n\
+             marathon_level_cap = 0\n\
+             board_width = 1\n\
+             board_height = 1000\n",
+        )
+        .unwrap();
+        settings.clamp_tunables();
+
+        assert_eq!(settings.das, DAS_RANGE.0);
+        assert_eq!(settings.arr, ARR_RANGE.1);
+        assert_eq!(settings.lock_delay, Some(LOCK_DELAY_RANGE.0));
+        assert_eq!(settings.screen_shake, SCREEN_SHAKE_RANGE.0);
+        assert_eq!(settings.shake_duration, SHAKE_DURATION_RANGE.1);
+        assert_eq!(settings.previews, MAX_PREVIEWS);
+        assert_eq!(settings.marathon_level_cap, MARATHON_LEVEL_CAP_RANGE.0);
+        assert_eq!(
+            settings.board_size(),
+            BoardSize {
+                width: 4,
+                height: 40
+            }
+        );
+    }
+
+    #[test]
+    fn values_in_range_are_kept() {
+        let mut settings: Settings = toml::from_str("das = 0.1\nlock_delay = 0.5\n").unwrap();
+        settings.clamp_tunables();
+
+        assert_eq!(settings.das, 0.1);
+        assert_eq!(settings.lock_delay, Some(0.5));
+        assert_eq!(settings.arr, Settings::default().arr);
+    }
+}
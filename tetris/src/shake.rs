@@ -0,0 +1,59 @@
+use crate::{camera::GameCamera, events::LinesCleared, settings::Settings};
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Largest camera offset of a full strength shake, in world units.
+const MAX_SHAKE_OFFSET: f32 = 12.;
+
+/// Shakes the game camera after tetrises and T-spins. Clears add trauma,
+/// which settles over `Settings::shake_duration`, and the camera is offset
+/// by the square of it so small shakes stay subtle.
+pub struct ShakePlugin;
+
+impl Plugin for ShakePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Trauma>()
+            .add_system(add_trauma)
+            .add_system(shake_camera.after(add_trauma));
+    }
+}
+
+/// Shake intensity from 0 to 1.
+#[derive(Default)]
+struct Trauma(f32);
+
+fn add_trauma(mut trauma: ResMut<Trauma>, mut cleared: EventReader<LinesCleared>) {
+    for event in cleared.iter() {
+        let amount = match (event.rows.len(), event.t_spin) {
+            (4, _) => 0.6,
+            (rows, true) => 0.3 + 0.1 * rows as f32,
+            _ => 0.,
+        };
+        trauma.0 = (trauma.0 + amount).min(1.);
+    }
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut trauma: ResMut<Trauma>,
+    mut query: Query<&mut Transform, With<GameCamera>>,
+) {
+    if trauma.0 == 0. {
+        return;
+    }
+
+    let decay = if settings.shake_duration > 0. {
+        time.delta_seconds() / settings.shake_duration
+    } else {
+        1.
+    };
+    trauma.0 = (trauma.0 - decay).max(0.);
+
+    let mut rng = rand::thread_rng();
+    let offset = MAX_SHAKE_OFFSET * settings.screen_shake * trauma.0 * trauma.0;
+    for mut transform in query.iter_mut() {
+        transform.translation.x = offset * rng.gen_range(-1. ..=1.);
+        transform.translation.y = offset * rng.gen_range(-1. ..=1.);
+    }
+}
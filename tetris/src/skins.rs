@@ -0,0 +1,137 @@
+use crate::{
+    board::BoardCell,
+    piece::{GhostSquare, HoldSquare, PieceSquare, PreviewSquare},
+    settings::Settings,
+};
+use bevy::{asset::LoadState, prelude::*, render::texture::DEFAULT_IMAGE_HANDLE};
+
+/// Skin drawing blocks as plain colored quads, always available.
+pub const FLAT: &str = "flat";
+
+/// Block textures of the skin picked in the settings.
+pub struct SkinPlugin;
+
+impl Plugin for SkinPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Skins::discover())
+            .init_resource::<SkinTexture>()
+            .add_system(load_skin)
+            .add_system(fall_back_on_missing_skin.after(load_skin))
+            .add_system(apply_skin.after(fall_back_on_missing_skin));
+    }
+}
+
+/// Skins found in `assets/skins`. Each is a folder with a grayscale
+/// `block.png` that is tinted in the color of the block.
+pub struct Skins {
+    pub names: Vec<String>,
+}
+
+/// Skins shipped with the game, the browser can't list the asset folder.
+#[cfg(target_arch = "wasm32")]
+const BUNDLED: [&str; 2] = ["bevel", "outline"];
+
+impl Skins {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn discover() -> Skins {
+        let dir = bevy::asset::FileAssetIo::get_root_path()
+            .join("assets")
+            .join("skins");
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().join("block.png").is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        names.sort();
+        names.insert(0, FLAT.to_string());
+
+        Skins { names }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn discover() -> Skins {
+        let names = std::iter::once(FLAT)
+            .chain(BUNDLED)
+            .map(String::from)
+            .collect();
+
+        Skins { names }
+    }
+
+    /// Skin `step` places after `current` in the list, wrapping around.
+    pub fn cycle(&self, current: &str, step: isize) -> String {
+        let index = self
+            .names
+            .iter()
+            .position(|name| name == current)
+            .unwrap_or(0) as isize;
+        let len = self.names.len() as isize;
+        self.names[(index + step).rem_euclid(len) as usize].clone()
+    }
+}
+
+/// Texture of the blocks in the current skin.
+pub struct SkinTexture(pub Handle<Image>);
+
+impl Default for SkinTexture {
+    fn default() -> SkinTexture {
+        SkinTexture(DEFAULT_IMAGE_HANDLE.typed())
+    }
+}
+
+fn load_skin(
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    mut texture: ResMut<SkinTexture>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let handle = if settings.skin == FLAT {
+        DEFAULT_IMAGE_HANDLE.typed()
+    } else {
+        asset_server.load(&format!("skins/{}/block.png", settings.skin))
+    };
+    if texture.0 != handle {
+        texture.0 = handle;
+    }
+}
+
+/// Goes back to flat blocks when the texture of the skin can't be loaded.
+fn fall_back_on_missing_skin(
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    mut texture: ResMut<SkinTexture>,
+) {
+    if asset_server.get_load_state(&texture.0) == LoadState::Failed {
+        warn!("failed to load skin {:?}, using flat blocks", settings.skin);
+        *texture = SkinTexture::default();
+    }
+}
+
+/// Textures every block sprite with the current skin.
+#[allow(clippy::type_complexity)]
+fn apply_skin(
+    texture: Res<SkinTexture>,
+    mut query: Query<
+        &mut Handle<Image>,
+        Or<(
+            With<BoardCell>,
+            With<PieceSquare>,
+            With<GhostSquare>,
+            With<PreviewSquare>,
+            With<HoldSquare>,
+        )>,
+    >,
+) {
+    if !texture.is_changed() {
+        return;
+    }
+
+    for mut handle in query.iter_mut() {
+        *handle = texture.0.clone();
+    }
+}
@@ -0,0 +1,132 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{io, time::SystemTime};
+
+/// Reads a TOML file from the config directory. Returns `None` when the file
+/// doesn't exist or can't be parsed, so callers can fall back to defaults.
+pub fn load<T: DeserializeOwned>(file_name: &str) -> Option<T> {
+    let content = backend::read(file_name)?;
+
+    match toml::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("ignoring invalid {}: {}", file_name, e);
+            None
+        }
+    }
+}
+
+/// Like `load`, but falls back to the default value and writes it to disk
+/// when the file doesn't exist yet, so players have a file to edit.
+pub fn load_or_init<T: Default + Serialize + DeserializeOwned>(file_name: &str) -> T {
+    if let Some(value) = load(file_name) {
+        return value;
+    }
+
+    let value = T::default();
+    if !backend::exists(file_name) {
+        if let Err(e) = save(file_name, &value) {
+            eprintln!("failed to write default {}: {}", file_name, e);
+        }
+    }
+
+    value
+}
+
+/// Reads a file of the config directory as text.
+pub fn read(file_name: &str) -> Option<String> {
+    backend::read(file_name)
+}
+
+pub fn write(file_name: &str, content: &str) -> io::Result<()> {
+    backend::write(file_name, content)
+}
+
+/// Writes a binary file to the config directory, creating its folders.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_bytes(file_name: &str, content: &[u8]) -> io::Result<()> {
+    backend::write_bytes(file_name, content)
+}
+
+/// Time a file was last written, `None` when it doesn't exist or the
+/// backend can't tell.
+pub fn modified(file_name: &str) -> Option<SystemTime> {
+    backend::modified(file_name)
+}
+
+pub fn save<T: Serialize>(file_name: &str, value: &T) -> io::Result<()> {
+    let content =
+        toml::to_string(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    backend::write(file_name, &content)
+}
+
+/// Files in the game's directory under the platform config directory.
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::{fs, io, path::PathBuf, time::SystemTime};
+
+    fn config_path(file_name: &str) -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("tetris").join(file_name))
+    }
+
+    pub fn read(file_name: &str) -> Option<String> {
+        fs::read_to_string(config_path(file_name)?).ok()
+    }
+
+    pub fn exists(file_name: &str) -> bool {
+        config_path(file_name).is_some_and(|path| path.exists())
+    }
+
+    pub fn modified(file_name: &str) -> Option<SystemTime> {
+        fs::metadata(config_path(file_name)?).ok()?.modified().ok()
+    }
+
+    pub fn write(file_name: &str, content: &str) -> io::Result<()> {
+        write_bytes(file_name, content.as_bytes())
+    }
+
+    pub fn write_bytes(file_name: &str, content: &[u8]) -> io::Result<()> {
+        let path = config_path(file_name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, content)
+    }
+}
+
+/// Entries of the browser's local storage, keyed by `tetris/<file name>`.
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use std::{io, time::SystemTime};
+    use web_sys::Storage;
+
+    fn local_storage() -> Option<Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn key(file_name: &str) -> String {
+        format!("tetris/{}", file_name)
+    }
+
+    pub fn read(file_name: &str) -> Option<String> {
+        local_storage()?.get_item(&key(file_name)).ok()?
+    }
+
+    pub fn exists(file_name: &str) -> bool {
+        read(file_name).is_some()
+    }
+
+    /// Local storage is only edited by the game itself.
+    pub fn modified(_file_name: &str) -> Option<SystemTime> {
+        None
+    }
+
+    pub fn write(file_name: &str, content: &str) -> io::Result<()> {
+        let storage = local_storage()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no local storage"))?;
+        storage
+            .set_item(&key(file_name), content)
+            .map_err(|e| io::Error::other(format!("{:?}", e)))
+    }
+}
@@ -0,0 +1,141 @@
+use crate::settings::Settings;
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use tetris_core::{Block, ShapeTypes};
+
+/// Colors of the theme picked in the settings.
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(select_theme)
+            .add_system(apply_theme.after(select_theme));
+    }
+}
+
+/// Color of item blocks in every theme, bright so they stand out of the
+/// stack.
+pub const ITEM_COLOR: Color = Color::rgb(1., 0.85, 0.3);
+
+/// Colors of the scene. Piece colors are listed in the order of
+/// `ShapeTypes::ALL`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    pieces: [Color; 7],
+    pub garbage: Color,
+    pub wall: Color,
+    pub background: Color,
+}
+
+impl Theme {
+    pub fn piece_color(&self, shape_type: ShapeTypes) -> Color {
+        self.pieces[shape_type as usize]
+    }
+
+    pub fn block_color(&self, block: Block) -> Color {
+        match block {
+            Block::Piece(shape_type) => self.piece_color(shape_type),
+            Block::Garbage => self.garbage,
+            Block::Item(_) => ITEM_COLOR,
+        }
+    }
+
+    /// Theme with the given name, the first one if there is none.
+    pub fn named(name: &str) -> &'static Theme {
+        THEMES
+            .iter()
+            .find(|theme| theme.name == name)
+            .unwrap_or(&THEMES[0])
+    }
+
+    /// The theme with colors replaced by hex codes such as `"ff8800"`, keyed
+    /// by piece letter, `garbage`, `wall` or `background`. Unknown keys and
+    /// invalid codes are reported and skipped.
+    pub fn with_colors(&self, colors: &BTreeMap<String, String>) -> Theme {
+        let mut theme = self.clone();
+        for (key, hex) in colors {
+            let color = match Color::hex(hex.trim_start_matches('#')) {
+                Ok(color) => color,
+                Err(_) => {
+                    warn!("ignoring invalid color {} of {}", hex, key);
+                    continue;
+                }
+            };
+            let mut letters = key.chars();
+            let shape_type = match (letters.next(), letters.next()) {
+                (Some(letter), None) => ShapeTypes::from_letter(letter),
+                _ => None,
+            };
+            match (key.as_str(), shape_type) {
+                ("garbage", _) => theme.garbage = color,
+                ("wall", _) => theme.wall = color,
+                ("background", _) => theme.background = color,
+                (_, Some(shape_type)) => theme.pieces[shape_type as usize] = color,
+                _ => warn!("ignoring color of unknown {}", key),
+            }
+        }
+        theme
+    }
+
+    /// Theme `step` places after this one, wrapping around.
+    pub fn cycle(&self, step: isize) -> &'static Theme {
+        let index = THEMES
+            .iter()
+            .position(|theme| theme.name == self.name)
+            .unwrap_or(0) as isize;
+        &THEMES[(index + step).rem_euclid(THEMES.len() as isize) as usize]
+    }
+}
+
+pub const THEMES: [Theme; 2] = [
+    Theme {
+        name: "classic",
+        pieces: [
+            Color::rgb(0.95, 0.85, 0.1),
+            Color::rgb(0.1, 0.85, 0.9),
+            Color::rgb(0.65, 0.2, 0.85),
+            Color::rgb(0.9, 0.15, 0.15),
+            Color::rgb(0.2, 0.8, 0.2),
+            Color::rgb(0.95, 0.55, 0.1),
+            Color::rgb(0.15, 0.3, 0.9),
+        ],
+        garbage: Color::rgb(0.5, 0.5, 0.5),
+        wall: Color::rgb(0.8, 0.8, 0.8),
+        background: Color::BLACK,
+    },
+    // Okabe-Ito colors, which stay apart for red-green color blindness.
+    Theme {
+        name: "deuteranopia",
+        pieces: [
+            Color::rgb(0.94, 0.89, 0.26),
+            Color::rgb(0.34, 0.71, 0.91),
+            Color::rgb(0.8, 0.47, 0.65),
+            Color::rgb(0.84, 0.37, 0.),
+            Color::rgb(0., 0.62, 0.45),
+            Color::rgb(0.9, 0.62, 0.),
+            Color::rgb(0., 0.45, 0.7),
+        ],
+        garbage: Color::rgb(0.6, 0.6, 0.6),
+        wall: Color::rgb(0.9, 0.9, 0.9),
+        background: Color::rgb(0.05, 0.05, 0.08),
+    },
+];
+
+pub fn select_theme(settings: Res<Settings>, mut theme: ResMut<Theme>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let selected = Theme::named(&settings.theme).with_colors(&settings.colors);
+    if *theme != selected {
+        *theme = selected;
+    }
+}
+
+/// Clears the screen in the background color of the theme.
+fn apply_theme(theme: Res<Theme>, mut clear_color: ResMut<ClearColor>) {
+    if theme.is_changed() {
+        clear_color.0 = theme.background;
+    }
+}
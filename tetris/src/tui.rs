@@ -0,0 +1,248 @@
+//! Terminal frontend started with `--tui`. It plays endless games on the
+//! same `Game` as the window, drawn with block characters.
+
+use crate::{
+    controls::{Action, KeyBindings},
+    game::game_config,
+    locale::Locale,
+    settings::Settings,
+    themes::Theme,
+};
+use bevy::prelude::KeyCode as Key;
+use crossterm::{
+    cursor::{self, MoveTo},
+    event::{self, Event, KeyCode, KeyModifiers},
+    execute, queue,
+    style::{Color, Print, ResetColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+use tetris_core::{Game, Input, Mode};
+
+const FRAME: Duration = Duration::from_millis(16);
+/// Columns between the right wall of the board and the panel beside it.
+const PANEL_GAP: u16 = 2;
+
+const LETTERS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+/// Key of the window the terminal key stands for, so the key bindings apply
+/// to both frontends.
+fn window_key(code: KeyCode) -> Option<Key> {
+    match code {
+        KeyCode::Left => Some(Key::Left),
+        KeyCode::Right => Some(Key::Right),
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::Esc => Some(Key::Escape),
+        KeyCode::Enter => Some(Key::Return),
+        KeyCode::Char(' ') => Some(Key::Space),
+        KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+            Some(LETTERS[(c.to_ascii_lowercase() as u8 - b'a') as usize])
+        }
+        _ => None,
+    }
+}
+
+fn terminal_color(color: bevy::prelude::Color) -> Color {
+    let [r, g, b, _] = color.as_rgba_f32();
+    Color::Rgb {
+        r: (r * 255.) as u8,
+        g: (g * 255.) as u8,
+        b: (b * 255.) as u8,
+    }
+}
+
+/// Raw mode on the alternate screen, restored when dropped, also when the
+/// game stops on an error.
+struct Terminal;
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        execute!(
+            io::stdout(),
+            EnterAlternateScreen,
+            cursor::Hide,
+            Clear(ClearType::All)
+        )?;
+        Ok(Terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), ResetColor, cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Plays until `q` or Ctrl+C. Terminals only report presses, so held keys
+/// act through the terminal's key repeat.
+pub fn run(seed: Option<u64>, settings: &Settings, bindings: &KeyBindings) -> io::Result<()> {
+    let theme = Theme::named(&settings.theme).with_colors(&settings.colors);
+    let locale = Locale::load(&settings.language);
+    let new_game = || {
+        Game::new(
+            seed.unwrap_or_else(rand::random),
+            Mode::Endless,
+            game_config(settings),
+        )
+    };
+    let mut game = new_game();
+    let mut paused = false;
+
+    let _terminal = Terminal::enter()?;
+    let mut stdout = io::stdout();
+    let mut last_frame = Instant::now();
+    loop {
+        let mut input = Input::default();
+        let mut ready = event::poll(FRAME)?;
+        while ready {
+            if let Event::Key(key) = event::read()? {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl_c || key.code == KeyCode::Char('q') {
+                    return Ok(());
+                }
+                if game.over && key.code == KeyCode::Enter {
+                    game = new_game();
+                }
+
+                let action = window_key(key.code).and_then(|key| {
+                    Action::ALL
+                        .into_iter()
+                        .find(|&action| bindings.keys(action).contains(&key))
+                });
+                match action {
+                    Some(Action::Pause) => paused = !paused,
+                    Some(action) => {
+                        if let Some(button) = action.button() {
+                            input.pressed.insert(button);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            ready = event::poll(Duration::ZERO)?;
+        }
+
+        let delta = last_frame.elapsed();
+        last_frame = Instant::now();
+        if !paused {
+            game.tick(input, delta);
+        }
+
+        draw(&mut stdout, &game, &theme, &locale, paused)?;
+    }
+}
+
+fn draw(
+    out: &mut impl Write,
+    game: &Game,
+    theme: &Theme,
+    locale: &Locale,
+    paused: bool,
+) -> io::Result<()> {
+    let piece = game.piece.cells();
+    let ghost = game.piece.shape.cells(game.piece.x, game.ghost_y());
+    let piece_color = theme.piece_color(game.piece.shape_type);
+
+    let (width, height) = (game.board.width(), game.board.height());
+    for row in 0..height {
+        let y = height - 1 - row;
+        queue!(out, MoveTo(0, row as u16), ResetColor, Print("│"))?;
+        for x in 0..width {
+            let cell = (x as i32, y as i32);
+            let (text, color) = if game.clearing_rows().contains(&y) {
+                ("██", Some(bevy::prelude::Color::WHITE))
+            } else if let Some(block) = game.board.cells[y][x] {
+                ("██", Some(theme.block_color(block)))
+            } else if piece.contains(&cell) && !game.over {
+                ("██", Some(piece_color))
+            } else if ghost.contains(&cell) && !game.over {
+                ("░░", Some(piece_color))
+            } else {
+                ("  ", None)
+            };
+            match color {
+                Some(color) => queue!(out, SetForegroundColor(terminal_color(color)))?,
+                None => queue!(out, ResetColor)?,
+            }
+            queue!(out, Print(text))?;
+        }
+        queue!(out, ResetColor, Print("│"))?;
+    }
+    queue!(
+        out,
+        MoveTo(0, height as u16),
+        Print(format!("└{}┘", "──".repeat(width)))
+    )?;
+
+    let next: String = game
+        .queue
+        .previews()
+        .map(|shape_type| shape_type.letter())
+        .collect();
+    let hold = game.hold.map_or('-', |shape_type| shape_type.letter());
+    let (status, cause, hint) = match game.game_over_cause() {
+        Some(cause) => (
+            locale.get("game_over.title"),
+            locale.get(cause.key()),
+            locale.get("tui.restart"),
+        ),
+        None if paused => (locale.get("pause.title"), "", locale.get("tui.quit")),
+        None => ("", "", locale.get("tui.quit")),
+    };
+    let panel = [
+        format!("{} {}", locale.get("hud.score"), game.score),
+        format!("{} {}", locale.get("hud.level"), game.level.level),
+        format!("{} {}", locale.get("hud.lines"), game.level.lines),
+        String::new(),
+        format!("{} {}", locale.get("hud.next"), next),
+        format!("{} {}", locale.get("hud.hold"), hold),
+        String::new(),
+        status.to_string(),
+        cause.to_string(),
+        String::new(),
+        hint.to_string(),
+    ];
+    for (i, line) in panel.iter().enumerate() {
+        queue!(
+            out,
+            MoveTo(width as u16 * 2 + 2 + PANEL_GAP, i as u16),
+            Print(format!("{:<50}", line))
+        )?;
+    }
+
+    out.flush()
+}
@@ -0,0 +1,196 @@
+mod game_over;
+mod hud;
+#[cfg(not(target_arch = "wasm32"))]
+mod lobby;
+mod menu;
+mod pause;
+mod settings;
+
+use crate::locale::Locale;
+use bevy::prelude::*;
+use game_over::GameOverPlugin;
+use hud::HudPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+use lobby::LobbyMenuPlugin;
+use menu::MainMenuPlugin;
+use pause::PauseMenuPlugin;
+use settings::SettingsMenuPlugin;
+use std::time::Duration;
+use tetris_core::Mode;
+
+pub use hud::{StatsText, STATS_LEFT};
+
+/// HUD and statistics beside the board, clear announcements, the countdown
+/// and the overlay screens: menu, puzzle select, lobby, pause, settings,
+/// stats, name entry, game over and results, each screen a plugin of its own.
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PlayerName(String::new()))
+            .add_plugin(HudPlugin)
+            .add_plugin(MainMenuPlugin)
+            .add_plugin(PauseMenuPlugin)
+            .add_plugin(SettingsMenuPlugin)
+            .add_plugin(GameOverPlugin);
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_plugin(LobbyMenuPlugin);
+    }
+}
+
+fn mode_name(mode: Mode, locale: &Locale) -> &str {
+    locale.get(match mode {
+        Mode::Endless => "mode.endless",
+        Mode::Sprint => "mode.sprint",
+        Mode::Ultra => "mode.ultra",
+        Mode::Marathon { .. } => "mode.marathon",
+        Mode::Puzzle => "mode.puzzle",
+        Mode::Finesse => "mode.finesse",
+        Mode::Items { .. } => "mode.items",
+        Mode::Invisible => "mode.invisible",
+        Mode::Zen => "mode.zen",
+        Mode::Daily => "mode.daily",
+        Mode::Tutorial => "mode.tutorial",
+        Mode::Practice => "mode.practice",
+    })
+}
+
+/// Name typed on the name entry screen, kept as the default for the next
+/// high score.
+struct PlayerName(String);
+
+const OVERLAY_COLOR: Color = Color::rgba(0., 0., 0., 0.6);
+
+/// Text between the title and hint of an overlay.
+#[derive(Component)]
+pub struct OverlayBody;
+
+/// Root UI node of the text overlay shown for the menu, pause and game over
+/// screens.
+#[derive(Component)]
+struct Overlay;
+
+pub fn label(asset_server: &AssetServer, text: &str, translation: Vec3) -> Text2dBundle {
+    Text2dBundle {
+        text: Text::with_section(
+            text,
+            TextStyle {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 24.,
+                color: Color::WHITE,
+            },
+            TextAlignment {
+                vertical: VerticalAlign::Center,
+                horizontal: HorizontalAlign::Center,
+            },
+        ),
+        transform: Transform::from_translation(translation),
+        ..default()
+    }
+}
+
+/// Formats a time as minutes, seconds and milliseconds, e.g. `1:05.042`.
+fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Style of the text between the title and hint of an overlay.
+fn body_style(asset_server: &AssetServer) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+        font_size: 20.,
+        color: Color::WHITE,
+    }
+}
+
+fn spawn_overlay(
+    commands: Commands,
+    asset_server: &AssetServer,
+    title: &str,
+    body: &str,
+    hint: &str,
+    background: Color,
+) {
+    let body = vec![TextSection {
+        value: body.to_string(),
+        style: body_style(asset_server),
+    }];
+    spawn_overlay_sections(commands, asset_server, title, body, hint, background);
+}
+
+/// Overlay whose body is made of several sections, such as a menu.
+fn spawn_overlay_sections(
+    mut commands: Commands,
+    asset_server: &AssetServer,
+    title: &str,
+    body: Vec<TextSection>,
+    hint: &str,
+    background: Color,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: background.into(),
+            ..default()
+        })
+        .insert(Overlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    title,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 60.,
+                        color: Color::WHITE,
+                    },
+                    default(),
+                ),
+                ..default()
+            });
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(20.)),
+                        ..default()
+                    },
+                    text: Text {
+                        sections: body,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(OverlayBody);
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    hint,
+                    TextStyle {
+                        font,
+                        font_size: 24.,
+                        color: Color::WHITE,
+                    },
+                    default(),
+                ),
+                ..default()
+            });
+        });
+}
+
+fn despawn_overlay(mut commands: Commands, query: Query<Entity, With<Overlay>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
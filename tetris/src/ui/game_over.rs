@@ -0,0 +1,296 @@
+//! Name entry, game over and results screens.
+
+use super::{
+    despawn_overlay, format_time, hud::FinesseSession, spawn_overlay, OverlayBody, PlayerName,
+    OVERLAY_COLOR,
+};
+use crate::{
+    game::SelectedPuzzle,
+    high_scores::{HighScore, HighScores},
+    locale::Locale,
+    menu::{MenuCommand, MenuControls},
+    puzzles::Puzzles,
+    records::{self, Records},
+    replays::Playback,
+    CurrentRoom, GameState,
+};
+use bevy::{prelude::*, window::ReceivedCharacter};
+use std::time::Duration;
+use tetris_core::{lines_per_minute, Game, Mode, ShapeTypes};
+
+/// The screens ending a game.
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::NameEntry).with_system(spawn_name_entry))
+            .add_system_set(SystemSet::on_update(GameState::NameEntry).with_system(enter_name))
+            .add_system_set(SystemSet::on_exit(GameState::NameEntry).with_system(despawn_overlay))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(spawn_game_over))
+            .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(start_on_enter))
+            .add_system_set(SystemSet::on_exit(GameState::GameOver).with_system(despawn_overlay))
+            .add_system_set(SystemSet::on_enter(GameState::Finished).with_system(spawn_results))
+            .add_system_set(SystemSet::on_update(GameState::Finished).with_system(start_on_enter))
+            .add_system_set(SystemSet::on_exit(GameState::Finished).with_system(despawn_overlay));
+    }
+}
+
+const MAX_NAME_LENGTH: usize = 10;
+
+/// Statistics of a game on one line, then the piece usage on another.
+fn stats_summary(game: &Game, locale: &Locale) -> String {
+    let stats = &game.stats;
+    let usage = ShapeTypes::ALL
+        .iter()
+        .map(|&shape_type| format!("{} {}", shape_type.letter(), stats.usage(shape_type)))
+        .collect::<Vec<_>>()
+        .join("  ");
+    format!(
+        "{}: {}  {}: {:.2}  {}: {:.1}  {}: {}\n{}",
+        locale.get("stats.pieces"),
+        stats.pieces,
+        locale.get("stats.pps"),
+        stats.pieces_per_second(game.elapsed),
+        locale.get("stats.lpm"),
+        lines_per_minute(game.level.lines, game.elapsed),
+        locale.get("stats.holds"),
+        stats.holds,
+        usage
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_game_over(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    high_scores: Res<HighScores>,
+    game: Res<Game>,
+    finesse: Res<FinesseSession>,
+    mut records: ResMut<Records>,
+    playback: Res<Playback>,
+    locale: Res<Locale>,
+) {
+    let accuracy = if game.mode == Mode::Finesse {
+        let accuracy = format!("{:.1}", finesse.accuracy());
+        format!(
+            "\n{}",
+            locale.format("game_over.finesse_accuracy", &[("accuracy", &accuracy)])
+        )
+    } else {
+        String::new()
+    };
+    let daily = if game.mode == Mode::Daily {
+        daily_summary(&game, &mut records, playback.0.is_none(), &locale)
+    } else {
+        String::new()
+    };
+    let cause = match game.game_over_cause() {
+        Some(cause) => format!("{}\n\n", locale.get(cause.key())),
+        None => String::new(),
+    };
+    spawn_overlay(
+        commands,
+        &asset_server,
+        locale.get("game_over.title"),
+        &format!(
+            "{}{}{}{}\n\n{}\n\n{}",
+            cause,
+            daily,
+            stats_summary(&game, &locale),
+            accuracy,
+            locale.format("game_over.seed", &[("seed", &game.seed())]),
+            high_scores.to_text(locale.get("menu.no_high_scores"))
+        ),
+        locale.get("game_over.hint"),
+        OVERLAY_COLOR,
+    );
+}
+
+/// Score of a daily challenge against the best of the day, recorded unless
+/// the game was a watched replay.
+fn daily_summary(game: &Game, records: &mut Records, record: bool, locale: &Locale) -> String {
+    let date = records::today();
+    let new_best = record && records.record_daily(date, game.score);
+    if new_best {
+        if let Err(e) = records.save() {
+            warn!("failed to save records: {}", e);
+        }
+    }
+    let best = records
+        .daily
+        .as_ref()
+        .filter(|best| best.date == date.to_string())
+        .map_or(game.score, |best| best.score);
+    let mut summary = format!(
+        "{}\n{}\n{}",
+        locale.format("daily.title", &[("date", &date)]),
+        locale.format("daily.score", &[("score", &game.score)]),
+        locale.format("daily.best", &[("score", &best)])
+    );
+    if new_best {
+        summary += &format!("\n{}", locale.get("daily.new_best"));
+    }
+    summary + "\n\n"
+}
+
+/// Shows how a finished game went, recording personal bests.
+#[allow(clippy::too_many_arguments)]
+fn spawn_results(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    puzzles: Res<Puzzles>,
+    puzzle: Res<SelectedPuzzle>,
+    mut records: ResMut<Records>,
+    playback: Res<Playback>,
+    locale: Res<Locale>,
+) {
+    let score = locale.format("results.score", &[("score", &game.score)]);
+    let lines = locale.format("results.lines", &[("lines", &game.level.lines)]);
+    let time = locale.format("results.time", &[("time", &format_time(game.elapsed))]);
+    let (title, body) = match game.mode {
+        Mode::Endless
+        | Mode::Finesse
+        | Mode::Items { .. }
+        | Mode::Invisible
+        | Mode::Zen
+        | Mode::Daily
+        | Mode::Practice => ("results.finished", score),
+        Mode::Tutorial => (
+            "results.tutorial_complete",
+            locale.get("results.tutorial_body").to_string(),
+        ),
+        Mode::Sprint => {
+            let new_best = playback.0.is_none() && records.record_sprint(game.elapsed);
+            if new_best {
+                if let Err(e) = records.save() {
+                    warn!("failed to save records: {}", e);
+                }
+            }
+            let best = records
+                .sprint
+                .map(Duration::from_millis)
+                .unwrap_or(game.elapsed);
+            let mut body = format!(
+                "{}\n{}",
+                time,
+                locale.format("results.best", &[("time", &format_time(best))])
+            );
+            if new_best {
+                body += &format!("\n\n{}", locale.get("results.new_best"));
+            }
+            ("results.sprint_complete", body)
+        }
+        Mode::Marathon { level_cap, .. } => (
+            "results.marathon_complete",
+            format!(
+                "{}\n\n{}\n{}\n{}",
+                locale.format("results.level_cleared", &[("level", &level_cap)]),
+                score,
+                lines,
+                time
+            ),
+        ),
+        Mode::Ultra => ("results.time_up", format!("{}\n{}", score, lines)),
+        Mode::Puzzle => (
+            "results.puzzle_solved",
+            format!(
+                "{}\n\n{}\n{}",
+                puzzles.list[puzzle.0].name,
+                locale.format("results.pieces_used", &[("pieces", &game.stats.pieces)]),
+                time
+            ),
+        ),
+    };
+
+    spawn_overlay(
+        commands,
+        &asset_server,
+        locale.get(title),
+        &format!("{}\n\n{}", body, stats_summary(&game, &locale)),
+        locale.get("game_over.hint"),
+        OVERLAY_COLOR,
+    );
+}
+
+/// Starts a new game on Enter, the confirm button or a tap. In a room the
+/// host starts the matches, Enter goes back to the lobby.
+fn start_on_enter(
+    mut controls: MenuControls,
+    touches: Res<Touches>,
+    room: CurrentRoom,
+    mut state: ResMut<State<GameState>>,
+) {
+    if controls.take(MenuCommand::Confirm) || touches.iter_just_pressed().next().is_some() {
+        let _ = state.set(if room.is_some() {
+            GameState::Lobby
+        } else {
+            GameState::Playing
+        });
+    }
+}
+
+fn spawn_name_entry(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    name: Res<PlayerName>,
+    locale: Res<Locale>,
+) {
+    spawn_overlay(
+        commands,
+        &asset_server,
+        locale.get("name_entry.title"),
+        &format!("{}_", name.0),
+        locale.get("name_entry.hint"),
+        OVERLAY_COLOR,
+    );
+}
+
+/// Edits the player name from typed characters and records the high score
+/// once Enter or the confirm button is pressed.
+#[allow(clippy::too_many_arguments)]
+fn enter_name(
+    mut characters: EventReader<ReceivedCharacter>,
+    mut controls: MenuControls,
+    mut name: ResMut<PlayerName>,
+    mut high_scores: ResMut<HighScores>,
+    game: Res<Game>,
+    locale: Res<Locale>,
+    mut state: ResMut<State<GameState>>,
+    mut query: Query<&mut Text, With<OverlayBody>>,
+) {
+    for event in characters.iter() {
+        if (event.char.is_alphanumeric() || event.char == ' ') && name.0.len() < MAX_NAME_LENGTH {
+            name.0.push(event.char);
+        }
+    }
+    if controls.key_just_pressed(KeyCode::Back) {
+        name.0.pop();
+    }
+
+    // Taking the press keeps the game over screen from restarting right away.
+    if controls.take(MenuCommand::Confirm) {
+        let player = match name.0.trim() {
+            "" => locale.get("name_entry.default_name"),
+            trimmed => trimmed,
+        };
+        high_scores.insert(HighScore {
+            name: player.to_string(),
+            score: game.score,
+            lines: game.level.lines,
+            date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        });
+        if let Err(e) = high_scores.save() {
+            warn!("failed to save high scores: {}", e);
+        }
+
+        let _ = state.set(GameState::GameOver);
+        return;
+    }
+
+    if name.is_changed() {
+        for mut text in query.iter_mut() {
+            text.sections[0].value = format!("{}_", name.0);
+        }
+    }
+}
@@ -0,0 +1,440 @@
+//! HUD and statistics beside the board, clear announcements, the tutorial
+//! and practice prompts and the countdown.
+
+use super::{format_time, label};
+use crate::{
+    board::TOP_WALL,
+    controls::{Action, KeyBindings},
+    events::{BoardCleared, FinesseChecked, ItemTriggered, LevelUp, LinesCleared},
+    game::{update_game, Countdown, COUNTDOWN_SECONDS},
+    locale::Locale,
+    piece::{HOLD_BOTTOM, HOLD_X},
+    themes::{self, Theme},
+    GameState,
+};
+use bevy::prelude::*;
+use tetris_core::{
+    lines_per_minute, Game, Mode, ShapeTypes, TutorialStep, SPRINT_LINES, ULTRA_TIME,
+};
+
+/// Draws the HUD and statistics beside the board while playing.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FinesseSession::default())
+            .add_startup_system(spawn_hud)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(sync_countdown_text.after(update_game)),
+            )
+            .add_system(apply_theme.after(themes::select_theme))
+            .add_system(track_finesse)
+            .add_system(sync_hud_text.after(track_finesse))
+            .add_system(sync_stats_text)
+            .add_system(sync_prompt_text)
+            .add_system(announce_clears);
+    }
+}
+
+fn tutorial_prompt(step: TutorialStep, locale: &Locale) -> &str {
+    locale.get(match step {
+        TutorialStep::Move => "tutorial.move",
+        TutorialStep::Rotate => "tutorial.rotate",
+        TutorialStep::SoftDrop => "tutorial.soft_drop",
+        TutorialStep::HardDrop => "tutorial.hard_drop",
+        TutorialStep::Hold => "tutorial.hold",
+        TutorialStep::ClearLine => "tutorial.clear_line",
+    })
+}
+
+/// Score, level, line count and streaks shown beside the playfield.
+#[derive(Component)]
+struct HudText;
+
+/// Live statistics of the game, left of the HUD.
+#[derive(Component)]
+pub struct StatsText;
+
+/// Left edge of the statistics panel, left of the hold column.
+pub const STATS_LEFT: f32 = HOLD_X - 200.;
+/// Characters in a line of the statistics panel.
+const STATS_WIDTH: usize = 13;
+
+/// Name of the last line clear, shown over the board until its timer runs
+/// out.
+#[derive(Component)]
+struct Announcement(Timer);
+
+const ANNOUNCEMENT_SECONDS: f32 = 1.5;
+
+#[derive(Component)]
+struct CountdownText;
+
+/// Line above the playfield: the prompt of the tutorial lesson or the
+/// palette of practice mode.
+#[derive(Component)]
+struct PromptText;
+
+/// Placements judged in finesse mode since the game was started, over all
+/// finesse games of the session.
+#[derive(Default)]
+pub struct FinesseSession {
+    placements: u32,
+    errors: u32,
+}
+
+impl FinesseSession {
+    /// Percentage of placements made with the fewest inputs.
+    pub fn accuracy(&self) -> f32 {
+        if self.placements == 0 {
+            100.
+        } else {
+            (self.placements - self.errors) as f32 / self.placements as f32 * 100.
+        }
+    }
+}
+
+fn sync_hud_text(
+    game: Res<Game>,
+    finesse: Res<FinesseSession>,
+    locale: Res<Locale>,
+    mut query: Query<&mut Text, With<HudText>>,
+) {
+    if !game.is_changed() && !finesse.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    // Label and value of each line of the HUD.
+    let lines: Vec<(&str, String)> = match game.mode {
+        Mode::Endless | Mode::Items { .. } | Mode::Invisible | Mode::Daily => vec![
+            ("hud.score", game.score.to_string()),
+            ("hud.level", game.level.level.to_string()),
+            ("hud.lines", game.level.lines.to_string()),
+        ],
+        Mode::Sprint => vec![
+            ("hud.time", format_time(game.elapsed)),
+            (
+                "hud.lines",
+                format!("{}/{}", game.level.lines, SPRINT_LINES),
+            ),
+        ],
+        Mode::Ultra => vec![
+            (
+                "hud.time",
+                format_time(ULTRA_TIME.saturating_sub(game.elapsed)),
+            ),
+            ("hud.score", game.score.to_string()),
+            ("hud.lines", game.level.lines.to_string()),
+        ],
+        Mode::Marathon { level_cap, .. } => vec![
+            ("hud.score", game.score.to_string()),
+            ("hud.level", format!("{}/{}", game.level.level, level_cap)),
+            ("hud.lines", game.level.lines.to_string()),
+        ],
+        Mode::Puzzle => vec![
+            (
+                "hud.pieces_left",
+                // The active piece, the queue and the held piece.
+                (1 + game.queue.pieces.len() + game.hold.iter().count()).to_string(),
+            ),
+            ("hud.lines", game.level.lines.to_string()),
+        ],
+        Mode::Zen | Mode::Practice => vec![
+            ("hud.score", game.score.to_string()),
+            ("hud.lines", game.level.lines.to_string()),
+        ],
+        Mode::Tutorial => vec![(
+            "hud.lesson",
+            format!(
+                "{}/{}",
+                game.tutorial
+                    .map_or(TutorialStep::ALL.len(), |step| step.index() + 1),
+                TutorialStep::ALL.len()
+            ),
+        )],
+        Mode::Finesse => vec![
+            ("hud.accuracy", format!("{:.1}%", finesse.accuracy())),
+            ("hud.errors", finesse.errors.to_string()),
+            ("hud.pieces", game.stats.pieces.to_string()),
+        ],
+    };
+    let mut value = lines
+        .iter()
+        .map(|(label, value)| format!("{}\n{}", locale.get(label), value))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let slow_gravity = game.slow_gravity_remaining();
+    if !slow_gravity.is_zero() {
+        let seconds = format!("{:.0}", slow_gravity.as_secs_f32().ceil());
+        value += &format!(
+            "\n\n{}",
+            locale.format("hud.slow", &[("seconds", &seconds)])
+        );
+    }
+    if game.combo > 1 {
+        let count = game.combo - 1;
+        value += &format!("\n\n{}", locale.format("hud.combo", &[("count", &count)]));
+    }
+    if game.back_to_back > 1 {
+        let count = game.back_to_back - 1;
+        value += &format!("\n{}", locale.format("hud.b2b", &[("count", &count)]));
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn sync_stats_text(
+    game: Res<Game>,
+    locale: Res<Locale>,
+    mut query: Query<&mut Text, With<StatsText>>,
+) {
+    if !game.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    let stats = &game.stats;
+    // Labels on the left and values on the right of a fixed width column.
+    let line = |key: &str, value: String| {
+        let label = locale.get(key);
+        let width = STATS_WIDTH.saturating_sub(label.chars().count() + 1);
+        format!("{} {:>width$}\n", label, value, width = width)
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!(
+            "{}\n\n{}{}{}{}\n",
+            locale.get("stats.title"),
+            line("stats.pieces", stats.pieces.to_string()),
+            line(
+                "stats.pps",
+                format!("{:.2}", stats.pieces_per_second(game.elapsed))
+            ),
+            line(
+                "stats.lpm",
+                format!("{:.1}", lines_per_minute(game.level.lines, game.elapsed))
+            ),
+            line("stats.holds", stats.holds.to_string())
+        );
+        for (section, &shape_type) in text.sections[1..].iter_mut().zip(ShapeTypes::ALL.iter()) {
+            section.value = format!("{} {:>11}\n", shape_type.letter(), stats.usage(shape_type));
+        }
+    }
+}
+
+/// Shows what the current tutorial lesson asks and the keys bound to it, or
+/// the keys of the practice palette.
+fn sync_prompt_text(
+    game: Res<Game>,
+    bindings: Res<KeyBindings>,
+    locale: Res<Locale>,
+    mut query: Query<&mut Text, With<PromptText>>,
+) {
+    if !game.is_changed() && !bindings.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    let value = match game.tutorial {
+        Some(step) => {
+            let keys: Vec<_> = Action::ALL
+                .iter()
+                .filter(|action| {
+                    action
+                        .button()
+                        .is_some_and(|button| step.buttons().contains(&button))
+                })
+                .flat_map(|&action| bindings.keys(action))
+                .map(|key| format!("{:?}", key))
+                .collect();
+            let prompt = tutorial_prompt(step, &locale);
+            if keys.is_empty() {
+                prompt.to_string()
+            } else {
+                format!("{}: {}", prompt, keys.join(", "))
+            }
+        }
+        None if game.mode == Mode::Practice => {
+            let pieces = ShapeTypes::ALL
+                .iter()
+                .enumerate()
+                .map(|(i, shape_type)| format!("{} {}", i + 1, shape_type.letter()))
+                .collect::<Vec<_>>()
+                .join("  ");
+            locale.format("practice.palette", &[("pieces", &pieces)])
+        }
+        None => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn track_finesse(mut checked: EventReader<FinesseChecked>, mut finesse: ResMut<FinesseSession>) {
+    for event in checked.iter() {
+        finesse.placements += 1;
+        if event.inputs > event.minimal {
+            finesse.errors += 1;
+        }
+    }
+}
+
+/// Names line clears, their streaks, level ups, items and finesse errors for
+/// a moment over the board.
+#[allow(clippy::too_many_arguments)]
+fn announce_clears(
+    time: Res<Time>,
+    locale: Res<Locale>,
+    mut cleared: EventReader<LinesCleared>,
+    mut level_up: EventReader<LevelUp>,
+    mut finesse: EventReader<FinesseChecked>,
+    mut items: EventReader<ItemTriggered>,
+    mut board_cleared: EventReader<BoardCleared>,
+    mut query: Query<(&mut Text, &mut Announcement, &mut Visibility)>,
+) {
+    let mut lines = Vec::new();
+    for event in cleared.iter() {
+        if event.back_to_back > 1 {
+            lines.push(locale.get("announce.back_to_back").to_string());
+        }
+        let name = locale.get(match event.rows.len() {
+            1 => "announce.single",
+            2 => "announce.double",
+            3 => "announce.triple",
+            _ => "announce.tetris",
+        });
+        lines.push(if event.t_spin {
+            locale.format("announce.t_spin", &[("clear", &name)])
+        } else {
+            name.to_string()
+        });
+        if event.combo > 1 {
+            let count = event.combo - 1;
+            lines.push(locale.format("announce.combo", &[("count", &count)]));
+        }
+    }
+    for event in level_up.iter() {
+        lines.push(locale.format("announce.level", &[("level", &event.level)]));
+    }
+    for event in items.iter() {
+        lines.push(locale.get(event.item.key()).to_string());
+    }
+    if board_cleared.iter().count() > 0 {
+        lines.push(locale.get("announce.fresh_start").to_string());
+    }
+    for event in finesse.iter().filter(|event| event.inputs > event.minimal) {
+        lines.push(locale.get("announce.finesse_error").to_string());
+        lines.push(locale.format(
+            "announce.finesse_inputs",
+            &[("inputs", &event.inputs), ("minimal", &event.minimal)],
+        ));
+    }
+
+    for (mut text, mut announcement, mut visibility) in query.iter_mut() {
+        if !lines.is_empty() {
+            text.sections[0].value = lines.join("\n");
+            announcement.0.reset();
+            visibility.is_visible = true;
+        } else if announcement.0.tick(time.delta()).just_finished() {
+            visibility.is_visible = false;
+        }
+    }
+}
+
+fn sync_countdown_text(
+    countdown: Res<Countdown>,
+    locale: Res<Locale>,
+    mut query: Query<(&mut Text, &mut Visibility), With<CountdownText>>,
+) {
+    if !countdown.is_changed() {
+        return;
+    }
+
+    let remaining = COUNTDOWN_SECONDS - countdown.0.elapsed_secs();
+    for (mut text, mut visibility) in query.iter_mut() {
+        text.sections[0].value = if remaining > 0. {
+            format!("{}", remaining.ceil())
+        } else {
+            locale.get("hud.go").to_string()
+        };
+        visibility.is_visible = !countdown.0.finished();
+    }
+}
+
+fn spawn_hud(mut commands: Commands, theme: Res<Theme>, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 24.,
+                    color: Color::WHITE,
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Top,
+                    horizontal: HorizontalAlign::Center,
+                },
+            ),
+            transform: Transform::from_translation(Vec3::new(HOLD_X, HOLD_BOTTOM - 10., 0.)),
+            ..default()
+        })
+        .insert(HudText);
+
+    let stats_style = TextStyle {
+        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+        font_size: 16.,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text {
+                // The summary, then one section per piece type in its color.
+                sections: std::iter::once(stats_style.clone())
+                    .chain(ShapeTypes::ALL.iter().map(|&shape_type| TextStyle {
+                        color: theme.piece_color(shape_type),
+                        ..stats_style.clone()
+                    }))
+                    .map(|style| TextSection {
+                        value: String::new(),
+                        style,
+                    })
+                    .collect(),
+                alignment: TextAlignment {
+                    vertical: VerticalAlign::Top,
+                    horizontal: HorizontalAlign::Left,
+                },
+            },
+            transform: Transform::from_translation(Vec3::new(STATS_LEFT, TOP_WALL - 10., 0.)),
+            ..default()
+        })
+        .insert(StatsText);
+
+    // A single line in the margin above the playfield.
+    let mut prompt = label(&asset_server, "", Vec3::new(0., TOP_WALL + 20., 1.));
+    prompt.text.sections[0].style.font_size = 20.;
+    commands.spawn_bundle(prompt).insert(PromptText);
+
+    let mut countdown = label(&asset_server, "", Vec3::new(0., 0., 1.));
+    countdown.text.sections[0].style.font_size = 96.;
+    commands.spawn_bundle(countdown).insert(CountdownText);
+
+    commands
+        .spawn_bundle(label(&asset_server, "", Vec3::new(0., 0., 1.)))
+        .insert(Announcement(Timer::from_seconds(
+            ANNOUNCEMENT_SECONDS,
+            false,
+        )));
+}
+
+/// Recolors the piece counts of the statistics panel.
+fn apply_theme(theme: Res<Theme>, mut stats: Query<&mut Text, With<StatsText>>) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    for mut text in stats.iter_mut() {
+        for (section, &shape_type) in text.sections[1..].iter_mut().zip(ShapeTypes::ALL.iter()) {
+            section.style.color = theme.piece_color(shape_type);
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! Versus lobby: hosting or joining a room, the room's settings and the
+//! players' readiness.
+
+use super::{
+    body_style, despawn_overlay, mode_name, spawn_overlay_sections, OverlayBody, PlayerName,
+    OVERLAY_COLOR,
+};
+use crate::{
+    game::Seed,
+    lobby::{self, LobbyNotice, Room, VERSUS_MODES},
+    locale::Locale,
+    menu::{Menu, MenuControls, MenuEvent},
+    network::{self, Connection, DEFAULT_PORT},
+    GameState,
+};
+use bevy::{prelude::*, window::ReceivedCharacter};
+use tetris_core::{Handicap, MAX_PREVIEWS};
+
+/// The lobby screen.
+pub struct LobbyMenuPlugin;
+
+impl Plugin for LobbyMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LobbyMenu::default())
+            .add_system_set(SystemSet::on_enter(GameState::Lobby).with_system(spawn_lobby))
+            .add_system_set(
+                SystemSet::on_update(GameState::Lobby)
+                    .with_system(navigate_lobby)
+                    .with_system(edit_address),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Lobby).with_system(despawn_overlay));
+    }
+}
+
+/// Focused items of the lobby screen outside of and in a room, the address
+/// to join and the handicap taken into the next room.
+struct LobbyMenu {
+    menu: Menu,
+    room_menu: Menu,
+    address: String,
+    handicap: Handicap,
+}
+
+impl Default for LobbyMenu {
+    fn default() -> LobbyMenu {
+        LobbyMenu {
+            menu: Menu::default(),
+            room_menu: Menu::default(),
+            address: format!("127.0.0.1:{}", DEFAULT_PORT),
+            handicap: Handicap::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LobbyItem {
+    Host,
+    Join,
+    Back,
+}
+
+const LOBBY_ITEMS: [LobbyItem; 3] = [LobbyItem::Host, LobbyItem::Join, LobbyItem::Back];
+
+const MAX_ADDRESS_LENGTH: usize = 64;
+
+/// Settings of a room, the mode only changed by the host.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RoomItem {
+    Mode,
+    Garbage,
+    Gravity,
+    Previews,
+}
+
+fn room_items(is_host: bool) -> Vec<RoomItem> {
+    let mut items = if is_host {
+        vec![RoomItem::Mode]
+    } else {
+        Vec::new()
+    };
+    items.extend([RoomItem::Garbage, RoomItem::Gravity, RoomItem::Previews]);
+    items
+}
+
+const MAX_HANDICAP_GARBAGE: usize = 10;
+/// Gravity multipliers a player can pick as a handicap.
+const HANDICAP_GRAVITY: [f64; 4] = [1., 1.5, 2., 3.];
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_lobby(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    menu: Res<LobbyMenu>,
+    room: Option<Res<Room>>,
+    connection: Option<Res<Connection>>,
+    notice: Res<LobbyNotice>,
+    locale: Res<Locale>,
+) {
+    let room = room.as_deref().zip(connection.as_deref());
+    spawn_overlay_sections(
+        commands,
+        &asset_server,
+        locale.get("lobby.title"),
+        lobby_sections(&body_style(&asset_server), &menu, room, &notice, &locale),
+        "",
+        OVERLAY_COLOR,
+    );
+}
+
+/// Host and join items outside of a room, the players and their ready
+/// states inside one.
+fn lobby_sections(
+    style: &TextStyle,
+    menu: &LobbyMenu,
+    room: Option<(&Room, &Connection)>,
+    notice: &LobbyNotice,
+    locale: &Locale,
+) -> Vec<TextSection> {
+    let notice = notice
+        .0
+        .map_or(String::new(), |key| format!("{}\n\n", locale.get(key)));
+    let (room, connection) = match room {
+        Some(room) => room,
+        None => {
+            let items: Vec<_> = LOBBY_ITEMS
+                .iter()
+                .map(|item| match item {
+                    LobbyItem::Host => locale.format("lobby.host", &[("port", &DEFAULT_PORT)]),
+                    LobbyItem::Join => {
+                        let address = format!("{}_", menu.address);
+                        locale.format("lobby.join", &[("address", &address)])
+                    }
+                    LobbyItem::Back => locale.get("lobby.back").to_string(),
+                })
+                .collect();
+            let footer = format!("\n{}{}", notice, locale.get("lobby.hint"));
+            return menu.menu.sections(style, &items, &footer);
+        }
+    };
+
+    let header = match &connection.mode {
+        network::Mode::Host(port) => locale.format("lobby.hosting", &[("port", port)]),
+        network::Mode::Connect(address) if room.joined => {
+            locale.format("lobby.joined", &[("address", address)])
+        }
+        network::Mode::Connect(address) => {
+            locale.format("lobby.connecting", &[("address", address)])
+        }
+    };
+    let mode = mode_name(room.mode, locale);
+    let mode = if connection.is_host() {
+        format!("< {} >", mode)
+    } else {
+        mode.to_string()
+    };
+    // Only the host picks the mode, the other player just sees it.
+    let fixed_mode = if connection.is_host() {
+        String::new()
+    } else {
+        format!("  {:<14}{}\n", locale.get("menu.mode"), mode)
+    };
+    let settings: Vec<_> = room_items(connection.is_host())
+        .into_iter()
+        .map(|item| {
+            let (name, value) = match item {
+                RoomItem::Mode => (locale.get("menu.mode"), mode.clone()),
+                RoomItem::Garbage => (
+                    locale.get("lobby.garbage"),
+                    room.handicap.garbage_rows.to_string(),
+                ),
+                RoomItem::Gravity => (
+                    locale.get("lobby.gravity"),
+                    format!("x{}", room.handicap.gravity),
+                ),
+                RoomItem::Previews => (
+                    locale.get("lobby.previews"),
+                    room.handicap.previews.to_string(),
+                ),
+            };
+            format!("{:<14}{}", name, value)
+        })
+        .collect();
+    let mut players: Vec<_> = room
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, player)| {
+            let player_name = match player.name.trim() {
+                "" => locale.get("name_entry.default_name"),
+                trimmed => trimmed,
+            };
+            let ready = locale.get(if player.ready {
+                "lobby.ready"
+            } else {
+                "lobby.not_ready"
+            });
+            let handicap = player.handicap;
+            if handicap == Handicap::default() {
+                format!("{}. {:<14}{}", i + 1, player_name, ready)
+            } else {
+                let handicap = locale.format(
+                    "lobby.handicap",
+                    &[
+                        ("garbage", &handicap.garbage_rows),
+                        ("gravity", &handicap.gravity),
+                        ("previews", &handicap.previews),
+                    ],
+                );
+                format!("{}. {:<14}{:<12}{}", i + 1, player_name, ready, handicap)
+            }
+        })
+        .collect();
+    if room.joined && room.players.len() < 2 {
+        players.push(format!("2. {}", locale.get("lobby.waiting")));
+    }
+    let hint = locale.get(if connection.is_host() {
+        "lobby.host_hint"
+    } else {
+        "lobby.guest_hint"
+    });
+    let footer = format!("\n{}\n\n{}{}", players.join("\n"), notice, hint);
+    let mut sections = vec![TextSection {
+        value: format!("{}\n\n{}", header, fixed_mode),
+        style: style.clone(),
+    }];
+    sections.extend(menu.room_menu.sections(style, &settings, &footer));
+    sections
+}
+
+/// The handicap with the value of `item` stepped by `step` within its range.
+fn step_handicap(mut handicap: Handicap, item: RoomItem, step: i32) -> Handicap {
+    match item {
+        RoomItem::Mode => {}
+        RoomItem::Garbage => {
+            handicap.garbage_rows = (handicap.garbage_rows as i32 + step)
+                .clamp(0, MAX_HANDICAP_GARBAGE as i32) as usize;
+        }
+        RoomItem::Gravity => {
+            let index = HANDICAP_GRAVITY
+                .iter()
+                .position(|&gravity| gravity == handicap.gravity)
+                .unwrap_or(0) as i32;
+            handicap.gravity = HANDICAP_GRAVITY
+                [(index + step).clamp(0, HANDICAP_GRAVITY.len() as i32 - 1) as usize];
+        }
+        RoomItem::Previews => {
+            handicap.previews =
+                (handicap.previews as i32 + step).clamp(0, MAX_PREVIEWS as i32) as usize;
+        }
+    }
+    handicap
+}
+
+/// Outside of a room, hosts one or joins the typed address. In a room,
+/// confirming marks the player ready or not, and the host starts the match
+/// once everyone is ready. Left and Right change the focused setting: the
+/// mode, picked by the host, or the player's handicap. Going back leaves the
+/// room, then the lobby.
+#[allow(clippy::too_many_arguments)]
+fn navigate_lobby(
+    mut commands: Commands,
+    mut controls: MenuControls,
+    mut menu: ResMut<LobbyMenu>,
+    room: Option<ResMut<Room>>,
+    connection: Option<Res<Connection>>,
+    mut notice: ResMut<LobbyNotice>,
+    name: Res<PlayerName>,
+    seed: Res<Seed>,
+    locale: Res<Locale>,
+    mut state: ResMut<State<GameState>>,
+    mut in_room: Local<bool>,
+    mut query: Query<&mut Text, With<OverlayBody>>,
+) {
+    let mut room = room;
+    match (room.as_mut(), &connection) {
+        (Some(room), Some(connection)) => {
+            let own = room.own_index(connection);
+            let ready = room.players.get(own).is_some_and(|player| player.ready);
+            let items = room_items(connection.is_host());
+            let item = items[menu.room_menu.focused.min(items.len() - 1)];
+            match menu.room_menu.navigate(&mut controls, items.len()) {
+                Some(MenuEvent::Focus(focused)) => menu.room_menu.focused = focused,
+                Some(MenuEvent::Change(step)) if item == RoomItem::Mode => {
+                    let index = VERSUS_MODES
+                        .iter()
+                        .position(|&mode| mode == room.mode)
+                        .unwrap_or(0) as i32;
+                    room.mode =
+                        VERSUS_MODES[(index + step).rem_euclid(VERSUS_MODES.len() as i32) as usize];
+                    room.send(connection);
+                }
+                Some(MenuEvent::Change(step)) => {
+                    let handicap = step_handicap(room.handicap, item, step);
+                    if handicap != room.handicap {
+                        room.set_handicap(connection, handicap);
+                        menu.handicap = handicap;
+                    }
+                }
+                Some(MenuEvent::Confirm) if connection.is_host() && room.can_start() => {
+                    room.start(connection, seed.0.unwrap_or_else(rand::random));
+                    let _ = state.set(GameState::Playing);
+                    return;
+                }
+                Some(MenuEvent::Confirm) if room.joined => room.set_ready(connection, !ready),
+                Some(MenuEvent::Back) => {
+                    lobby::leave_room(&mut commands);
+                    notice.0 = None;
+                }
+                _ => {}
+            }
+        }
+        _ => {
+            let item = LOBBY_ITEMS[menu.menu.focused];
+            match menu.menu.navigate(&mut controls, LOBBY_ITEMS.len()) {
+                Some(MenuEvent::Focus(focused)) => menu.menu.focused = focused,
+                Some(MenuEvent::Confirm) if item != LobbyItem::Back => {
+                    let mode = match item {
+                        LobbyItem::Host => network::Mode::Host(DEFAULT_PORT),
+                        _ => network::Mode::Connect(menu.address.trim().to_string()),
+                    };
+                    lobby::open_room(
+                        &mut commands,
+                        mode,
+                        name.0.trim().to_string(),
+                        menu.handicap,
+                    );
+                    menu.room_menu = Menu::default();
+                    notice.0 = None;
+                }
+                Some(MenuEvent::Confirm | MenuEvent::Back) => {
+                    notice.0 = None;
+                    let _ = state.set(GameState::Menu);
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Rooms come and go through commands, seen here a frame later.
+    let room_changed = room.as_ref().is_some_and(|room| room.is_changed())
+        || connection
+            .as_ref()
+            .is_some_and(|connection| connection.is_changed())
+        || room.is_some() != *in_room;
+    *in_room = room.is_some();
+    if room_changed || menu.is_changed() || notice.is_changed() {
+        for mut text in query.iter_mut() {
+            let style = text.sections[0].style.clone();
+            text.sections = lobby_sections(
+                &style,
+                &menu,
+                room.as_deref().zip(connection.as_deref()),
+                &notice,
+                &locale,
+            );
+        }
+    }
+}
+
+/// Edits the address to join from typed characters while the join item is
+/// focused, Backspace removes the last one.
+fn edit_address(
+    mut characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    room: Option<Res<Room>>,
+    mut menu: ResMut<LobbyMenu>,
+) {
+    if room.is_some() || LOBBY_ITEMS[menu.menu.focused] != LobbyItem::Join {
+        return;
+    }
+
+    for event in characters.iter() {
+        let allowed = event.char.is_ascii_alphanumeric() || ".:-[]".contains(event.char);
+        if allowed && menu.address.len() < MAX_ADDRESS_LENGTH {
+            menu.address.push(event.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        menu.address.pop();
+    }
+}
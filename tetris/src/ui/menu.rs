@@ -0,0 +1,448 @@
+//! Main menu, puzzle select and the stats screen.
+
+use super::{
+    body_style, despawn_overlay, mode_name, spawn_overlay, spawn_overlay_sections, OverlayBody,
+    OVERLAY_COLOR,
+};
+use crate::{
+    analytics::{Analytics, Totals, TREND_GAMES},
+    game::{Seed, SelectedMode, SelectedPuzzle},
+    high_scores::HighScores,
+    locale::Locale,
+    menu::{Menu, MenuCommand, MenuControls, MenuEvent},
+    puzzles::Puzzles,
+    settings::{self, Settings},
+    GameState,
+};
+use bevy::{prelude::*, window::ReceivedCharacter};
+use tetris_core::Mode;
+
+/// The main menu and the screens only it opens.
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MainMenu::default())
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(spawn_menu))
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu)
+                    .with_system(navigate_menu)
+                    .with_system(edit_seed),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(despawn_overlay))
+            .add_system_set(SystemSet::on_pause(GameState::Menu).with_system(despawn_overlay))
+            .add_system_set(SystemSet::on_resume(GameState::Menu).with_system(spawn_menu))
+            .add_system_set(
+                SystemSet::on_enter(GameState::PuzzleSelect).with_system(spawn_puzzle_select),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::PuzzleSelect).with_system(select_puzzle),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::PuzzleSelect).with_system(despawn_overlay),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Stats).with_system(spawn_stats))
+            .add_system_set(SystemSet::on_update(GameState::Stats).with_system(leave_stats))
+            .add_system_set(SystemSet::on_exit(GameState::Stats).with_system(despawn_overlay));
+    }
+}
+
+const MODES: [Mode; 12] = [
+    Mode::Endless,
+    Mode::Sprint,
+    Mode::Ultra,
+    Mode::Marathon {
+        start_level: 1,
+        level_cap: settings::DEFAULT_MARATHON_LEVEL_CAP,
+    },
+    Mode::Puzzle,
+    Mode::Finesse,
+    Mode::Items { versus: false },
+    Mode::Invisible,
+    Mode::Zen,
+    Mode::Daily,
+    Mode::Tutorial,
+    Mode::Practice,
+];
+
+/// Focused item of the main menu, kept while playing.
+#[derive(Default)]
+struct MainMenu(Menu);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MainMenuItem {
+    Play,
+    Mode,
+    StartLevel,
+    #[cfg(not(target_arch = "wasm32"))]
+    Versus,
+    Stats,
+    Settings,
+    Editor,
+}
+
+/// Items of the main menu, the start level only for a marathon. The web
+/// build can't open network connections and has no versus.
+fn main_menu_items(mode: Mode) -> Vec<MainMenuItem> {
+    let mut items = vec![MainMenuItem::Play, MainMenuItem::Mode];
+    if let Mode::Marathon { .. } = mode {
+        items.push(MainMenuItem::StartLevel);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    items.push(MainMenuItem::Versus);
+    items.extend([
+        MainMenuItem::Stats,
+        MainMenuItem::Settings,
+        MainMenuItem::Editor,
+    ]);
+    items
+}
+
+fn spawn_menu(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    menu: Res<MainMenu>,
+    high_scores: Res<HighScores>,
+    seed: Res<Seed>,
+    mode: Res<SelectedMode>,
+    locale: Res<Locale>,
+) {
+    spawn_overlay_sections(
+        commands,
+        &asset_server,
+        locale.get("menu.title"),
+        menu_sections(
+            &menu,
+            &body_style(&asset_server),
+            &high_scores,
+            &seed,
+            &mode,
+            &locale,
+        ),
+        locale.get("menu.hint"),
+        OVERLAY_COLOR,
+    );
+}
+
+fn menu_sections(
+    menu: &MainMenu,
+    style: &TextStyle,
+    high_scores: &HighScores,
+    seed: &Seed,
+    mode: &SelectedMode,
+    locale: &Locale,
+) -> Vec<TextSection> {
+    let items: Vec<_> = main_menu_items(mode.0)
+        .into_iter()
+        .map(|item| match item {
+            MainMenuItem::Play => locale.get("menu.play").to_string(),
+            MainMenuItem::Mode => format!(
+                "{:<14}< {} >",
+                locale.get("menu.mode"),
+                mode_name(mode.0, locale)
+            ),
+            MainMenuItem::StartLevel => match mode.0 {
+                Mode::Marathon { start_level, .. } => {
+                    format!("{:<14}{}", locale.get("menu.start_level"), start_level)
+                }
+                _ => unreachable!(),
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            MainMenuItem::Versus => locale.get("menu.versus").to_string(),
+            MainMenuItem::Stats => locale.get("menu.stats").to_string(),
+            MainMenuItem::Settings => locale.get("menu.settings").to_string(),
+            MainMenuItem::Editor => locale.get("menu.editor").to_string(),
+        })
+        .collect();
+    let seed = match seed.0 {
+        Some(seed) => seed.to_string(),
+        None => locale.get("menu.random_seed").to_string(),
+    };
+    let footer = format!(
+        "\n{}\n\n{}",
+        high_scores.to_text(locale.get("menu.no_high_scores")),
+        locale.format("menu.seed", &[("seed", &seed)])
+    );
+    menu.0.sections(style, &items, &footer)
+}
+
+/// Moves through the main menu: Left/Right cycle through the modes and
+/// change the start level of a marathon, confirming starts the selected
+/// mode, puzzles being picked on their own screen first, or opens the
+/// settings, the stats or the editor. A tap also starts the game.
+#[allow(clippy::too_many_arguments)]
+fn navigate_menu(
+    mut controls: MenuControls,
+    touches: Res<Touches>,
+    mut menu: ResMut<MainMenu>,
+    mut mode: ResMut<SelectedMode>,
+    high_scores: Res<HighScores>,
+    seed: Res<Seed>,
+    locale: Res<Locale>,
+    mut state: ResMut<State<GameState>>,
+    mut query: Query<&mut Text, With<OverlayBody>>,
+) {
+    let items = main_menu_items(mode.0);
+    let item = items[menu.0.focused.min(items.len() - 1)];
+    let event = if touches.iter_just_pressed().next().is_some() {
+        Some(MenuEvent::Confirm)
+    } else {
+        menu.0.navigate(&mut controls, items.len())
+    };
+
+    match event {
+        Some(MenuEvent::Focus(focused)) => menu.0.focused = focused,
+        Some(MenuEvent::Change(step)) if item == MainMenuItem::Mode => {
+            let index = MODES
+                .iter()
+                .position(|m| std::mem::discriminant(m) == std::mem::discriminant(&mode.0))
+                .unwrap_or(0) as i32;
+            mode.0 = MODES[(index + step).rem_euclid(MODES.len() as i32) as usize];
+        }
+        Some(MenuEvent::Change(step)) if item == MainMenuItem::StartLevel => {
+            if let Mode::Marathon { start_level, .. } = &mut mode.0 {
+                let max = settings::MARATHON_LEVEL_CAP_RANGE.1;
+                *start_level = (*start_level as i32 + step).clamp(1, max as i32) as u32;
+            }
+        }
+        Some(MenuEvent::Confirm) => {
+            let next = match item {
+                MainMenuItem::Settings => {
+                    let _ = state.push(GameState::Settings);
+                    return;
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                MainMenuItem::Versus => GameState::Lobby,
+                MainMenuItem::Stats => GameState::Stats,
+                MainMenuItem::Editor => GameState::Editor,
+                _ if mode.0 == Mode::Puzzle => GameState::PuzzleSelect,
+                _ => GameState::Playing,
+            };
+            let _ = state.set(next);
+            return;
+        }
+        _ => {}
+    }
+
+    if menu.is_changed() || mode.is_changed() {
+        // Leaving a marathon drops the start level item.
+        menu.0.focused = menu.0.focused.min(main_menu_items(mode.0).len() - 1);
+        for mut text in query.iter_mut() {
+            let style = text.sections[0].style.clone();
+            text.sections = menu_sections(&menu, &style, &high_scores, &seed, &mode, &locale);
+        }
+    }
+}
+
+/// Edits the seed of the next games from typed digits, Backspace removes the
+/// last digit.
+#[allow(clippy::too_many_arguments)]
+fn edit_seed(
+    mut characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut seed: ResMut<Seed>,
+    menu: Res<MainMenu>,
+    high_scores: Res<HighScores>,
+    mode: Res<SelectedMode>,
+    locale: Res<Locale>,
+    mut query: Query<&mut Text, With<OverlayBody>>,
+) {
+    for event in characters.iter() {
+        if let Some(digit) = event.char.to_digit(10) {
+            let current = seed.0.unwrap_or(0);
+            if let Some(next) = current
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(digit as u64))
+            {
+                seed.0 = Some(next);
+            }
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        seed.0 = seed.0.map(|value| value / 10).filter(|&value| value > 0);
+    }
+
+    if seed.is_changed() {
+        for mut text in query.iter_mut() {
+            let style = text.sections[0].style.clone();
+            text.sections = menu_sections(&menu, &style, &high_scores, &seed, &mode, &locale);
+        }
+    }
+}
+
+fn spawn_puzzle_select(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    puzzles: Res<Puzzles>,
+    selected: Res<SelectedPuzzle>,
+    locale: Res<Locale>,
+) {
+    spawn_overlay_sections(
+        commands,
+        &asset_server,
+        locale.get("puzzles.title"),
+        puzzle_select_sections(&body_style(&asset_server), &puzzles, &selected, &locale),
+        locale.get("puzzles.hint"),
+        OVERLAY_COLOR,
+    );
+}
+
+fn puzzle_select_sections(
+    style: &TextStyle,
+    puzzles: &Puzzles,
+    selected: &SelectedPuzzle,
+    locale: &Locale,
+) -> Vec<TextSection> {
+    if puzzles.list.is_empty() {
+        return vec![TextSection {
+            value: locale.get("puzzles.none").to_string(),
+            style: style.clone(),
+        }];
+    }
+
+    let items: Vec<_> = puzzles
+        .list
+        .iter()
+        .map(|puzzle| {
+            let count = format!("{:>2}", puzzle.pieces().len());
+            format!(
+                "{:<20} {}",
+                puzzle.name,
+                locale.format("puzzles.pieces", &[("count", &count)])
+            )
+        })
+        .collect();
+    Menu::focused_on(selected.0).sections(style, &items, "")
+}
+
+fn select_puzzle(
+    mut controls: MenuControls,
+    puzzles: Res<Puzzles>,
+    mut selected: ResMut<SelectedPuzzle>,
+    locale: Res<Locale>,
+    mut state: ResMut<State<GameState>>,
+    mut query: Query<&mut Text, With<OverlayBody>>,
+) {
+    match Menu::focused_on(selected.0).navigate(&mut controls, puzzles.list.len()) {
+        Some(MenuEvent::Focus(focused)) => selected.0 = focused,
+        Some(MenuEvent::Confirm) => {
+            let _ = state.set(GameState::Playing);
+            return;
+        }
+        Some(MenuEvent::Back) => {
+            let _ = state.set(GameState::Menu);
+            return;
+        }
+        _ => return,
+    }
+
+    for mut text in query.iter_mut() {
+        let style = text.sections[0].style.clone();
+        text.sections = puzzle_select_sections(&style, &puzzles, &selected, &locale);
+    }
+}
+
+fn spawn_stats(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    analytics: Res<Analytics>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+) {
+    spawn_overlay(
+        commands,
+        &asset_server,
+        locale.get("analytics.title"),
+        &stats_text(&analytics, settings.analytics, &locale),
+        locale.get("analytics.hint"),
+        OVERLAY_COLOR,
+    );
+}
+
+/// Sums of every recorded game, the best score of each mode and the latest
+/// games against those before them.
+fn stats_text(analytics: &Analytics, enabled: bool, locale: &Locale) -> String {
+    let mut text = String::new();
+    if !enabled {
+        text.push_str(locale.get("analytics.disabled"));
+        text.push_str("\n\n");
+    }
+    if analytics.sessions.is_empty() {
+        text.push_str(locale.get("analytics.empty"));
+        return text;
+    }
+
+    let lifetime = analytics.lifetime();
+    let minutes = lifetime.time.as_secs() / 60;
+    let clears = lifetime.clears;
+    let rows = [
+        (locale.get("analytics.games"), lifetime.games.to_string()),
+        (
+            locale.get("analytics.time"),
+            locale.format(
+                "analytics.hours",
+                &[("hours", &(minutes / 60)), ("minutes", &(minutes % 60))],
+            ),
+        ),
+        (locale.get("hud.lines"), lifetime.lines.to_string()),
+        (locale.get("hud.pieces"), lifetime.pieces.to_string()),
+        (
+            locale.get("analytics.finesse_errors"),
+            lifetime.finesse_errors.to_string(),
+        ),
+        (
+            locale.get("analytics.clears"),
+            locale.format(
+                "analytics.clear_counts",
+                &[
+                    ("singles", &clears.singles),
+                    ("doubles", &clears.doubles),
+                    ("triples", &clears.triples),
+                    ("tetrises", &clears.tetrises),
+                    ("t_spins", &clears.t_spins),
+                ],
+            ),
+        ),
+    ];
+    for (name, value) in rows {
+        text.push_str(&format!("{:<18}{}\n", name, value));
+    }
+
+    text.push_str(&format!("\n{}\n", locale.get("analytics.best")));
+    for (mode, score) in analytics.best_scores() {
+        text.push_str(&format!("{:<18}{}\n", mode_name(mode, locale), score));
+    }
+
+    let (recent, earlier) = analytics.trend();
+    let trend = |totals: &Totals| {
+        format!(
+            "{:>8}  {:>6.2} {}  {:>6.1} {}",
+            totals.average_score(),
+            totals.pieces_per_second(),
+            locale.get("stats.pps"),
+            totals.lines_per_minute(),
+            locale.get("stats.lpm"),
+        )
+    };
+    text.push_str(&format!(
+        "\n{}\n{:<18}{}",
+        locale.format("analytics.trend", &[("games", &TREND_GAMES)]),
+        locale.get("analytics.recent"),
+        trend(&recent)
+    ));
+    if let Some(earlier) = earlier {
+        text.push_str(&format!(
+            "\n{:<18}{}",
+            locale.get("analytics.earlier"),
+            trend(&earlier)
+        ));
+    }
+    text
+}
+
+/// Enter or Esc goes back to the menu.
+fn leave_stats(mut controls: MenuControls, mut state: ResMut<State<GameState>>) {
+    if controls.take(MenuCommand::Confirm) || controls.take(MenuCommand::Back) {
+        let _ = state.set(GameState::Menu);
+    }
+}
@@ -0,0 +1,127 @@
+//! Pause menu.
+
+use super::{body_style, despawn_overlay, spawn_overlay_sections, OverlayBody, OVERLAY_COLOR};
+use crate::{
+    locale::Locale,
+    menu::{Menu, MenuControls, MenuEvent},
+    CurrentRoom, GameState,
+};
+use bevy::prelude::*;
+
+/// The pause menu, hidden while the settings opened from it are shown.
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PauseMenu::default())
+            .insert_resource(PauseSettings {
+                hide_playfield: true,
+            })
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(spawn_pause))
+            .add_system_set(SystemSet::on_update(GameState::Paused).with_system(navigate_pause))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(despawn_overlay))
+            .add_system_set(SystemSet::on_pause(GameState::Paused).with_system(despawn_overlay))
+            .add_system_set(SystemSet::on_resume(GameState::Paused).with_system(spawn_pause));
+    }
+}
+
+/// Pause screen behavior. Hiding the playfield keeps players from planning
+/// their next moves while the game is paused.
+struct PauseSettings {
+    hide_playfield: bool,
+}
+
+#[derive(Default)]
+struct PauseMenu(Menu);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PauseMenuItem {
+    Resume,
+    Settings,
+    Quit,
+}
+
+const PAUSE_MENU_ITEMS: [PauseMenuItem; 3] = [
+    PauseMenuItem::Resume,
+    PauseMenuItem::Settings,
+    PauseMenuItem::Quit,
+];
+
+fn spawn_pause(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<PauseSettings>,
+    menu: Res<PauseMenu>,
+    locale: Res<Locale>,
+) {
+    let background = if settings.hide_playfield {
+        Color::BLACK
+    } else {
+        OVERLAY_COLOR
+    };
+
+    spawn_overlay_sections(
+        commands,
+        &asset_server,
+        locale.get("pause.title"),
+        pause_sections(&menu, &body_style(&asset_server), &locale),
+        locale.get("pause.hint"),
+        background,
+    );
+}
+
+fn pause_sections(menu: &PauseMenu, style: &TextStyle, locale: &Locale) -> Vec<TextSection> {
+    let items: Vec<_> = PAUSE_MENU_ITEMS
+        .iter()
+        .map(|item| {
+            locale
+                .get(match item {
+                    PauseMenuItem::Resume => "pause.resume",
+                    PauseMenuItem::Settings => "pause.settings",
+                    PauseMenuItem::Quit => "pause.quit",
+                })
+                .to_string()
+        })
+        .collect();
+    menu.0.sections(style, &items, "")
+}
+
+/// Resumes the game, opens the settings or quits to the main menu, or to the
+/// lobby in a room. Going back resumes.
+fn navigate_pause(
+    mut controls: MenuControls,
+    mut menu: ResMut<PauseMenu>,
+    room: CurrentRoom,
+    locale: Res<Locale>,
+    mut state: ResMut<State<GameState>>,
+    mut query: Query<&mut Text, With<OverlayBody>>,
+) {
+    let item = PAUSE_MENU_ITEMS[menu.0.focused];
+    match menu.0.navigate(&mut controls, PAUSE_MENU_ITEMS.len()) {
+        Some(MenuEvent::Focus(focused)) => menu.0.focused = focused,
+        Some(MenuEvent::Confirm) if item == PauseMenuItem::Settings => {
+            let _ = state.push(GameState::Settings);
+            return;
+        }
+        Some(MenuEvent::Confirm) if item == PauseMenuItem::Quit => {
+            *menu = PauseMenu::default();
+            let _ = state.replace(if room.is_some() {
+                GameState::Lobby
+            } else {
+                GameState::Menu
+            });
+            return;
+        }
+        Some(MenuEvent::Confirm | MenuEvent::Back) => {
+            *menu = PauseMenu::default();
+            let _ = state.pop();
+            return;
+        }
+        _ => return,
+    }
+
+    for mut text in query.iter_mut() {
+        let style = text.sections[0].style.clone();
+        text.sections = pause_sections(&menu, &style, &locale);
+    }
+}
@@ -0,0 +1,408 @@
+//! Settings screen, opened from the main menu and the pause menu.
+
+use super::{body_style, despawn_overlay, spawn_overlay_sections, OverlayBody};
+use crate::{
+    audio::{self, AudioSettings},
+    controls::{Action, KeyBindings},
+    display::DisplaySettings,
+    locale::{self, Locale},
+    menu::{Menu, MenuControls, MenuEvent},
+    settings::{self, Settings},
+    skins::Skins,
+    themes::Theme,
+    GameState,
+};
+use bevy::prelude::*;
+use tetris_core::MAX_PREVIEWS;
+
+/// The settings screen and S opening it from the main and pause menus.
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SettingsMenu::default())
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(open_settings))
+            .add_system_set(SystemSet::on_update(GameState::Paused).with_system(open_settings))
+            .add_system_set(SystemSet::on_enter(GameState::Settings).with_system(spawn_settings))
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings).with_system(navigate_settings),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Settings)
+                    .with_system(save_settings)
+                    .with_system(despawn_overlay),
+            );
+    }
+}
+
+/// Line of the settings screen.
+#[derive(Clone, Copy)]
+enum SettingsRow {
+    Language,
+    MusicVolume,
+    EffectsVolume,
+    Mute,
+    Fullscreen,
+    Vsync,
+    Resolution,
+    Das,
+    Arr,
+    GhostPiece,
+    Analytics,
+    ScreenShake,
+    Rotation,
+    Scoring,
+    Previews,
+    Skin,
+    Theme,
+    MarathonLevelCap,
+    Binding(Action),
+}
+
+fn settings_rows() -> Vec<SettingsRow> {
+    [
+        SettingsRow::Language,
+        SettingsRow::MusicVolume,
+        SettingsRow::EffectsVolume,
+        SettingsRow::Mute,
+        SettingsRow::Fullscreen,
+        SettingsRow::Vsync,
+        SettingsRow::Resolution,
+        SettingsRow::Das,
+        SettingsRow::Arr,
+        SettingsRow::GhostPiece,
+        SettingsRow::Analytics,
+        SettingsRow::ScreenShake,
+        SettingsRow::Rotation,
+        SettingsRow::Scoring,
+        SettingsRow::Previews,
+        SettingsRow::Skin,
+        SettingsRow::Theme,
+        SettingsRow::MarathonLevelCap,
+    ]
+    .into_iter()
+    // The canvas of the web build follows the size of the browser window.
+    .filter(|row| cfg!(not(target_arch = "wasm32")) || !matches!(row, SettingsRow::Resolution))
+    .chain(Action::ALL.into_iter().map(SettingsRow::Binding))
+    .collect()
+}
+
+/// Focused row of the settings screen and whether it waits for a key to
+/// bind to the focused action.
+#[derive(Default)]
+struct SettingsMenu {
+    menu: Menu,
+    rebinding: bool,
+}
+
+fn action_name(action: Action, locale: &Locale) -> &str {
+    locale.get(match action {
+        Action::MoveLeft => "action.move_left",
+        Action::MoveRight => "action.move_right",
+        Action::SoftDrop => "action.soft_drop",
+        Action::HardDrop => "action.hard_drop",
+        Action::RotateClockwise => "action.rotate_clockwise",
+        Action::RotateCounterClockwise => "action.rotate_counter_clockwise",
+        Action::Rotate180 => "action.rotate_180",
+        Action::Hold => "action.hold",
+        Action::Pause => "action.pause",
+    })
+}
+
+fn on_off(value: bool, locale: &Locale) -> String {
+    locale
+        .get(if value { "settings.on" } else { "settings.off" })
+        .to_string()
+}
+
+/// Opens the settings screen on top of the current one.
+fn open_settings(mut keyboard_input: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::S) {
+        keyboard_input.reset(KeyCode::S);
+        let _ = state.push(GameState::Settings);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_settings(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut menu: ResMut<SettingsMenu>,
+    settings: Res<Settings>,
+    audio: Res<AudioSettings>,
+    display: Res<DisplaySettings>,
+    bindings: Res<KeyBindings>,
+    locale: Res<Locale>,
+) {
+    *menu = SettingsMenu::default();
+    spawn_overlay_sections(
+        commands,
+        &asset_server,
+        locale.get("settings.title"),
+        settings_sections(
+            &menu,
+            &body_style(&asset_server),
+            &settings,
+            &audio,
+            &display,
+            &bindings,
+            &locale,
+        ),
+        locale.get("settings.hint"),
+        Color::BLACK,
+    );
+}
+
+fn settings_sections(
+    menu: &SettingsMenu,
+    style: &TextStyle,
+    settings: &Settings,
+    audio: &AudioSettings,
+    display: &DisplaySettings,
+    bindings: &KeyBindings,
+    locale: &Locale,
+) -> Vec<TextSection> {
+    let items: Vec<_> = settings_rows()
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let selected = i == menu.menu.focused;
+            let (name, value) = match row {
+                SettingsRow::Language => (
+                    locale.get("settings.language"),
+                    locale.get("language").to_string(),
+                ),
+                SettingsRow::MusicVolume => (
+                    locale.get("settings.music_volume"),
+                    format!("{:.0}%", audio.music_volume * 100.),
+                ),
+                SettingsRow::EffectsVolume => (
+                    locale.get("settings.effects_volume"),
+                    format!("{:.0}%", audio.effects_volume * 100.),
+                ),
+                SettingsRow::Mute => (locale.get("settings.mute"), on_off(audio.muted, locale)),
+                SettingsRow::Fullscreen => (
+                    locale.get("settings.fullscreen"),
+                    on_off(display.fullscreen, locale),
+                ),
+                SettingsRow::Vsync => (locale.get("settings.vsync"), on_off(display.vsync, locale)),
+                SettingsRow::Resolution => (
+                    locale.get("settings.resolution"),
+                    format!("{}x{}", display.width, display.height),
+                ),
+                SettingsRow::Das => (
+                    locale.get("settings.das"),
+                    format!("{:.0} ms", settings.das * 1000.),
+                ),
+                SettingsRow::Arr => (
+                    locale.get("settings.arr"),
+                    format!("{:.0} ms", settings.arr * 1000.),
+                ),
+                SettingsRow::GhostPiece => (
+                    locale.get("settings.ghost_piece"),
+                    on_off(settings.ghost_piece, locale),
+                ),
+                SettingsRow::Analytics => (
+                    locale.get("settings.analytics"),
+                    on_off(settings.analytics, locale),
+                ),
+                SettingsRow::ScreenShake => (
+                    locale.get("settings.screen_shake"),
+                    format!("{:.0}%", settings.screen_shake * 100.),
+                ),
+                SettingsRow::Rotation => (
+                    locale.get("settings.rotation"),
+                    settings.rotation.name().to_string(),
+                ),
+                SettingsRow::Scoring => (
+                    locale.get("settings.scoring"),
+                    settings.scoring.name().to_string(),
+                ),
+                SettingsRow::Previews => (
+                    locale.get("settings.previews"),
+                    settings.previews.to_string(),
+                ),
+                SettingsRow::Skin => (locale.get("settings.skin"), settings.skin.clone()),
+                SettingsRow::Theme => (locale.get("settings.theme"), settings.theme.clone()),
+                SettingsRow::MarathonLevelCap => (
+                    locale.get("settings.marathon_level_cap"),
+                    settings.marathon_level_cap.to_string(),
+                ),
+                SettingsRow::Binding(action) if selected && menu.rebinding => (
+                    action_name(action, locale),
+                    locale.get("settings.press_a_key").to_string(),
+                ),
+                SettingsRow::Binding(action) => (
+                    action_name(action, locale),
+                    bindings
+                        .keys(action)
+                        .iter()
+                        .map(|key| format!("{:?}", key))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+            };
+            format!("{:<26}{:>14}", name, value)
+        })
+        .collect();
+    menu.menu.sections(style, &items, "")
+}
+
+/// Changes the focused value with Left/Right and waits for a new key for
+/// the focused action after Enter. Esc cancels a rebind or goes back to the
+/// previous screen.
+#[allow(clippy::too_many_arguments)]
+fn navigate_settings(
+    mut controls: MenuControls,
+    mut menu: ResMut<SettingsMenu>,
+    mut settings: ResMut<Settings>,
+    mut audio: ResMut<AudioSettings>,
+    mut display: ResMut<DisplaySettings>,
+    skins: Res<Skins>,
+    mut bindings: ResMut<KeyBindings>,
+    locale: Res<Locale>,
+    mut state: ResMut<State<GameState>>,
+    mut query: Query<&mut Text, With<OverlayBody>>,
+) {
+    let rows = settings_rows();
+    let row = rows[menu.menu.focused];
+
+    if menu.rebinding {
+        if let Some(key) = controls.take_key() {
+            match row {
+                SettingsRow::Binding(action) if key != KeyCode::Escape => {
+                    *bindings.keys_mut(action) = vec![key];
+                }
+                _ => {}
+            }
+            menu.rebinding = false;
+        }
+    } else {
+        match menu.menu.navigate(&mut controls, rows.len()) {
+            Some(MenuEvent::Focus(focused)) => menu.menu.focused = focused,
+            Some(MenuEvent::Change(step)) => {
+                adjust_setting(
+                    &mut settings,
+                    &mut audio,
+                    &mut display,
+                    &skins,
+                    row,
+                    step as f32,
+                );
+            }
+            Some(MenuEvent::Confirm) => {
+                if let SettingsRow::Binding(_) = row {
+                    menu.rebinding = true;
+                }
+            }
+            Some(MenuEvent::Back) => {
+                let _ = state.pop();
+                return;
+            }
+            None => {}
+        }
+    }
+
+    if menu.is_changed()
+        || settings.is_changed()
+        || audio.is_changed()
+        || display.is_changed()
+        || bindings.is_changed()
+        || locale.is_changed()
+    {
+        for mut text in query.iter_mut() {
+            let style = text.sections[0].style.clone();
+            text.sections = settings_sections(
+                &menu, &style, &settings, &audio, &display, &bindings, &locale,
+            );
+        }
+    }
+}
+
+fn adjust_setting(
+    settings: &mut Settings,
+    audio: &mut AudioSettings,
+    display: &mut DisplaySettings,
+    skins: &Skins,
+    row: SettingsRow,
+    direction: f32,
+) {
+    // DAS and ARR are kept in whole milliseconds despite float steps.
+    let step = |value: f32, delta: f32, (min, max): (f32, f32)| {
+        ((value + direction * delta) * 1000.)
+            .round()
+            .clamp(min * 1000., max * 1000.)
+            / 1000.
+    };
+
+    match row {
+        SettingsRow::Language => {
+            settings.language =
+                locale::cycle_language(&settings.language, direction as isize).to_string();
+        }
+        SettingsRow::MusicVolume => {
+            audio.music_volume = step(audio.music_volume, audio::VOLUME_STEP, (0., 1.));
+        }
+        SettingsRow::EffectsVolume => {
+            audio.effects_volume = step(audio.effects_volume, audio::VOLUME_STEP, (0., 1.));
+        }
+        SettingsRow::Mute => audio.muted = !audio.muted,
+        SettingsRow::Fullscreen => display.fullscreen = !display.fullscreen,
+        SettingsRow::Vsync => display.vsync = !display.vsync,
+        SettingsRow::Resolution => display.cycle_resolution(direction as isize),
+        SettingsRow::Das => {
+            settings.das = step(settings.das, settings::TIMING_STEP, settings::DAS_RANGE);
+        }
+        SettingsRow::Arr => {
+            settings.arr = step(settings.arr, settings::TIMING_STEP, settings::ARR_RANGE);
+        }
+        SettingsRow::GhostPiece => settings.ghost_piece = !settings.ghost_piece,
+        SettingsRow::Analytics => settings.analytics = !settings.analytics,
+        SettingsRow::ScreenShake => {
+            settings.screen_shake = step(
+                settings.screen_shake,
+                settings::SCREEN_SHAKE_STEP,
+                settings::SCREEN_SHAKE_RANGE,
+            );
+        }
+        SettingsRow::Rotation => settings.rotation = settings.rotation.cycle(direction as isize),
+        SettingsRow::Scoring => settings.scoring = settings.scoring.cycle(direction as isize),
+        SettingsRow::Previews => {
+            settings.previews = (settings.previews as i32 + direction as i32)
+                .clamp(0, MAX_PREVIEWS as i32) as usize;
+        }
+        SettingsRow::Skin => settings.skin = skins.cycle(&settings.skin, direction as isize),
+        SettingsRow::Theme => {
+            settings.theme = Theme::named(&settings.theme)
+                .cycle(direction as isize)
+                .name
+                .to_string();
+        }
+        SettingsRow::MarathonLevelCap => {
+            let (min, max) = settings::MARATHON_LEVEL_CAP_RANGE;
+            settings.marathon_level_cap = (settings.marathon_level_cap as i32 + direction as i32)
+                .clamp(min as i32, max as i32) as u32;
+        }
+        SettingsRow::Binding(_) => {}
+    }
+}
+
+fn save_settings(
+    settings: Res<Settings>,
+    audio: Res<AudioSettings>,
+    display: Res<DisplaySettings>,
+    bindings: Res<KeyBindings>,
+) {
+    if let Err(e) = settings.save() {
+        warn!("failed to save settings: {}", e);
+    }
+    if let Err(e) = audio.save() {
+        warn!("failed to save audio settings: {}", e);
+    }
+    if let Err(e) = display.save() {
+        warn!("failed to save display settings: {}", e);
+    }
+    if let Err(e) = bindings.save() {
+        warn!("failed to save key bindings: {}", e);
+    }
+}
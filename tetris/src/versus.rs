@@ -0,0 +1,419 @@
+//! Matches against the player in the room: both games are simulated here
+//! from the inputs of both players with rollback, and the opponent's board
+//! is drawn beside the own one. Not in the web build, which has no network.
+
+use crate::{
+    board::{square_sprite, wall_sprite, BoardLayout, Wall, WallLocation, BOTTOM_WALL, TOP_WALL},
+    controls::Actions,
+    events::GameEvents,
+    game::{match_config, start_game, Countdown, InputBuffer},
+    lobby::Room,
+    locale::{Locale, Translated},
+    network::{Connection, Message, NetEvent},
+    piece::PREVIEW_X,
+    settings::Settings,
+    skins::SkinTexture,
+    themes::Theme,
+    ui::label,
+    GameState,
+};
+use bevy::prelude::*;
+use std::time::Duration;
+use tetris_core::{
+    Board, Buttons, Event as GameEvent, Game, Input as GameInput, Rollback, BOARD_HEIGHT,
+    BOARD_WIDTH, ROLLBACK_FRAME,
+};
+
+/// Plays the matches of a room and shows the opponent's board while in one.
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RemoteBoard {
+            board: Board::new(),
+            status: RemoteStatus::Waiting,
+        })
+        .init_resource::<Versus>()
+        .add_startup_system(spawn_remote_board)
+        .add_system_set(
+            SystemSet::on_enter(GameState::Playing).with_system(send_new_game.after(start_game)),
+        )
+        .add_system(follow_room)
+        .add_system(receive_games)
+        .add_system(update_versus.after(receive_games))
+        .add_system(sync_remote_board.after(update_versus))
+        .add_system(apply_skin);
+    }
+}
+
+/// Opponent's board is drawn at this scale right of the preview panel.
+const REMOTE_SCALE: f32 = 0.5;
+const REMOTE_X: f32 = PREVIEW_X + 130.;
+
+/// Opponent's board in a room, from their game simulated here.
+struct RemoteBoard {
+    board: Board,
+    status: RemoteStatus,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RemoteStatus {
+    Waiting,
+    Connected,
+    Playing,
+    GameOver,
+    Disconnected,
+}
+
+impl RemoteStatus {
+    /// Locale key of the status text.
+    fn key(self) -> &'static str {
+        match self {
+            RemoteStatus::Waiting => "versus.waiting",
+            RemoteStatus::Connected => "versus.connected",
+            RemoteStatus::Playing => "versus.playing",
+            RemoteStatus::GameOver => "versus.game_over",
+            RemoteStatus::Disconnected => "versus.disconnected",
+        }
+    }
+}
+
+/// Networked match, simulating the games of both players from their inputs
+/// with rollback. It starts once both players started a game.
+#[derive(Default)]
+pub struct Versus {
+    session: Option<Rollback>,
+    /// Game started here, waiting for the opponent's.
+    local: Option<Game>,
+    /// Game started by the opponent, waiting for the own.
+    remote: Option<Game>,
+    /// Inputs of the opponent received before the match started.
+    early_inputs: Vec<GameInput>,
+    /// Inputs of the opponent received since their game started.
+    received: u32,
+    /// Time not simulated yet, less than a frame unless waiting for the
+    /// opponent.
+    lag: Duration,
+    game_over_sent: bool,
+}
+
+impl Versus {
+    /// Whether the game is played in fixed frames against the opponent,
+    /// or waits to be.
+    pub fn is_running(&self) -> bool {
+        self.session.is_some() || self.local.is_some()
+    }
+
+    /// Starts the match once both games are there.
+    fn try_start(&mut self) {
+        if let (Some(local), Some(remote)) = (&self.local, &self.remote) {
+            let mut session = Rollback::new(local.clone(), remote.clone());
+            for input in self.early_inputs.drain(..) {
+                session.receive(input);
+            }
+            self.session = Some(session);
+            self.local = None;
+            self.remote = None;
+            self.lag = Duration::ZERO;
+            self.game_over_sent = false;
+        }
+    }
+}
+
+/// Sprite showing a cell of the opponent's board.
+#[derive(Component)]
+struct RemoteCell {
+    x: usize,
+    y: usize,
+}
+
+/// Connection and game state of the opponent, shown under their board.
+#[derive(Component)]
+struct RemoteStatusText;
+
+/// Walls and labels of the opponent's board, shown while in a room.
+#[derive(Component)]
+struct RemoteFrame;
+
+/// World position of a cell of the opponent's board, a standard one as
+/// matches are played on.
+fn remote_cell_to_world(x: i32, y: i32) -> Vec3 {
+    Vec3::new(REMOTE_X, 0., 0.) + BoardLayout::default().cell_to_world(x, y) * REMOTE_SCALE
+}
+
+/// Spawns the opponent's board hidden, it is shown while in a room.
+fn spawn_remote_board(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+) {
+    for location in [
+        WallLocation::Left,
+        WallLocation::Right,
+        WallLocation::Bottom,
+        WallLocation::Top,
+    ] {
+        let mut sprite = wall_sprite(location, &BoardLayout::default(), theme.wall);
+        sprite.transform.translation =
+            Vec3::new(REMOTE_X, 0., 0.) + sprite.transform.translation * REMOTE_SCALE;
+        sprite.transform.scale *= REMOTE_SCALE;
+        sprite.visibility.is_visible = false;
+        commands
+            .spawn_bundle(sprite)
+            .insert(Wall)
+            .insert(RemoteFrame);
+    }
+
+    for y in 0..BOARD_HEIGHT {
+        for x in 0..BOARD_WIDTH {
+            let mut sprite = square_sprite(Color::NONE, remote_cell_to_world(x as i32, y as i32));
+            sprite.transform.scale *= REMOTE_SCALE;
+            sprite.visibility.is_visible = false;
+            commands.spawn_bundle(sprite).insert(RemoteCell { x, y });
+        }
+    }
+
+    let mut title = label(
+        &asset_server,
+        locale.get("versus.opponent"),
+        Vec3::new(REMOTE_X, TOP_WALL * REMOTE_SCALE + 20., 0.),
+    );
+    title.visibility.is_visible = false;
+    commands
+        .spawn_bundle(title)
+        .insert(RemoteFrame)
+        .insert(Translated("versus.opponent"));
+    let mut status = label(
+        &asset_server,
+        locale.get(RemoteStatus::Waiting.key()),
+        Vec3::new(REMOTE_X, BOTTOM_WALL * REMOTE_SCALE - 20., 0.),
+    );
+    status.visibility.is_visible = false;
+    commands
+        .spawn_bundle(status)
+        .insert(RemoteStatusText)
+        .insert(RemoteFrame);
+}
+
+/// Shows the opponent's board on entering a room and hides it on leaving,
+/// along with whatever match was played there.
+fn follow_room(
+    room: Option<Res<Room>>,
+    mut in_room: Local<bool>,
+    mut versus: ResMut<Versus>,
+    mut remote: ResMut<RemoteBoard>,
+    mut frame: Query<&mut Visibility, With<RemoteFrame>>,
+) {
+    if room.is_some() == *in_room {
+        return;
+    }
+
+    *in_room = room.is_some();
+    *versus = Versus::default();
+    *remote = RemoteBoard {
+        board: Board::new(),
+        status: RemoteStatus::Waiting,
+    };
+    for mut visibility in frame.iter_mut() {
+        visibility.is_visible = *in_room;
+    }
+}
+
+/// Tells the opponent about the new game, the match starts once theirs is
+/// known too.
+fn send_new_game(
+    connection: Option<Res<Connection>>,
+    room: Option<Res<Room>>,
+    game: Res<Game>,
+    settings: Res<Settings>,
+    mut versus: ResMut<Versus>,
+) {
+    let connection = match connection {
+        Some(connection) => connection,
+        None => return,
+    };
+
+    connection.send(Message::NewGame {
+        seed: game.seed(),
+        mode: game.mode,
+        config: Box::new(match_config(&settings, room.map(|room| room.handicap))),
+    });
+    versus.session = None;
+    versus.local = Some(game.clone());
+    versus.try_start();
+}
+
+/// Handles the games the opponent starts and their inputs. A match the
+/// opponent left is played on alone.
+fn receive_games(
+    mut events: EventReader<NetEvent>,
+    mut versus: ResMut<Versus>,
+    mut remote: ResMut<RemoteBoard>,
+) {
+    for event in events.iter() {
+        match event {
+            NetEvent::Connected => remote.status = RemoteStatus::Connected,
+            NetEvent::Received(Message::NewGame { seed, mode, config }) => {
+                // A new game of the opponent ends the running match, the
+                // next one starts with a new game here too.
+                versus.session = None;
+                let game = Game::new(*seed, *mode, (**config).clone());
+                remote.board = game.board.clone();
+                remote.status = RemoteStatus::Playing;
+                versus.remote = Some(game);
+                versus.early_inputs.clear();
+                versus.received = 0;
+                versus.try_start();
+            }
+            &NetEvent::Received(Message::Input { frame, input }) => {
+                if frame != versus.received {
+                    warn!(
+                        "ignoring input of frame {}, expected {}",
+                        frame, versus.received
+                    );
+                    continue;
+                }
+                versus.received += 1;
+                match &mut versus.session {
+                    Some(session) => session.receive(input),
+                    None => versus.early_inputs.push(input),
+                }
+            }
+            NetEvent::Disconnected => {
+                remote.status = RemoteStatus::Disconnected;
+                *versus = Versus::default();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Advances the match by the frames that fit in the time passed, sending
+/// the own inputs and showing both games. It holds while the game is paused
+/// or the opponent's inputs lag too far behind, and keeps going after the
+/// own game is over so the opponent can play on.
+#[allow(clippy::too_many_arguments)]
+fn update_versus(
+    time: Res<Time>,
+    actions: Res<Actions>,
+    state: Res<State<GameState>>,
+    connection: Option<Res<Connection>>,
+    mut countdown: ResMut<Countdown>,
+    mut buffer: ResMut<InputBuffer>,
+    mut versus: ResMut<Versus>,
+    mut game: ResMut<Game>,
+    mut remote: ResMut<RemoteBoard>,
+    mut events: GameEvents,
+) {
+    let connection = match connection {
+        Some(connection) if versus.is_running() => connection,
+        _ => return,
+    };
+
+    let playing = *state.current() == GameState::Playing;
+    let input = if playing {
+        actions.input()
+    } else {
+        GameInput::default()
+    };
+    if playing && countdown.is_counting() {
+        buffer.0 = buffer.0.union(input.pressed);
+        countdown.0.tick(time.delta());
+        return;
+    }
+    if playing && !countdown.0.finished() {
+        countdown.0.tick(time.delta());
+    }
+
+    let versus = &mut *versus;
+    let session = match &mut versus.session {
+        Some(session) if playing || session.games[0].over => session,
+        _ => return,
+    };
+    // Presses wait in the buffer for the next frame, those of the countdown
+    // for the first one.
+    buffer.0 = buffer.0.union(input.pressed);
+    versus.lag += time.delta();
+    while versus.lag >= ROLLBACK_FRAME {
+        let input = GameInput {
+            held: input.held,
+            pressed: buffer.0,
+        };
+        let frame = session.frame();
+        let [local_events, _] = match session.advance(input) {
+            Some(events) => events,
+            None => {
+                // Time spent waiting for the opponent isn't caught up on.
+                versus.lag = Duration::ZERO;
+                break;
+            }
+        };
+        buffer.0 = Buttons::default();
+        versus.lag -= ROLLBACK_FRAME;
+        connection.send(Message::Input { frame, input });
+        for event in local_events {
+            // The opponent's late inputs may still save the game.
+            if !matches!(event, GameEvent::GameOver { .. }) {
+                events.send(event);
+            }
+        }
+    }
+
+    if session.is_confirmed() {
+        if let Some(cause) = session.games[0].game_over_cause() {
+            if !versus.game_over_sent {
+                versus.game_over_sent = true;
+                events.send(GameEvent::GameOver { cause });
+            }
+        }
+        if session.games[1].over {
+            remote.status = RemoteStatus::GameOver;
+        }
+    }
+    *game = session.games[0].clone();
+    if remote.board.cells != session.games[1].board.cells {
+        remote.board = session.games[1].board.clone();
+    }
+}
+
+fn sync_remote_board(
+    remote: Res<RemoteBoard>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    mut cells: Query<(&RemoteCell, &mut Sprite, &mut Visibility)>,
+    mut status: Query<&mut Text, With<RemoteStatusText>>,
+) {
+    if !remote.is_changed() && !theme.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    for (cell, mut sprite, mut visibility) in cells.iter_mut() {
+        let block = remote
+            .board
+            .cells
+            .get(cell.y)
+            .and_then(|row| row.get(cell.x));
+        match block.copied().flatten() {
+            Some(block) => {
+                sprite.color = theme.block_color(block);
+                visibility.is_visible = true;
+            }
+            None => visibility.is_visible = false,
+        }
+    }
+    for mut text in status.iter_mut() {
+        text.sections[0].value = locale.get(remote.status.key()).to_string();
+    }
+}
+
+/// Textures the opponent's board with the current skin.
+fn apply_skin(texture: Res<SkinTexture>, mut query: Query<&mut Handle<Image>, With<RemoteCell>>) {
+    if !texture.is_changed() {
+        return;
+    }
+
+    for mut handle in query.iter_mut() {
+        *handle = texture.0.clone();
+    }
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+
+/// Window drawing into the `<canvas id="tetris">` of `index.html`.
+pub fn window_descriptor() -> WindowDescriptor {
+    WindowDescriptor {
+        title: String::from("Tetris"),
+        canvas: Some(String::from("#tetris")),
+        ..default()
+    }
+}
+
+/// Resizes the canvas with the browser window. The camera then fits the
+/// scene into it like for a resized desktop window.
+pub fn fit_canvas(mut windows: ResMut<Windows>) {
+    let browser = match web_sys::window() {
+        Some(browser) => browser,
+        None => return,
+    };
+    let size = (browser.inner_width(), browser.inner_height());
+    let (width, height) = match size {
+        (Ok(width), Ok(height)) => match (width.as_f64(), height.as_f64()) {
+            (Some(width), Some(height)) => (width as f32, height as f32),
+            _ => return,
+        },
+        _ => return,
+    };
+
+    if let Some(window) = windows.get_primary_mut() {
+        if window.width() != width || window.height() != height {
+            window.set_resolution(width, height);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+/// Garbage rows sent to the opponent for each kind of clear, read from a
+/// data file to tune versus balance. Missing entries keep their defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttackTable {
+    /// Rows sent for clearing 1, 2, 3 and 4 lines at once.
+    pub lines: [u32; 4],
+    /// Rows sent for T-spins clearing 1, 2 and 3 lines, instead of `lines`.
+    pub t_spin: [u32; 3],
+    /// Rows added from the second clear in a row on, the first entry for
+    /// the second clear. Longer combos keep the last entry.
+    pub combo: Vec<u32>,
+}
+
+impl Default for AttackTable {
+    fn default() -> AttackTable {
+        AttackTable {
+            lines: [0, 1, 2, 4],
+            t_spin: [0, 1, 2],
+            combo: Vec::new(),
+        }
+    }
+}
+
+impl AttackTable {
+    /// Garbage rows of clearing `lines` rows at once as the `combo`th clear
+    /// in a row.
+    pub fn attack(&self, lines: usize, t_spin: bool, combo: u32) -> u32 {
+        if lines == 0 {
+            return 0;
+        }
+
+        let base = if t_spin {
+            self.t_spin[(lines - 1).min(self.t_spin.len() - 1)]
+        } else {
+            self.lines[(lines - 1).min(self.lines.len() - 1)]
+        };
+        let combo_bonus = match combo.checked_sub(2) {
+            Some(step) => self
+                .combo
+                .get(step as usize)
+                .or_else(|| self.combo.last())
+                .copied()
+                .unwrap_or(0),
+            None => 0,
+        };
+        base + combo_bonus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combos_add_to_the_clear() {
+        let table = AttackTable {
+            combo: vec![1, 2],
+            ..AttackTable::default()
+        };
+
+        assert_eq!(table.attack(4, false, 1), 4);
+        assert_eq!(table.attack(2, true, 1), 1);
+        assert_eq!(table.attack(1, false, 2), 1);
+        assert_eq!(table.attack(2, false, 3), 3);
+        assert_eq!(table.attack(2, false, 9), 3);
+    }
+}
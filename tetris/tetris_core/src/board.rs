@@ -0,0 +1,329 @@
+use crate::{
+    item::Item,
+    piece::{Shape, ShapeTypes},
+};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Columns of the standard board, that of puzzles and the tutorial.
+pub const BOARD_WIDTH: usize = 10;
+/// Rows of the visible playfield of the standard board.
+pub const BOARD_HEIGHT: usize = 20;
+/// Rows above the playfield where pieces spawn, part of the grid so blocks
+/// locked there are kept and block the next spawn.
+pub const SPAWN_ROWS: usize = 2;
+/// Rows of the grid of the standard board.
+pub const GRID_HEIGHT: usize = BOARD_HEIGHT + SPAWN_ROWS;
+
+/// Columns and visible rows of a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for BoardSize {
+    fn default() -> BoardSize {
+        BoardSize {
+            width: BOARD_WIDTH,
+            height: BOARD_HEIGHT,
+        }
+    }
+}
+
+impl BoardSize {
+    /// Widths a board can have, from the length of the I piece up.
+    pub const WIDTHS: RangeInclusive<usize> = 4..=20;
+    /// Visible heights a board can have, enough for pieces to turn in.
+    pub const HEIGHTS: RangeInclusive<usize> = 8..=40;
+
+    /// The size with its width and height brought into their ranges.
+    pub fn clamped(self) -> BoardSize {
+        BoardSize {
+            width: self.width.clamp(*Self::WIDTHS.start(), *Self::WIDTHS.end()),
+            height: self
+                .height
+                .clamp(*Self::HEIGHTS.start(), *Self::HEIGHTS.end()),
+        }
+    }
+}
+
+/// Content of an occupied cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Block {
+    Piece(ShapeTypes),
+    Garbage,
+    /// Square of a piece carrying an item.
+    Item(Item),
+}
+
+/// Rows of cells, row 0 at the bottom.
+pub type Cells = Vec<Vec<Option<Block>>>;
+
+/// Occupancy grid of the playfield and the single source of truth for
+/// collisions. `cells[y][x]` holds the block of a locked square, row 0 is the
+/// bottom of the playfield.
+#[derive(Debug, Clone)]
+pub struct Board {
+    pub cells: Cells,
+}
+
+impl Default for Board {
+    fn default() -> Board {
+        Board::new()
+    }
+}
+
+impl Board {
+    /// Empty standard board.
+    pub fn new() -> Board {
+        Board::with_size(BoardSize::default())
+    }
+
+    /// Empty board of the given size, its spawn rows on top.
+    pub fn with_size(size: BoardSize) -> Board {
+        Board {
+            cells: vec![vec![None; size.width]; size.height + SPAWN_ROWS],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
+    }
+
+    /// Rows of the visible playfield, the spawn rows left out.
+    pub fn height(&self) -> usize {
+        self.cells.len().saturating_sub(SPAWN_ROWS)
+    }
+
+    pub fn size(&self) -> BoardSize {
+        BoardSize {
+            width: self.width(),
+            height: self.height(),
+        }
+    }
+
+    /// Cells above the top of the grid are free so pieces can rotate
+    /// partially outside of it.
+    pub fn is_free(&self, x: i32, y: i32) -> bool {
+        if x < 0 || x >= self.width() as i32 || y < 0 {
+            return false;
+        }
+        if y >= self.cells.len() as i32 {
+            return true;
+        }
+
+        self.cells[y as usize][x as usize].is_none()
+    }
+
+    pub fn fits(&self, shape: &Shape, x: i32, y: i32) -> bool {
+        shape
+            .squares
+            .iter()
+            .all(|square| self.is_free(x + square.x, y + square.y))
+    }
+
+    /// Lowest row the pivot of a piece can be moved down to from `y`.
+    pub fn drop_y(&self, shape: &Shape, x: i32, y: i32) -> i32 {
+        let mut drop_y = y;
+        while self.fits(shape, x, drop_y - 1) {
+            drop_y -= 1;
+        }
+
+        drop_y
+    }
+
+    /// Writes the squares of a piece into the grid. Squares above the grid
+    /// are dropped.
+    pub fn lock(&mut self, shape: &Shape, x: i32, y: i32, block: Block) {
+        self.place(&shape.cells(x, y), block);
+    }
+
+    /// Fills the given `(x, y)` cells, dropping those above the grid.
+    pub fn place(&mut self, cells: &[(i32, i32)], block: Block) {
+        for &(x, y) in cells {
+            if y < self.cells.len() as i32 {
+                self.cells[y as usize][x as usize] = Some(block);
+            }
+        }
+    }
+
+    /// Empties the board, keeping its size.
+    pub fn clear(&mut self) {
+        *self = Board::with_size(self.size());
+    }
+}
+
+/// Indices of the full rows of a grid, bottom first.
+pub fn full_rows(cells: &[Vec<Option<Block>>]) -> Vec<usize> {
+    cells
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| row.iter().all(|cell| cell.is_some()))
+        .map(|(y, _)| y)
+        .collect()
+}
+
+/// Removes the given rows, shifting everything above each of them down and
+/// filling the top with empty rows so the grid keeps its height.
+pub fn collapse_rows(cells: &mut Cells, rows: &[usize]) {
+    let height = cells.len();
+    let width = cells.first().map_or(0, |row| row.len());
+
+    let mut y = 0;
+    cells.retain(|_| {
+        let keep = !rows.contains(&y);
+        y += 1;
+        keep
+    });
+    cells.resize(height, vec![None; width]);
+}
+
+/// Pushes the stack up by `rows` and fills the bottom with garbage rows that
+/// are full except for the `hole` column. Squares pushed past the top of the
+/// grid are lost.
+pub fn insert_garbage(cells: &mut Cells, rows: usize, hole: usize) {
+    let height = cells.len();
+    let width = cells.first().map_or(0, |row| row.len());
+
+    let mut garbage = vec![Some(Block::Garbage); width];
+    garbage[hole] = None;
+    cells.splice(0..0, std::iter::repeat_n(garbage, rows));
+    cells.truncate(height);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Cells {
+        // Rows are written top first to read like the playfield.
+        rows.iter()
+            .rev()
+            .map(|row| {
+                row.chars()
+                    .map(|c| (c == '#').then_some(Block::Garbage))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn full_rows_finds_nothing_on_a_partial_board() {
+        let cells = grid(&["....", "#.##", "###."]);
+        assert!(full_rows(&cells).is_empty());
+    }
+
+    #[test]
+    fn full_rows_returns_bottom_first() {
+        let cells = grid(&["####", "#.##", "####", "####"]);
+        assert_eq!(full_rows(&cells), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn collapse_single_row_shifts_rows_above_down() {
+        let mut cells = grid(&["..#.", "#...", "####"]);
+        collapse_rows(&mut cells, &[0]);
+        assert_eq!(cells, grid(&["....", "..#.", "#..."]));
+    }
+
+    #[test]
+    fn collapse_non_adjacent_rows() {
+        let mut cells = grid(&["#...", "####", ".#..", "####", "..#."]);
+        let rows = full_rows(&cells);
+        collapse_rows(&mut cells, &rows);
+        assert_eq!(cells, grid(&["....", "....", "#...", ".#..", "..#."]));
+    }
+
+    #[test]
+    fn collapse_four_rows_keeps_height() {
+        let mut cells = grid(&["#..#", "####", "####", "####", "####"]);
+        let rows = full_rows(&cells);
+        assert_eq!(rows.len(), 4);
+
+        collapse_rows(&mut cells, &rows);
+        assert_eq!(cells, grid(&["....", "....", "....", "....", "#..#"]));
+    }
+
+    #[test]
+    fn collapse_top_row() {
+        let mut cells = grid(&["####", "#...", ".#.."]);
+        collapse_rows(&mut cells, &[2]);
+        assert_eq!(cells, grid(&["....", "#...", ".#.."]));
+    }
+
+    #[test]
+    fn lock_and_clear_on_the_board() {
+        let mut board = Board::new();
+        for x in 0..BOARD_WIDTH - 4 {
+            board.cells[0][x] = Some(Block::Garbage);
+            board.cells[1][x] = Some(Block::Garbage);
+        }
+
+        // Vertical lines fill the gap of the two bottom rows.
+        let shape = ShapeTypes::Line.build().rotated();
+        let block = Block::Piece(ShapeTypes::Line);
+        for x in BOARD_WIDTH as i32 - 4..BOARD_WIDTH as i32 {
+            let y = board.drop_y(&shape, x, BOARD_HEIGHT as i32);
+            board.lock(&shape, x, y, block);
+        }
+
+        let rows = full_rows(&board.cells);
+        assert_eq!(rows, vec![0, 1]);
+
+        collapse_rows(&mut board.cells, &rows);
+        assert_eq!(board.cells.len(), GRID_HEIGHT);
+        assert!(board.cells[0][..BOARD_WIDTH - 4]
+            .iter()
+            .all(Option::is_none));
+        assert!(board.cells[0][BOARD_WIDTH - 4..]
+            .iter()
+            .all(Option::is_some));
+        assert!(board.cells[1][BOARD_WIDTH - 4..]
+            .iter()
+            .all(Option::is_some));
+        assert!(board.cells[2].iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn garbage_pushes_the_stack_up() {
+        let mut cells = grid(&["....", "..#.", "#..."]);
+        insert_garbage(&mut cells, 1, 2);
+        assert_eq!(cells, grid(&["..#.", "#...", "##.#"]));
+    }
+
+    #[test]
+    fn garbage_drops_squares_pushed_past_the_top() {
+        let mut cells = grid(&["#...", "....", "....", ".#.."]);
+        insert_garbage(&mut cells, 2, 0);
+        assert_eq!(cells, grid(&["....", ".#..", ".###", ".###"]));
+    }
+
+    #[test]
+    fn boards_have_the_size_they_are_made_with() {
+        let size = BoardSize {
+            width: 6,
+            height: 12,
+        };
+        let board = Board::with_size(size);
+        assert_eq!(board.size(), size);
+        assert_eq!(board.cells.len(), 12 + SPAWN_ROWS);
+        assert!(board.is_free(5, 0));
+        assert!(!board.is_free(6, 0));
+    }
+
+    #[test]
+    fn sizes_are_clamped_into_their_ranges() {
+        let size = BoardSize {
+            width: 2,
+            height: 100,
+        };
+        assert_eq!(
+            size.clamped(),
+            BoardSize {
+                width: 4,
+                height: 40
+            }
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use crate::{
+    board::{collapse_rows, full_rows, Block, Board},
+    game::{Button, Config, Event, Game, Input, Mode},
+    piece::{ActivePiece, Shape, ShapeTypes},
+    rollback::exchange_attacks,
+    rotation::Rotation,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::time::Duration;
+
+/// Presses a driver makes for a piece beyond a move across the whole board
+/// before it gives up on the placement and drops the piece where it is: a
+/// hold and two turns, with some margin.
+const EXTRA_PRESSES: u32 = 4;
+
+/// Where a bot puts the active piece: held first when `hold` is set, then
+/// turned to the `rotation` state, moved until its pivot is in column `x`
+/// and hard dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub hold: bool,
+    pub rotation: u8,
+    pub x: i32,
+}
+
+/// A player deciding where pieces go, for autoplay and bot matches.
+pub trait TetrisBot: Send + Sync {
+    /// Placement of the `active` piece on `board`, knowing the pieces shown
+    /// in the preview, the next one first, and the held piece. Holding
+    /// swaps in the held piece, or the next one when nothing is held.
+    fn choose_move(
+        &mut self,
+        board: &Board,
+        active: ShapeTypes,
+        queue: &[ShapeTypes],
+        hold: Option<ShapeTypes>,
+    ) -> Placement;
+}
+
+/// Every spot a newly spawned piece can be turned and shifted above and
+/// dropped to, as the piece resting there.
+pub fn landing_positions(
+    board: &Board,
+    shape_type: ShapeTypes,
+    rotation: Rotation,
+) -> Vec<ActivePiece> {
+    let spawn = ActivePiece::spawn(shape_type, rotation, board);
+    let states = if shape_type == ShapeTypes::Square {
+        0..1
+    } else {
+        0..4
+    };
+
+    let mut positions = Vec::new();
+    for state in states {
+        let shape = Shape {
+            squares: rotation.system().squares(shape_type, state),
+        };
+        for x in -2..board.width() as i32 + 2 {
+            if !board.fits(&shape, x, spawn.y) {
+                continue;
+            }
+            positions.push(ActivePiece {
+                shape_type,
+                y: board.drop_y(&shape, x, spawn.y),
+                shape: shape.clone(),
+                x,
+                rotation: state,
+                item: None,
+            });
+        }
+    }
+    positions
+}
+
+/// Plays a game with a bot: asks it where each piece goes when the piece
+/// enters and presses one button a tick to put it there.
+pub struct BotDriver {
+    bot: Box<dyn TetrisBot>,
+    /// Placement chosen for the active piece, and the number of pieces
+    /// locked when it was chosen.
+    plan: Option<(Placement, u32)>,
+    presses: u32,
+}
+
+impl BotDriver {
+    pub fn new(bot: impl TetrisBot + 'static) -> BotDriver {
+        BotDriver {
+            bot: Box::new(bot),
+            plan: None,
+            presses: 0,
+        }
+    }
+
+    /// Input of the next tick of `game`. Nothing is pressed while rows are
+    /// being cleared, so no press carries over to the next piece.
+    pub fn input(&mut self, game: &Game) -> Input {
+        let mut input = Input::default();
+        if game.over || !game.clearing_rows().is_empty() {
+            return input;
+        }
+
+        let pieces = game.stats.pieces;
+        if !matches!(self.plan, Some((_, chosen)) if chosen == pieces) {
+            let queue: Vec<_> = game.queue.previews().collect();
+            let placement =
+                self.bot
+                    .choose_move(&game.board, game.piece.shape_type, &queue, game.hold);
+            self.plan = Some((placement, pieces));
+            self.presses = 0;
+        }
+        let (placement, _) = self.plan.as_mut().unwrap();
+        self.presses += 1;
+
+        let piece = &game.piece;
+        let turns = (placement.rotation + 4 - piece.rotation) % 4;
+        let button = if placement.hold {
+            placement.hold = false;
+            Button::Hold
+        } else if self.presses > game.board.width() as u32 + EXTRA_PRESSES {
+            Button::HardDrop
+        } else if piece.shape_type != ShapeTypes::Square && turns != 0 {
+            match turns {
+                1 => Button::RotateClockwise,
+                2 => Button::Rotate180,
+                _ => Button::RotateCounterClockwise,
+            }
+        } else if piece.x > placement.x {
+            Button::MoveLeft
+        } else if piece.x < placement.x {
+            Button::MoveRight
+        } else {
+            Button::HardDrop
+        };
+        input.pressed.insert(button);
+        input
+    }
+}
+
+/// Bot dropping every piece on a random spot it can reach, never holding.
+pub struct RandomBot {
+    rotation: Rotation,
+    rng: StdRng,
+}
+
+impl RandomBot {
+    /// Bot for games with the given rotation system, choosing the same spots
+    /// for the same seed.
+    pub fn new(seed: u64, rotation: Rotation) -> RandomBot {
+        RandomBot {
+            rotation,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl TetrisBot for RandomBot {
+    fn choose_move(
+        &mut self,
+        board: &Board,
+        active: ShapeTypes,
+        _queue: &[ShapeTypes],
+        _hold: Option<ShapeTypes>,
+    ) -> Placement {
+        let piece = landing_positions(board, active, self.rotation)
+            .choose(&mut self.rng)
+            .cloned()
+            .unwrap_or_else(|| ActivePiece::spawn(active, self.rotation, board));
+        Placement {
+            hold: false,
+            rotation: piece.rotation,
+            x: piece.x,
+        }
+    }
+}
+
+/// Bot weighing the spots of the active and the hold piece by the lines they
+/// clear and the height, holes and bumpiness of the stack they leave.
+pub struct HeuristicBot {
+    rotation: Rotation,
+}
+
+impl HeuristicBot {
+    /// Bot for games with the given rotation system.
+    pub fn new(rotation: Rotation) -> HeuristicBot {
+        HeuristicBot { rotation }
+    }
+}
+
+impl TetrisBot for HeuristicBot {
+    fn choose_move(
+        &mut self,
+        board: &Board,
+        active: ShapeTypes,
+        queue: &[ShapeTypes],
+        hold: Option<ShapeTypes>,
+    ) -> Placement {
+        let mut candidates = vec![(false, active)];
+        if let Some(swapped) = hold.or_else(|| queue.first().copied()) {
+            candidates.push((true, swapped));
+        }
+
+        let mut best = None;
+        for (hold, shape_type) in candidates {
+            for piece in landing_positions(board, shape_type, self.rotation) {
+                let value = evaluate(board, &piece);
+                if !matches!(best, Some((best_value, _)) if best_value >= value) {
+                    let placement = Placement {
+                        hold,
+                        rotation: piece.rotation,
+                        x: piece.x,
+                    };
+                    best = Some((value, placement));
+                }
+            }
+        }
+        best.map_or(
+            Placement {
+                hold: false,
+                rotation: 0,
+                x: ActivePiece::spawn(active, self.rotation, board).x,
+            },
+            |(_, placement)| placement,
+        )
+    }
+}
+
+/// How good the board is after locking `piece` into it, higher is better.
+fn evaluate(board: &Board, piece: &ActivePiece) -> f32 {
+    let mut board = board.clone();
+    board.place(&piece.cells(), Block::Piece(piece.shape_type));
+    let lines = full_rows(&board.cells);
+    collapse_rows(&mut board.cells, &lines);
+
+    let heights: Vec<_> = (0..board.width())
+        .map(|x| {
+            board
+                .cells
+                .iter()
+                .rposition(|row| row[x].is_some())
+                .map_or(0, |y| y + 1)
+        })
+        .collect();
+    let holes = (0..board.width())
+        .map(|x| {
+            board.cells[..heights[x]]
+                .iter()
+                .filter(|row| row[x].is_none())
+                .count()
+        })
+        .sum::<usize>();
+    let bumpiness = heights
+        .windows(2)
+        .map(|pair| pair[0].abs_diff(pair[1]))
+        .sum::<usize>();
+    let height = heights.iter().sum::<usize>();
+
+    0.76 * lines.len() as f32 - 0.51 * height as f32 - 0.36 * holes as f32 - 0.18 * bumpiness as f32
+}
+
+/// Two bots playing versus on the same piece sequence, each sending its
+/// garbage to the other.
+pub struct BotMatch {
+    pub games: [Game; 2],
+    drivers: [BotDriver; 2],
+}
+
+impl BotMatch {
+    pub fn new(seed: u64, config: Config, bots: [BotDriver; 2]) -> BotMatch {
+        BotMatch {
+            games: [
+                Game::new(seed, Mode::Endless, config.clone()),
+                Game::new(seed, Mode::Endless, config),
+            ],
+            drivers: bots,
+        }
+    }
+
+    /// Advances both games by `delta` and returns what happened in each.
+    pub fn tick(&mut self, delta: Duration) -> [Vec<Event>; 2] {
+        let mut events = [Vec::new(), Vec::new()];
+        for ((game, driver), events) in self
+            .games
+            .iter_mut()
+            .zip(&mut self.drivers)
+            .zip(&mut events)
+        {
+            *events = game.tick(driver.input(game), delta);
+        }
+        exchange_attacks(&mut self.games, &events);
+        events
+    }
+
+    /// Index of the player left standing once the other topped out.
+    pub fn winner(&self) -> Option<usize> {
+        match (self.games[0].over, self.games[1].over) {
+            (false, true) => Some(0),
+            (true, false) => Some(1),
+            _ => None,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.games.iter().any(|game| game.over)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_driver_puts_pieces_where_the_bot_chose() {
+        struct Flip;
+        impl TetrisBot for Flip {
+            fn choose_move(
+                &mut self,
+                _: &Board,
+                _: ShapeTypes,
+                _: &[ShapeTypes],
+                _: Option<ShapeTypes>,
+            ) -> Placement {
+                Placement {
+                    hold: false,
+                    rotation: 2,
+                    x: 2,
+                }
+            }
+        }
+
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        let mut driver = BotDriver::new(Flip);
+        let shape_type = game.piece.shape_type;
+        let mut locked = None;
+        for _ in 0..20 {
+            let events = game.tick(driver.input(&game), Duration::from_millis(16));
+            locked = locked.or_else(|| {
+                events.into_iter().find_map(|event| match event {
+                    Event::PieceLocked { cells, .. } => Some(cells),
+                    _ => None,
+                })
+            });
+        }
+
+        let state = if shape_type == ShapeTypes::Square {
+            0
+        } else {
+            2
+        };
+        let expected = landing_positions(&Board::new(), shape_type, Rotation::Srs)
+            .into_iter()
+            .find(|piece| piece.rotation == state && piece.x == 2)
+            .unwrap();
+        assert_eq!(locked, Some(expected.cells()));
+    }
+
+    #[test]
+    fn heuristic_bots_keep_playing() {
+        let bot = || BotDriver::new(HeuristicBot::new(Rotation::Srs));
+        let mut bot_match = BotMatch::new(11, Config::default(), [bot(), bot()]);
+        for _ in 0..3000 {
+            bot_match.tick(Duration::from_millis(16));
+        }
+
+        assert!(!bot_match.is_over());
+        assert!(bot_match.games[0].level.lines >= 20);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{
+    board::{Board, BoardSize},
+    piece::{ActivePiece, ShapeTypes},
+    rotation::{KickTable, Rotation},
+};
+use std::collections::{HashSet, VecDeque};
+
+/// Columns a piece covers and how its squares stack in them, which is what
+/// a placement is judged by: the cells with the lowest one moved to row 0.
+fn footprint(cells: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    let bottom = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    let mut footprint: Vec<_> = cells.iter().map(|&(x, y)| (x, y - bottom)).collect();
+    footprint.sort_unstable();
+    footprint
+}
+
+/// Fewest presses of the move and rotate buttons, 180° turns included,
+/// taking a newly spawned piece above the `cells` it was placed at, on an
+/// empty board of `size`. Holding a direction until the piece reaches the
+/// wall counts as one press. `None` when no moves from above lead there.
+pub fn minimal_inputs(
+    size: BoardSize,
+    shape_type: ShapeTypes,
+    rotation: Rotation,
+    kicks: Option<&KickTable>,
+    cells: &[(i32, i32)],
+) -> Option<u32> {
+    let board = Board::with_size(size);
+    let target = footprint(cells);
+    let spawn = ActivePiece::spawn(shape_type, rotation, &board);
+
+    let mut seen = HashSet::from([(spawn.x, spawn.y, spawn.rotation)]);
+    let mut queue = VecDeque::from([(spawn, 0)]);
+    while let Some((piece, inputs)) = queue.pop_front() {
+        if footprint(&piece.cells()) == target {
+            return Some(inputs);
+        }
+
+        let shifted = |direction: i32, to_wall: bool| {
+            let mut moved = piece.clone();
+            while board.fits(&moved.shape, moved.x + direction, moved.y) {
+                moved.x += direction;
+                if !to_wall {
+                    break;
+                }
+            }
+            moved
+        };
+        let mut next = vec![
+            shifted(-1, false),
+            shifted(1, false),
+            shifted(-1, true),
+            shifted(1, true),
+        ];
+        if shape_type != ShapeTypes::Square {
+            next.extend(rotation.turn(&board, &piece, 1, kicks));
+            next.extend(rotation.turn(&board, &piece, 2, kicks));
+            next.extend(rotation.turn(&board, &piece, 3, kicks));
+        }
+        for moved in next {
+            if seen.insert((moved.x, moved.y, moved.rotation)) {
+                queue.push_back((moved, inputs + 1));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(shape_type: ShapeTypes, turns: u8, x: i32) -> Vec<(i32, i32)> {
+        let mut piece = ActivePiece::spawn(shape_type, Rotation::Srs, &Board::new());
+        for _ in 0..turns {
+            piece = Rotation::Srs.turn(&Board::new(), &piece, 1, None).unwrap();
+        }
+        piece.x = x;
+        piece.cells()
+    }
+
+    #[test]
+    fn counts_the_fewest_presses() {
+        let minimal = |shape_type, turns, x| {
+            minimal_inputs(
+                BoardSize::default(),
+                shape_type,
+                Rotation::Srs,
+                None,
+                &placement(shape_type, turns, x),
+            )
+        };
+        let spawn_x = ActivePiece::spawn(ShapeTypes::SquareTop, Rotation::Srs, &Board::new()).x;
+
+        assert_eq!(minimal(ShapeTypes::SquareTop, 0, spawn_x), Some(0));
+        assert_eq!(minimal(ShapeTypes::SquareTop, 0, spawn_x - 1), Some(1));
+        // Shifting to the wall is a single press.
+        assert_eq!(minimal(ShapeTypes::SquareTop, 0, 1), Some(1));
+        // Flipping is a single 180° turn.
+        assert_eq!(minimal(ShapeTypes::SquareTop, 2, spawn_x), Some(1));
+        // One tap away from the wall.
+        assert_eq!(minimal(ShapeTypes::Square, 0, 1), Some(2));
+    }
+}
@@ -0,0 +1,1811 @@
+use crate::{
+    attack::AttackTable,
+    board::{
+        collapse_rows, full_rows, insert_garbage, Block, Board, BoardSize, BOARD_WIDTH, GRID_HEIGHT,
+    },
+    finesse::minimal_inputs,
+    item::{Item, ITEM_CHANCE, SLOW_GRAVITY_FACTOR, SLOW_GRAVITY_TIME},
+    motion::{rotate, step, StepResult, Turn},
+    piece::{ActivePiece, PieceQueue, ShapeTypes, MAX_PREVIEWS, NEXT_PIECES},
+    puzzle::Puzzle,
+    rotation::{KickTable, Rotation},
+    scoring::{Clear, Scoring},
+    speed::SpeedCurves,
+    stats::Stats,
+    tutorial::TutorialStep,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const LINES_PER_LEVEL: u32 = 10;
+
+/// Lines to clear in a sprint.
+pub const SPRINT_LINES: u32 = 40;
+/// Length of an ultra game.
+pub const ULTRA_TIME: Duration = Duration::from_secs(120);
+
+/// Goal of a game, besides not topping out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    /// Play on until topping out.
+    #[default]
+    Endless,
+    /// Clear `SPRINT_LINES` lines as fast as possible.
+    Sprint,
+    /// Score as much as possible in `ULTRA_TIME`.
+    Ultra,
+    /// Classic game from a chosen level with guideline gravity, won by
+    /// clearing `level_cap`.
+    Marathon { start_level: u32, level_cap: u32 },
+    /// Clear the preset board of a `Puzzle` with its pieces.
+    Puzzle,
+    /// Play on like endless while every placement is checked for finesse,
+    /// whether it took the fewest key presses possible.
+    Finesse,
+    /// Play on like endless with pieces sometimes carrying an `Item`. In
+    /// `versus` items can also hinder the opponent.
+    Items { versus: bool },
+    /// Play on like endless with the locked blocks hidden by the frontend,
+    /// the stack has to be remembered.
+    Invisible,
+    /// Relaxed play without levels or game over: topping out empties the
+    /// board.
+    Zen,
+    /// Play on like endless on the piece sequence of the day, the frontend
+    /// derives the seed from the date.
+    Daily,
+    /// Lessons on the controls played like zen, each waiting for the player
+    /// to do what it asks. Passing the last one finishes the game.
+    Tutorial,
+    /// Sandbox played like zen without gravity: pieces stay put until they
+    /// are dropped, the next piece can be chosen with `Game::choose_next`
+    /// and the board emptied with `Game::reset_board`.
+    Practice,
+}
+
+/// Buttons of the game itself. Pausing and menus are up to the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Rotate180,
+    Hold,
+}
+
+/// Set of buttons.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Buttons(u8);
+
+impl Buttons {
+    pub fn insert(&mut self, button: Button) {
+        self.0 |= 1 << button as u8;
+    }
+
+    pub fn contains(self, button: Button) -> bool {
+        self.0 & (1 << button as u8) != 0
+    }
+
+    /// Buttons in either set.
+    pub fn union(self, other: Buttons) -> Buttons {
+        Buttons(self.0 | other.0)
+    }
+}
+
+/// Input of one tick: the buttons held down and those pressed since the
+/// previous tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Input {
+    pub held: Buttons,
+    pub pressed: Buttons,
+}
+
+/// What happened during a tick, for frontends to react to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A new active piece entered the board, from the queue or the hold.
+    PieceSpawned { shape_type: ShapeTypes },
+    /// The active piece was written into the board.
+    PieceLocked {
+        cells: Vec<(i32, i32)>,
+        block: Block,
+    },
+    /// The locked piece completed rows, they are removed after the line
+    /// clear delay.
+    ClearStarted { rows: Vec<usize> },
+    /// Full rows were removed from the board, bottom first. `combo` and
+    /// `back_to_back` are the streaks this clear is part of.
+    LinesCleared {
+        rows: Vec<usize>,
+        t_spin: bool,
+        combo: u32,
+        back_to_back: u32,
+    },
+    /// The stack topped out in zen or the tutorial and the board was
+    /// emptied.
+    BoardCleared,
+    /// A line clear attacks the opponent with garbage rows.
+    GarbageSent { rows: u32 },
+    /// Received garbage was pushed into the bottom of the board.
+    GarbageInserted { rows: usize, hole: usize },
+    /// Cleared lines raised the level.
+    LevelUp { level: u32 },
+    /// In finesse mode, a piece was placed with `inputs` presses of the move
+    /// and rotate buttons where `minimal` would have done. Pieces that were
+    /// soft dropped aren't judged.
+    FinesseChecked { inputs: u32, minimal: u32 },
+    /// A cleared row held an item and its effect was applied. Effects on
+    /// the opponent are up to the frontend.
+    ItemTriggered { item: Item },
+    /// The player did what a tutorial lesson asked.
+    TutorialStepDone { step: TutorialStep },
+    /// The game was lost, for the given reason.
+    GameOver { cause: GameOverCause },
+    /// The goal of the mode was reached, the game is over.
+    Finished,
+}
+
+/// Why a game was lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOverCause {
+    /// A piece locked entirely above the visible playfield.
+    LockOut,
+    /// A new piece spawned overlapping the stack.
+    BlockOut,
+    /// A puzzle's pieces ran out before its board was cleared.
+    OutOfPieces,
+}
+
+impl GameOverCause {
+    /// Locale key of the text explaining the cause.
+    pub fn key(self) -> &'static str {
+        match self {
+            GameOverCause::LockOut => "game_over.lock_out",
+            GameOverCause::BlockOut => "game_over.block_out",
+            GameOverCause::OutOfPieces => "game_over.out_of_pieces",
+        }
+    }
+}
+
+/// Handling settings, timings in seconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Delayed Auto Shift: how long a horizontal move has to be held before
+    /// the piece starts moving on its own.
+    pub das: f32,
+    /// Auto Repeat Rate: time between moves once DAS has charged.
+    pub arr: f32,
+    /// Time between soft drop steps while soft drop is held.
+    pub soft_drop_interval: f32,
+    /// Time completed rows stay on the board before they are removed, during
+    /// which the game waits for the next piece.
+    pub line_clear_delay: f32,
+    /// Time received garbage waits before it can enter the board.
+    pub garbage_delay: f32,
+    /// How pieces turn and kick off walls and the stack.
+    pub rotation: Rotation,
+    /// Kicks replacing those of the rotation system.
+    pub kicks: Option<KickTable>,
+    /// Number of upcoming pieces shown, up to `MAX_PREVIEWS`.
+    pub previews: usize,
+    /// How locks and drops score.
+    pub scoring: Scoring,
+    /// Garbage rows sent to the opponent for each kind of clear.
+    pub attack: AttackTable,
+    /// Gravity and lock delay by level of each mode.
+    pub speed: SpeedCurves,
+    /// Garbage rows on the board at the start, each with its own hole.
+    #[serde(default)]
+    pub starting_garbage: usize,
+    /// Size of the board, brought into `BoardSize::WIDTHS` and `HEIGHTS`.
+    /// Puzzles, scripted games and the tutorial are played on the standard
+    /// board.
+    #[serde(default)]
+    pub board_size: BoardSize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            das: 0.17,
+            arr: 0.05,
+            soft_drop_interval: 0.05,
+            line_clear_delay: 0.,
+            garbage_delay: 0.,
+            rotation: Rotation::Srs,
+            kicks: None,
+            previews: NEXT_PIECES,
+            scoring: Scoring::Guideline,
+            attack: AttackTable::default(),
+            speed: SpeedCurves::default(),
+            starting_garbage: 0,
+            board_size: BoardSize::default(),
+        }
+    }
+}
+
+impl Config {
+    /// The config made harder by `handicap`.
+    pub fn with_handicap(mut self, handicap: Handicap) -> Config {
+        self.starting_garbage += handicap.garbage_rows;
+        self.previews = self.previews.min(handicap.previews);
+        self.speed = self.speed.with_gravity_multiplier(handicap.gravity.max(1.));
+        self
+    }
+}
+
+/// What a stronger player takes on in a versus match to even it out.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Handicap {
+    /// Garbage rows on the board at the start.
+    pub garbage_rows: usize,
+    /// Factor gravity is sped up by, at least 1.
+    pub gravity: f64,
+    /// Upcoming pieces shown at most.
+    pub previews: usize,
+}
+
+impl Default for Handicap {
+    fn default() -> Handicap {
+        Handicap {
+            garbage_rows: 0,
+            gravity: 1.,
+            previews: MAX_PREVIEWS,
+        }
+    }
+}
+
+/// Garbage rows of one attack received from the opponent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncomingGarbage {
+    pub rows: u32,
+    /// Time left before the rows can enter the board.
+    pub delay: Duration,
+}
+
+/// Duration of `seconds` from a config, zero when they are negative or not
+/// a number instead of panicking.
+fn config_duration(seconds: f32) -> Duration {
+    Duration::try_from_secs_f32(seconds).unwrap_or(Duration::ZERO)
+}
+
+/// Repeating timer counting how many intervals passed in a tick.
+#[derive(Debug, Clone)]
+struct Repeat {
+    interval: Duration,
+    elapsed: Duration,
+}
+
+impl Repeat {
+    fn new(seconds: f32) -> Repeat {
+        Repeat {
+            interval: config_duration(seconds),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    fn set_interval(&mut self, seconds: f32) {
+        self.interval = config_duration(seconds);
+    }
+
+    fn set_duration(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+
+    fn remaining(&self) -> Duration {
+        self.interval.saturating_sub(self.elapsed)
+    }
+
+    fn tick(&mut self, delta: Duration) -> u32 {
+        if self.interval.is_zero() {
+            return 0;
+        }
+
+        self.elapsed += delta;
+        let times = (self.elapsed.as_nanos() / self.interval.as_nanos()) as u32;
+        self.elapsed -= self.interval * times;
+        times
+    }
+}
+
+/// Horizontal auto shift state of the held direction (-1 left, 1 right, 0
+/// none).
+#[derive(Debug, Clone)]
+struct AutoShift {
+    direction: i32,
+    delay: Duration,
+    charged: Duration,
+    repeat: Repeat,
+}
+
+impl AutoShift {
+    fn new(delay: f32, repeat: f32) -> AutoShift {
+        AutoShift {
+            direction: 0,
+            delay: config_duration(delay),
+            charged: Duration::ZERO,
+            repeat: Repeat::new(repeat),
+        }
+    }
+
+    fn set_timings(&mut self, delay: f32, repeat: f32) {
+        self.delay = config_duration(delay);
+        self.repeat.set_interval(repeat);
+    }
+
+    fn start(&mut self, direction: i32) {
+        self.direction = direction;
+        self.charged = Duration::ZERO;
+        self.repeat.reset();
+    }
+
+    /// Number of cells to move for the elapsed time: one when the delay
+    /// runs out and one per repeat interval afterwards.
+    fn tick(&mut self, delta: Duration) -> u32 {
+        if self.charged < self.delay {
+            self.charged = (self.charged + delta).min(self.delay);
+            return (self.charged == self.delay) as u32;
+        }
+
+        self.repeat.tick(delta)
+    }
+}
+
+/// Current level, raised every `LINES_PER_LEVEL` cleared lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    pub level: u32,
+    pub lines: u32,
+    pub start: u32,
+}
+
+impl Default for Level {
+    fn default() -> Level {
+        Level::new()
+    }
+}
+
+impl Level {
+    pub fn new() -> Level {
+        Level::starting_at(1)
+    }
+
+    pub fn starting_at(start: u32) -> Level {
+        Level {
+            level: start,
+            lines: 0,
+            start,
+        }
+    }
+
+    pub fn add_lines(&mut self, lines: u32) {
+        self.lines += lines;
+        self.level = self.start + self.lines / LINES_PER_LEVEL;
+    }
+}
+
+/// Rows waiting for the line clear delay to run out.
+#[derive(Debug, Clone)]
+struct Clearing {
+    rows: Vec<usize>,
+    t_spin: bool,
+    remaining: Duration,
+}
+
+/// A single player game. Everything random is derived from the seed, so the
+/// same seed and inputs replay the same game.
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub mode: Mode,
+    pub board: Board,
+    pub piece: ActivePiece,
+    pub queue: PieceQueue,
+    /// Piece put aside with the hold button.
+    pub hold: Option<ShapeTypes>,
+    pub score: u32,
+    pub level: Level,
+    /// Attacks received from the opponent and not inserted yet, oldest
+    /// first. Once their delay ran out they enter the board when a piece
+    /// locks without clearing lines, line clears cancel them first.
+    pub incoming_garbage: Vec<IncomingGarbage>,
+    /// Consecutive locks that cleared lines.
+    pub combo: u32,
+    /// Consecutive difficult clears, tetrises and T-spins, not broken by
+    /// locks without clears. From the second on they score half more.
+    pub back_to_back: u32,
+    pub over: bool,
+    game_over_cause: Option<GameOverCause>,
+    /// Time played, not counting pauses.
+    pub elapsed: Duration,
+    pub stats: Stats,
+    /// Lesson of the tutorial mode the player is on, `None` in other modes.
+    pub tutorial: Option<TutorialStep>,
+    /// Whether the last move of the active piece was a rotation, which
+    /// makes a T piece wedged in place a T-spin.
+    rotated_last: bool,
+    seed: u64,
+    piece_rng: StdRng,
+    // Garbage holes use their own generator to keep the piece sequence
+    // identical for both players of a versus match.
+    garbage_rng: StdRng,
+    item_rng: StdRng,
+    gravity: Repeat,
+    auto_shift: AutoShift,
+    soft_drop: Repeat,
+    line_clear_delay: Duration,
+    garbage_delay: Duration,
+    rotation: Rotation,
+    kicks: Option<KickTable>,
+    scoring: Scoring,
+    attack: AttackTable,
+    speed: SpeedCurves,
+    /// Time the active piece has been resting on the stack.
+    lock_timer: Duration,
+    clearing: Option<Clearing>,
+    /// Buttons pressed during the line clear delay, applied to the next
+    /// piece when it spawns.
+    buffered: Buttons,
+    /// Move and rotate presses for the active piece, judged for finesse
+    /// when it locks.
+    piece_inputs: u32,
+    soft_dropped: bool,
+    /// Whether the hold button still works for the active piece, once per
+    /// piece taken from the queue.
+    can_hold: bool,
+    /// Time left of the slowed down gravity of `Item::SlowGravity`.
+    slow_gravity: Duration,
+}
+
+impl Game {
+    pub fn new(seed: u64, mode: Mode, config: Config) -> Game {
+        let mut piece_rng = StdRng::seed_from_u64(seed);
+        let mut queue = PieceQueue::new(&mut piece_rng, config.previews.min(MAX_PREVIEWS));
+        let first = queue.pop(&mut piece_rng).unwrap();
+        let level = match mode {
+            Mode::Marathon { start_level, .. } => Level::starting_at(start_level),
+            _ => Level::new(),
+        };
+
+        let gravity = config.speed.for_mode(mode).gravity_interval(level.level);
+        let starting_garbage = config.starting_garbage;
+        // The lessons are laid out for the standard board.
+        let board = if mode == Mode::Tutorial {
+            Board::new()
+        } else {
+            Board::with_size(config.board_size.clamped())
+        };
+        let mut game = Game {
+            mode,
+            piece: ActivePiece::spawn(first, config.rotation, &board),
+            board,
+            queue,
+            hold: None,
+            score: 0,
+            level,
+            incoming_garbage: Vec::new(),
+            combo: 0,
+            back_to_back: 0,
+            over: false,
+            game_over_cause: None,
+            elapsed: Duration::ZERO,
+            stats: Stats::default(),
+            tutorial: (mode == Mode::Tutorial).then_some(TutorialStep::Move),
+            rotated_last: false,
+            seed,
+            piece_rng,
+            garbage_rng: StdRng::seed_from_u64(seed.wrapping_add(1)),
+            item_rng: StdRng::seed_from_u64(seed.wrapping_add(2)),
+            gravity: Repeat::new(gravity.as_secs_f32()),
+            auto_shift: AutoShift::new(config.das, config.arr),
+            soft_drop: Repeat::new(config.soft_drop_interval),
+            line_clear_delay: config_duration(config.line_clear_delay),
+            garbage_delay: config_duration(config.garbage_delay),
+            rotation: config.rotation,
+            kicks: config.kicks,
+            scoring: config.scoring,
+            attack: config.attack,
+            speed: config.speed,
+            lock_timer: Duration::ZERO,
+            clearing: None,
+            buffered: Buttons::default(),
+            piece_inputs: 0,
+            soft_dropped: false,
+            can_hold: true,
+            slow_gravity: Duration::ZERO,
+        };
+        for _ in 0..starting_garbage {
+            let hole = game.garbage_rng.gen_range(0..game.board.width());
+            insert_garbage(&mut game.board.cells, 1, hole);
+        }
+        game.enter(first);
+        game
+    }
+
+    /// Game on the board of a puzzle, dealing its pieces.
+    pub fn puzzle(seed: u64, puzzle: &Puzzle, config: Config) -> Game {
+        let previews = config.previews.min(MAX_PREVIEWS);
+        let mut game = Game::new(seed, Mode::Puzzle, config);
+        game.board.cells = puzzle.cells().clone();
+        game.board
+            .cells
+            .resize(GRID_HEIGHT, vec![None; BOARD_WIDTH]);
+        game.queue = PieceQueue::fixed(puzzle.pieces(), previews);
+        let first = game.queue.pop(&mut game.piece_rng).unwrap();
+        game.enter(first);
+        game
+    }
+
+    /// Game of any mode on the board of `puzzle`, dealing its pieces first
+    /// and random ones from the seed after them. Sets up scripted games.
+    pub fn scripted(seed: u64, mode: Mode, puzzle: &Puzzle, config: Config) -> Game {
+        let mut game = Game::new(seed, mode, config);
+        game.board.cells = puzzle.cells().clone();
+        game.board
+            .cells
+            .resize(GRID_HEIGHT, vec![None; BOARD_WIDTH]);
+        for &shape_type in puzzle.pieces().iter().rev() {
+            game.queue.pieces.push_front(shape_type);
+        }
+        let first = game.queue.pop(&mut game.piece_rng).unwrap();
+        game.enter(first);
+        game
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Time left until gravity is back to its normal speed after
+    /// `Item::SlowGravity`.
+    pub fn slow_gravity_remaining(&self) -> Duration {
+        self.slow_gravity
+    }
+
+    /// Reorders the upcoming pieces, the effect of an opponent's
+    /// `Item::ShuffleQueue`.
+    pub fn shuffle_queue(&mut self) {
+        self.queue
+            .pieces
+            .make_contiguous()
+            .shuffle(&mut self.item_rng);
+    }
+
+    /// Makes `shape_type` the next piece dealt, practice mode's palette.
+    pub fn choose_next(&mut self, shape_type: ShapeTypes) {
+        self.queue.set_next(shape_type);
+    }
+
+    /// Empties the board and the hold and brings the active piece back to
+    /// its spawn position, to set up a practice board from scratch.
+    pub fn reset_board(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        if self.over {
+            return events;
+        }
+
+        self.board.clear();
+        self.incoming_garbage.clear();
+        self.clearing = None;
+        self.buffered = Buttons::default();
+        self.hold = None;
+        self.can_hold = true;
+        self.combo = 0;
+        self.back_to_back = 0;
+        self.enter(self.piece.shape_type);
+        events.push(Event::BoardCleared);
+        events
+    }
+
+    /// Why the game was lost, `None` while it goes on or when it was won.
+    pub fn game_over_cause(&self) -> Option<GameOverCause> {
+        self.game_over_cause
+    }
+
+    /// Whether the active piece can still be swapped with the held one.
+    pub fn can_hold(&self) -> bool {
+        self.can_hold
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.auto_shift.set_timings(config.das, config.arr);
+        self.soft_drop.set_interval(config.soft_drop_interval);
+        self.line_clear_delay = config_duration(config.line_clear_delay);
+        self.garbage_delay = config_duration(config.garbage_delay);
+        self.rotation = config.rotation;
+        self.kicks = config.kicks;
+        self.scoring = config.scoring;
+        self.attack = config.attack;
+        self.speed = config.speed;
+        self.queue
+            .set_previews(config.previews.min(MAX_PREVIEWS), &mut self.piece_rng);
+    }
+
+    /// Lowest row the active piece can drop to.
+    pub fn ghost_y(&self) -> i32 {
+        self.board
+            .drop_y(&self.piece.shape, self.piece.x, self.piece.y)
+    }
+
+    /// Completed rows waiting for the line clear delay, empty otherwise. The
+    /// active piece is already part of the board meanwhile.
+    pub fn clearing_rows(&self) -> &[usize] {
+        match &self.clearing {
+            Some(clearing) => &clearing.rows,
+            None => &[],
+        }
+    }
+
+    /// Time until the next gravity step.
+    pub fn gravity_remaining(&self) -> Duration {
+        self.gravity.remaining()
+    }
+
+    /// Time left of the line clear delay, zero when no rows are cleared.
+    pub fn line_clear_remaining(&self) -> Duration {
+        self.clearing
+            .as_ref()
+            .map_or(Duration::ZERO, |clearing| clearing.remaining)
+    }
+
+    pub fn receive_garbage(&mut self, rows: u32) {
+        self.incoming_garbage.push(IncomingGarbage {
+            rows,
+            delay: self.garbage_delay,
+        });
+    }
+
+    /// Garbage rows received and not inserted yet.
+    pub fn pending_garbage(&self) -> u32 {
+        self.incoming_garbage
+            .iter()
+            .map(|garbage| garbage.rows)
+            .sum()
+    }
+
+    /// Pending garbage rows whose delay ran out, inserted by the next lock
+    /// that doesn't clear lines.
+    pub fn ready_garbage(&self) -> u32 {
+        self.incoming_garbage
+            .iter()
+            .filter(|garbage| garbage.delay.is_zero())
+            .map(|garbage| garbage.rows)
+            .sum()
+    }
+
+    /// Initial hold and rotation: holds and rotates the piece that just
+    /// spawned when `buttons` contain them, for buttons pressed or held
+    /// before it entered the board.
+    pub fn apply_initial_input(&mut self, buttons: Buttons) -> Vec<Event> {
+        let mut events = Vec::new();
+        if self.over {
+            return events;
+        }
+
+        let input = Input {
+            held: Buttons::default(),
+            pressed: buttons,
+        };
+        self.hold(input, &mut events);
+        if buttons.contains(Button::RotateClockwise)
+            || buttons.contains(Button::RotateCounterClockwise)
+            || buttons.contains(Button::Rotate180)
+        {
+            self.piece_inputs += 1;
+        }
+        self.rotate(input);
+        events
+    }
+
+    /// Advances the game by `delta` with the given input and returns what
+    /// happened. A finished game doesn't change anymore.
+    pub fn tick(&mut self, input: Input, delta: Duration) -> Vec<Event> {
+        let mut events = self.advance(input, delta);
+        if let Some(step) = self.tutorial {
+            if !self.over && step.is_done(input, &events) {
+                events.push(Event::TutorialStepDone { step });
+                self.tutorial = step.next();
+                match self.tutorial {
+                    Some(next) => next.prepare(&mut self.board),
+                    None => {
+                        self.over = true;
+                        events.push(Event::Finished);
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    fn advance(&mut self, input: Input, delta: Duration) -> Vec<Event> {
+        let mut events = Vec::new();
+        if self.over {
+            return events;
+        }
+
+        self.elapsed += delta;
+        self.slow_gravity = self.slow_gravity.saturating_sub(delta);
+        for garbage in &mut self.incoming_garbage {
+            garbage.delay = garbage.delay.saturating_sub(delta);
+        }
+        if self.mode == Mode::Ultra && self.elapsed >= ULTRA_TIME {
+            self.elapsed = ULTRA_TIME;
+            self.over = true;
+            events.push(Event::Finished);
+            return events;
+        }
+
+        // The game stands still until the rows are cleared, rotations and
+        // holds pressed meanwhile wait for the next piece.
+        if let Some(clearing) = &mut self.clearing {
+            self.buffered = self.buffered.union(input.pressed);
+            if clearing.remaining > delta {
+                clearing.remaining -= delta;
+                return events;
+            }
+
+            let t_spin = clearing.t_spin;
+            self.clearing = None;
+            self.clear_lines(t_spin, &mut events);
+            self.spawn_next_piece(&mut events);
+            let initial = std::mem::take(&mut self.buffered).union(input.held);
+            events.extend(self.apply_initial_input(initial));
+            return events;
+        }
+
+        self.piece_inputs += [
+            Button::MoveLeft,
+            Button::MoveRight,
+            Button::RotateClockwise,
+            Button::RotateCounterClockwise,
+            Button::Rotate180,
+        ]
+        .into_iter()
+        .filter(|&button| input.pressed.contains(button))
+        .count() as u32;
+        self.shift(input, delta);
+        self.rotate(input);
+        self.hold(input, &mut events);
+        self.soft_drop(input, delta);
+        if input.pressed.contains(Button::HardDrop) {
+            self.hard_drop(&mut events);
+        } else if self.mode != Mode::Practice {
+            self.apply_gravity(delta, &mut events);
+        }
+
+        events
+    }
+
+    /// Moves the piece once when a direction is pressed and auto repeats
+    /// after the DAS delay while it is held. The most recently pressed
+    /// direction wins.
+    fn shift(&mut self, input: Input, delta: Duration) {
+        let left = input.held.contains(Button::MoveLeft);
+        let right = input.held.contains(Button::MoveRight);
+        let auto_shift = &mut self.auto_shift;
+
+        let moves = if input.pressed.contains(Button::MoveLeft) {
+            auto_shift.start(-1);
+            1
+        } else if input.pressed.contains(Button::MoveRight) {
+            auto_shift.start(1);
+            1
+        } else if (auto_shift.direction == -1 && !left) || (auto_shift.direction == 1 && !right) {
+            // Fall back to the other direction if it is still held.
+            auto_shift.start(if left {
+                -1
+            } else if right {
+                1
+            } else {
+                0
+            });
+            0
+        } else if auto_shift.direction != 0 {
+            auto_shift.tick(delta)
+        } else {
+            0
+        };
+
+        let direction = auto_shift.direction;
+        for _ in 0..moves {
+            if !self
+                .board
+                .fits(&self.piece.shape, self.piece.x + direction, self.piece.y)
+            {
+                break;
+            }
+            self.piece.x += direction;
+            self.rotated_last = false;
+        }
+    }
+
+    /// Turns the piece a quarter turn either way or half around to the
+    /// rotation state of the rotation system, moved by the first of its kicks
+    /// that fits.
+    fn rotate(&mut self, input: Input) {
+        if self.piece.shape_type == ShapeTypes::Square {
+            return;
+        }
+
+        let turn = if input.pressed.contains(Button::RotateClockwise) {
+            Turn::Clockwise
+        } else if input.pressed.contains(Button::RotateCounterClockwise) {
+            Turn::CounterClockwise
+        } else if input.pressed.contains(Button::Rotate180) {
+            Turn::Half
+        } else {
+            return;
+        };
+
+        // Kicks read from a file stand in for those of the rotation system.
+        let rotated = match &self.kicks {
+            Some(kicks) => {
+                self.rotation
+                    .turn(&self.board, &self.piece, turn.quarters(), Some(kicks))
+            }
+            None => rotate(&self.board, &self.piece, turn, self.rotation),
+        };
+        if let Some(rotated) = rotated {
+            self.piece = rotated;
+            self.rotated_last = true;
+        }
+    }
+
+    /// Swaps the active piece with the held one, or with the next piece from
+    /// the queue when nothing is held yet.
+    fn hold(&mut self, input: Input, events: &mut Vec<Event>) {
+        if !input.pressed.contains(Button::Hold) || !self.can_hold {
+            return;
+        }
+
+        let next = match self.hold {
+            Some(held) => held,
+            None => match self.queue.pop(&mut self.piece_rng) {
+                Some(next) => next,
+                // The last piece of a puzzle can't be put aside.
+                None => return,
+            },
+        };
+        self.hold = Some(self.piece.shape_type);
+        self.stats.holds += 1;
+        self.can_hold = false;
+        self.spawn(next, events);
+    }
+
+    /// Moves the piece down once when soft drop is pressed and then every
+    /// soft drop interval while it is held, independently of gravity.
+    fn soft_drop(&mut self, input: Input, delta: Duration) {
+        let steps = if input.pressed.contains(Button::SoftDrop) {
+            self.soft_drop.reset();
+            1
+        } else if input.held.contains(Button::SoftDrop) {
+            self.soft_drop.tick(delta)
+        } else {
+            0
+        };
+
+        for _ in 0..steps {
+            match step(&self.board, &self.piece) {
+                StepResult::Fell(piece) => self.piece = piece,
+                StepResult::Landed => break,
+            }
+            self.rotated_last = false;
+            self.soft_dropped = true;
+            self.lock_timer = Duration::ZERO;
+            self.score += self.scoring.policy().soft_drop();
+        }
+    }
+
+    /// Drops the active piece straight to the lowest position it fits and
+    /// locks it there.
+    fn hard_drop(&mut self, events: &mut Vec<Event>) {
+        let drop_y = self.ghost_y();
+        if drop_y < self.piece.y {
+            self.score += self
+                .scoring
+                .policy()
+                .hard_drop((self.piece.y - drop_y) as u32);
+            self.piece.y = drop_y;
+            self.rotated_last = false;
+        }
+
+        self.lock_piece(events);
+    }
+
+    /// Moves the active piece one row down every gravity interval. Once it
+    /// rests on the stack it locks into the board after the lock delay,
+    /// which starts over whenever it drops a row.
+    fn apply_gravity(&mut self, delta: Duration, events: &mut Vec<Event>) {
+        let gravity_delta = if self.slow_gravity.is_zero() {
+            delta
+        } else {
+            delta / SLOW_GRAVITY_FACTOR
+        };
+        let steps = self.gravity.tick(gravity_delta);
+        let curve = self.speed.for_mode(self.mode);
+        self.gravity
+            .set_duration(curve.gravity_interval(self.level.level));
+        let lock_delay = curve.lock_delay(self.level.level);
+
+        for _ in 0..steps {
+            match step(&self.board, &self.piece) {
+                StepResult::Fell(piece) => self.piece = piece,
+                StepResult::Landed => break,
+            }
+            self.rotated_last = false;
+            self.lock_timer = Duration::ZERO;
+        }
+
+        if self
+            .board
+            .fits(&self.piece.shape, self.piece.x, self.piece.y - 1)
+        {
+            return;
+        }
+        self.lock_timer += delta;
+        if self.lock_timer >= lock_delay {
+            self.lock_piece(events);
+        }
+    }
+
+    /// Writes the active piece into the board, clears the completed rows and
+    /// spawns the next piece. With a line clear delay, completed rows wait
+    /// for it to run out first.
+    fn lock_piece(&mut self, events: &mut Vec<Event>) {
+        let t_spin = self.is_t_spin();
+        let cells = self.piece.cells();
+        let block = Block::Piece(self.piece.shape_type);
+        self.board.place(&cells, block);
+        if let Some((index, item)) = self.piece.item {
+            let (x, y) = cells[index];
+            if (y as usize) < self.board.cells.len() {
+                self.board.cells[y as usize][x as usize] = Some(Block::Item(item));
+            }
+        }
+        self.stats.record_piece(self.piece.shape_type);
+        events.push(Event::PieceLocked {
+            cells: cells.clone(),
+            block,
+        });
+        if self.mode == Mode::Finesse && !self.soft_dropped {
+            if let Some(minimal) = minimal_inputs(
+                self.board.size(),
+                self.piece.shape_type,
+                self.rotation,
+                self.kicks.as_ref(),
+                &cells,
+            ) {
+                events.push(Event::FinesseChecked {
+                    inputs: self.piece_inputs,
+                    minimal,
+                });
+            }
+        }
+        let locked_out = cells.iter().all(|&(_, y)| y >= self.board.height() as i32);
+        if locked_out && self.top_out(GameOverCause::LockOut, events) {
+            return;
+        }
+
+        let rows = full_rows(&self.board.cells);
+        if !rows.is_empty() && !self.line_clear_delay.is_zero() {
+            events.push(Event::ClearStarted { rows: rows.clone() });
+            self.clearing = Some(Clearing {
+                rows,
+                t_spin,
+                remaining: self.line_clear_delay,
+            });
+            return;
+        }
+
+        self.clear_lines(t_spin, events);
+        self.spawn_next_piece(events);
+    }
+
+    /// Takes the next piece from the queue, ending the game if it doesn't
+    /// fit. When a puzzle's queue runs out the held piece is played last,
+    /// after that the game is lost.
+    fn spawn_next_piece(&mut self, events: &mut Vec<Event>) {
+        if self.over {
+            return;
+        }
+
+        let next = self
+            .queue
+            .pop(&mut self.piece_rng)
+            .or_else(|| self.hold.take());
+        let next = match next {
+            Some(next) => next,
+            None => {
+                self.lose(GameOverCause::OutOfPieces, events);
+                return;
+            }
+        };
+        self.can_hold = true;
+        self.spawn(next, events);
+    }
+
+    fn trigger(&mut self, item: Item, events: &mut Vec<Event>) {
+        match item {
+            Item::ClearBottomRow => collapse_rows(&mut self.board.cells, &[0]),
+            Item::SlowGravity => self.slow_gravity = SLOW_GRAVITY_TIME,
+            Item::ShuffleQueue => {}
+        }
+        events.push(Event::ItemTriggered { item });
+    }
+
+    /// Brings in the next active piece. One overlapping the stack blocks
+    /// out.
+    fn spawn(&mut self, shape_type: ShapeTypes, events: &mut Vec<Event>) {
+        if !self.enter(shape_type) {
+            if self.top_out(GameOverCause::BlockOut, events) {
+                return;
+            }
+            self.enter(shape_type);
+        }
+        events.push(Event::PieceSpawned { shape_type });
+    }
+
+    /// Ends the game, except in zen, the tutorial and practice where the
+    /// board is emptied to play on. Returns whether the game is over.
+    fn top_out(&mut self, cause: GameOverCause, events: &mut Vec<Event>) -> bool {
+        if !matches!(self.mode, Mode::Zen | Mode::Tutorial | Mode::Practice) {
+            self.lose(cause, events);
+            return true;
+        }
+        self.board.clear();
+        self.incoming_garbage.clear();
+        if let Some(step) = self.tutorial {
+            step.prepare(&mut self.board);
+        }
+        events.push(Event::BoardCleared);
+        false
+    }
+
+    fn lose(&mut self, cause: GameOverCause, events: &mut Vec<Event>) {
+        self.over = true;
+        self.game_over_cause = Some(cause);
+        events.push(Event::GameOver { cause });
+    }
+
+    /// Puts a piece into play at its spawn position, right above the
+    /// playfield, and moves it a row down at once when nothing is in the
+    /// way. Returns whether it fit, a piece overlapping the stack ends the
+    /// game.
+    fn enter(&mut self, shape_type: ShapeTypes) -> bool {
+        self.piece = ActivePiece::spawn(shape_type, self.rotation, &self.board);
+        self.rotated_last = false;
+        self.piece_inputs = 0;
+        self.soft_dropped = false;
+        self.lock_timer = Duration::ZERO;
+        let piece = &mut self.piece;
+        if !self.board.fits(&piece.shape, piece.x, piece.y) {
+            return false;
+        }
+        if self.board.fits(&piece.shape, piece.x, piece.y - 1) {
+            piece.y -= 1;
+        }
+        if let Mode::Items { versus } = self.mode {
+            if self.item_rng.gen_bool(ITEM_CHANCE) {
+                let index = self.item_rng.gen_range(0..piece.shape.squares.len());
+                piece.item = Some((index, Item::random(&mut self.item_rng, versus)));
+            }
+        }
+        true
+    }
+
+    /// A T piece rotated into place with at least three of the four cells
+    /// diagonal to its center blocked.
+    fn is_t_spin(&self) -> bool {
+        if self.piece.shape_type != ShapeTypes::SquareTop || !self.rotated_last {
+            return false;
+        }
+
+        let (x, y) = (self.piece.x, self.piece.y);
+        let blocked = [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+            .iter()
+            .filter(|(dx, dy)| !self.board.is_free(x + dx, y + dy))
+            .count();
+        blocked >= 3
+    }
+
+    /// Removes the rows completed by a locked piece and scores the lock.
+    /// Multi-line clears cancel pending garbage and attack the opponent with
+    /// the rest, a lock without clears lets the pending garbage in.
+    fn clear_lines(&mut self, t_spin: bool, events: &mut Vec<Event>) {
+        let rows = full_rows(&self.board.cells);
+        if rows.is_empty() {
+            self.combo = 0;
+            self.score += self.scoring.policy().clear(Clear {
+                lines: 0,
+                t_spin,
+                level: self.level.level,
+                combo: self.combo,
+                back_to_back: self.back_to_back,
+            });
+            // Every attack comes with its own hole.
+            while let Some(garbage) = self.incoming_garbage.first() {
+                if !garbage.delay.is_zero() {
+                    break;
+                }
+                let rows = garbage.rows as usize;
+                let hole = self.garbage_rng.gen_range(0..self.board.width());
+                insert_garbage(&mut self.board.cells, rows, hole);
+                events.push(Event::GarbageInserted { rows, hole });
+                self.incoming_garbage.remove(0);
+            }
+            return;
+        }
+
+        let items: Vec<Item> = rows
+            .iter()
+            .flat_map(|&y| &self.board.cells[y])
+            .filter_map(|cell| match cell {
+                Some(Block::Item(item)) => Some(*item),
+                _ => None,
+            })
+            .collect();
+        collapse_rows(&mut self.board.cells, &rows);
+
+        self.combo += 1;
+        if rows.len() == 4 || t_spin {
+            self.back_to_back += 1;
+        } else {
+            self.back_to_back = 0;
+        }
+        let level = self.level.level;
+        self.score += self.scoring.policy().clear(Clear {
+            lines: rows.len(),
+            t_spin,
+            level,
+            combo: self.combo,
+            back_to_back: self.back_to_back,
+        });
+        self.level.add_lines(rows.len() as u32);
+        // Zen, the tutorial and practice count the lines without levelling
+        // up.
+        if matches!(self.mode, Mode::Zen | Mode::Tutorial | Mode::Practice) {
+            self.level.level = self.level.start;
+        }
+
+        // The attack cancels the oldest incoming garbage first.
+        let mut attack = self.attack.attack(rows.len(), t_spin, self.combo);
+        while attack > 0 {
+            let garbage = match self.incoming_garbage.first_mut() {
+                Some(garbage) => garbage,
+                None => break,
+            };
+            let cancelled = attack.min(garbage.rows);
+            garbage.rows -= cancelled;
+            attack -= cancelled;
+            if garbage.rows == 0 {
+                self.incoming_garbage.remove(0);
+            }
+        }
+        if attack > 0 {
+            events.push(Event::GarbageSent { rows: attack });
+        }
+        events.push(Event::LinesCleared {
+            rows,
+            t_spin,
+            combo: self.combo,
+            back_to_back: self.back_to_back,
+        });
+        for item in items {
+            self.trigger(item, events);
+        }
+
+        let finished = match self.mode {
+            Mode::Sprint => self.level.lines >= SPRINT_LINES,
+            Mode::Puzzle => self.board.cells.iter().flatten().all(Option::is_none),
+            Mode::Marathon { level_cap, .. } if self.level.level > level_cap => {
+                self.level.level = level_cap;
+                true
+            }
+            _ => false,
+        };
+        if finished {
+            self.over = true;
+            events.push(Event::Finished);
+        } else if self.level.level > level {
+            events.push(Event::LevelUp {
+                level: self.level.level,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::BOARD_HEIGHT, piece::Shape, scoring::COMBO_POINTS};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn same_seed_gives_the_same_pieces() {
+        let sequence = |seed| {
+            let mut game = Game::new(seed, Mode::Endless, Config::default());
+            (0..50)
+                .map(|_| game.queue.pop(&mut game.piece_rng).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sequence(42), sequence(42));
+    }
+
+    #[test]
+    fn preview_count_doesnt_change_the_pieces() {
+        let sequence = |previews: &[usize]| {
+            let mut game = Game::new(42, Mode::Endless, Config::default());
+            let mut pieces = Vec::new();
+            for &previews in previews {
+                game.set_config(Config {
+                    previews,
+                    ..Config::default()
+                });
+                assert_eq!(game.queue.previews().count(), previews);
+                pieces.extend((0..10).map(|_| game.queue.pop(&mut game.piece_rng).unwrap()));
+            }
+            pieces
+        };
+        assert_eq!(sequence(&[5, 5, 5]), sequence(&[0, 6, 2]));
+    }
+
+    #[test]
+    fn handicaps_start_with_garbage_fewer_previews_and_faster_gravity() {
+        let handicap = Handicap {
+            garbage_rows: 4,
+            gravity: 2.,
+            previews: 1,
+        };
+        let plain = Game::new(42, Mode::Endless, Config::default());
+        let game = Game::new(42, Mode::Endless, Config::default().with_handicap(handicap));
+
+        for row in &game.board.cells[..4] {
+            assert_eq!(row.iter().filter(|cell| cell.is_none()).count(), 1);
+        }
+        assert!(game.board.cells[4].iter().all(Option::is_none));
+        assert_eq!(game.queue.previews().count(), 1);
+        assert_eq!(game.gravity.interval * 2, plain.gravity.interval);
+    }
+
+    #[test]
+    fn hard_drop_locks_and_spawns_the_next_piece() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        let next = game.queue.pieces[0];
+
+        let mut input = Input::default();
+        input.pressed.insert(Button::HardDrop);
+        let events = game.tick(input, Duration::ZERO);
+
+        assert!(matches!(
+            events[..],
+            [Event::PieceLocked { .. }, Event::PieceSpawned { shape_type }] if shape_type == next
+        ));
+        assert_eq!(game.piece.shape_type, next);
+        assert!(game.score > 0);
+    }
+
+    fn hard_drop(game: &mut Game) -> Vec<Event> {
+        let mut input = Input::default();
+        input.pressed.insert(Button::HardDrop);
+        game.tick(input, Duration::ZERO)
+    }
+
+    /// Fills the bottom `rows` rows except where the lowest squares of the
+    /// active piece land when dropped straight down.
+    fn fill_around_piece(game: &mut Game, rows: usize) {
+        let cells = game.piece.cells();
+        let bottom = cells.iter().map(|&(_, y)| y).min().unwrap();
+        for y in 0..rows {
+            for (x, cell) in game.board.cells[y].iter_mut().enumerate() {
+                let landing = cells
+                    .iter()
+                    .any(|&(cx, cy)| cx == x as i32 && (cy - bottom) as usize == y);
+                if !landing {
+                    *cell = Some(Block::Garbage);
+                }
+            }
+        }
+    }
+
+    /// Game whose active T piece stands against the left wall in the given
+    /// rotation state.
+    fn t_at_left_wall(rotation: Rotation, state: u8) -> Game {
+        let config = Config {
+            rotation,
+            ..Config::default()
+        };
+        let mut game = Game::new(7, Mode::Endless, config);
+        game.piece = ActivePiece::spawn(ShapeTypes::SquareTop, rotation, &game.board);
+        game.piece.shape = Shape {
+            squares: rotation.system().squares(ShapeTypes::SquareTop, state),
+        };
+        game.piece.rotation = state;
+        game.piece.x = 0;
+        game.piece.y = 10;
+        game
+    }
+
+    fn rotate_clockwise(game: &mut Game) {
+        let mut input = Input::default();
+        input.pressed.insert(Button::RotateClockwise);
+        game.tick(input, Duration::ZERO);
+    }
+
+    #[test]
+    fn srs_kicks_off_the_wall() {
+        let mut game = t_at_left_wall(Rotation::Srs, 1);
+        rotate_clockwise(&mut game);
+
+        assert_eq!(game.piece.rotation, 2);
+        assert_eq!((game.piece.x, game.piece.y), (1, 10));
+    }
+
+    #[test]
+    fn classic_rotation_does_not_kick() {
+        let mut game = t_at_left_wall(Rotation::Classic, 3);
+        rotate_clockwise(&mut game);
+
+        assert_eq!(game.piece.rotation, 3);
+        assert_eq!((game.piece.x, game.piece.y), (0, 10));
+    }
+
+    #[test]
+    fn kick_tables_replace_the_rotation_system_kicks() {
+        let mut game = t_at_left_wall(Rotation::Classic, 3);
+        game.kicks = Some(KickTable(BTreeMap::from([(
+            "T".to_string(),
+            BTreeMap::from([("L>0".to_string(), vec![(0, 0), (1, 0)])]),
+        )])));
+        rotate_clockwise(&mut game);
+
+        assert_eq!(game.piece.rotation, 0);
+        assert_eq!((game.piece.x, game.piece.y), (1, 10));
+    }
+
+    #[test]
+    fn rotate_180_turns_half_around_in_place() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        game.piece = ActivePiece::spawn(ShapeTypes::SquareTop, Rotation::Srs, &game.board);
+        let (x, y) = (game.piece.x, game.piece.y);
+        let mut input = Input::default();
+        input.pressed.insert(Button::Rotate180);
+        game.tick(input, Duration::ZERO);
+
+        assert_eq!(game.piece.rotation, 2);
+        assert_eq!((game.piece.x, game.piece.y), (x, y));
+        assert_eq!(
+            game.piece.shape.squares,
+            Rotation::Srs.system().squares(ShapeTypes::SquareTop, 2)
+        );
+    }
+
+    #[test]
+    fn finesse_mode_judges_each_placement() {
+        let mut game = Game::new(7, Mode::Finesse, Config::default());
+        for button in [Button::MoveLeft, Button::MoveRight] {
+            let mut input = Input::default();
+            input.pressed.insert(button);
+            game.tick(input, Duration::ZERO);
+        }
+
+        assert!(hard_drop(&mut game).contains(&Event::FinesseChecked {
+            inputs: 2,
+            minimal: 0
+        }));
+        assert!(hard_drop(&mut game).contains(&Event::FinesseChecked {
+            inputs: 0,
+            minimal: 0
+        }));
+    }
+
+    /// Items game whose bottom row is completed by the active piece, with
+    /// `item` in one of its cells.
+    fn items_game(item: Item) -> Game {
+        let mut game = Game::new(7, Mode::Items { versus: false }, Config::default());
+        fill_around_piece(&mut game, 1);
+        let x = game.board.cells[0]
+            .iter()
+            .position(Option::is_some)
+            .unwrap();
+        game.board.cells[0][x] = Some(Block::Item(item));
+        game
+    }
+
+    #[test]
+    fn clearing_a_slow_gravity_item_slows_gravity() {
+        let mut game = items_game(Item::SlowGravity);
+
+        let events = hard_drop(&mut game);
+
+        assert!(events.contains(&Event::ItemTriggered {
+            item: Item::SlowGravity
+        }));
+        assert_eq!(game.slow_gravity_remaining(), SLOW_GRAVITY_TIME);
+    }
+
+    #[test]
+    fn clearing_a_clear_row_item_removes_the_bottom_row() {
+        let mut game = items_game(Item::ClearBottomRow);
+        let column = (0..BOARD_WIDTH)
+            .find(|&x| game.piece.cells().iter().all(|&(cx, _)| cx != x as i32))
+            .unwrap();
+        game.board.cells[1][column] = Some(Block::Garbage);
+
+        hard_drop(&mut game);
+
+        // The garbage fell into the bottom row with what was left of the
+        // piece and was removed by the item.
+        assert!(game.board.cells.iter().flatten().all(Option::is_none));
+    }
+
+    #[test]
+    fn zen_empties_the_board_instead_of_ending() {
+        let mut game = Game::new(7, Mode::Zen, Config::default());
+        for y in 0..GRID_HEIGHT {
+            game.board.cells[y][BOARD_WIDTH / 2] = Some(Block::Garbage);
+        }
+
+        let events = hard_drop(&mut game);
+
+        assert!(events.contains(&Event::BoardCleared));
+        assert!(!game.over);
+        assert!(game.board.cells.iter().flatten().all(Option::is_none));
+    }
+
+    #[test]
+    fn practice_pieces_wait_for_a_drop_and_follow_the_palette() {
+        let mut game = Game::new(7, Mode::Practice, Config::default());
+        let y = game.piece.y;
+        game.tick(Input::default(), Duration::from_secs(10));
+        assert_eq!(game.piece.y, y);
+        assert!(game.board.cells.iter().flatten().all(Option::is_none));
+
+        game.choose_next(ShapeTypes::SquareTop);
+        hard_drop(&mut game);
+        assert_eq!(game.piece.shape_type, ShapeTypes::SquareTop);
+
+        assert!(game.reset_board().contains(&Event::BoardCleared));
+        assert!(game.board.cells.iter().flatten().all(Option::is_none));
+        assert_eq!(game.piece.shape_type, ShapeTypes::SquareTop);
+    }
+
+    #[test]
+    fn clearing_the_tenth_line_levels_up() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        game.level.add_lines(9);
+        fill_around_piece(&mut game, 1);
+
+        assert!(hard_drop(&mut game).contains(&Event::LevelUp { level: 2 }));
+    }
+
+    #[test]
+    fn sprint_finishes_at_the_last_line() {
+        let mut game = Game::new(7, Mode::Sprint, Config::default());
+        game.level.add_lines(SPRINT_LINES - 1);
+        fill_around_piece(&mut game, 1);
+
+        let events = hard_drop(&mut game);
+
+        assert_eq!(events.last(), Some(&Event::Finished));
+        assert!(game.over);
+    }
+
+    #[test]
+    fn ultra_finishes_when_the_time_is_up() {
+        let mut game = Game::new(7, Mode::Ultra, Config::default());
+        game.tick(Input::default(), Duration::from_millis(10));
+        assert!(!game.over);
+
+        let events = game.tick(Input::default(), ULTRA_TIME);
+
+        assert_eq!(events, [Event::Finished]);
+        assert_eq!(game.elapsed, ULTRA_TIME);
+    }
+
+    #[test]
+    fn marathon_finishes_after_clearing_the_level_cap() {
+        let mode = Mode::Marathon {
+            start_level: 3,
+            level_cap: 4,
+        };
+        let mut game = Game::new(7, mode, Config::default());
+        assert_eq!(game.level.level, 3);
+        game.level.add_lines(2 * LINES_PER_LEVEL - 1);
+        fill_around_piece(&mut game, 1);
+
+        let events = hard_drop(&mut game);
+
+        assert_eq!(events.last(), Some(&Event::Finished));
+        assert_eq!(game.level.level, 4);
+    }
+
+    #[test]
+    fn rows_stay_until_the_line_clear_delay_runs_out() {
+        let config = Config {
+            line_clear_delay: 0.25,
+            ..Config::default()
+        };
+        let mut game = Game::new(7, Mode::Endless, config);
+        fill_around_piece(&mut game, 1);
+
+        let events = hard_drop(&mut game);
+        assert_eq!(events.last(), Some(&Event::ClearStarted { rows: vec![0] }));
+        assert_eq!(game.clearing_rows(), [0]);
+
+        assert!(game
+            .tick(Input::default(), Duration::from_millis(125))
+            .is_empty());
+        let events = game.tick(Input::default(), Duration::from_millis(125));
+        assert!(matches!(events[0], Event::LinesCleared { .. }));
+        assert!(game.clearing_rows().is_empty());
+    }
+
+    #[test]
+    fn pieces_lock_after_resting_for_the_lock_delay() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        game.piece.y = game.ghost_y();
+
+        assert!(game
+            .tick(Input::default(), Duration::from_millis(400))
+            .is_empty());
+        let events = game.tick(Input::default(), Duration::from_millis(100));
+        assert!(matches!(events[0], Event::PieceLocked { .. }));
+    }
+
+    #[test]
+    fn hold_works_once_per_piece() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        let first = game.piece.shape_type;
+        let mut input = Input::default();
+        input.pressed.insert(Button::Hold);
+
+        game.tick(input, Duration::ZERO);
+        let second = game.piece.shape_type;
+        game.tick(input, Duration::ZERO);
+        assert_eq!(game.piece.shape_type, second);
+        assert_eq!(game.hold, Some(first));
+        assert!(!game.can_hold());
+
+        hard_drop(&mut game);
+        game.tick(input, Duration::ZERO);
+        assert_eq!(game.piece.shape_type, first);
+        assert_eq!(game.stats.holds, 2);
+    }
+
+    #[test]
+    fn spawning_into_the_stack_ends_the_game() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        for y in 0..GRID_HEIGHT {
+            game.board.cells[y][BOARD_WIDTH / 2] = Some(Block::Garbage);
+        }
+
+        let events = hard_drop(&mut game);
+
+        assert_eq!(
+            events.last(),
+            Some(&Event::GameOver {
+                cause: GameOverCause::BlockOut
+            })
+        );
+        assert!(game.over);
+    }
+
+    #[test]
+    fn locking_above_the_playfield_ends_the_game() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        for y in 0..BOARD_HEIGHT {
+            game.board.cells[y][4] = Some(Block::Garbage);
+            game.board.cells[y][5] = Some(Block::Garbage);
+        }
+        game.piece = ActivePiece::new(ShapeTypes::Square);
+        game.piece.x = 4;
+        game.piece.y = BOARD_HEIGHT as i32 + 1;
+
+        let events = hard_drop(&mut game);
+
+        assert_eq!(
+            events.last(),
+            Some(&Event::GameOver {
+                cause: GameOverCause::LockOut
+            })
+        );
+        assert_eq!(game.game_over_cause(), Some(GameOverCause::LockOut));
+    }
+
+    #[test]
+    fn hold_pressed_during_the_line_clear_delay_holds_the_next_piece() {
+        let config = Config {
+            line_clear_delay: 0.25,
+            ..Config::default()
+        };
+        let mut game = Game::new(7, Mode::Endless, config);
+        fill_around_piece(&mut game, 1);
+        hard_drop(&mut game);
+        let next = game.queue.pieces[0];
+
+        let mut input = Input::default();
+        input.pressed.insert(Button::Hold);
+        game.tick(input, Duration::from_millis(125));
+        assert_eq!(game.hold, None);
+        game.tick(Input::default(), Duration::from_millis(125));
+
+        assert_eq!(game.hold, Some(next));
+        assert_eq!(game.stats.holds, 1);
+    }
+
+    #[test]
+    fn consecutive_clears_build_a_combo() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        for combo in 1..=3 {
+            game.board.clear();
+            fill_around_piece(&mut game, 1);
+            hard_drop(&mut game);
+            assert_eq!(game.combo, combo);
+        }
+
+        game.board.clear();
+        hard_drop(&mut game);
+        assert_eq!(game.combo, 0);
+    }
+
+    #[test]
+    fn back_to_back_tetrises_score_half_more() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        let tetris = |game: &mut Game| {
+            game.board.clear();
+            game.piece = ActivePiece::new(ShapeTypes::Line);
+            game.piece.shape = game.piece.shape.rotated();
+            fill_around_piece(game, 4);
+            let score = game.score;
+            hard_drop(game);
+            game.score - score
+        };
+
+        let first = tetris(&mut game);
+        let second = tetris(&mut game);
+
+        assert_eq!(game.back_to_back, 2);
+        assert_eq!(second - first, 800 / 2 + COMBO_POINTS);
+    }
+
+    #[test]
+    fn simple_scoring_only_counts_lines() {
+        let config = Config {
+            scoring: Scoring::Simple,
+            ..Config::default()
+        };
+        let mut game = Game::new(7, Mode::Endless, config);
+        game.piece = ActivePiece::new(ShapeTypes::Line);
+        game.piece.shape = game.piece.shape.rotated();
+        fill_around_piece(&mut game, 4);
+        hard_drop(&mut game);
+
+        assert_eq!(game.score, 400);
+    }
+
+    #[test]
+    fn garbage_enters_the_board_after_its_delay() {
+        let config = Config {
+            garbage_delay: 1.,
+            ..Config::default()
+        };
+        let mut game = Game::new(7, Mode::Endless, config);
+        game.receive_garbage(2);
+
+        hard_drop(&mut game);
+        assert_eq!(game.pending_garbage(), 2);
+        assert_eq!(game.ready_garbage(), 0);
+
+        game.tick(Input::default(), Duration::from_secs(1));
+        assert_eq!(game.ready_garbage(), 2);
+        let events = hard_drop(&mut game);
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, Event::GarbageInserted { rows: 2, .. })));
+        assert_eq!(game.pending_garbage(), 0);
+    }
+
+    #[test]
+    fn clears_cancel_the_oldest_garbage_first() {
+        let mut game = Game::new(7, Mode::Endless, Config::default());
+        game.receive_garbage(1);
+        game.receive_garbage(5);
+        game.piece = ActivePiece::new(ShapeTypes::Line);
+        game.piece.shape = game.piece.shape.rotated();
+        fill_around_piece(&mut game, 4);
+
+        let events = hard_drop(&mut game);
+
+        assert_eq!(game.pending_garbage(), 2);
+        assert_eq!(game.incoming_garbage.len(), 1);
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, Event::GarbageSent { .. })));
+    }
+
+    #[test]
+    fn clearing_the_whole_board_solves_a_puzzle() {
+        let puzzle = Puzzle::parse("test", &[], "I").unwrap();
+        let mut game = Game::puzzle(7, &puzzle, Config::default());
+        fill_around_piece(&mut game, 1);
+
+        assert_eq!(hard_drop(&mut game).last(), Some(&Event::Finished));
+    }
+
+    #[test]
+    fn a_puzzle_is_lost_when_its_pieces_run_out() {
+        let rows = ["#.........".to_string()];
+        let puzzle = Puzzle::parse("test", &rows, "O O").unwrap();
+        let mut game = Game::puzzle(7, &puzzle, Config::default());
+
+        hard_drop(&mut game);
+        assert!(!game.over);
+        assert_eq!(
+            hard_drop(&mut game).last(),
+            Some(&Event::GameOver {
+                cause: GameOverCause::OutOfPieces
+            })
+        );
+    }
+
+    #[test]
+    fn games_are_played_on_the_board_of_their_config() {
+        let size = BoardSize {
+            width: 6,
+            height: 12,
+        };
+        let config = Config {
+            board_size: size,
+            ..Config::default()
+        };
+        let game = Game::new(1, Mode::Endless, config);
+        assert_eq!(game.board.size(), size);
+        assert!(game
+            .piece
+            .cells()
+            .iter()
+            .all(|&(x, y)| (0..6).contains(&x) && y >= 11));
+
+        let config = Config {
+            board_size: BoardSize {
+                width: 100,
+                height: 1,
+            },
+            ..Config::default()
+        };
+        let game = Game::new(1, Mode::Endless, config);
+        assert_eq!(
+            game.board.size(),
+            BoardSize {
+                width: 20,
+                height: 8
+            }
+        );
+    }
+
+    #[test]
+    fn negative_timings_are_treated_as_zero() {
+        let config = Config {
+            das: -0.1,
+            arr: f32::NAN,
+            garbage_delay: -1.,
+            ..Config::default()
+        };
+        let mut game = Game::new(1, Mode::Endless, config.clone());
+        game.set_config(config);
+
+        let x = game.piece.x;
+        let mut input = Input::default();
+        input.pressed.insert(Button::MoveRight);
+        input.held.insert(Button::MoveRight);
+        game.tick(input, Duration::from_millis(16));
+        assert!(game.piece.x > x);
+    }
+}
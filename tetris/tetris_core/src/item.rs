@@ -0,0 +1,41 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Chance of a new piece carrying an item in the items mode.
+pub const ITEM_CHANCE: f64 = 0.1;
+/// How long `Item::SlowGravity` slows gravity down.
+pub const SLOW_GRAVITY_TIME: Duration = Duration::from_secs(10);
+/// Factor gravity is slowed down by meanwhile.
+pub(crate) const SLOW_GRAVITY_FACTOR: u32 = 2;
+
+/// Special block of the items mode, carried by one square of a piece.
+/// Clearing the row it was locked in triggers its effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Item {
+    /// Removes the bottom row of the board.
+    ClearBottomRow,
+    /// Halves the speed of gravity for `SLOW_GRAVITY_TIME`.
+    SlowGravity,
+    /// Shuffles the opponent's upcoming pieces, only dealt in versus.
+    ShuffleQueue,
+}
+
+impl Item {
+    pub const ALL: [Item; 3] = [Item::ClearBottomRow, Item::SlowGravity, Item::ShuffleQueue];
+
+    /// Locale key of the item's name.
+    pub fn key(self) -> &'static str {
+        match self {
+            Item::ClearBottomRow => "announce.clear_row",
+            Item::SlowGravity => "announce.slow_gravity",
+            Item::ShuffleQueue => "announce.shuffle",
+        }
+    }
+
+    /// Random item, leaving out those that need an opponent outside versus.
+    pub(crate) fn random<R: Rng>(rng: &mut R, versus: bool) -> Item {
+        let count = if versus { Item::ALL.len() } else { 2 };
+        Item::ALL[rng.gen_range(0..count)]
+    }
+}
@@ -0,0 +1,47 @@
+//! Rules of the game, independent of any renderer. A `Game` advances with
+//! `Game::tick` from the player's input and the elapsed time, frontends draw
+//! its state and react to the events it returns.
+
+mod attack;
+mod board;
+mod bot;
+mod finesse;
+mod game;
+mod item;
+mod motion;
+mod piece;
+mod puzzle;
+mod replay;
+mod rollback;
+mod rotation;
+mod scoring;
+mod script;
+mod speed;
+mod stats;
+mod tutorial;
+
+pub use attack::AttackTable;
+pub use board::{
+    collapse_rows, full_rows, insert_garbage, Block, Board, BoardSize, Cells, BOARD_HEIGHT,
+    BOARD_WIDTH, GRID_HEIGHT,
+};
+pub use bot::{
+    landing_positions, BotDriver, BotMatch, HeuristicBot, Placement, RandomBot, TetrisBot,
+};
+pub use finesse::minimal_inputs;
+pub use game::{
+    Button, Buttons, Config, Event, Game, GameOverCause, Handicap, IncomingGarbage, Input, Level,
+    Mode, SPRINT_LINES, ULTRA_TIME,
+};
+pub use item::{Item, ITEM_CHANCE, SLOW_GRAVITY_TIME};
+pub use motion::{rotate, step, StepResult, Turn};
+pub use piece::{ActivePiece, PieceQueue, Shape, ShapeTypes, Square, MAX_PREVIEWS, NEXT_PIECES};
+pub use puzzle::{Puzzle, PuzzleError};
+pub use replay::{Replay, ReplayError, ReplayResult, REPLAY_VERSION};
+pub use rollback::{Rollback, MAX_ROLLBACK_FRAMES, ROLLBACK_FRAME};
+pub use rotation::{Classic, KickTable, Pivot, Rotation, RotationSystem, Srs};
+pub use scoring::{Clear, Guideline, Scoring, ScoringPolicy, Simple};
+pub use script::{Script, ScriptError, Step};
+pub use speed::{SpeedCurve, SpeedCurves};
+pub use stats::{lines_per_minute, Stats};
+pub use tutorial::TutorialStep;
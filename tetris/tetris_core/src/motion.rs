@@ -0,0 +1,185 @@
+use crate::{board::Board, piece::ActivePiece, rotation::Rotation};
+
+/// Way a piece turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+    Clockwise,
+    CounterClockwise,
+    Half,
+}
+
+impl Turn {
+    pub const ALL: [Turn; 3] = [Turn::Clockwise, Turn::CounterClockwise, Turn::Half];
+
+    /// Clockwise quarter turns this makes.
+    pub fn quarters(self) -> u8 {
+        match self {
+            Turn::Clockwise => 1,
+            Turn::CounterClockwise => 3,
+            Turn::Half => 2,
+        }
+    }
+}
+
+/// What one row of falling did to a piece.
+#[derive(Debug, Clone)]
+pub enum StepResult {
+    /// The piece moved a row down, to here.
+    Fell(ActivePiece),
+    /// The piece rests on the stack or the floor and stays where it is.
+    Landed,
+}
+
+/// `piece` turned on `board` by `rotation` and its kicks, or `None` when it
+/// fits nowhere.
+pub fn rotate(
+    board: &Board,
+    piece: &ActivePiece,
+    turn: Turn,
+    rotation: Rotation,
+) -> Option<ActivePiece> {
+    rotation.turn(board, piece, turn.quarters(), None)
+}
+
+/// Moves `piece` a row down on `board` if it fits there.
+pub fn step(board: &Board, piece: &ActivePiece) -> StepResult {
+    if !board.fits(&piece.shape, piece.x, piece.y - 1) {
+        return StepResult::Landed;
+    }
+    StepResult::Fell(ActivePiece {
+        y: piece.y - 1,
+        ..piece.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::{Block, BOARD_WIDTH, GRID_HEIGHT},
+        piece::ShapeTypes,
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    const CASES: usize = 2000;
+
+    /// Board with about a third of its cells filled at random.
+    fn random_board(rng: &mut StdRng) -> Board {
+        let mut board = Board::new();
+        for row in &mut board.cells {
+            for cell in row.iter_mut() {
+                if rng.gen_bool(0.3) {
+                    *cell = Some(Block::Garbage);
+                }
+            }
+        }
+        board
+    }
+
+    /// Random piece of `rotation` somewhere it fits on `board`, in any
+    /// rotation state.
+    fn random_piece(rng: &mut StdRng, board: &Board, rotation: Rotation) -> Option<ActivePiece> {
+        let shape_type = ShapeTypes::ALL[rng.gen_range(0..ShapeTypes::ALL.len())];
+        let mut piece = ActivePiece {
+            x: 4,
+            y: 10,
+            ..ActivePiece::spawn(shape_type, rotation, &Board::new())
+        };
+        for _ in 0..rng.gen_range(0..4) {
+            piece = rotate(&Board::new(), &piece, Turn::Clockwise, rotation).unwrap();
+        }
+        piece.x = rng.gen_range(0..BOARD_WIDTH as i32);
+        piece.y = rng.gen_range(0..GRID_HEIGHT as i32);
+        board.fits(&piece.shape, piece.x, piece.y).then_some(piece)
+    }
+
+    fn assert_fits(board: &Board, piece: &ActivePiece) {
+        for (x, y) in piece.cells() {
+            assert!(board.is_free(x, y), "{:?} overlaps at {:?}", piece, (x, y));
+        }
+    }
+
+    #[test]
+    fn four_turns_in_open_space_return_to_the_start() {
+        let board = Board::new();
+        for rotation in Rotation::ALL {
+            for shape_type in ShapeTypes::ALL {
+                for turn in Turn::ALL {
+                    for x in 2..BOARD_WIDTH as i32 - 2 {
+                        for y in 2..GRID_HEIGHT as i32 - 2 {
+                            let start = ActivePiece {
+                                x,
+                                y,
+                                ..ActivePiece::spawn(shape_type, rotation, &Board::new())
+                            };
+                            let mut piece = start.clone();
+                            for _ in 0..4 {
+                                piece = rotate(&board, &piece, turn, rotation).unwrap();
+                            }
+                            assert_eq!((piece.x, piece.y), (start.x, start.y));
+                            assert_eq!(piece.rotation, start.rotation);
+                            assert_eq!(piece.cells(), start.cells());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_turn_and_its_reverse_in_open_space_cancel_out() {
+        let board = Board::new();
+        for rotation in Rotation::ALL {
+            for shape_type in ShapeTypes::ALL {
+                let start = ActivePiece {
+                    x: 4,
+                    y: 10,
+                    ..ActivePiece::spawn(shape_type, rotation, &Board::new())
+                };
+                let turned = rotate(&board, &start, Turn::Clockwise, rotation).unwrap();
+                let back = rotate(&board, &turned, Turn::CounterClockwise, rotation).unwrap();
+                assert_eq!(back.cells(), start.cells());
+            }
+        }
+    }
+
+    #[test]
+    fn rotations_never_overlap_the_stack_or_leave_the_board() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for rotation in Rotation::ALL {
+            for _ in 0..CASES {
+                let board = random_board(&mut rng);
+                let piece = match random_piece(&mut rng, &board, rotation) {
+                    Some(piece) => piece,
+                    None => continue,
+                };
+                for turn in Turn::ALL {
+                    if let Some(turned) = rotate(&board, &piece, turn, rotation) {
+                        assert_fits(&board, &turned);
+                        assert_eq!(turned.rotation, (piece.rotation + turn.quarters()) % 4);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn steps_fall_one_row_until_landing() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..CASES {
+            let board = random_board(&mut rng);
+            let rotation = Rotation::ALL[rng.gen_range(0..Rotation::ALL.len())];
+            let mut piece = match random_piece(&mut rng, &board, rotation) {
+                Some(piece) => piece,
+                None => continue,
+            };
+            let landing_y = board.drop_y(&piece.shape, piece.x, piece.y);
+            while let StepResult::Fell(fallen) = step(&board, &piece) {
+                assert_eq!((fallen.x, fallen.y), (piece.x, piece.y - 1));
+                assert_fits(&board, &fallen);
+                piece = fallen;
+            }
+            assert_eq!(piece.y, landing_y);
+        }
+    }
+}
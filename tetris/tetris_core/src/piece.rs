@@ -0,0 +1,299 @@
+use crate::{
+    board::{Board, BoardSize},
+    item::Item,
+    rotation::Rotation,
+};
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Offset of a square from the rotation pivot of its shape, in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Square {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Square {
+    pub fn new(x: i32, y: i32) -> Square {
+        Square { x, y }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shape {
+    pub squares: Vec<Square>,
+}
+
+impl Shape {
+    /// Board cells covered by the shape with its pivot at `(x, y)`.
+    pub fn cells(&self, x: i32, y: i32) -> Vec<(i32, i32)> {
+        self.squares
+            .iter()
+            .map(|square| (x + square.x, y + square.y))
+            .collect()
+    }
+
+    /// Rotates clockwise by 90 degrees around the pivot.
+    pub fn rotated(&self) -> Shape {
+        Shape {
+            squares: self
+                .squares
+                .iter()
+                .map(|square| Square::new(square.y, -square.x))
+                .collect(),
+        }
+    }
+
+    /// Rotates counter-clockwise by 90 degrees around the pivot.
+    pub fn rotated_counter_clockwise(&self) -> Shape {
+        Shape {
+            squares: self
+                .squares
+                .iter()
+                .map(|square| Square::new(-square.y, square.x))
+                .collect(),
+        }
+    }
+}
+
+/// The seven guideline tetrominoes: O, I, T, Z, S, L and J.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ShapeTypes {
+    Square,
+    Line,
+    SquareTop,
+    Zigzag,
+    ReverseZigzag,
+    LShape,
+    JShape,
+}
+
+impl Distribution<ShapeTypes> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> ShapeTypes {
+        match rng.gen_range(0..7) {
+            0 => ShapeTypes::Square,
+            1 => ShapeTypes::Line,
+            2 => ShapeTypes::SquareTop,
+            3 => ShapeTypes::Zigzag,
+            4 => ShapeTypes::ReverseZigzag,
+            5 => ShapeTypes::LShape,
+            6 => ShapeTypes::JShape,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl ShapeTypes {
+    pub const ALL: [ShapeTypes; 7] = [
+        ShapeTypes::Square,
+        ShapeTypes::Line,
+        ShapeTypes::SquareTop,
+        ShapeTypes::Zigzag,
+        ShapeTypes::ReverseZigzag,
+        ShapeTypes::LShape,
+        ShapeTypes::JShape,
+    ];
+
+    /// Guideline letter of the piece.
+    pub fn letter(self) -> char {
+        match self {
+            ShapeTypes::Square => 'O',
+            ShapeTypes::Line => 'I',
+            ShapeTypes::SquareTop => 'T',
+            ShapeTypes::Zigzag => 'Z',
+            ShapeTypes::ReverseZigzag => 'S',
+            ShapeTypes::LShape => 'L',
+            ShapeTypes::JShape => 'J',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<ShapeTypes> {
+        ShapeTypes::ALL
+            .into_iter()
+            .find(|shape_type| shape_type.letter() == letter)
+    }
+
+    pub fn build(self) -> Shape {
+        match self {
+            ShapeTypes::Square => Shape {
+                squares: vec![
+                    Square::new(0, 0),
+                    Square::new(1, 0),
+                    Square::new(0, -1),
+                    Square::new(1, -1),
+                ],
+            },
+            ShapeTypes::Line => Shape {
+                squares: vec![
+                    Square::new(-1, 0),
+                    Square::new(0, 0),
+                    Square::new(1, 0),
+                    Square::new(2, 0),
+                ],
+            },
+            ShapeTypes::SquareTop => Shape {
+                squares: vec![
+                    Square::new(-1, 0),
+                    Square::new(0, 0),
+                    Square::new(1, 0),
+                    Square::new(0, 1),
+                ],
+            },
+            ShapeTypes::Zigzag => Shape {
+                squares: vec![
+                    Square::new(-1, 0),
+                    Square::new(0, 0),
+                    Square::new(0, -1),
+                    Square::new(1, -1),
+                ],
+            },
+            ShapeTypes::ReverseZigzag => Shape {
+                squares: vec![
+                    Square::new(1, 0),
+                    Square::new(0, 0),
+                    Square::new(0, -1),
+                    Square::new(-1, -1),
+                ],
+            },
+            ShapeTypes::LShape => Shape {
+                squares: vec![
+                    Square::new(-1, 0),
+                    Square::new(0, 0),
+                    Square::new(1, 0),
+                    Square::new(1, 1),
+                ],
+            },
+            ShapeTypes::JShape => Shape {
+                squares: vec![
+                    Square::new(-1, 1),
+                    Square::new(-1, 0),
+                    Square::new(0, 0),
+                    Square::new(1, 0),
+                ],
+            },
+        }
+    }
+}
+
+/// The falling piece, in board coordinates. `x`/`y` is the cell of the
+/// rotation pivot.
+#[derive(Debug, Clone)]
+pub struct ActivePiece {
+    pub shape_type: ShapeTypes,
+    pub shape: Shape,
+    pub x: i32,
+    pub y: i32,
+    /// Rotation state of the piece's rotation system, 0 when spawned.
+    pub rotation: u8,
+    /// Index of the square carrying an item and the item, in the items
+    /// mode.
+    pub item: Option<(usize, Item)>,
+}
+
+impl ActivePiece {
+    pub fn new(shape_type: ShapeTypes) -> ActivePiece {
+        ActivePiece::entering(shape_type, shape_type.build(), BoardSize::default())
+    }
+
+    /// Piece entering `board` in the spawn state of `rotation`.
+    pub fn spawn(shape_type: ShapeTypes, rotation: Rotation, board: &Board) -> ActivePiece {
+        let shape = Shape {
+            squares: rotation.system().squares(shape_type, 0),
+        };
+        ActivePiece::entering(shape_type, shape, board.size())
+    }
+
+    /// Piece at the guideline spawn position: its lowest squares in the row
+    /// right above the playfield and horizontally centered, rounded to the
+    /// left.
+    fn entering(shape_type: ShapeTypes, shape: Shape, size: BoardSize) -> ActivePiece {
+        let left = shape.squares.iter().map(|square| square.x).min().unwrap();
+        let right = shape.squares.iter().map(|square| square.x).max().unwrap();
+        let bottom = shape.squares.iter().map(|square| square.y).min().unwrap();
+        ActivePiece {
+            shape_type,
+            shape,
+            x: (size.width as i32 - (right - left + 1)) / 2 - left,
+            y: size.height as i32 - bottom,
+            rotation: 0,
+            item: None,
+        }
+    }
+
+    pub fn cells(&self) -> Vec<(i32, i32)> {
+        self.shape.cells(self.x, self.y)
+    }
+}
+
+/// Default number of upcoming pieces shown.
+pub const NEXT_PIECES: usize = 5;
+pub const MAX_PREVIEWS: usize = 6;
+
+/// Upcoming pieces, front first. New pieces are always taken from here so the
+/// preview panel shows exactly what will spawn.
+#[derive(Debug, Clone)]
+pub struct PieceQueue {
+    pub pieces: VecDeque<ShapeTypes>,
+    /// Number of pieces shown ahead, endless queues draw that many in
+    /// advance.
+    previews: usize,
+    /// Whether a random piece is drawn for every piece taken. A fixed
+    /// sequence runs out instead.
+    endless: bool,
+}
+
+impl PieceQueue {
+    pub fn new<R: Rng>(rng: &mut R, previews: usize) -> PieceQueue {
+        PieceQueue {
+            pieces: (0..previews).map(|_| rng.gen()).collect(),
+            previews,
+            endless: true,
+        }
+    }
+
+    /// Queue dealing exactly the given pieces.
+    pub fn fixed(pieces: &[ShapeTypes], previews: usize) -> PieceQueue {
+        PieceQueue {
+            pieces: pieces.iter().copied().collect(),
+            previews,
+            endless: false,
+        }
+    }
+
+    /// Pieces shown in the preview, the next one first.
+    pub fn previews(&self) -> impl Iterator<Item = ShapeTypes> + '_ {
+        self.pieces.iter().copied().take(self.previews)
+    }
+
+    /// Shows `previews` pieces ahead from now on. Pieces already drawn stay
+    /// in the queue, so the sequence of a seed doesn't change.
+    pub fn set_previews<R: Rng>(&mut self, previews: usize, rng: &mut R) {
+        self.previews = previews;
+        while self.endless && self.pieces.len() < previews {
+            self.pieces.push_back(rng.gen());
+        }
+    }
+
+    /// Makes `shape_type` the next piece taken, in place of the one drawn
+    /// for it.
+    pub fn set_next(&mut self, shape_type: ShapeTypes) {
+        match self.pieces.front_mut() {
+            Some(next) => *next = shape_type,
+            None => self.pieces.push_back(shape_type),
+        }
+    }
+
+    /// Takes the next piece, `None` once a fixed sequence is used up.
+    pub fn pop<R: Rng>(&mut self, rng: &mut R) -> Option<ShapeTypes> {
+        // A queue shortened by `set_previews` runs down to its new length
+        // first.
+        if self.endless && self.pieces.len() <= self.previews {
+            self.pieces.push_back(rng.gen());
+        }
+        self.pieces.pop_front()
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{
+    board::{Block, Cells, BOARD_HEIGHT, BOARD_WIDTH},
+    piece::ShapeTypes,
+};
+use std::{error::Error, fmt};
+
+/// Preset board with a fixed sequence of pieces. It is solved by clearing
+/// every block before the pieces run out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub name: String,
+    cells: Cells,
+    pieces: Vec<ShapeTypes>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PuzzleError {
+    TooManyRows(usize),
+    /// Row `row`, counted from the top, isn't `BOARD_WIDTH` cells wide.
+    RowWidth {
+        row: usize,
+        width: usize,
+    },
+    UnknownBlock(char),
+    UnknownPiece(char),
+    NoPieces,
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PuzzleError::TooManyRows(rows) => {
+                write!(f, "{} rows, the board has {}", rows, BOARD_HEIGHT)
+            }
+            PuzzleError::RowWidth { row, width } => write!(
+                f,
+                "row {} has {} cells instead of {}",
+                row + 1,
+                width,
+                BOARD_WIDTH
+            ),
+            PuzzleError::UnknownBlock(block) => write!(f, "unknown block {:?}", block),
+            PuzzleError::UnknownPiece(piece) => write!(f, "unknown piece {:?}", piece),
+            PuzzleError::NoPieces => write!(f, "no pieces"),
+        }
+    }
+}
+
+impl Error for PuzzleError {}
+
+impl Puzzle {
+    /// Reads a layout given as its bottom rows from top to bottom, with `.`
+    /// for an empty cell, `#` for garbage and a piece letter for a block of
+    /// that piece. `pieces` are the letters of the pieces in the order they
+    /// are dealt, whitespace is ignored.
+    pub fn parse(name: &str, rows: &[String], pieces: &str) -> Result<Puzzle, PuzzleError> {
+        if rows.len() > BOARD_HEIGHT {
+            return Err(PuzzleError::TooManyRows(rows.len()));
+        }
+
+        let mut cells = vec![vec![None; BOARD_WIDTH]; BOARD_HEIGHT];
+        for (i, row) in rows.iter().enumerate() {
+            let width = row.chars().count();
+            if width != BOARD_WIDTH {
+                return Err(PuzzleError::RowWidth { row: i, width });
+            }
+
+            let y = rows.len() - 1 - i;
+            for (x, c) in row.chars().enumerate() {
+                cells[y][x] = match c {
+                    '.' => None,
+                    '#' => Some(Block::Garbage),
+                    c => match ShapeTypes::from_letter(c) {
+                        Some(shape_type) => Some(Block::Piece(shape_type)),
+                        None => return Err(PuzzleError::UnknownBlock(c)),
+                    },
+                };
+            }
+        }
+
+        let pieces = pieces
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| ShapeTypes::from_letter(c).ok_or(PuzzleError::UnknownPiece(c)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if pieces.is_empty() {
+            return Err(PuzzleError::NoPieces);
+        }
+
+        Ok(Puzzle {
+            name: name.to_string(),
+            cells,
+            pieces,
+        })
+    }
+
+    pub fn cells(&self) -> &Cells {
+        &self.cells
+    }
+
+    /// Pieces in the order they are dealt, never empty.
+    pub fn pieces(&self) -> &[ShapeTypes] {
+        &self.pieces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_rows_are_read_from_the_top() {
+        let rows = ["T.........".to_string(), "##########".to_string()];
+        let puzzle = Puzzle::parse("test", &rows, "I O").unwrap();
+
+        assert_eq!(puzzle.cells()[0], vec![Some(Block::Garbage); BOARD_WIDTH]);
+        assert_eq!(
+            puzzle.cells()[1][0],
+            Some(Block::Piece(ShapeTypes::SquareTop))
+        );
+        assert_eq!(puzzle.pieces(), [ShapeTypes::Line, ShapeTypes::Square]);
+        assert_eq!(
+            Puzzle::parse("test", &rows[..1], "X"),
+            Err(PuzzleError::UnknownPiece('X'))
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use crate::game::{Buttons, Config, Game, Input, Mode};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt, time::Duration};
+
+/// Version written into new replays. Bump it whenever the rules or the
+/// format change in a way that plays old inputs differently.
+pub const REPLAY_VERSION: u32 = 2;
+
+/// Final numbers of a recorded game, to tell whether a playback ended the
+/// same way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub score: u32,
+    pub lines: u32,
+    pub pieces: u32,
+}
+
+impl ReplayResult {
+    pub fn of(game: &Game) -> ReplayResult {
+        ReplayResult {
+            score: game.score,
+            lines: game.level.lines,
+            pieces: game.stats.pieces,
+        }
+    }
+}
+
+/// A game as its seed, settings and the input of every tick, which is
+/// enough to play it again since games are deterministic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub mode: Mode,
+    pub config: Config,
+    /// Buttons applied to the first piece once the countdown was over.
+    pub initial: Buttons,
+    /// Nanoseconds, held and pressed buttons of every tick.
+    pub ticks: Vec<(u64, Buttons, Buttons)>,
+    /// Configs the game switched to while it was played, each with the
+    /// number of ticks played before the switch.
+    #[serde(default)]
+    pub config_changes: Vec<(usize, Config)>,
+    pub result: ReplayResult,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The replay was written by another version of the game.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "replay version {} can't be played, this game plays version {}",
+                version, REPLAY_VERSION
+            ),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl Replay {
+    /// Starts recording a game created with the same arguments.
+    pub fn new(seed: u64, mode: Mode, config: Config) -> Replay {
+        Replay {
+            version: REPLAY_VERSION,
+            seed,
+            mode,
+            config,
+            initial: Buttons::default(),
+            ticks: Vec::new(),
+            config_changes: Vec::new(),
+            result: ReplayResult::default(),
+        }
+    }
+
+    pub fn record_initial(&mut self, buttons: Buttons) {
+        self.initial = self.initial.union(buttons);
+    }
+
+    pub fn record_tick(&mut self, input: Input, delta: Duration) {
+        self.ticks
+            .push((delta.as_nanos() as u64, input.held, input.pressed));
+    }
+
+    /// Records a switch of the game to `config` after the ticks recorded so
+    /// far.
+    pub fn record_config(&mut self, config: Config) {
+        self.config_changes.push((self.ticks.len(), config));
+    }
+
+    pub fn finish(&mut self, game: &Game) {
+        self.result = ReplayResult::of(game);
+    }
+
+    /// Brings a replay of an older version up to date, or refuses one this
+    /// game can't play.
+    pub fn upgrade(self) -> Result<Replay, ReplayError> {
+        match self.version {
+            REPLAY_VERSION => Ok(self),
+            // Version 1 didn't record config changes, its games had none.
+            1 => Ok(Replay {
+                version: REPLAY_VERSION,
+                ..self
+            }),
+            version => Err(ReplayError::UnsupportedVersion(version)),
+        }
+    }
+
+    /// The recorded game before its first tick.
+    pub fn start(&self) -> Game {
+        Game::new(self.seed, self.mode, self.config.clone())
+    }
+
+    /// Input and time of the tick at `index`.
+    pub fn tick(&self, index: usize) -> Option<(Input, Duration)> {
+        let &(nanos, held, pressed) = self.ticks.get(index)?;
+        Some((Input { held, pressed }, Duration::from_nanos(nanos)))
+    }
+
+    /// Switches `game` to the configs recorded after `ticks` ticks.
+    pub fn apply_config_changes(&self, game: &mut Game, ticks: usize) {
+        for (_, config) in self.config_changes.iter().filter(|(at, _)| *at == ticks) {
+            game.set_config(config.clone());
+        }
+    }
+
+    /// Plays the whole replay at once and returns the game at its end.
+    pub fn play(&self) -> Game {
+        let mut game = self.start();
+        self.apply_config_changes(&mut game, 0);
+        game.apply_initial_input(self.initial);
+        for index in 0..self.ticks.len() {
+            let (input, delta) = self.tick(index).unwrap();
+            game.tick(input, delta);
+            self.apply_config_changes(&mut game, index + 1);
+        }
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Button;
+
+    #[test]
+    fn playing_a_replay_ends_like_the_recorded_game() {
+        let mut game = Game::new(3, Mode::Endless, Config::default());
+        let mut replay = Replay::new(3, Mode::Endless, Config::default());
+        let buttons = [Button::MoveLeft, Button::RotateClockwise, Button::HardDrop];
+        for tick in 0..300 {
+            let mut input = Input::default();
+            input.pressed.insert(buttons[tick % buttons.len()]);
+            let delta = Duration::from_millis(16);
+            replay.record_tick(input, delta);
+            game.tick(input, delta);
+        }
+        replay.finish(&game);
+
+        assert!(replay.result.pieces > 0);
+        assert_eq!(ReplayResult::of(&replay.play()), replay.result);
+    }
+
+    #[test]
+    fn config_changes_are_played_back() {
+        let mut game = Game::new(3, Mode::Endless, Config::default());
+        let mut replay = Replay::new(3, Mode::Endless, Config::default());
+        let buttons = [Button::MoveRight, Button::RotateClockwise, Button::HardDrop];
+        for tick in 0..300 {
+            if tick == 100 {
+                // More previews draw pieces ahead, and slower shifts move
+                // pieces less far.
+                let config = Config {
+                    previews: 6,
+                    das: 0.3,
+                    arr: 0.1,
+                    ..Config::default()
+                };
+                replay.record_config(config.clone());
+                game.set_config(config);
+            }
+            let mut input = Input::default();
+            input.held.insert(Button::MoveRight);
+            input.pressed.insert(buttons[tick % buttons.len()]);
+            let delta = Duration::from_millis(16);
+            replay.record_tick(input, delta);
+            game.tick(input, delta);
+        }
+        replay.finish(&game);
+
+        let played = replay.play();
+        assert_eq!(ReplayResult::of(&played), replay.result);
+        assert_eq!(played.board.cells, game.board.cells);
+        assert_eq!(played.queue.pieces, game.queue.pieces);
+    }
+
+    #[test]
+    fn version_1_replays_are_upgraded() {
+        let mut replay = Replay::new(3, Mode::Endless, Config::default());
+        replay.version = 1;
+
+        assert_eq!(replay.upgrade().unwrap().version, REPLAY_VERSION);
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let mut replay = Replay::new(3, Mode::Endless, Config::default());
+        replay.version = REPLAY_VERSION + 1;
+
+        assert_eq!(
+            replay.upgrade().unwrap_err(),
+            ReplayError::UnsupportedVersion(REPLAY_VERSION + 1)
+        );
+    }
+}
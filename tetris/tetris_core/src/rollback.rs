@@ -0,0 +1,242 @@
+use crate::{
+    game::{Event, Game, Input},
+    item::Item,
+};
+use std::{collections::VecDeque, time::Duration};
+
+/// Length of a frame of a rollback session. Both sides advance their games
+/// in frames of this length, so an input is tied to a frame number.
+pub const ROLLBACK_FRAME: Duration = Duration::from_micros(16_667);
+/// Frames the local game may run ahead of the last input received from the
+/// opponent. Past that it waits, since every frame ahead may have to be
+/// simulated again.
+pub const MAX_ROLLBACK_FRAMES: u32 = 30;
+
+/// Versus match simulated on each side from the inputs of both players: the
+/// local game and a copy of the opponent's, index 0 and 1. The opponent's
+/// input is predicted until it arrives, and one that differs from its
+/// prediction rewinds both games to the frame it belongs to and simulates
+/// the frames since again.
+#[derive(Debug, Clone)]
+pub struct Rollback {
+    pub games: [Game; 2],
+    /// Next frame to simulate.
+    frame: u32,
+    local_inputs: Vec<Input>,
+    /// Inputs of the opponent received so far, for the frames from 0 on.
+    remote_inputs: Vec<Input>,
+    /// States before the frames that were simulated on a predicted input,
+    /// with that prediction.
+    snapshots: VecDeque<([Game; 2], Input)>,
+    /// First frame whose prediction turned out wrong.
+    mispredicted: Option<u32>,
+}
+
+impl Rollback {
+    pub fn new(local: Game, remote: Game) -> Rollback {
+        Rollback {
+            games: [local, remote],
+            frame: 0,
+            local_inputs: Vec::new(),
+            remote_inputs: Vec::new(),
+            snapshots: VecDeque::new(),
+            mispredicted: None,
+        }
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Whether every simulated frame used the opponent's actual input, so
+    /// the games won't change anymore.
+    pub fn is_confirmed(&self) -> bool {
+        self.remote_inputs.len() as u32 >= self.frame && self.mispredicted.is_none()
+    }
+
+    /// Whether the local game has to wait for the opponent's input before
+    /// it can advance.
+    pub fn is_stalled(&self) -> bool {
+        self.frame >= self.remote_inputs.len() as u32 + MAX_ROLLBACK_FRAMES
+    }
+
+    /// Records the opponent's input of their next frame. Inputs arrive in
+    /// order, one per frame.
+    pub fn receive(&mut self, input: Input) {
+        let frame = self.remote_inputs.len() as u32;
+        self.remote_inputs.push(input);
+        if frame >= self.frame {
+            return;
+        }
+
+        let (_, predicted) = &self.snapshots[(frame - self.first_snapshot()) as usize];
+        if *predicted != input && self.mispredicted.is_none() {
+            self.mispredicted = Some(frame);
+        }
+    }
+
+    /// Simulates the next frame with the local input after rewinding to a
+    /// mispredicted frame, if any. Returns the events of both games in this
+    /// frame, `None` while stalled. Events of the frames simulated again
+    /// aren't returned, they were already once.
+    pub fn advance(&mut self, input: Input) -> Option<[Vec<Event>; 2]> {
+        if self.is_stalled() {
+            return None;
+        }
+
+        self.rewind();
+        self.local_inputs.push(input);
+        let events = self.simulate(self.frame);
+        self.frame += 1;
+        self.forget_confirmed();
+        Some(events)
+    }
+
+    /// Goes back to the first mispredicted frame and simulates the frames
+    /// up to the current one again with the inputs known now.
+    fn rewind(&mut self) {
+        let frame = match self.mispredicted.take() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        // The frames before the mispredicted one have all been received.
+        let index = (frame - self.first_snapshot()) as usize;
+        let (games, _) = self.snapshots.remove(index).unwrap();
+        self.snapshots.clear();
+        self.games = games;
+        for frame in frame..self.frame {
+            self.simulate(frame);
+        }
+    }
+
+    /// Ticks both games for a frame and lets each attack the other, keeping
+    /// a snapshot when the opponent's input is only predicted.
+    fn simulate(&mut self, frame: u32) -> [Vec<Event>; 2] {
+        let remote = match self.remote_inputs.get(frame as usize) {
+            Some(&input) => input,
+            None => {
+                let predicted = self.predict();
+                self.snapshots.push_back((self.games.clone(), predicted));
+                predicted
+            }
+        };
+        let inputs = [self.local_inputs[frame as usize], remote];
+
+        let mut events = [Vec::new(), Vec::new()];
+        for ((game, input), events) in self.games.iter_mut().zip(inputs).zip(&mut events) {
+            *events = game.tick(input, ROLLBACK_FRAME);
+        }
+        exchange_attacks(&mut self.games, &events);
+        events
+    }
+
+    /// The opponent keeps holding what they held last, without pressing
+    /// anything new.
+    fn predict(&self) -> Input {
+        Input {
+            held: self
+                .remote_inputs
+                .last()
+                .map(|input| input.held)
+                .unwrap_or_default(),
+            pressed: Default::default(),
+        }
+    }
+
+    /// Frame of the oldest snapshot: snapshots are kept from the first
+    /// frame without the opponent's input on.
+    fn first_snapshot(&self) -> u32 {
+        self.frame - self.snapshots.len() as u32
+    }
+
+    /// Drops the snapshots of frames that can't be rewound to anymore.
+    fn forget_confirmed(&mut self) {
+        let confirmed = self.remote_inputs.len() as u32;
+        while !self.snapshots.is_empty() && self.first_snapshot() < confirmed {
+            self.snapshots.pop_front();
+        }
+    }
+}
+
+/// Sends the garbage and queue shuffles of a tick of two versus games to
+/// the other game.
+pub(crate) fn exchange_attacks(games: &mut [Game; 2], events: &[Vec<Event>; 2]) {
+    for (player, events) in events.iter().enumerate() {
+        for event in events {
+            match *event {
+                Event::GarbageSent { rows } => games[1 - player].receive_garbage(rows),
+                Event::ItemTriggered {
+                    item: Item::ShuffleQueue,
+                } => games[1 - player].shuffle_queue(),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Button, Buttons, Config, Mode};
+
+    /// Inputs of a player hard dropping every 10 frames and moving left in
+    /// between now and then.
+    fn input(frame: u32, seed: u32) -> Input {
+        let mut pressed = Buttons::default();
+        if frame % 10 == 9 {
+            pressed.insert(Button::HardDrop);
+        } else if (frame + seed).is_multiple_of(7) {
+            pressed.insert(Button::MoveLeft);
+        }
+        Input {
+            held: Buttons::default(),
+            pressed,
+        }
+    }
+
+    fn session() -> Rollback {
+        let game = || Game::new(5, Mode::Endless, Config::default());
+        Rollback::new(game(), game())
+    }
+
+    #[test]
+    fn late_inputs_end_up_where_timely_ones_would() {
+        let mut timely = session();
+        let mut late = session();
+        for frame in 0..120 {
+            timely.receive(input(frame, 3));
+            timely.advance(input(frame, 0)).unwrap();
+
+            // The opponent's inputs arrive 5 frames late.
+            if frame >= 5 {
+                late.receive(input(frame - 5, 3));
+            }
+            late.advance(input(frame, 0)).unwrap();
+        }
+        for frame in 115..=120 {
+            late.receive(input(frame, 3));
+        }
+        late.advance(input(120, 0)).unwrap();
+        timely.receive(input(120, 3));
+        timely.advance(input(120, 0)).unwrap();
+
+        assert!(late.is_confirmed());
+        for (late, timely) in late.games.iter().zip(&timely.games) {
+            assert_eq!(late.board.cells, timely.board.cells);
+            assert_eq!(late.piece.cells(), timely.piece.cells());
+        }
+    }
+
+    #[test]
+    fn waits_for_an_opponent_too_far_behind() {
+        let mut session = session();
+        for frame in 0..MAX_ROLLBACK_FRAMES {
+            assert!(session.advance(input(frame, 0)).is_some());
+        }
+        assert!(session.advance(Input::default()).is_none());
+
+        session.receive(Input::default());
+        assert!(session.advance(Input::default()).is_some());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{
+    board::Board,
+    piece::{ActivePiece, Shape, ShapeTypes, Square},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How pieces turn. Rotation states are numbered 0 to 3, 0 being the spawn
+/// state and every further state a quarter turn clockwise from the previous.
+pub trait RotationSystem {
+    /// Squares of the piece in the given rotation state, as offsets from its
+    /// pivot.
+    fn squares(&self, shape_type: ShapeTypes, state: u8) -> Vec<Square>;
+
+    /// Offsets tried in order when turning from state `from` to `to`, the
+    /// piece moves by the first one it fits at. Without kicks a piece only
+    /// turns in place.
+    fn kicks(&self, _shape_type: ShapeTypes, _from: u8, _to: u8) -> Vec<(i32, i32)> {
+        vec![(0, 0)]
+    }
+}
+
+/// Rotation systems to choose from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rotation {
+    /// Super Rotation System of the guideline, with wall kicks.
+    Srs,
+    /// Right-handed Nintendo rotation of the NES game: pieces spawn flat
+    /// side up, I, S and Z only have two states and nothing kicks.
+    Classic,
+    /// Turns the spawn shapes around their pivot square, without kicks.
+    Pivot,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 3] = [Rotation::Srs, Rotation::Classic, Rotation::Pivot];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rotation::Srs => "SRS",
+            Rotation::Classic => "Classic",
+            Rotation::Pivot => "Pivot",
+        }
+    }
+
+    pub fn system(self) -> &'static dyn RotationSystem {
+        match self {
+            Rotation::Srs => &Srs,
+            Rotation::Classic => &Classic,
+            Rotation::Pivot => &Pivot,
+        }
+    }
+
+    /// System `step` places after this one in `ALL`, wrapping around.
+    pub fn cycle(self, step: isize) -> Rotation {
+        let index = Rotation::ALL
+            .iter()
+            .position(|&rotation| rotation == self)
+            .unwrap() as isize;
+        let len = Rotation::ALL.len() as isize;
+        Rotation::ALL[(index + step).rem_euclid(len) as usize]
+    }
+
+    /// `piece` turned clockwise `turns` quarter turns, moved by the first
+    /// kick it fits on `board` at, or `None` when it doesn't fit anywhere.
+    /// Given `kicks` replace those of the rotation system.
+    pub fn turn(
+        self,
+        board: &Board,
+        piece: &ActivePiece,
+        turns: u8,
+        kicks: Option<&KickTable>,
+    ) -> Option<ActivePiece> {
+        let system = self.system();
+        let to = (piece.rotation + turns) % 4;
+        let shape = Shape {
+            squares: system.squares(piece.shape_type, to),
+        };
+        let offsets = match kicks {
+            Some(kicks) => kicks.kicks(piece.shape_type, piece.rotation, to),
+            None => system.kicks(piece.shape_type, piece.rotation, to),
+        };
+        offsets
+            .into_iter()
+            .find(|&(x, y)| board.fits(&shape, piece.x + x, piece.y + y))
+            .map(|(x, y)| ActivePiece {
+                shape_type: piece.shape_type,
+                shape,
+                x: piece.x + x,
+                y: piece.y + y,
+                rotation: to,
+                item: piece.item,
+            })
+    }
+}
+
+/// Wall kicks read from a data file, to try other rotation behaviors
+/// without recompiling. Pieces are keyed by their letter and turns by the
+/// rotation states they go between, `0`, `R`, `2` and `L`, as in `"0>R"`.
+/// Turns that aren't listed only turn in place.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KickTable(pub BTreeMap<String, BTreeMap<String, Vec<(i32, i32)>>>);
+
+const STATE_NAMES: [&str; 4] = ["0", "R", "2", "L"];
+
+impl KickTable {
+    /// Offsets tried in order when turning from state `from` to `to`.
+    pub fn kicks(&self, shape_type: ShapeTypes, from: u8, to: u8) -> Vec<(i32, i32)> {
+        let turn = format!(
+            "{}>{}",
+            STATE_NAMES[from as usize % 4],
+            STATE_NAMES[to as usize % 4]
+        );
+        self.0
+            .get(&shape_type.letter().to_string())
+            .and_then(|turns| turns.get(&turn))
+            .cloned()
+            .unwrap_or_else(|| vec![(0, 0)])
+    }
+}
+
+fn squares(offsets: [(i32, i32); 4]) -> Vec<Square> {
+    offsets.iter().map(|&(x, y)| Square::new(x, y)).collect()
+}
+
+/// Turns `squares` clockwise around the pivot `turns` times.
+fn turned(squares: Vec<Square>, turns: u8) -> Vec<Square> {
+    (0..turns % 4).fold(squares, |squares, _| {
+        squares
+            .into_iter()
+            .map(|square| Square::new(square.y, -square.x))
+            .collect()
+    })
+}
+
+pub struct Srs;
+
+/// Kicks of the J, L, S, T and Z pieces from the spawn and reverse states
+/// to the right and left ones. The opposite turns use the same offsets
+/// negated.
+const JLSTZ_KICKS: [[(i32, i32); 5]; 4] = [
+    // 0 -> R
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    // 2 -> R
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    // 2 -> L
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    // 0 -> L
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+];
+
+/// Kicks of the I piece, in the order of `JLSTZ_KICKS`.
+const I_KICKS: [[(i32, i32); 5]; 4] = [
+    // 0 -> R
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    // 2 -> R
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    // 2 -> L
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    // 0 -> L
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+];
+
+impl RotationSystem for Srs {
+    fn squares(&self, shape_type: ShapeTypes, state: u8) -> Vec<Square> {
+        match shape_type {
+            ShapeTypes::Square => squares([(0, 0), (1, 0), (0, -1), (1, -1)]),
+            // The I piece turns around the center of its 4x4 box, between
+            // cells, so its states are listed.
+            ShapeTypes::Line => squares(match state % 4 {
+                0 => [(-1, 0), (0, 0), (1, 0), (2, 0)],
+                1 => [(1, 1), (1, 0), (1, -1), (1, -2)],
+                2 => [(-1, -1), (0, -1), (1, -1), (2, -1)],
+                _ => [(0, 1), (0, 0), (0, -1), (0, -2)],
+            }),
+            ShapeTypes::Zigzag => turned(squares([(-1, 1), (0, 1), (0, 0), (1, 0)]), state),
+            ShapeTypes::ReverseZigzag => turned(squares([(0, 1), (1, 1), (-1, 0), (0, 0)]), state),
+            _ => turned(shape_type.build().squares, state),
+        }
+    }
+
+    fn kicks(&self, shape_type: ShapeTypes, from: u8, to: u8) -> Vec<(i32, i32)> {
+        let table = match shape_type {
+            ShapeTypes::Square => return vec![(0, 0)],
+            ShapeTypes::Line => &I_KICKS,
+            _ => &JLSTZ_KICKS,
+        };
+        // Turns towards R or L are listed, turns away from them negated.
+        let (row, sign) = match (from % 4, to % 4) {
+            (0, 1) => (0, 1),
+            (1, 0) => (0, -1),
+            (2, 1) => (1, 1),
+            (1, 2) => (1, -1),
+            (2, 3) => (2, 1),
+            (3, 2) => (2, -1),
+            (0, 3) => (3, 1),
+            (3, 0) => (3, -1),
+            _ => return vec![(0, 0)],
+        };
+        table[row]
+            .iter()
+            .map(|&(x, y)| (x * sign, y * sign))
+            .collect()
+    }
+}
+
+pub struct Classic;
+
+impl RotationSystem for Classic {
+    fn squares(&self, shape_type: ShapeTypes, state: u8) -> Vec<Square> {
+        match shape_type {
+            ShapeTypes::Square => shape_type.build().squares,
+            ShapeTypes::Line => squares(match state % 2 {
+                0 => [(-2, 0), (-1, 0), (0, 0), (1, 0)],
+                _ => [(0, 2), (0, 1), (0, 0), (0, -1)],
+            }),
+            ShapeTypes::Zigzag => squares(match state % 2 {
+                0 => [(-1, 0), (0, 0), (0, -1), (1, -1)],
+                _ => [(1, 1), (1, 0), (0, 0), (0, -1)],
+            }),
+            ShapeTypes::ReverseZigzag => squares(match state % 2 {
+                0 => [(0, 0), (1, 0), (-1, -1), (0, -1)],
+                _ => [(0, 1), (0, 0), (1, 0), (1, -1)],
+            }),
+            ShapeTypes::SquareTop => turned(squares([(-1, 0), (0, 0), (1, 0), (0, -1)]), state),
+            ShapeTypes::LShape => turned(squares([(-1, 0), (0, 0), (1, 0), (-1, -1)]), state),
+            ShapeTypes::JShape => turned(squares([(-1, 0), (0, 0), (1, 0), (1, -1)]), state),
+        }
+    }
+}
+
+pub struct Pivot;
+
+impl RotationSystem for Pivot {
+    fn squares(&self, shape_type: ShapeTypes, state: u8) -> Vec<Square> {
+        match shape_type {
+            ShapeTypes::Square => shape_type.build().squares,
+            _ => turned(shape_type.build().squares, state),
+        }
+    }
+}
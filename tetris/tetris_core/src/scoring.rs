@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+const HARD_DROP_POINTS: u32 = 2;
+const SOFT_DROP_POINTS: u32 = 1;
+/// Points per lock in a combo after the first, times the level.
+pub(crate) const COMBO_POINTS: u32 = 50;
+/// Points per cleared line of the simple scoring.
+const LINE_POINTS: u32 = 100;
+
+/// A locked piece as far as scoring is concerned. `combo` and
+/// `back_to_back` include this lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clear {
+    pub lines: usize,
+    pub t_spin: bool,
+    pub level: u32,
+    pub combo: u32,
+    pub back_to_back: u32,
+}
+
+/// How locks and drops are turned into points.
+pub trait ScoringPolicy {
+    /// Points of a locked piece, whether it cleared lines or not.
+    fn clear(&self, clear: Clear) -> u32;
+
+    /// Points of a soft drop of one row.
+    fn soft_drop(&self) -> u32 {
+        0
+    }
+
+    /// Points of a hard drop over `rows` rows.
+    fn hard_drop(&self, _rows: u32) -> u32 {
+        0
+    }
+}
+
+/// Scoring tables to choose from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scoring {
+    /// Points per cleared line, nothing else scores.
+    Simple,
+    /// Level multiplied line clears and T-spins, combo and back-to-back
+    /// bonuses and drop points.
+    Guideline,
+}
+
+impl Scoring {
+    pub const ALL: [Scoring; 2] = [Scoring::Simple, Scoring::Guideline];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scoring::Simple => "Simple",
+            Scoring::Guideline => "Guideline",
+        }
+    }
+
+    pub fn policy(self) -> &'static dyn ScoringPolicy {
+        match self {
+            Scoring::Simple => &Simple,
+            Scoring::Guideline => &Guideline,
+        }
+    }
+
+    /// Scoring `step` places after this one in `ALL`, wrapping around.
+    pub fn cycle(self, step: isize) -> Scoring {
+        let index = Scoring::ALL
+            .iter()
+            .position(|&scoring| scoring == self)
+            .unwrap() as isize;
+        let len = Scoring::ALL.len() as isize;
+        Scoring::ALL[(index + step).rem_euclid(len) as usize]
+    }
+}
+
+pub struct Simple;
+
+impl ScoringPolicy for Simple {
+    fn clear(&self, clear: Clear) -> u32 {
+        clear.lines as u32 * LINE_POINTS
+    }
+}
+
+pub struct Guideline;
+
+impl ScoringPolicy for Guideline {
+    fn clear(&self, clear: Clear) -> u32 {
+        if clear.lines == 0 {
+            return clear_points(0, clear.t_spin) * clear.level;
+        }
+
+        let mut points = clear_points(clear.lines, clear.t_spin);
+        if clear.back_to_back > 1 {
+            points += points / 2;
+        }
+        points += COMBO_POINTS * clear.combo.saturating_sub(1);
+        points * clear.level
+    }
+
+    fn soft_drop(&self) -> u32 {
+        SOFT_DROP_POINTS
+    }
+
+    fn hard_drop(&self, rows: u32) -> u32 {
+        rows * HARD_DROP_POINTS
+    }
+}
+
+/// Points of a line clear on level 1.
+fn clear_points(lines: usize, t_spin: bool) -> u32 {
+    match (lines, t_spin) {
+        (0, false) => 0,
+        (1, false) => 100,
+        (2, false) => 300,
+        (3, false) => 500,
+        (_, false) => 800,
+        (0, true) => 400,
+        (1, true) => 800,
+        (2, true) => 1200,
+        (_, true) => 1600,
+    }
+}
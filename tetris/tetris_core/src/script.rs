@@ -0,0 +1,215 @@
+use crate::game::{Button, Event, Game, Input};
+use std::{error::Error, fmt, time::Duration};
+
+/// Waits are played in frames of this length, like a frontend running at
+/// 60 frames a second.
+const WAIT_FRAME: Duration = Duration::from_micros(16_667);
+
+/// Step of a script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Presses a button and releases it, without any time passing.
+    Press(Button),
+    /// Lets time pass without pressing anything, so gravity and the lock
+    /// delay run.
+    Wait(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    UnknownStep(String),
+    /// A repeat count or a wait that isn't a number.
+    InvalidNumber(String),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptError::UnknownStep(step) => write!(f, "unknown step {:?}", step),
+            ScriptError::InvalidNumber(step) => write!(f, "invalid number in {:?}", step),
+        }
+    }
+}
+
+impl Error for ScriptError {}
+
+/// Inputs played on a game without a frontend, for tests and tools. Paired
+/// with `Game::scripted` the whole game is set up from text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub steps: Vec<Step>,
+}
+
+impl Script {
+    /// Reads whitespace separated steps: `left`, `right`, `soft`, `drop`,
+    /// `cw`, `ccw`, `180` and `hold` press a button, `wait:<ms>` waits. A
+    /// step followed by `*<count>` is repeated, like `right*4`.
+    pub fn parse(text: &str) -> Result<Script, ScriptError> {
+        let mut steps = Vec::new();
+        for word in text.split_whitespace() {
+            let (step, count) = match word.split_once('*') {
+                Some((step, count)) => (
+                    step,
+                    count
+                        .parse()
+                        .map_err(|_| ScriptError::InvalidNumber(word.to_string()))?,
+                ),
+                None => (word, 1),
+            };
+            let step = match step {
+                "left" => Step::Press(Button::MoveLeft),
+                "right" => Step::Press(Button::MoveRight),
+                "soft" => Step::Press(Button::SoftDrop),
+                "drop" => Step::Press(Button::HardDrop),
+                "cw" => Step::Press(Button::RotateClockwise),
+                "ccw" => Step::Press(Button::RotateCounterClockwise),
+                "180" => Step::Press(Button::Rotate180),
+                "hold" => Step::Press(Button::Hold),
+                _ => match step.strip_prefix("wait:") {
+                    Some(millis) => Step::Wait(Duration::from_millis(
+                        millis
+                            .parse()
+                            .map_err(|_| ScriptError::InvalidNumber(word.to_string()))?,
+                    )),
+                    None => return Err(ScriptError::UnknownStep(word.to_string())),
+                },
+            };
+            steps.extend(std::iter::repeat_n(step, count));
+        }
+        Ok(Script { steps })
+    }
+
+    /// Plays every step on `game`, returning the events of all of them.
+    pub fn play(&self, game: &mut Game) -> Vec<Event> {
+        let mut events = Vec::new();
+        for &step in &self.steps {
+            match step {
+                Step::Press(button) => {
+                    let mut input = Input::default();
+                    input.pressed.insert(button);
+                    events.extend(game.tick(input, Duration::ZERO));
+                }
+                Step::Wait(mut left) => {
+                    while !left.is_zero() {
+                        let frame = left.min(WAIT_FRAME);
+                        events.extend(game.tick(Input::default(), frame));
+                        left -= frame;
+                    }
+                }
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::{Config, GameOverCause, Mode},
+        puzzle::Puzzle,
+        rotation::Rotation,
+        BOARD_HEIGHT,
+    };
+
+    /// Endless game on a board of `rows`, dealing `pieces` first, after
+    /// playing `script` on it.
+    fn play(rows: &[&str], pieces: &str, script: &str, config: Config) -> (Game, Vec<Event>) {
+        let rows: Vec<_> = rows.iter().map(|row| row.to_string()).collect();
+        let puzzle = Puzzle::parse("script", &rows, pieces).unwrap();
+        let mut game = Game::scripted(3, Mode::Endless, &puzzle, config);
+        let events = Script::parse(script).unwrap().play(&mut game);
+        (game, events)
+    }
+
+    fn cleared_rows(events: &[Event]) -> Vec<usize> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::LinesCleared { rows, .. } => Some(rows.len()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_repeats_and_waits() {
+        let script = Script::parse("right*2 wait:100 drop").unwrap();
+        assert_eq!(
+            script.steps,
+            vec![
+                Step::Press(Button::MoveRight),
+                Step::Press(Button::MoveRight),
+                Step::Wait(Duration::from_millis(100)),
+                Step::Press(Button::HardDrop),
+            ]
+        );
+        assert_eq!(
+            Script::parse("jump"),
+            Err(ScriptError::UnknownStep("jump".to_string()))
+        );
+        assert!(Script::parse("left*many").is_err());
+    }
+
+    #[test]
+    fn an_upright_i_kicks_off_the_left_wall_with_srs_only() {
+        let script = "cw left*9 ccw";
+        let (srs, _) = play(&[], "I", script, Config::default());
+        assert_eq!(srs.piece.rotation, 0);
+        assert!(srs.piece.cells().iter().all(|&(x, _)| x >= 0));
+
+        let classic = Config {
+            rotation: Rotation::Classic,
+            ..Config::default()
+        };
+        let (classic, _) = play(&[], "I", script, classic);
+        assert_ne!(classic.piece.rotation, 0);
+    }
+
+    #[test]
+    fn rotating_against_the_stack_never_overlaps_it() {
+        let rows = ["#.........", "#.........", "#........#", "#........#"];
+        let (game, _) = play(
+            &rows,
+            "T",
+            "left*9 soft*20 cw ccw 180 cw",
+            Config::default(),
+        );
+        for (x, y) in game.piece.cells() {
+            assert!(game.board.cells[y as usize][x as usize].is_none());
+        }
+    }
+
+    #[test]
+    fn an_i_in_the_well_clears_a_tetris() {
+        let rows = ["#########."; 4];
+        let (game, events) = play(&rows, "I", "cw right*9 drop", Config::default());
+        assert_eq!(cleared_rows(&events), vec![4]);
+        assert!(game.board.cells.iter().flatten().all(Option::is_none));
+        assert!(game.score >= 800);
+    }
+
+    #[test]
+    fn a_piece_left_alone_falls_and_locks_on_the_floor() {
+        let (game, events) = play(&[], "O", "wait:8000", Config::default());
+        let first_lock = events.iter().find_map(|event| match event {
+            Event::PieceLocked { cells, .. } => Some(cells.clone()),
+            _ => None,
+        });
+        assert!(first_lock.unwrap().iter().all(|&(_, y)| y <= 1));
+        assert_eq!(game.board.cells[0].iter().flatten().count(), 2);
+        assert!(!game.over);
+    }
+
+    #[test]
+    fn stacking_in_the_middle_tops_out() {
+        let (game, _) = play(&[], "OOOOOOOOOOOO", "drop*12", Config::default());
+        assert!(game.over);
+        // The spawn rows stay clear above the stack, so the last O locks
+        // out before one could spawn into it.
+        assert_eq!(game.game_over_cause(), Some(GameOverCause::LockOut));
+        assert!(game.board.cells[BOARD_HEIGHT - 1]
+            .iter()
+            .any(Option::is_some));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::game::Mode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Seconds between gravity steps on level 1 of the classic curve.
+const BASE_GRAVITY_INTERVAL: f64 = 0.3;
+/// Factor applied to the classic gravity interval on every level up.
+const GRAVITY_SPEEDUP: f64 = 0.85;
+const MIN_GRAVITY_INTERVAL: f64 = 0.02;
+const CLASSIC_LEVELS: u32 = 18;
+const GUIDELINE_LEVELS: u32 = 20;
+/// Seconds a piece rests on the stack before it locks.
+const LOCK_DELAY: f64 = 0.5;
+
+/// Gravity and lock delay by level, in seconds, the first entries for level
+/// 1. Levels past the end of a list keep its last entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeedCurve {
+    /// Time between gravity steps.
+    pub gravity: Vec<f64>,
+    /// Time a piece rests on the stack before it locks.
+    pub lock_delay: Vec<f64>,
+}
+
+impl SpeedCurve {
+    /// Speeds up by a fixed factor every level down to 20 milliseconds.
+    pub fn classic() -> SpeedCurve {
+        SpeedCurve {
+            gravity: (0..CLASSIC_LEVELS)
+                .map(|level| {
+                    rounded(
+                        (BASE_GRAVITY_INTERVAL * GRAVITY_SPEEDUP.powi(level as i32))
+                            .max(MIN_GRAVITY_INTERVAL),
+                    )
+                })
+                .collect(),
+            lock_delay: vec![LOCK_DELAY],
+        }
+    }
+
+    /// The guideline curve, one second on level 1 and under a frame from
+    /// level 14 on.
+    pub fn guideline() -> SpeedCurve {
+        SpeedCurve {
+            gravity: (0..GUIDELINE_LEVELS)
+                .map(|level| {
+                    let level = level as f64;
+                    rounded((0.8 - level * 0.007).powf(level))
+                })
+                .collect(),
+            lock_delay: vec![LOCK_DELAY],
+        }
+    }
+
+    pub fn gravity_interval(&self, level: u32) -> Duration {
+        at_level(&self.gravity, level)
+    }
+
+    pub fn lock_delay(&self, level: u32) -> Duration {
+        at_level(&self.lock_delay, level)
+    }
+}
+
+/// Seconds rounded to a tenth of a millisecond, which keeps the written
+/// file readable.
+fn rounded(seconds: f64) -> f64 {
+    (seconds * 10_000.).round() / 10_000.
+}
+
+fn at_level(seconds: &[f64], level: u32) -> Duration {
+    let index = (level.max(1) as usize - 1).min(seconds.len().saturating_sub(1));
+    Duration::from_secs_f64(seconds.get(index).copied().unwrap_or(0.).max(0.))
+}
+
+/// Speed curves of the modes, read from a data file. Missing entries keep
+/// their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeedCurves {
+    /// Curve of the modes without their own.
+    pub default: SpeedCurve,
+    pub marathon: SpeedCurve,
+    pub sprint: SpeedCurve,
+    pub ultra: SpeedCurve,
+}
+
+impl Default for SpeedCurves {
+    fn default() -> SpeedCurves {
+        SpeedCurves {
+            default: SpeedCurve::classic(),
+            marathon: SpeedCurve::guideline(),
+            sprint: SpeedCurve::classic(),
+            ultra: SpeedCurve::classic(),
+        }
+    }
+}
+
+impl SpeedCurves {
+    /// The curves with the same lock delay on every level.
+    pub fn with_lock_delay(mut self, seconds: f64) -> SpeedCurves {
+        for curve in [
+            &mut self.default,
+            &mut self.marathon,
+            &mut self.sprint,
+            &mut self.ultra,
+        ] {
+            curve.lock_delay = vec![seconds];
+        }
+        self
+    }
+
+    /// The curves with gravity `multiplier` times as fast on every level.
+    pub fn with_gravity_multiplier(mut self, multiplier: f64) -> SpeedCurves {
+        for curve in [
+            &mut self.default,
+            &mut self.marathon,
+            &mut self.sprint,
+            &mut self.ultra,
+        ] {
+            for interval in &mut curve.gravity {
+                *interval /= multiplier;
+            }
+        }
+        self
+    }
+
+    pub fn for_mode(&self, mode: Mode) -> &SpeedCurve {
+        match mode {
+            Mode::Marathon { .. } => &self.marathon,
+            Mode::Sprint => &self.sprint,
+            Mode::Ultra => &self.ultra,
+            _ => &self.default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_past_the_end_keep_the_last_entry() {
+        let curve = SpeedCurve::guideline();
+
+        assert_eq!(curve.gravity_interval(1), Duration::from_secs(1));
+        assert!(curve.gravity_interval(14) < Duration::from_secs_f64(1. / 60.));
+        assert_eq!(curve.gravity_interval(30), curve.gravity_interval(20));
+        assert_eq!(curve.lock_delay(30), Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::piece::ShapeTypes;
+use std::time::Duration;
+
+/// Counters of a game, for statistics shown while playing and on the results.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Pieces locked into the board.
+    pub pieces: u32,
+    /// Times a piece was put aside with the hold button.
+    pub holds: u32,
+    usage: [u32; ShapeTypes::ALL.len()],
+}
+
+impl Stats {
+    pub fn record_piece(&mut self, shape_type: ShapeTypes) {
+        self.pieces += 1;
+        self.usage[shape_type as usize] += 1;
+    }
+
+    /// Pieces of the given type locked into the board.
+    pub fn usage(&self, shape_type: ShapeTypes) -> u32 {
+        self.usage[shape_type as usize]
+    }
+
+    pub fn pieces_per_second(&self, elapsed: Duration) -> f32 {
+        rate(self.pieces, elapsed.as_secs_f32())
+    }
+}
+
+/// Lines cleared per minute of play.
+pub fn lines_per_minute(lines: u32, elapsed: Duration) -> f32 {
+    rate(lines, elapsed.as_secs_f32() / 60.)
+}
+
+fn rate(count: u32, time: f32) -> f32 {
+    if time > 0. {
+        count as f32 / time
+    } else {
+        0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_pieces_by_type() {
+        let mut stats = Stats::default();
+        stats.record_piece(ShapeTypes::Line);
+        stats.record_piece(ShapeTypes::Line);
+        stats.record_piece(ShapeTypes::Square);
+
+        assert_eq!(stats.pieces, 3);
+        assert_eq!(stats.usage(ShapeTypes::Line), 2);
+        assert_eq!(stats.usage(ShapeTypes::JShape), 0);
+        assert_eq!(stats.pieces_per_second(Duration::from_secs(2)), 1.5);
+        assert_eq!(stats.pieces_per_second(Duration::ZERO), 0.);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    board::{Block, Board},
+    game::{Button, Event, Input},
+};
+
+/// Columns left open in the bottom row the line clear lesson starts on, as
+/// wide as a flat I piece.
+const GAP_WIDTH: usize = 4;
+
+/// Lessons of the tutorial mode, in order. Each waits for the player to do
+/// what its prompt asks before the next one starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TutorialStep {
+    Move,
+    Rotate,
+    SoftDrop,
+    HardDrop,
+    Hold,
+    ClearLine,
+}
+
+impl TutorialStep {
+    pub const ALL: [TutorialStep; 6] = [
+        TutorialStep::Move,
+        TutorialStep::Rotate,
+        TutorialStep::SoftDrop,
+        TutorialStep::HardDrop,
+        TutorialStep::Hold,
+        TutorialStep::ClearLine,
+    ];
+
+    pub fn prompt(self) -> &'static str {
+        match self {
+            TutorialStep::Move => "Move the piece left or right",
+            TutorialStep::Rotate => "Rotate the piece",
+            TutorialStep::SoftDrop => "Soft drop the piece to speed up its fall",
+            TutorialStep::HardDrop => "Hard drop the piece to lock it at once",
+            TutorialStep::Hold => "Hold the piece to put it aside for later",
+            TutorialStep::ClearLine => "Fill the gap in the bottom row to clear it",
+        }
+    }
+
+    /// Buttons the lesson is about, for frontends to show their keys.
+    pub fn buttons(self) -> &'static [Button] {
+        match self {
+            TutorialStep::Move => &[Button::MoveLeft, Button::MoveRight],
+            TutorialStep::Rotate => &[
+                Button::RotateClockwise,
+                Button::RotateCounterClockwise,
+                Button::Rotate180,
+            ],
+            TutorialStep::SoftDrop => &[Button::SoftDrop],
+            TutorialStep::HardDrop => &[Button::HardDrop],
+            TutorialStep::Hold => &[Button::Hold],
+            TutorialStep::ClearLine => &[],
+        }
+    }
+
+    /// Position of the lesson, from 0.
+    pub fn index(self) -> usize {
+        TutorialStep::ALL
+            .iter()
+            .position(|&step| step == self)
+            .unwrap()
+    }
+
+    /// Lesson after this one, `None` after the last.
+    pub fn next(self) -> Option<TutorialStep> {
+        TutorialStep::ALL.get(self.index() + 1).copied()
+    }
+
+    /// Whether the player did what the lesson asks during a tick with this
+    /// input and these events.
+    pub fn is_done(self, input: Input, events: &[Event]) -> bool {
+        match self {
+            TutorialStep::HardDrop => {
+                input.pressed.contains(Button::HardDrop)
+                    && events
+                        .iter()
+                        .any(|event| matches!(event, Event::PieceLocked { .. }))
+            }
+            TutorialStep::Hold => {
+                input.pressed.contains(Button::Hold)
+                    && events
+                        .iter()
+                        .any(|event| matches!(event, Event::PieceSpawned { .. }))
+            }
+            TutorialStep::ClearLine => events
+                .iter()
+                .any(|event| matches!(event, Event::LinesCleared { .. })),
+            step => step
+                .buttons()
+                .iter()
+                .any(|&button| input.pressed.contains(button)),
+        }
+    }
+
+    /// Sets up the board for the lesson: the line clear lesson starts on a
+    /// bottom row of garbage with a gap on the left, the others leave the
+    /// board as it is.
+    pub fn prepare(self, board: &mut Board) {
+        if self != TutorialStep::ClearLine {
+            return;
+        }
+
+        board.clear();
+        for x in GAP_WIDTH..board.width() {
+            board.cells[0][x] = Some(Block::Garbage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::BOARD_WIDTH,
+        game::{Buttons, Config, Game, Mode},
+    };
+    use std::time::Duration;
+
+    fn press(game: &mut Game, button: Button) -> Vec<Event> {
+        let mut buttons = Buttons::default();
+        buttons.insert(button);
+        let input = Input {
+            held: Buttons::default(),
+            pressed: buttons,
+        };
+        game.tick(input, Duration::from_millis(16))
+    }
+
+    #[test]
+    fn lessons_wait_for_their_action() {
+        let mut game = Game::new(7, Mode::Tutorial, Config::default());
+        press(&mut game, Button::Hold);
+        assert_eq!(game.tutorial, Some(TutorialStep::Move));
+
+        for step in &TutorialStep::ALL[..5] {
+            let events = press(&mut game, step.buttons()[0]);
+            assert!(events.contains(&Event::TutorialStepDone { step: *step }));
+        }
+
+        assert_eq!(game.tutorial, Some(TutorialStep::ClearLine));
+        assert_eq!(
+            game.board.cells[0]
+                .iter()
+                .filter(|cell| cell.is_some())
+                .count(),
+            BOARD_WIDTH - GAP_WIDTH
+        );
+        assert!(!game.over);
+    }
+}